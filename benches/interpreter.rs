@@ -0,0 +1,152 @@
+// Benchmarks of the interpreter loop and of the workloads most sensitive to
+// its internals (memory expansion, hashing, storage and nested calls).
+//
+// Run with `cargo bench`. Save a baseline before a change with
+// `cargo bench -- --save-baseline main` and compare against it afterwards with
+// `cargo bench -- --baseline main`. Reference numbers are in the README.
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+
+use evm_from_scrust::asm::Assembler;
+use evm_from_scrust::*;
+use Opcode::*;
+
+const LOOP_ITERATIONS: usize = 100_000;
+const MEMORY_SIZE: usize = 1 << 20;
+const HASH_ITERATIONS: usize = 1_000;
+const REPEATED_HASHES: usize = 100_000;
+const STORAGE_SLOTS: usize = 1_000;
+const CALL_DEPTH: usize = 100;
+const REPEATED_CALLS: usize = 1_000;
+const CALLEE_SIZE: usize = 4096;
+
+fn run(code: &Bytes) -> EvmResult {
+    run_with(code, CfgEnv::default())
+}
+
+fn run_with(code: &Bytes, cfg: CfgEnv) -> EvmResult {
+    let mut evm = ExecutionContext::new(TxEnv::default(), Call::default(), Block::default(), State::default(), code.clone())
+        .with_cfg(cfg);
+    let result = evm.run();
+    assert!(result.success);
+    result
+}
+
+// Loops while `counter < limit`, with the counter on top of the stack. `body`
+// must leave the stack as it found it.
+fn counting_loop(limit: usize, step: usize, body: impl FnOnce(Assembler) -> Assembler) -> Bytes {
+    let asm = Assembler::new().push(0).jumpdest_label("loop");
+    body(asm)
+        .push(step)
+        .op(ADD)
+        .op(DUP1)
+        .push(limit)
+        .op(GT)
+        .jumpi_to("loop")
+        .build()
+}
+
+// ADD/MUL/JUMPI counting to 100k
+fn arithmetic(c: &mut Criterion) {
+    let code = counting_loop(LOOP_ITERATIONS, 1, |asm| asm.op(DUP1).push(3).ops(&[MUL, POP]));
+    c.bench_function("arithmetic loop (100k iterations)", |b| b.iter(|| run(&code)));
+}
+
+// MSTORE/MLOAD of every word up to 1 MB
+fn memory(c: &mut Criterion) {
+    let code = counting_loop(MEMORY_SIZE, 32, |asm| asm.ops(&[DUP1, DUP1, MSTORE, DUP1, MLOAD, POP]));
+    let mut group = c.benchmark_group("memory walk");
+    group.throughput(Throughput::Bytes(MEMORY_SIZE as u64));
+    group.bench_function("1 MB", |b| b.iter(|| run(&code)));
+    group.finish();
+}
+
+// SHA3 of the same region, 1000 times
+fn keccak(c: &mut Criterion) {
+    let mut group = c.benchmark_group("sha3 (1000 hashes)");
+    for size in [32, 256, 4096] {
+        let code = counting_loop(HASH_ITERATIONS, 1, |asm| asm.push(size).push(0).ops(&[SHA3, POP]));
+        group.throughput(Throughput::Bytes((size * HASH_ITERATIONS) as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(size), &code, |b, code| b.iter(|| run(code)));
+    }
+    group.finish();
+}
+
+// SHA3 of the same 64 bytes, 100k times, with and without the cache of
+// `CfgEnv::keccak_cache`
+fn keccak_cache(c: &mut Criterion) {
+    let code = counting_loop(REPEATED_HASHES, 1, |asm| asm.push(64).push(0).ops(&[SHA3, POP]));
+    let mut group = c.benchmark_group("sha3 of the same 64 bytes (100k hashes)");
+    for keccak_cache in [false, true] {
+        let cfg = CfgEnv { keccak_cache, ..CfgEnv::default() };
+        let name = if keccak_cache { "cached" } else { "uncached" };
+        group.bench_with_input(BenchmarkId::from_parameter(name), &cfg, |b, cfg| b.iter(|| run_with(&code, cfg.clone())));
+    }
+    group.finish();
+}
+
+// SSTORE then SLOAD of 1000 distinct slots
+fn storage(c: &mut Criterion) {
+    let code = counting_loop(STORAGE_SLOTS, 1, |asm| asm.ops(&[DUP1, DUP1, SSTORE, DUP1, SLOAD, POP]));
+    c.bench_function("storage loop (1000 slots)", |b| b.iter(|| run(&code)));
+}
+
+// A contract calling itself with a decreasing counter (in the calldata) until
+// it reaches zero
+fn calls(c: &mut Criterion) {
+    let address = Address::from_slice(&[0xaa; 20]);
+    let code = Assembler::new()
+        .push(0)
+        .ops(&[CALLDATALOAD, DUP1, ISZERO])
+        .jumpi_to("end")
+        .push(1)
+        .ops(&[SWAP1, SUB])
+        .push(0)
+        .op(MSTORE)
+        .push(0)
+        .push(0)
+        .push(32)
+        .push(0)
+        .push(0)
+        .ops(&[ADDRESS, GAS, CALL, STOP])
+        .jumpdest_label("end")
+        .build();
+
+    let mut state = State::new();
+    state.insert(address, AccountState::from_parts(address, U256::zero(), U256::one(), code.clone()));
+    let data = Bytes::from_bytes32(Bytes32::from_u256(CALL_DEPTH.into()));
+    let call = Call::new(Address::zero(), address, address, data, U256::zero(), false);
+
+    c.bench_function("nested calls (depth 100)", |b| {
+        b.iter(|| {
+            let mut evm = ExecutionContext::new(TxEnv::default(), call.clone(), Block::default(), state.clone(), code.clone());
+            let result = evm.run();
+            assert_eq!(result.max_call_depth, CALL_DEPTH);
+            result
+        })
+    });
+}
+
+// 1000 CALLs to the same 4 KB contract, which stops right away (its code is
+// only fetched and analyzed)
+fn repeated_calls(c: &mut Criterion) {
+    let address = Address::from_slice(&[0xbb; 20]);
+    let callee = Assembler::new().op(STOP).bytes(&[JUMPDEST.byte(); CALLEE_SIZE - 1]).build();
+    let callee_word = Bytes32::from_address(address).to_u256();
+    let code = counting_loop(REPEATED_CALLS, 1, |asm| {
+        asm.push(0).push(0).push(0).push(0).push(0).push(callee_word).ops(&[GAS, CALL, POP])
+    });
+
+    let mut state = State::new();
+    state.insert(address, AccountState::from_parts(address, U256::zero(), U256::one(), callee));
+    c.bench_function("calls to the same 4 KB contract (1000 calls)", |b| {
+        b.iter(|| {
+            let mut evm = ExecutionContext::new(TxEnv::default(), Call::default(), Block::default(), state.clone(), code.clone());
+            let result = evm.run();
+            assert!(result.success);
+            result
+        })
+    });
+}
+
+criterion_group!(benches, arithmetic, memory, keccak, keccak_cache, storage, calls, repeated_calls);
+criterion_main!(benches);
@@ -0,0 +1,66 @@
+// Deploys a contract with a creation transaction, calls one of its functions
+// and prints the logs it emitted and its storage.
+//
+//   cargo run --example deploy_and_call
+use evm_from_scrust::abi::AbiValue;
+use evm_from_scrust::asm::Assembler;
+use evm_from_scrust::*;
+use Opcode::*;
+
+// `set(uint256)`: stores the argument at slot 0, logs it and returns it.
+// Whatever the function, as this contract has a single one.
+fn runtime_code() -> Bytes {
+    Assembler::new()
+        .push(4)
+        .op(CALLDATALOAD)
+        .op(DUP1)
+        .push(0)
+        .op(SSTORE)
+        .push(0)
+        .op(MSTORE)
+        .push(32)
+        .push(0)
+        .op(LOG0)
+        .push(32)
+        .push(0)
+        .op(RETURN)
+        .build()
+}
+
+// Init code returning the runtime code (copied from the end of the init code)
+fn creation_code(runtime: &Bytes) -> Bytes {
+    let prefix = Assembler::new()
+        .push(runtime.len() as u64)
+        .op(DUP1)
+        .push(11)
+        .push(0)
+        .op(CODECOPY)
+        .push(0)
+        .op(RETURN)
+        .build();
+    Bytes::from_vec([prefix.as_slice(), runtime.as_slice()].concat())
+}
+
+pub fn main() {
+    let deployer = Address::from_slice(&[0xde; 20]);
+    let mut evm = Evm::new(Block::default(), State::new());
+    evm.set_balance(&deployer, U256::exp10(18));
+
+    // A call to the zero address is a creation, running the call data
+    let creation = Call::new(deployer, Address::zero(), Address::zero(), creation_code(&runtime_code()), U256::zero(), false);
+    let result = evm.transact(TxEnv::default(), creation);
+    assert!(result.success, "{:?}", result.halt);
+    let contract = evm.receipts()[0].contract_address.expect("created contract");
+    println!("deployed at {} ({} bytes of code)", contract, evm.state().code_size(&contract));
+
+    let output = evm.call_fn(contract, "set(uint256)", &[AbiValue::Uint(U256::from(42))]).expect("successful call");
+    println!("returned: {}", output[0].to_u256());
+
+    for log in evm.logs() {
+        println!("log from {}: 0x{} (topics: {:?})", log.address, hex::encode(log.data.as_slice()), log.topics());
+    }
+    println!("storage:");
+    for (key, value) in evm.state().get(&contract).expect("contract").storage().iter_sorted() {
+        println!("  {:#x} => {:#x}", key, value.to_u256());
+    }
+}
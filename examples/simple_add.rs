@@ -0,0 +1,18 @@
+// Assembles a few instructions, runs them and prints the resulting stack.
+//
+//   cargo run --example simple_add
+use evm_from_scrust::asm::assemble;
+use evm_from_scrust::evm;
+
+pub fn main() {
+    let code = assemble("PUSH1 2\nPUSH1 3\nADD\nPUSH1 10\nMUL").expect("valid assembly");
+    println!("code: 0x{}", hex::encode(code.as_slice()));
+
+    let result = evm::eval(code.as_slice(), &[]);
+    assert!(result.success, "{:?}", result.halt);
+    println!("gas used: {}", result.gas_used);
+    println!("stack (top first):");
+    for item in &result.stack {
+        println!("  {}", item.to_u256());
+    }
+}
@@ -0,0 +1,25 @@
+// Runs bytecode with the step tracer and prints an EIP-3155 trace: a JSON line
+// per instruction, then a summary line.
+//
+//   cargo run --example trace
+use evm_from_scrust::asm::assemble;
+use evm_from_scrust::*;
+
+pub fn main() {
+    // Stores 1 + 2 in memory and returns it
+    let code = assemble("PUSH1 1\nPUSH1 2\nADD\nPUSH1 0\nMSTORE\nPUSH1 32\nPUSH1 0\nRETURN").expect("valid assembly");
+    let contract = Address::from_slice(&[0xee; 20]);
+    let call = Call::new(Address::zero(), contract, contract, Bytes::new(), U256::zero(), false);
+    let mut ctx = ExecutionContext::new(TxEnv::default(), call, Block::default(), State::new(), code).with_step_tracer();
+    let result = ctx.run();
+
+    for step in ctx.steps().expect("traced execution") {
+        println!("{}", serde_json::to_string(step).expect("serializable step"));
+    }
+    let summary = serde_json::json!({
+        "output": hex::encode(result.result.as_slice()),
+        "gasUsed": format!("{:#x}", result.gas_used),
+        "pass": result.success,
+    });
+    println!("{}", summary);
+}
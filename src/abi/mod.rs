@@ -0,0 +1,67 @@
+// Minimal Solidity ABI encoding, to call contracts from tests and tools without
+// hand-building selectors and padded arguments. Only static types for now.
+use sha3::{Digest, Keccak256};
+
+use crate::prelude::*;
+use crate::types::{Address, Bytes, Bytes32, U256};
+
+// A static ABI argument, encoded as a single 32-byte word
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AbiValue {
+    // address, left-padded
+    Address(Address),
+    // uint256 (and the smaller uintN, which encode the same)
+    Uint(U256),
+    // bool, as 0 or 1
+    Bool(bool),
+    // bytes32
+    Bytes32(Bytes32),
+    // bytesN with N <= 32, right-padded
+    FixedBytes(Vec<u8>),
+}
+
+impl AbiValue {
+    pub fn encode(&self) -> Bytes32 {
+        match self {
+            AbiValue::Address(address) => Bytes32::from_address(*address),
+            AbiValue::Uint(value) => Bytes32::from_u256(*value),
+            AbiValue::Bool(value) => Bytes32::from_u256(U256::from(*value as u8)),
+            AbiValue::Bytes32(word) => *word,
+            AbiValue::FixedBytes(bytes) => {
+                assert!(bytes.len() <= 32, "bytesN holds at most 32 bytes, got {}", bytes.len());
+                let mut word = [0u8; 32];
+                word[..bytes.len()].copy_from_slice(bytes);
+                Bytes32::from(word)
+            }
+        }
+    }
+}
+
+// First 4 bytes of the Keccak hash of a function signature, like
+// "transfer(address,uint256)"
+pub fn selector(signature: &str) -> [u8; 4] {
+    let hash = Keccak256::digest(signature.as_bytes());
+    [hash[0], hash[1], hash[2], hash[3]]
+}
+
+// Call data of a call to `signature` with `args`
+pub fn encode_call(signature: &str, args: &[AbiValue]) -> Bytes {
+    let mut data = selector(signature).to_vec();
+    for arg in args {
+        data.extend_from_slice(arg.encode().as_slice());
+    }
+    Bytes::from_vec(data)
+}
+
+// Splits return data into words. A trailing partial word is right-padded with
+// zeros.
+pub fn decode_words(data: &Bytes) -> Vec<Bytes32> {
+    data.as_slice()
+        .chunks(32)
+        .map(|chunk| {
+            let mut word = [0u8; 32];
+            word[..chunk.len()].copy_from_slice(chunk);
+            Bytes32::from(word)
+        })
+        .collect()
+}
@@ -0,0 +1,268 @@
+// Helpers to build and read bytecode programmatically, for tests and benchmarks.
+use core::fmt;
+
+use crate::prelude::*;
+use crate::interpreter::Opcode;
+use crate::spec::SpecId;
+use crate::types::{parse_u256, Bytes, U256};
+
+#[derive(Debug, Clone)]
+enum Item {
+    Bytes(Vec<u8>),
+    // JUMPDEST defining a label
+    Label(String),
+    // PUSHn of the offset of a label
+    PushLabel(String),
+}
+
+// Bytecode builder. Labels can be referenced before they are defined: their
+// offsets are resolved when building, with the smallest PUSHn that fits them.
+#[derive(Debug, Default, Clone)]
+pub struct Assembler {
+    items: Vec<Item>,
+}
+
+impl Assembler {
+    pub fn new() -> Self {
+        Self { items: Vec::new() }
+    }
+
+    // Appends an opcode (its immediate, if any, must be appended with `bytes`)
+    pub fn op(self, opcode: Opcode) -> Self {
+        self.bytes(&[opcode.byte()])
+    }
+
+    pub fn ops(self, opcodes: &[Opcode]) -> Self {
+        opcodes.iter().fold(self, |asm, opcode| asm.op(*opcode))
+    }
+
+    // Appends the shortest PUSHn of `value` (PUSH1 for zero, which unlike
+    // PUSH0 is available in every fork)
+    pub fn push(self, value: impl Into<U256>) -> Self {
+        let value: U256 = value.into();
+        let mut word = [0u8; 32];
+        value.to_big_endian(&mut word);
+        let size = value.bits().div_ceil(8).max(1);
+        self.bytes(&push_bytes(&word[32 - size..]))
+    }
+
+    // Appends raw bytes
+    pub fn bytes(mut self, bytes: &[u8]) -> Self {
+        match self.items.last_mut() {
+            Some(Item::Bytes(code)) => code.extend_from_slice(bytes),
+            _ => self.items.push(Item::Bytes(bytes.to_vec())),
+        }
+        self
+    }
+
+    // Appends a JUMPDEST named `label`
+    pub fn jumpdest_label(mut self, label: &str) -> Self {
+        self.items.push(Item::Label(label.to_string()));
+        self
+    }
+
+    // Pushes the offset of `label`
+    pub fn push_label(mut self, label: &str) -> Self {
+        self.items.push(Item::PushLabel(label.to_string()));
+        self
+    }
+
+    pub fn jump_to(self, label: &str) -> Self {
+        self.push_label(label).op(Opcode::JUMP)
+    }
+
+    pub fn jumpi_to(self, label: &str) -> Self {
+        self.push_label(label).op(Opcode::JUMPI)
+    }
+
+    // Panics if a label is referenced but never defined, or defined twice
+    pub fn build(self) -> Bytes {
+        let offsets = self.resolve();
+        let mut code = Vec::new();
+        for item in &self.items {
+            match item {
+                Item::Bytes(bytes) => code.extend_from_slice(bytes),
+                Item::Label(_) => code.push(Opcode::JUMPDEST.byte()),
+                Item::PushLabel(label) => code.extend(push_offset(offsets[label])),
+            }
+        }
+        Bytes::from_vec(code)
+    }
+
+    // Offsets of the labels. A label push grows with the offset it holds, which
+    // can move the labels after it, so the layout is repeated until it settles
+    // (pushes only ever grow, so it always does).
+    fn resolve(&self) -> HashMap<String, usize> {
+        let mut offsets = HashMap::new();
+        loop {
+            let mut next = HashMap::new();
+            let mut offset = 0;
+            for item in &self.items {
+                match item {
+                    Item::Bytes(bytes) => offset += bytes.len(),
+                    Item::Label(label) => {
+                        if next.insert(label.clone(), offset).is_some() {
+                            panic!("label `{}` defined twice", label);
+                        }
+                        offset += 1;
+                    },
+                    Item::PushLabel(label) => {
+                        offset += push_offset(offsets.get(label).copied().unwrap_or(0)).len();
+                    },
+                }
+            }
+            for item in &self.items {
+                if let Item::PushLabel(label) = item {
+                    if !next.contains_key(label) {
+                        panic!("label `{}` is never defined", label);
+                    }
+                }
+            }
+            if next == offsets {
+                return offsets;
+            }
+            offsets = next;
+        }
+    }
+}
+
+fn push_bytes(immediate: &[u8]) -> Vec<u8> {
+    let mut code = vec![Opcode::PUSH1.byte() + immediate.len() as u8 - 1];
+    code.extend_from_slice(immediate);
+    code
+}
+
+fn push_offset(offset: usize) -> Vec<u8> {
+    let bytes = offset.to_be_bytes();
+    let start = bytes.iter().position(|byte| *byte != 0).unwrap_or(bytes.len() - 1);
+    push_bytes(&bytes[start..])
+}
+
+// A decoded instruction. Bytes that aren't an opcode are kept as they are.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Instruction {
+    pub offset: usize,
+    pub byte: u8,
+    // Immediate of a PUSHn (shorter than n if the code ends before it)
+    pub immediate: Vec<u8>,
+}
+
+impl Instruction {
+    pub fn opcode(&self) -> Option<Opcode> {
+        Opcode::try_from(self.byte).ok()
+    }
+
+    // Text of the instruction with the opcode names of `spec` (displaying it
+    // uses the names of the latest fork)
+    pub fn text(&self, spec: SpecId) -> String {
+        let name = match self.opcode() {
+            Some(opcode) => opcode.name(spec),
+            None => format!("0x{:02x}", self.byte),
+        };
+        match self.immediate.is_empty() {
+            true => name,
+            false => format!("{} 0x{}", name, hex::encode(&self.immediate)),
+        }
+    }
+}
+
+impl fmt::Display for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.text(SpecId::LATEST))
+    }
+}
+
+// Splits the code into instructions, skipping over the immediates of PUSHn
+pub fn disassemble(code: &[u8]) -> Vec<Instruction> {
+    let mut instructions = Vec::new();
+    let mut offset = 0;
+    while offset < code.len() {
+        let byte = code[offset];
+        let size = Opcode::try_from(byte).map_or(0, |opcode| opcode.immediate_size());
+        let end = (offset + 1 + size).min(code.len());
+        instructions.push(Instruction { offset, byte, immediate: code[offset + 1..end].to_vec() });
+        offset += 1 + size;
+    }
+    instructions
+}
+
+// Error of assembling source text, with the line (from 1) where it happened
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AsmError {
+    UnknownMnemonic { line: usize, mnemonic: String },
+    // PUSHn without an immediate
+    MissingImmediate { line: usize, opcode: Opcode },
+    // Immediate that isn't a decimal or 0x-prefixed hex number
+    InvalidImmediate { line: usize, immediate: String },
+    // Immediate that doesn't fit in the n bytes of its PUSHn
+    ImmediateTooWide { line: usize, opcode: Opcode, immediate: String },
+}
+
+impl fmt::Display for AsmError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AsmError::UnknownMnemonic { line, mnemonic } => write!(f, "line {}: unknown mnemonic `{}`", line, mnemonic),
+            AsmError::MissingImmediate { line, opcode } => write!(f, "line {}: {:?} without an immediate", line, opcode),
+            AsmError::InvalidImmediate { line, immediate } => {
+                write!(f, "line {}: invalid immediate `{}`", line, immediate)
+            }
+            AsmError::ImmediateTooWide { line, opcode, immediate } => {
+                write!(f, "line {}: `{}` doesn't fit in {:?}", line, immediate, opcode)
+            }
+        }
+    }
+}
+
+// Assembles the mnemonics of the test fixtures, like "PUSH1 0x01\nPUSH1 2\nADD".
+// Mnemonics are case-insensitive, PUSHn immediates are decimal or 0x-prefixed
+// hex (left-padded to n bytes) and comments start with `//` or `#`. A bare hex
+// number is copied as raw bytes, for the undefined opcodes.
+pub fn assemble(source: &str) -> Result<Bytes, AsmError> {
+    let mut code = Vec::new();
+    for (index, text) in source.lines().enumerate() {
+        let line = index + 1;
+        let text = text.split("//").next().unwrap_or_default();
+        let text = text.split('#').next().unwrap_or_default();
+        let mut tokens = text.split_whitespace();
+        while let Some(token) = tokens.next() {
+            if let Some(digits) = token.strip_prefix("0x").or_else(|| token.strip_prefix("0X")) {
+                let bytes = hex::decode(digits).map_err(|_| AsmError::InvalidImmediate { line, immediate: token.to_string() })?;
+                code.extend(bytes);
+                continue;
+            }
+            let opcode = mnemonic(token).ok_or_else(|| AsmError::UnknownMnemonic { line, mnemonic: token.to_string() })?;
+            code.push(opcode.byte());
+            let size = opcode.immediate_size();
+            if size > 0 {
+                let immediate = tokens.next().ok_or(AsmError::MissingImmediate { line, opcode })?;
+                code.extend(immediate_bytes(line, opcode, immediate)?);
+            }
+        }
+    }
+    Ok(Bytes::from_vec(code))
+}
+
+// Opcode named `name`, in any case. The old names of renamed opcodes are
+// accepted too.
+fn mnemonic(name: &str) -> Option<Opcode> {
+    let name = name.to_uppercase();
+    let name = match name.as_str() {
+        "KECCAK256" => "SHA3",
+        "DIFFICULTY" => "PREVRANDAO",
+        name => name,
+    };
+    (0..=u8::MAX)
+        .filter_map(|byte| Opcode::try_from(byte).ok())
+        .find(|opcode| format!("{:?}", opcode) == name)
+}
+
+fn immediate_bytes(line: usize, opcode: Opcode, immediate: &str) -> Result<Vec<u8>, AsmError> {
+    let value = parse_u256(immediate).map_err(|_| AsmError::InvalidImmediate { line, immediate: immediate.to_string() })?;
+    let size = opcode.immediate_size();
+    if value.bits() > size * 8 {
+        return Err(AsmError::ImmediateTooWide { line, opcode, immediate: immediate.to_string() });
+    }
+    let mut word = [0u8; 32];
+    value.to_big_endian(&mut word);
+    Ok(word[32 - size..].to_vec())
+}
@@ -0,0 +1,89 @@
+// Static analyses of bytecode, for tooling: no execution involved, only the
+// instructions as split by the disassembler.
+use crate::prelude::*;
+use crate::asm::{disassemble, Instruction};
+use crate::interpreter::Opcode;
+use crate::types::{Bytes, U256};
+
+// Offsets of the JUMPDESTs that are instructions (and not part of the
+// immediate of a PUSHn), in order
+pub fn jumpdests(code: &Bytes) -> Vec<usize> {
+    disassemble(code.as_slice())
+        .into_iter()
+        .filter(|instruction| instruction.opcode() == Some(Opcode::JUMPDEST))
+        .map(|instruction| instruction.offset)
+        .collect()
+}
+
+// Selectors compared against by the function dispatcher of Solidity and Vyper,
+// `PUSH4 selector (DUP2) EQ PUSHn destination JUMPI`, in order of appearance
+// and without duplicates. Comparisons that only split the dispatcher in
+// ranges (GT, LT) aren't selectors.
+pub fn function_selectors(code: &Bytes) -> Vec<[u8; 4]> {
+    let instructions = disassemble(code.as_slice());
+    let mut selectors = Vec::new();
+    for (index, instruction) in instructions.iter().enumerate() {
+        if instruction.opcode() != Some(Opcode::PUSH4) || instruction.immediate.len() != 4 {
+            continue;
+        }
+        let rest = opcodes(&instructions[index + 1..]);
+        let dispatches = match rest.as_slice() {
+            [Some(Opcode::EQ), Some(push), Some(Opcode::JUMPI), ..]
+            | [Some(Opcode::DUP2), Some(Opcode::EQ), Some(push), Some(Opcode::JUMPI), ..] => push.immediate_size() > 0,
+            _ => false,
+        };
+        let selector: [u8; 4] = instruction.immediate.as_slice().try_into().unwrap();
+        if dispatches && !selectors.contains(&selector) {
+            selectors.push(selector);
+        }
+    }
+    selectors
+}
+
+// Splits creation code with the usual constructor ending, which copies the
+// runtime code to memory and returns it:
+//
+//   PUSHn size DUP1 PUSHn offset PUSH1 0 CODECOPY PUSH1 0 RETURN
+//
+// (with PUSH0 for the zeros since Shanghai). Returns the code before the
+// runtime code and the runtime code itself. Anything after it, like the
+// constructor arguments, is left out. None if there is no such ending, or it
+// copies code from out of bounds.
+pub fn split_constructor(code: &Bytes) -> Option<(Bytes, Bytes)> {
+    let instructions = disassemble(code.as_slice());
+    instructions.windows(7).find_map(|window| {
+        let [size, dup, offset, destination, copy, zero, ret] = window else { return None };
+        let matches = dup.opcode() == Some(Opcode::DUP1)
+            && copy.opcode() == Some(Opcode::CODECOPY)
+            && ret.opcode() == Some(Opcode::RETURN)
+            && push_value(destination)? == U256::zero()
+            && push_value(zero)? == U256::zero();
+        if !matches {
+            return None;
+        }
+        let size = push_value(size)?;
+        let offset = push_value(offset)?;
+        let end = offset.checked_add(size)?;
+        if end > U256::from(code.len()) {
+            return None;
+        }
+        let (offset, end) = (offset.as_usize(), end.as_usize());
+        Some((Bytes::from_slice(&code.as_slice()[..offset]), Bytes::from_slice(&code.as_slice()[offset..end])))
+    })
+}
+
+fn opcodes(instructions: &[Instruction]) -> Vec<Option<Opcode>> {
+    instructions.iter().take(4).map(|instruction| instruction.opcode()).collect()
+}
+
+// Value pushed by a complete PUSHn or PUSH0
+fn push_value(instruction: &Instruction) -> Option<U256> {
+    let opcode = instruction.opcode()?;
+    match opcode {
+        Opcode::PUSH0 => Some(U256::zero()),
+        _ if opcode.immediate_size() > 0 && instruction.immediate.len() == opcode.immediate_size() => {
+            Some(U256::from_big_endian(&instruction.immediate))
+        }
+        _ => None,
+    }
+}
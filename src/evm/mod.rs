@@ -0,0 +1,325 @@
+// Stateful facade over `ExecutionContext::transact`, for embedders running
+// several transactions against the same state (test frameworks, simulations).
+//
+// Besides threading the block, the state, the logs and the receipts through
+// the transactions, it can snapshot all of them and revert to a snapshot later,
+// like the `vm.snapshot()` / `vm.revertTo(id)` cheatcodes of Foundry.
+// Snapshots are taken between transactions, when there are no pending
+// selfdestructs (they are applied at the end of every execution).
+use crate::prelude::*;
+use crate::abi::{decode_words, encode_call, AbiValue};
+use crate::primitives::*;
+use crate::{CallTrace, EvmResult, ExecutionContext, Halt};
+
+// Identifier of a snapshot, as returned by `Evm::snapshot`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct SnapshotId(u64);
+
+// Everything a snapshot restores
+#[derive(Debug, Clone)]
+struct Checkpoint {
+    block: Block,
+    state: State,
+    logs: Vec<Log>,
+    receipts: Vec<Receipt>,
+    cumulative_gas_used: usize,
+}
+
+#[derive(Debug, Clone)]
+pub struct Evm {
+    spec: SpecId,
+    cfg: CfgEnv,
+    current: Checkpoint,
+    // Valid snapshots, from the oldest to the newest
+    snapshots: Vec<(SnapshotId, Checkpoint)>,
+    next_snapshot: u64,
+}
+
+impl Evm {
+    pub fn new(block: Block, state: State) -> Self {
+        Self {
+            spec: SpecId::default(),
+            cfg: CfgEnv::default(),
+            current: Checkpoint {
+                block,
+                state,
+                logs: Vec::new(),
+                receipts: Vec::new(),
+                cumulative_gas_used: 0,
+            },
+            snapshots: Vec::new(),
+            next_snapshot: 0,
+        }
+    }
+
+    pub fn with_spec(mut self, spec: SpecId) -> Self {
+        self.spec = spec;
+        self
+    }
+
+    pub fn with_memory_limit(mut self, memory_limit: usize) -> Self {
+        self.cfg.memory_limit = memory_limit;
+        self
+    }
+
+    // Halts every transaction after `max_steps` instructions
+    pub fn with_max_steps(mut self, max_steps: u64) -> Self {
+        self.cfg.max_steps = Some(max_steps);
+        self
+    }
+
+    pub fn with_cfg(mut self, cfg: CfgEnv) -> Self {
+        self.cfg = cfg;
+        self
+    }
+
+    pub fn with_chain_id(mut self, chain_id: u64) -> Self {
+        self.cfg.chain_id = chain_id;
+        self
+    }
+
+    // Caches the results of SHA3 within each transaction (see `KeccakCache`)
+    pub fn with_keccak_cache(mut self) -> Self {
+        self.cfg.keccak_cache = true;
+        self
+    }
+
+    // Relaxed checks of the transactions, for simulations (see `CfgEnv`)
+
+    pub fn disable_balance_check(mut self, disable: bool) -> Self {
+        self.cfg.disable_balance_check = disable;
+        self
+    }
+
+    pub fn disable_block_gas_limit(mut self, disable: bool) -> Self {
+        self.cfg.disable_block_gas_limit = disable;
+        self
+    }
+
+    pub fn disable_nonce_check(mut self, disable: bool) -> Self {
+        self.cfg.disable_nonce_check = disable;
+        self
+    }
+
+    // Executes a transaction on top of the current state, keeping its logs and
+    // receipt
+    pub fn transact(&mut self, tx: TxEnv, call: Call) -> EvmResult {
+        let env = self.env(tx, call);
+        let state = core::mem::take(&mut self.current.state);
+        let (result, receipt, state) = ExecutionContext::transact_env(env, state, self.current.cumulative_gas_used);
+        self.commit(state, receipt);
+        result
+    }
+
+    // Like `transact`, also returning the call tree of the transaction
+    pub fn transact_traced(&mut self, tx: TxEnv, call: Call) -> (EvmResult, CallTrace) {
+        let env = self.env(tx, call);
+        let state = core::mem::take(&mut self.current.state);
+        let (result, receipt, state, trace) =
+            ExecutionContext::transact_traced(env, state, self.current.cumulative_gas_used);
+        self.commit(state, receipt);
+        (result, trace)
+    }
+
+    // Simulates a transaction on top of the current state, like eth_call: it
+    // runs as `transact` would (the contract sees its own writes and the value
+    // it is sent), but nothing of it is kept. The sender's nonce isn't checked
+    // nor bumped, and gas isn't paid for, so the sender needs no funds for it.
+    pub fn call(&mut self, tx: TxEnv, call: Call) -> EvmResult {
+        let id = self.snapshot();
+        let result = self.simulate(tx, call);
+        self.revert_to(id);
+        self.discard(id);
+        result
+    }
+
+    // Like `call`, with `overrides` applied on top of the current state for
+    // the duration of the call only (eth_call's `stateOverride`)
+    pub fn call_with_overrides(&mut self, tx: TxEnv, call: Call, overrides: &Overrides) -> Result<EvmResult, OverrideError> {
+        let id = self.snapshot();
+        let result = overrides.apply(&mut self.current.state).map(|()| self.simulate(tx, call));
+        self.revert_to(id);
+        self.discard(id);
+        result
+    }
+
+    // Runs a simulated transaction, to be reverted by the caller
+    fn simulate(&mut self, tx: TxEnv, call: Call) -> EvmResult {
+        let mut env = self.env(tx, call);
+        env.cfg.disable_balance_check = true;
+        env.cfg.disable_nonce_check = true;
+        let state = core::mem::take(&mut self.current.state);
+        let (result, _, state) = ExecutionContext::simulate_env(env, state, self.current.cumulative_gas_used);
+        self.current.state = state;
+        result
+    }
+
+    fn env(&self, tx: TxEnv, call: Call) -> Env {
+        Env {
+            spec: self.spec,
+            cfg: self.cfg.clone(),
+            ..Env::new(tx, call, self.current.block.clone())
+        }
+    }
+
+    fn commit(&mut self, state: State, receipt: Receipt) {
+        let current = &mut self.current;
+        current.state = state;
+        current.cumulative_gas_used = receipt.cumulative_gas_used;
+        current.logs.extend(receipt.logs.iter().cloned());
+        current.receipts.push(receipt);
+    }
+
+    // Calls the function `signature` of `to` with `args` (from the zero
+    // address, as a transaction with its next nonce), and splits the return
+    // data into words. Fails with the reason of the halt.
+    pub fn call_fn(&mut self, to: Address, signature: &str, args: &[AbiValue]) -> Result<Vec<Bytes32>, Halt> {
+        let call = Call::new(Address::zero(), to, to, encode_call(signature, args), U256::zero(), false);
+        let tx = TxEnv { nonce: self.current.state.nonce(&Address::zero()), ..TxEnv::default() };
+        let result = self.transact(tx, call);
+        match result.halt {
+            None => Ok(decode_words(&result.result)),
+            Some(halt) => Err(halt),
+        }
+    }
+
+    // Captures the block, the state, the logs and the receipts. The snapshot
+    // stays valid until it is discarded, or a snapshot taken before it is
+    // reverted to.
+    pub fn snapshot(&mut self) -> SnapshotId {
+        let id = SnapshotId(self.next_snapshot);
+        self.next_snapshot += 1;
+        self.snapshots.push((id, self.current.clone()));
+        id
+    }
+
+    // Restores a snapshot, which stays valid (so it can be reverted to again),
+    // and invalidates the ones taken after it. Returns whether the snapshot
+    // was valid.
+    pub fn revert_to(&mut self, id: SnapshotId) -> bool {
+        match self.snapshots.iter().position(|(snapshot, _)| *snapshot == id) {
+            Some(index) => {
+                self.snapshots.truncate(index + 1);
+                self.current = self.snapshots[index].1.clone();
+                true
+            }
+            None => false,
+        }
+    }
+
+    // Forgets a snapshot. Returns whether it was valid.
+    pub fn discard(&mut self, id: SnapshotId) -> bool {
+        let len = self.snapshots.len();
+        self.snapshots.retain(|(snapshot, _)| *snapshot != id);
+        self.snapshots.len() != len
+    }
+
+    // Cheatcodes. They take effect right away and persist for the following
+    // transactions, until a snapshot taken before them is reverted to.
+
+    pub fn set_balance(&mut self, address: &Address, balance: U256) {
+        self.current.state.set_balance(address, balance);
+    }
+
+    pub fn set_nonce(&mut self, address: &Address, nonce: U256) {
+        self.current.state.set_nonce(address, nonce);
+    }
+
+    pub fn set_code(&mut self, address: &Address, code: Bytes) {
+        self.current.state.set_code(address, code);
+    }
+
+    pub fn set_storage(&mut self, address: &Address, key: U256, value: Bytes32) {
+        self.current.state.set_storage(address, key, value);
+    }
+
+    // Delegates the code of `eoa` to `target` (EIP-7702), or removes its
+    // delegation if `target` is the zero address
+    #[cfg(feature = "eip7702")]
+    pub fn set_delegation(&mut self, eoa: &Address, target: &Address) {
+        self.current.state.set_delegation(eoa, target);
+    }
+
+    // Backs the state with another host from the next transaction on. Accounts
+    // already written to stay in memory, the rest is looked up in `host`.
+    pub fn set_host(&mut self, host: Arc<dyn Host>) {
+        self.current.state.set_host(host);
+    }
+
+    pub fn warp(&mut self, timestamp: U256) {
+        self.current.block.warp(timestamp);
+    }
+
+    pub fn roll(&mut self, number: u64) {
+        self.current.block.roll(number);
+    }
+
+    pub fn fee(&mut self, base_fee: U256) {
+        self.current.block.fee(base_fee);
+    }
+
+    pub fn block(&self) -> &Block {
+        &self.current.block
+    }
+
+    pub fn state(&self) -> &State {
+        &self.current.state
+    }
+
+    // Takes the current state out of the facade, which is left with an empty
+    // one (the snapshots are kept)
+    pub fn take_state(&mut self) -> State {
+        core::mem::take(&mut self.current.state)
+    }
+
+    // Logs of all the transactions executed so far, in order
+    pub fn logs(&self) -> &[Log] {
+        &self.current.logs
+    }
+
+    pub fn receipts(&self) -> &[Receipt] {
+        &self.current.receipts
+    }
+
+    pub fn cumulative_gas_used(&self) -> usize {
+        self.current.cumulative_gas_used
+    }
+}
+
+/// Runs `code` with `calldata` in a throwaway `Evm`: default block and
+/// transaction (30M gas), empty state, called by the zero address. The code
+/// is deployed at 0xeeee…ee.
+///
+/// ```
+/// use evm_from_scrust::{evm, U256};
+///
+/// // PUSH1 1 PUSH1 2 ADD
+/// let result = evm::eval(&hex::decode("6001600201").unwrap(), &[]);
+/// assert!(result.success);
+/// assert_eq!(result.stack.last().unwrap().to_u256(), U256::from(3));
+/// ```
+pub fn eval(code: &[u8], calldata: &[u8]) -> EvmResult {
+    eval_with(code, calldata, |_| {})
+}
+
+/// Like `eval`, after `configure` has adjusted the environment. The code is
+/// deployed at the `code_target` of the configured call.
+///
+/// ```
+/// use evm_from_scrust::{evm, U256};
+///
+/// // NUMBER
+/// let result = evm::eval_with(&[0x43], &[], |env| env.block.roll(42));
+/// assert_eq!(result.stack[0].to_u256(), U256::from(42));
+/// ```
+pub fn eval_with(code: &[u8], calldata: &[u8], configure: impl FnOnce(&mut Env)) -> EvmResult {
+    let contract = Address::from_slice(&[0xee; 20]);
+    let call = Call::new(Address::zero(), contract, contract, Bytes::from_slice(calldata), U256::zero(), false);
+    let mut env = Env::new(TxEnv::default(), call, Block::default());
+    env.cfg.disable_nonce_check = true;
+    configure(&mut env);
+
+    let mut evm = Evm::new(env.block, State::new()).with_spec(env.spec).with_cfg(env.cfg);
+    evm.set_code(&env.call.code_target, Bytes::from_slice(code));
+    evm.transact(env.tx, env.call)
+}
@@ -0,0 +1,484 @@
+// Runner of the test suite `evm.json` developed by w1nt3r.eth, which has been
+// borrowed from his Github repo `EVM From Scratch`. Used by both the `evm_json`
+// integration test and the binary.
+use log::warn;
+use serde::Deserialize;
+use std::fmt::Write;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+
+use crate::primitives::*;
+use crate::{Evm, EvmResult, ExecutionContext};
+
+// Path of the test suite, relative to the crate root
+pub const SUITE: &str = "evm.json";
+
+// Cargo features that tests may require, and whether they are enabled
+const FEATURES: [(&str, bool); 2] = [
+    ("eip7702", cfg!(feature = "eip7702")),
+    ("fork", cfg!(feature = "fork")),
+];
+
+// Struct to deserialize the test inputs
+#[derive(Debug, Default, Deserialize)]
+pub struct Evmtest {
+    // Common fields for all tests
+    pub name: String,
+    pub hint: String,
+    expect: Expect,
+    // Optional fields
+    code: Option<Code>,
+    tx: Option<Tx>,
+    block: Option<TestBlock>,
+    state: Option<State>,
+    // Fork whose rules apply (Shanghai by default)
+    #[serde(default)]
+    spec: SpecId,
+    // Cargo features needed by the test, which is skipped without them
+    #[serde(default)]
+    requires: Vec<String>,
+    // Why the fixture couldn't be parsed, in which case the test fails
+    #[serde(skip)]
+    invalid: Option<String>,
+}
+
+// Struct to deserialize a scenario: transactions executed in order against a
+// shared pre-state, each checked against its own expectations. What they
+// change in the state carries over to the next one.
+#[derive(Debug, Default, Deserialize)]
+pub struct Scenario {
+    pub name: String,
+    pub hint: String,
+    txs: Vec<Transaction>,
+    // Optional fields, shared by all the transactions
+    block: Option<TestBlock>,
+    state: Option<State>,
+    #[serde(default)]
+    spec: SpecId,
+    #[serde(default)]
+    requires: Vec<String>,
+    #[serde(skip)]
+    invalid: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Transaction {
+    tx: Tx,
+    expect: Expect,
+}
+
+// Struct to deserialize the test block, which may also carry the chain id
+#[derive(Debug, Clone, Deserialize)]
+struct TestBlock {
+    #[serde(flatten)]
+    block: Block,
+    #[serde(default, rename = "chainId", deserialize_with = "deserialize_u64_option")]
+    chain_id: Option<U64>,
+}
+
+// Struct to deserialize the test transaction, which may also carry the code
+// to be executed at its recipient
+#[derive(Debug, Deserialize)]
+struct Tx {
+    #[serde(flatten)]
+    env: TxEnv,
+    #[serde(flatten)]
+    call: Call,
+    #[serde(default)]
+    code: Option<Code>,
+}
+
+// Struct to deserialize the expected test outcomes
+#[derive(Debug, Default, Deserialize)]
+struct Expect {
+    // Whether the transaction should be successful or not
+    success: bool,
+    // EVM stack after finalizing the execution of the test, as hex strings
+    // (with or without the 0x prefix)
+    #[serde(default, deserialize_with = "deserialize_stack")]
+    stack: Vec<U256>,
+    // EVM logs after finalizing the execution of the test
+    #[serde(default)]
+    logs: Vec<JsonLog>,
+    // Result of executing the transaction
+    #[serde(default, rename = "return", deserialize_with = "hex_string_to_bytes")]
+    result: Bytes,
+    // Gas consumed by the execution, only checked when present
+    gas: Option<usize>,
+}
+
+// Outcome of running a test
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Outcome {
+    Pass,
+    // Skipped, because of the missing feature
+    Skip(String),
+    // Failed, with a report of the differences
+    Fail(String),
+}
+
+// Loads the test suite at `path`. Only an unreadable file or one that isn't a
+// list fails: fixtures that can't be parsed are kept as failing tests.
+pub fn load(path: &str) -> Result<Vec<Evmtest>, String> {
+    let text = std::fs::read_to_string(path).map_err(|error| format!("{}: {}", path, error))?;
+    let fixtures: Vec<serde_json::Value> = serde_json::from_str(&text).map_err(|error| format!("{}: {}", path, error))?;
+    Ok(fixtures.into_iter().enumerate().map(|(index, fixture)| Evmtest::parse(index, fixture)).collect())
+}
+
+// Loads a list of scenarios, like `load`
+pub fn load_scenarios(path: &str) -> Result<Vec<Scenario>, String> {
+    let text = std::fs::read_to_string(path).map_err(|error| format!("{}: {}", path, error))?;
+    let fixtures: Vec<serde_json::Value> = serde_json::from_str(&text).map_err(|error| format!("{}: {}", path, error))?;
+    Ok(fixtures.into_iter().enumerate().map(|(index, fixture)| Scenario::parse(index, fixture)).collect())
+}
+
+// Name of the fixture at `index`, for the error reports
+fn fixture_name(index: usize, fixture: &serde_json::Value) -> String {
+    match fixture.get("name").and_then(|name| name.as_str()) {
+        Some(name) => name.to_string(),
+        None => format!("#{}", index),
+    }
+}
+
+fn deserialize<T: serde::de::DeserializeOwned>(fixture: serde_json::Value) -> Result<T, String> {
+    serde_path_to_error::deserialize(fixture).map_err(|error| format!("`{}`: {}", error.path(), error.inner()))
+}
+
+impl Evmtest {
+    // Parses the fixture at `index` of a suite. On errors, the test is named
+    // after the fixture (or its index) and reports the offending field.
+    pub fn parse(index: usize, fixture: serde_json::Value) -> Evmtest {
+        let name = fixture_name(index, &fixture);
+        let parsed = deserialize(fixture).and_then(|test: Evmtest| test.validate().map(|_| test));
+        parsed.unwrap_or_else(|error| Evmtest {
+            name,
+            invalid: Some(error),
+            ..Default::default()
+        })
+    }
+
+    // Checks what serde can't: the expected logs must be convertible
+    fn validate(&self) -> Result<(), String> {
+        self.expect.validate("expect")
+    }
+
+    fn tx_env(&self) -> TxEnv {
+        tx_env(self.tx.as_ref())
+    }
+
+    fn call(&self) -> Call {
+        match &self.tx {
+            Some(tx) => tx.call.clone(),
+            None => Call::default(),
+        }
+    }
+
+    // Code to be executed. It can be specified in the state of the tx recipient,
+    // in the tx itself or at the top level of the test, in that order of precedence.
+    fn code(&self) -> Bytes {
+        let recipient = self.call().recipient;
+        let sources = [
+            ("state", self.state.as_ref().map(|state| state.code(&recipient))),
+            ("tx", self.tx.as_ref().and_then(|tx| tx.code.as_ref()).map(code_bytes)),
+            ("test", self.code.as_ref().map(code_bytes)),
+        ];
+        let mut sources = sources
+            .into_iter()
+            .filter_map(|(source, code)| code.filter(|code| !code.is_empty()).map(|code| (source, code)));
+
+        match sources.next() {
+            Some((source, code)) => {
+                for (other, other_code) in sources {
+                    if other_code != code {
+                        warn!(target: "evm::evmtest", "code in {} and {} disagree, using the one in {}", source, other, source);
+                    }
+                }
+                code
+            }
+            None => Bytes::new(),
+        }
+    }
+
+    fn asm(&self) -> Option<&String> {
+        let tx_code = self.tx.as_ref().and_then(|tx| tx.code.as_ref());
+        [tx_code, self.code.as_ref()]
+            .into_iter()
+            .flatten()
+            .find_map(|code| code.asm.as_ref())
+    }
+
+    // Why the code of the fixture is corrupt: its `asm` doesn't assemble, or
+    // not to its `bin`
+    fn corrupt_code(&self) -> Option<String> {
+        let tx_code = self.tx.as_ref().and_then(|tx| tx.code.as_ref());
+        [tx_code, self.code.as_ref()].into_iter().flatten().find_map(|code| {
+            let asm = code.asm.as_ref().filter(|_| !code.bin.is_empty())?;
+            match Bytes::from_asm(asm) {
+                Ok(assembled) if assembled == code_bytes(code) => None,
+                Ok(assembled) => Some(format!("asm assembles to {}, but bin is {}", hex::encode(assembled.as_slice()), code.bin)),
+                Err(error) => Some(format!("asm doesn't assemble: {}", error)),
+            }
+        })
+    }
+
+    fn missing_feature(&self) -> Option<&String> {
+        missing_feature(&self.requires)
+    }
+
+    fn block(&self) -> Block {
+        match &self.block {
+            Some(block) => block.block.clone(),
+            None => Block::default(),
+        }
+    }
+
+    fn cfg(&self) -> CfgEnv {
+        cfg(self.block.as_ref())
+    }
+
+    fn state(&self) -> State {
+        match &self.state {
+            Some(state) => state.clone(),
+            None => State::default(),
+        }
+    }
+
+    // Runs the test. A panicking execution counts as a failure.
+    pub fn run(&self) -> Outcome {
+        self.run_with(|_| {})
+    }
+
+    // Like `run`, after `configure` has adjusted the settings of the fixture
+    // (to check that an optional feature doesn't change the outcomes)
+    pub fn run_with(&self, configure: impl FnOnce(&mut CfgEnv)) -> Outcome {
+        if let Some(error) = &self.invalid {
+            return Outcome::Fail(format!("Invalid fixture {}: {}\n", self.name, error));
+        }
+        if let Some(feature) = self.missing_feature() {
+            return Outcome::Skip(feature.clone());
+        }
+        if let Some(reason) = self.corrupt_code() {
+            return Outcome::Fail(format!("Corrupt fixture: {}\n\nHint: {}\n", reason, self.hint));
+        }
+
+        let mut cfg = self.cfg();
+        configure(&mut cfg);
+        let execution = catch_unwind(AssertUnwindSafe(|| {
+            let mut evm = ExecutionContext::new(self.tx_env(), self.call(), self.block(), self.state(), self.code())
+                .with_spec(self.spec)
+                .with_cfg(cfg);
+            evm.run()
+        }));
+
+        match execution {
+            Ok(result) => match self.expect.report(&result, self.asm(), &self.hint) {
+                Some(report) => Outcome::Fail(report),
+                None => Outcome::Pass,
+            },
+            Err(panic) => Outcome::Fail(panicked(panic, &self.hint)),
+        }
+    }
+}
+
+impl Scenario {
+    // Parses the fixture at `index` of a list of scenarios, like `Evmtest::parse`
+    pub fn parse(index: usize, fixture: serde_json::Value) -> Scenario {
+        let name = fixture_name(index, &fixture);
+        let parsed = deserialize(fixture).and_then(|scenario: Scenario| scenario.validate().map(|_| scenario));
+        parsed.unwrap_or_else(|error| Scenario {
+            name,
+            invalid: Some(error),
+            ..Default::default()
+        })
+    }
+
+    // The code comes from the state, as it can't be run in place: a
+    // transaction only executes what is deployed at its recipient
+    fn validate(&self) -> Result<(), String> {
+        for (index, transaction) in self.txs.iter().enumerate() {
+            if transaction.tx.code.is_some() {
+                return Err(format!("`txs[{}].tx.code`: the code of a scenario goes in its state", index));
+            }
+            transaction.expect.validate(&format!("txs[{}].expect", index))?;
+        }
+        Ok(())
+    }
+
+    // Runs the transactions in order, stopping at the first one that doesn't
+    // meet its expectations. A panicking execution counts as a failure.
+    pub fn run(&self) -> Outcome {
+        if let Some(error) = &self.invalid {
+            return Outcome::Fail(format!("Invalid scenario {}: {}\n", self.name, error));
+        }
+        if let Some(feature) = missing_feature(&self.requires) {
+            return Outcome::Skip(feature.clone());
+        }
+
+        let execution = catch_unwind(AssertUnwindSafe(|| {
+            let block = self.block.as_ref().map(|block| block.block.clone()).unwrap_or_default();
+            let state = self.state.clone().unwrap_or_default();
+            // The transactions of a scenario don't carry nonces
+            let mut evm = Evm::new(block, state)
+                .with_spec(self.spec)
+                .with_cfg(cfg(self.block.as_ref()))
+                .disable_nonce_check(true);
+            self.txs.iter().enumerate().find_map(|(index, transaction)| {
+                let result = evm.transact(tx_env(Some(&transaction.tx)), transaction.tx.call.clone());
+                let report = transaction.expect.report(&result, None, &self.hint)?;
+                Some(format!("Transaction {} of {}:\n{}", index + 1, self.txs.len(), report))
+            })
+        }));
+
+        match execution {
+            Ok(None) => Outcome::Pass,
+            Ok(Some(report)) => Outcome::Fail(report),
+            Err(panic) => Outcome::Fail(panicked(panic, &self.hint)),
+        }
+    }
+}
+
+impl Expect {
+    fn validate(&self, path: &str) -> Result<(), String> {
+        for (index, log) in self.logs.iter().enumerate() {
+            Log::from_json(log).map_err(|error| format!("`{}.logs[{}]`: {}", path, index, error))?;
+        }
+        Ok(())
+    }
+
+    // Report of the differences between the result and the expected outcome.
+    // None if they match.
+    fn report(&self, result: &EvmResult, asm: Option<&String>, hint: &str) -> Option<String> {
+        let expected_stack: Vec<Bytes32> = self
+            .stack
+            .iter()
+            .map(|v| Bytes32::from_u256(*v))
+            .collect();
+
+        let expected_logs: Vec<Log> = self
+            .logs
+            .iter()
+            .map(|l| Log::from_json(l).unwrap())
+            .collect();
+
+        let matching = result.success == self.success
+            && result.result == self.result
+            && result.stack == expected_stack
+            && result.logs == expected_logs
+            && self.gas.is_none_or(|gas| gas == result.gas_used);
+        if matching {
+            return None;
+        }
+
+        let mut report = String::new();
+        if let Some(asm) = asm {
+            writeln!(report, "Instructions: \n{}\n", asm).unwrap();
+        }
+
+        writeln!(report, "Expected result: {:?}", self.result).unwrap();
+        writeln!(report, "Actual result: {:?}\n", result.result).unwrap();
+
+        writeln!(report, "Expected success: {:?}", self.success).unwrap();
+        writeln!(report, "Expected stack: [").unwrap();
+        for w in &expected_stack {
+            writeln!(report, "  {:#X},", w).unwrap();
+        }
+        writeln!(report, "]\nExpected logs: [").unwrap();
+        for l in &expected_logs {
+            writeln!(report, "  {:#?},", l).unwrap();
+        }
+        writeln!(report, "]\n").unwrap();
+
+        writeln!(report, "Actual success: {:?}", result.success).unwrap();
+        if let Some(halt) = &result.halt {
+            writeln!(report, "Halt reason: {}", halt).unwrap();
+        }
+        writeln!(report, "Actual stack: [").unwrap();
+        for v in &result.stack {
+            writeln!(report, "  {:#X},", v).unwrap();
+        }
+        writeln!(report, "]\nActual logs: [").unwrap();
+        for l in &result.logs {
+            writeln!(report, "  {:#?},", l).unwrap();
+        }
+        writeln!(report, "]\n").unwrap();
+
+        if let Some(gas) = self.gas {
+            writeln!(report, "Expected gas: {}, actual: {}", gas, result.gas_used).unwrap();
+        }
+
+        writeln!(report, "\nHint: {}", hint).unwrap();
+        Some(report)
+    }
+}
+
+// Transaction environment. The originator defaults to the sender.
+fn tx_env(tx: Option<&Tx>) -> TxEnv {
+    match tx {
+        Some(tx) if tx.env.originator == Address::zero() => TxEnv {
+            originator: tx.call.sender,
+            ..tx.env.clone()
+        },
+        Some(tx) => tx.env.clone(),
+        None => TxEnv::default(),
+    }
+}
+
+// Chain settings, with the chain id of the block if it has one
+fn cfg(block: Option<&TestBlock>) -> CfgEnv {
+    let mut cfg = CfgEnv::default();
+    if let Some(chain_id) = block.and_then(|block| block.chain_id) {
+        cfg.chain_id = chain_id.as_u64();
+    }
+    cfg
+}
+
+fn missing_feature(requires: &[String]) -> Option<&String> {
+    requires.iter().find(|feature| !enabled(feature))
+}
+
+fn panicked(panic: Box<dyn core::any::Any + Send>, hint: &str) -> String {
+    let message = panic
+        .downcast_ref::<&str>()
+        .map(|message| message.to_string())
+        .or_else(|| panic.downcast_ref::<String>().cloned())
+        .unwrap_or_default();
+    format!("Panicked: {}\n\nHint: {}\n", message, hint)
+}
+
+fn enabled(feature: &str) -> bool {
+    FEATURES.iter().any(|(name, enabled)| *name == feature && *enabled)
+}
+
+// A stack item: a hex string, or a JSON number
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum StackItem {
+    Number(u64),
+    Hex(String),
+}
+
+fn deserialize_stack<'de, D>(deserializer: D) -> Result<Vec<U256>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let items = Option::<Vec<StackItem>>::deserialize(deserializer)?.unwrap_or_default();
+    items
+        .into_iter()
+        .map(|item| match item {
+            StackItem::Number(number) => Ok(number.into()),
+            StackItem::Hex(item) => {
+                let digits = item.strip_prefix("0x").or_else(|| item.strip_prefix("0X")).unwrap_or(&item);
+                match digits {
+                    "" => Err(format!("empty stack item `{}`", item)),
+                    _ if !digits.bytes().all(|byte| byte.is_ascii_hexdigit()) => Err(format!("invalid hex `{}`", item)),
+                    _ => U256::from_str_radix(digits, 16).map_err(|_| format!("`{}` doesn't fit in 256 bits", item)),
+                }
+            }
+        })
+        .collect::<Result<_, _>>()
+        .map_err(serde::de::Error::custom)
+}
+
+// The hex of `bin` is checked when deserializing
+fn code_bytes(code: &Code) -> Bytes {
+    Bytes::from_vec(hex::decode(&code.bin).unwrap())
+}
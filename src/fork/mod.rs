@@ -0,0 +1,208 @@
+// Forking of live chain state. Requires the `fork` feature.
+//
+// A `ForkHost` lazily fetches the accounts and storage slots of a chain over
+// JSON-RPC at a pinned block number, caching every result so that repeated
+// accesses don't hit the network. It is used as the host of a `State`, which
+// keeps all the writes local.
+//
+// Hosts can't fail, so a request that fails (or whose result doesn't parse)
+// makes the account read as missing and the slot as zero. The failure is
+// logged and kept in `errors`, and isn't cached: the next access retries.
+
+use log::warn;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::fmt::{self, Debug};
+use std::sync::{Arc, Mutex};
+
+use crate::host::Host;
+use crate::state::{AccountState, State};
+use crate::types::{Address, Bytes, Bytes32, U256, U64};
+
+// Transport of JSON-RPC requests
+pub trait Transport: Debug + Send + Sync {
+    // Sends a request, returning the `result` of the response
+    fn request(&self, method: &str, params: Value) -> Result<Value, String>;
+}
+
+// JSON-RPC over (blocking) HTTP
+#[derive(Debug, Clone)]
+pub struct HttpTransport {
+    url: String,
+}
+
+impl HttpTransport {
+    pub fn new(url: &str) -> Self {
+        Self { url: url.to_string() }
+    }
+}
+
+impl Transport for HttpTransport {
+    fn request(&self, method: &str, params: Value) -> Result<Value, String> {
+        let request = json!({ "jsonrpc": "2.0", "id": 1, "method": method, "params": params });
+        let mut response: Value = ureq::post(&self.url)
+            .send_json(request)
+            .and_then(|mut response| response.body_mut().read_json())
+            .map_err(|error| error.to_string())?;
+
+        match response.get("error") {
+            Some(error) => Err(error.to_string()),
+            None => Ok(response["result"].take()),
+        }
+    }
+}
+
+// Failure of a request to the forked chain
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ForkError {
+    // The transport failed, or the node answered with an error
+    Request { method: String, error: String },
+    // The result isn't a string
+    UnexpectedResult { method: String, result: String },
+    // The result isn't a valid hex quantity or data
+    InvalidHex { method: String, data: String },
+}
+
+impl fmt::Display for ForkError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ForkError::Request { method, error } => write!(f, "{} failed: {}", method, error),
+            ForkError::UnexpectedResult { method, result } => write!(f, "unexpected {} result: {}", method, result),
+            ForkError::InvalidHex { method, data } => write!(f, "invalid {} result: {}", method, data),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct ForkHost {
+    // Transport of the JSON-RPC requests
+    transport: Box<dyn Transport>,
+    // Block at which the chain state is forked
+    block_number: U64,
+    // Accounts fetched so far
+    accounts: Mutex<HashMap<Address, Option<AccountState>>>,
+    // Storage slots fetched so far
+    storage: Mutex<HashMap<(Address, U256), Bytes32>>,
+    // Failed requests, in order
+    errors: Mutex<Vec<ForkError>>,
+}
+
+impl ForkHost {
+    pub fn new(url: &str, block_number: U64) -> Self {
+        Self::with_transport(Box::new(HttpTransport::new(url)), block_number)
+    }
+
+    pub fn with_transport(transport: Box<dyn Transport>, block_number: U64) -> Self {
+        Self {
+            transport,
+            block_number,
+            accounts: Mutex::new(HashMap::new()),
+            storage: Mutex::new(HashMap::new()),
+            errors: Mutex::new(Vec::new()),
+        }
+    }
+
+    // Empty state on top of the forked chain
+    pub fn into_state(self) -> State {
+        State::with_host(Arc::new(self))
+    }
+
+    pub fn block_number(&self) -> U64 {
+        self.block_number
+    }
+
+    // Requests that failed so far. The accounts and slots they were for were
+    // served as missing and zero.
+    pub fn errors(&self) -> Vec<ForkError> {
+        self.errors.lock().unwrap().clone()
+    }
+
+    // Account at `address`, fetched on the first successful call only
+    pub fn try_account(&self, address: &Address) -> Result<Option<AccountState>, ForkError> {
+        let mut accounts = self.accounts.lock().unwrap();
+        if let Some(account) = accounts.get(address) {
+            return Ok(account.clone());
+        }
+        let account = self.fetch_account(address)?;
+        accounts.insert(*address, account.clone());
+        Ok(account)
+    }
+
+    // Storage slot `key` of the account at `address`, fetched on the first
+    // successful call only
+    pub fn try_storage(&self, address: &Address, key: U256) -> Result<Bytes32, ForkError> {
+        let mut storage = self.storage.lock().unwrap();
+        if let Some(value) = storage.get(&(*address, key)) {
+            return Ok(*value);
+        }
+        let value = self.fetch_storage(address, key)?;
+        storage.insert((*address, key), value);
+        Ok(value)
+    }
+
+    fn request(&self, method: &str, mut params: Vec<Value>) -> Result<String, ForkError> {
+        params.push(json!(format!("{:#x}", self.block_number)));
+        match self.transport.request(method, Value::Array(params)) {
+            Ok(Value::String(result)) => Ok(result),
+            Ok(result) => Err(ForkError::UnexpectedResult { method: method.to_string(), result: result.to_string() }),
+            Err(error) => Err(ForkError::Request { method: method.to_string(), error }),
+        }
+    }
+
+    fn fetch_account(&self, address: &Address) -> Result<Option<AccountState>, ForkError> {
+        let params = || vec![json!(format!("0x{}", hex::encode(address.as_slice())))];
+        let balance = parse_u256("eth_getBalance", &self.request("eth_getBalance", params())?)?;
+        let nonce = parse_u256("eth_getTransactionCount", &self.request("eth_getTransactionCount", params())?)?;
+        let code = parse_bytes("eth_getCode", &self.request("eth_getCode", params())?)?;
+
+        if balance.is_zero() && nonce.is_zero() && code.is_empty() {
+            Ok(None)
+        } else {
+            Ok(Some(AccountState::from_parts(*address, balance, nonce, code)))
+        }
+    }
+
+    fn fetch_storage(&self, address: &Address, key: U256) -> Result<Bytes32, ForkError> {
+        let params = vec![
+            json!(format!("0x{}", hex::encode(address.as_slice()))),
+            json!(format!("{:#x}", key)),
+        ];
+        // Nodes return the full word, but some drop its leading zeros
+        let value = parse_u256("eth_getStorageAt", &self.request("eth_getStorageAt", params)?)?;
+        Ok(Bytes32::from_u256(value))
+    }
+
+    fn record(&self, error: ForkError) {
+        warn!(target: "evm::fork", "{}", error);
+        self.errors.lock().unwrap().push(error);
+    }
+}
+
+impl Host for ForkHost {
+    fn account(&self, address: &Address) -> Option<AccountState> {
+        self.try_account(address).unwrap_or_else(|error| {
+            self.record(error);
+            None
+        })
+    }
+
+    fn storage(&self, address: &Address, key: U256) -> Bytes32 {
+        self.try_storage(address, key).unwrap_or_else(|error| {
+            self.record(error);
+            Bytes32::zero()
+        })
+    }
+}
+
+fn parse_u256(method: &str, quantity: &str) -> Result<U256, ForkError> {
+    let digits = quantity.trim_start_matches("0x");
+    U256::from_str_radix(if digits.is_empty() { "0" } else { digits }, 16)
+        .map_err(|_| ForkError::InvalidHex { method: method.to_string(), data: quantity.to_string() })
+}
+
+fn parse_bytes(method: &str, data: &str) -> Result<Bytes, ForkError> {
+    let digits = data.trim_start_matches("0x");
+    hex::decode(digits)
+        .map(Bytes::from_vec)
+        .map_err(|_| ForkError::InvalidHex { method: method.to_string(), data: data.to_string() })
+}
@@ -0,0 +1,86 @@
+use core::ops::Deref;
+
+use crate::prelude::*;
+use crate::types::{Bytes, Bytes32};
+
+// Opcode bytes looked at by the analysis
+const JUMPDEST: u8 = 0x5b;
+const PUSH1: u8 = 0x60;
+const PUSH32: u8 = 0x7f;
+
+// Code of a frame, along with its valid jump destinations: the JUMPDESTs that
+// are instructions, and not part of the immediate of a PUSHn. Shared between
+// the frames running the same code through a `CodeCache`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AnalyzedCode {
+    code: Bytes,
+    // One bit per byte of code
+    jumpdests: Vec<u64>,
+}
+
+impl AnalyzedCode {
+    pub fn new(code: Bytes) -> Self {
+        let mut jumpdests = vec![0u64; code.len().div_ceil(64)];
+        let mut pc = 0;
+        while pc < code.len() {
+            let byte = code[pc];
+            if byte == JUMPDEST {
+                jumpdests[pc / 64] |= 1 << (pc % 64);
+            }
+            // PUSH1 to PUSH32 are followed by 1 to 32 bytes of immediate
+            pc += match byte {
+                PUSH1..=PUSH32 => (byte - PUSH1) as usize + 2,
+                _ => 1,
+            };
+        }
+        Self { code, jumpdests }
+    }
+
+    pub fn is_jumpdest(&self, offset: usize) -> bool {
+        offset < self.code.len() && self.jumpdests[offset / 64] & (1 << (offset % 64)) != 0
+    }
+
+    pub fn bytes(&self) -> &Bytes {
+        &self.code
+    }
+}
+
+impl Deref for AnalyzedCode {
+    type Target = Bytes;
+
+    fn deref(&self) -> &Bytes {
+        &self.code
+    }
+}
+
+// Analyzed code of the accounts called during a transaction, keyed by code
+// hash, so that calling a contract again doesn't copy nor analyze its code
+// again. Code changing (a contract deployed mid-transaction) has another
+// hash, and so another entry. Cheap to clone: the frames share the entries
+// until one of them adds its own.
+#[derive(Debug, Clone, Default)]
+pub struct CodeCache {
+    entries: Arc<HashMap<Bytes32, Arc<AnalyzedCode>>>,
+}
+
+impl CodeCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn get(&self, code_hash: &Bytes32) -> Option<Arc<AnalyzedCode>> {
+        self.entries.get(code_hash).cloned()
+    }
+
+    pub fn insert(&mut self, code_hash: Bytes32, code: Arc<AnalyzedCode>) {
+        Arc::make_mut(&mut self.entries).insert(code_hash, code);
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
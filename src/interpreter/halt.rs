@@ -0,0 +1,80 @@
+use core::fmt;
+
+use crate::state::TransferError;
+use crate::types::{Address, U256};
+
+// Why an execution frame stopped without succeeding
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Halt {
+    // The opcode needs more items than the stack holds
+    StackUnderflow,
+    // The opcode would leave more than 1024 items in the stack
+    StackOverflow,
+    // JUMP or JUMPI to something else than a JUMPDEST
+    InvalidJump,
+    // INVALID, an undefined byte or an opcode of a later fork
+    InvalidOpcode,
+    // State modification inside a STATICCALL
+    StaticCallViolation,
+    // The execution needs more gas than its limit (or a gas cost overflows)
+    OutOfGas,
+    // Memory access out of bounds or past the memory limit
+    MemoryLimitExceeded,
+    // RETURNDATACOPY reading past the end of the return data (EIP-211)
+    ReturnDataOutOfBounds,
+    // The account can't afford to transfer `value`
+    InsufficientBalance { address: Address, balance: U256, value: U256 },
+    // The balance of the recipient of `value` would overflow
+    BalanceOverflow { address: Address, balance: U256, value: U256 },
+    // Explicit REVERT (its data is the result of the execution)
+    Revert,
+    // Creation at an address that already has code or a nonce (EIP-684)
+    CreateCollision,
+    // Creation transaction with init code over the size limit (EIP-3860),
+    // rejected before execution
+    InitCodeSizeLimit,
+    // Transaction whose nonce isn't the one of its sender, rejected before
+    // execution
+    InvalidNonce { expected: U256, actual: U256 },
+    // Transaction with a gas limit over the one of its block, rejected before
+    // execution
+    BlockGasLimitExceeded,
+    // EIP-1559 transaction whose max fee doesn't cover the base fee of its
+    // block, rejected before execution
+    MaxFeeBelowBaseFee { max_fee: U256, base_fee: U256 },
+    // The execution ran the `max_steps` instructions it was allowed
+    StepLimit,
+}
+
+impl Halt {
+    // Exceptional halts consume all the gas of the frame, while a REVERT
+    // leaves the rest to the caller
+    pub fn consumes_all_gas(&self) -> bool {
+        !matches!(self, Halt::Revert)
+    }
+}
+
+impl From<TransferError> for Halt {
+    fn from(error: TransferError) -> Self {
+        match error {
+            TransferError::InsufficientBalance { address, balance, value } => Halt::InsufficientBalance { address, balance, value },
+            TransferError::BalanceOverflow { address, balance, value } => Halt::BalanceOverflow { address, balance, value },
+        }
+    }
+}
+
+impl fmt::Display for Halt {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Halt::InsufficientBalance { address, balance, value } => {
+                write!(f, "InsufficientBalance({:#X}): {:#X} < {:#X}", address, balance, value)
+            }
+            Halt::BalanceOverflow { address, balance, value } => {
+                write!(f, "BalanceOverflow({:#X}): {:#X} + {:#X}", address, balance, value)
+            }
+            Halt::InvalidNonce { expected, actual } => write!(f, "InvalidNonce: expected {}, got {}", expected, actual),
+            Halt::MaxFeeBelowBaseFee { max_fee, base_fee } => write!(f, "MaxFeeBelowBaseFee: {} < {}", max_fee, base_fee),
+            halt => write!(f, "{:?}", halt),
+        }
+    }
+}
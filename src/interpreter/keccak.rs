@@ -0,0 +1,58 @@
+use sha3::{Digest, Keccak256};
+
+use crate::prelude::*;
+use crate::types::Bytes32;
+
+// Hashes kept by a `KeccakCache`
+pub const KECCAK_CACHE_SIZE: usize = 64;
+// Longer inputs are hashed without going through the cache, which would hold
+// a copy of each
+pub const KECCAK_CACHE_MAX_INPUT: usize = 1024;
+
+// Least-recently-used cache of the results of SHA3, keyed by the hashed bytes
+// (not by where they are in memory). Enabled with `CfgEnv::keccak_cache`, it
+// lives as long as the transaction and is shared by all its frames.
+#[derive(Debug, Clone, Default)]
+pub struct KeccakCache {
+    // Hash of each input, and when it was last used
+    entries: HashMap<Vec<u8>, (Bytes32, u64)>,
+    clock: u64,
+}
+
+impl KeccakCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn hash(&mut self, data: &[u8]) -> Bytes32 {
+        if data.len() > KECCAK_CACHE_MAX_INPUT {
+            return keccak256(data);
+        }
+        self.clock += 1;
+        if let Some((hash, used)) = self.entries.get_mut(data) {
+            *used = self.clock;
+            return *hash;
+        }
+        if self.entries.len() == KECCAK_CACHE_SIZE {
+            let oldest = self.entries.iter().min_by_key(|(_, (_, used))| *used).map(|(input, _)| input.clone());
+            if let Some(oldest) = oldest {
+                self.entries.remove(&oldest);
+            }
+        }
+        let hash = keccak256(data);
+        self.entries.insert(data.to_vec(), (hash, self.clock));
+        hash
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+pub fn keccak256(data: &[u8]) -> Bytes32 {
+    Bytes32::from_slice(Keccak256::digest(data).as_slice())
+}
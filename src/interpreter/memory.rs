@@ -1,48 +1,139 @@
-use crate::types::Bytes;
+use crate::types::{Bytes, Bytes32};
 
-// EVM Memory. A byte-addressable array of bytes.
+// EVM Memory. A byte-addressable array of bytes, which grows in 32-byte words
+// to cover the highest offset accessed (read or written).
 #[derive(Debug, Default, Clone)]
-pub struct Memory(Bytes);
+pub struct Memory {
+    data: Bytes,
+    // Active size, in 32-byte words
+    words: usize,
+}
 
 impl Memory {
     pub fn new() -> Self {
-        Self(Bytes::new())
+        Self {
+            data: Bytes::new(),
+            words: 0,
+        }
     }
 
     pub fn len(&self) -> usize {
-        self.0.len()
+        self.data.len()
     }
 
     pub fn is_empty(&self) -> bool {
-        self.0.len() == 0
+        self.words == 0
     }
 
+    // Active size in bytes (always a multiple of 32), as reported by MSIZE
     pub fn size(&self) -> usize {
-        ((self.len() + 31) / 32) * 32
+        self.words * 32
+    }
+
+    // Words added to the memory by an access of `size` bytes from `offset`
+    // (0 if it is already covered). Zero-length accesses never expand it,
+    // whatever the offset.
+    pub fn expansion_words(&self, offset: usize, size: usize) -> usize {
+        words_needed(offset, size).saturating_sub(self.words)
+    }
+
+    // Gas cost of expanding the memory to cover `size` bytes from `offset`
+    pub fn expansion_cost(&self, offset: usize, size: usize) -> usize {
+        let words = words_needed(offset, size);
+        if words <= self.words {
+            return 0;
+        }
+        memory_cost(words) - memory_cost(self.words)
+    }
+
+    // Like `expansion_cost`, but `None` if the cost overflows (with a memory
+    // limit raised close to usize::MAX)
+    pub fn checked_expansion_cost(&self, offset: usize, size: usize) -> Option<usize> {
+        let words = words_needed(offset, size);
+        if words <= self.words {
+            return Some(0);
+        }
+        Some(checked_memory_cost(words)? - memory_cost(self.words))
     }
 
-    pub fn expansion(&self, offset: usize, size: usize) -> usize {
-        if offset + size > self.len() {
-            offset + size - self.len()
-        } else {
-            0
+    // Grows the active size to cover `size` bytes from `offset` (in whole
+    // words). Returns the gas cost of the expansion, for the caller to charge.
+    #[must_use = "the expansion cost must be charged"]
+    pub fn expand(&mut self, offset: usize, size: usize) -> usize {
+        let cost = self.expansion_cost(offset, size);
+        let words = words_needed(offset, size);
+        if words > self.words {
+            self.words = words;
+            self.data.resize(words * 32, 0);
         }
+        cost
     }
 
+    // Exactly `size` bytes from `offset`. Reading past the active size grows it
+    // like a write would (the new words read as zeros), so it shows in MSIZE.
     pub fn load(&mut self, offset: usize, size: usize) -> Bytes {
-        // if out of bounds, expand the memory
-        if offset + size > self.0.len() {
-            self.0.resize(offset + size, 0);
+        if size == 0 {
+            return Bytes::new();
         }
-        Bytes::from_slice(&self.0[offset..offset + size])
+        // Reads are charged by the opcodes, before reading
+        let _ = self.expand(offset, size);
+        Bytes::from_slice(&self.data[offset..offset + size])
     }
 
-    pub fn store(&mut self, offset: usize, data: Bytes) {
-        // if out of bounds, expand the memory
-        let end = offset + data.len();
-        if end > self.len() {
-            self.0.resize(((end + 31) / 32) * 32, 0);
+    // Writes `data` from `offset`. Returns the gas cost of the expansion.
+    #[must_use = "the expansion cost must be charged"]
+    pub fn store(&mut self, offset: usize, data: Bytes) -> usize {
+        if data.is_empty() {
+            return 0;
         }
-        self.0[offset..offset + data.len()].copy_from_slice(data.as_slice());
+        let cost = self.expand(offset, data.len());
+        self.data[offset..offset + data.len()].copy_from_slice(data.as_slice());
+        cost
+    }
+
+    // Word-level accesses (MLOAD, MSTORE, MSTORE8), copying straight between
+    // the word and the memory without an intermediate `Bytes`
+
+    // MLOAD. Grows the memory like `load`.
+    pub fn get_word(&mut self, offset: usize) -> Bytes32 {
+        // Reads are charged by the opcodes, before reading
+        let _ = self.expand(offset, 32);
+        let mut word = [0u8; 32];
+        word.copy_from_slice(&self.data[offset..offset + 32]);
+        Bytes32::from(word)
+    }
+
+    // MSTORE
+    #[must_use = "the expansion cost must be charged"]
+    pub fn set_word(&mut self, offset: usize, word: &Bytes32) -> usize {
+        let cost = self.expand(offset, 32);
+        self.data[offset..offset + 32].copy_from_slice(word.as_slice());
+        cost
+    }
+
+    // MSTORE8: a single byte, which still grows the memory by a whole word
+    #[must_use = "the expansion cost must be charged"]
+    pub fn set_byte(&mut self, offset: usize, byte: u8) -> usize {
+        let cost = self.expand(offset, 1);
+        self.data[offset] = byte;
+        cost
+    }
+}
+
+// Words needed to cover `size` bytes from `offset` (none if `size` is zero)
+fn words_needed(offset: usize, size: usize) -> usize {
+    if size == 0 {
+        0
+    } else {
+        (offset + size).div_ceil(32)
     }
 }
+
+// Gas cost of a memory of `words` 32-byte words
+fn memory_cost(words: usize) -> usize {
+    3 * words + words * words / 512
+}
+
+fn checked_memory_cost(words: usize) -> Option<usize> {
+    (3 * words).checked_add(words.checked_mul(words)? / 512)
+}
@@ -0,0 +1,105 @@
+use core::fmt;
+
+use crate::prelude::*;
+use crate::interpreter::Opcode;
+
+// Opcode-level profile of an execution, collected by the `ExecutionContext`
+// when enabled with `with_metrics` (otherwise it costs a single check per
+// opcode). Subcalls are included.
+#[derive(Debug, Clone)]
+pub struct Metrics {
+    // Executions and gas per opcode byte (undefined bytes count as INVALID).
+    // The gas of the calls and creations includes the gas of their subcalls.
+    counts: [u64; 256],
+    gas: [u64; 256],
+    // Deepest call (0 for the top-level frame)
+    pub max_call_depth: usize,
+    // Largest memory of a frame, in bytes
+    pub max_memory_bytes: usize,
+    // SLOADs and SSTOREs
+    pub storage_reads: u64,
+    pub storage_writes: u64,
+}
+
+impl Default for Metrics {
+    fn default() -> Self {
+        Self {
+            counts: [0; 256],
+            gas: [0; 256],
+            max_call_depth: 0,
+            max_memory_bytes: 0,
+            storage_reads: 0,
+            storage_writes: 0,
+        }
+    }
+}
+
+impl Metrics {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Records an execution of `opcode` (read from `byte`) costing `gas`, which
+    // left the memory of the frame at `memory` bytes
+    pub fn record(&mut self, byte: u8, opcode: Opcode, gas: usize, memory: usize, depth: usize) {
+        let slot = match opcode {
+            Opcode::INVALID => 0xfe,
+            _ => byte,
+        } as usize;
+        self.counts[slot] += 1;
+        self.gas[slot] += gas as u64;
+        self.max_call_depth = self.max_call_depth.max(depth);
+        self.max_memory_bytes = self.max_memory_bytes.max(memory);
+        match opcode {
+            Opcode::SLOAD => self.storage_reads += 1,
+            Opcode::SSTORE => self.storage_writes += 1,
+            _ => {}
+        }
+    }
+
+    // Adds the metrics of a subcall
+    pub fn merge(&mut self, other: &Metrics) {
+        for slot in 0..256 {
+            self.counts[slot] += other.counts[slot];
+            self.gas[slot] += other.gas[slot];
+        }
+        self.max_call_depth = self.max_call_depth.max(other.max_call_depth);
+        self.max_memory_bytes = self.max_memory_bytes.max(other.max_memory_bytes);
+        self.storage_reads += other.storage_reads;
+        self.storage_writes += other.storage_writes;
+    }
+
+    pub fn count(&self, opcode: Opcode) -> u64 {
+        self.counts[opcode.byte() as usize]
+    }
+
+    pub fn gas(&self, opcode: Opcode) -> u64 {
+        self.gas[opcode.byte() as usize]
+    }
+
+    // Executed opcodes with their count and gas, the most expensive first (and
+    // by byte among equals)
+    pub fn report(&self) -> Vec<(Opcode, u64, u64)> {
+        let mut report: Vec<(Opcode, u64, u64)> = (0..=u8::MAX)
+            .filter(|byte| self.counts[*byte as usize] > 0)
+            .filter_map(|byte| {
+                let opcode = Opcode::try_from(byte).ok()?;
+                Some((opcode, self.counts[byte as usize], self.gas[byte as usize]))
+            })
+            .collect();
+        report.sort_by_key(|(_, _, gas)| core::cmp::Reverse(*gas));
+        report
+    }
+}
+
+impl fmt::Display for Metrics {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "{:<16}{:>12}{:>14}", "opcode", "count", "gas")?;
+        for (opcode, count, gas) in self.report() {
+            writeln!(f, "{:<16}{:>12}{:>14}", format!("{:?}", opcode), count, gas)?;
+        }
+        writeln!(f, "max call depth: {}", self.max_call_depth)?;
+        writeln!(f, "max memory: {} bytes", self.max_memory_bytes)?;
+        write!(f, "storage reads: {}, writes: {}", self.storage_reads, self.storage_writes)
+    }
+}
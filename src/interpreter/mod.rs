@@ -1,7 +1,19 @@
 pub mod opcode;
 pub mod memory;
 pub mod stack;
+pub mod halt;
+pub mod metrics;
+pub mod tracer;
+pub mod keccak;
+pub mod analyzed_code;
+pub mod result;
 
 pub use crate::opcode::*;
 pub use crate::memory::*;
-pub use crate::stack::*;
\ No newline at end of file
+pub use crate::stack::*;
+pub use crate::halt::*;
+pub use crate::metrics::*;
+pub use crate::tracer::*;
+pub use crate::keccak::*;
+pub use crate::analyzed_code::*;
+pub use crate::result::*;
@@ -1,15 +1,21 @@
-use std::convert::TryFrom;
-use sha3::{Digest, Keccak256};
-use std::ops::{BitAnd, BitOr, BitXor, Not, Shl, Shr};
+use core::convert::TryFrom;
+use log::trace;
+use core::ops::{BitAnd, BitOr, BitXor, Not, Shl, Shr};
 
-use crate::types::{Bytes, Bytes32, Address, U256};
-use crate::utils::rlp_encode;
+use crate::prelude::*;
+use crate::types::{Address, Bytes, Bytes32, U256};
+use crate::utils::{create2_address, create_address};
 use crate::env::Call;
 use crate::logs::Log;
+use crate::spec::SpecId;
+use crate::halt::Halt;
+use crate::tracer::CallKind;
+use crate::keccak::keccak256;
+use crate::analyzed_code::AnalyzedCode;
 
 use super::super::ExecutionContext;
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Opcode {
     STOP,
     ADD,
@@ -63,6 +69,8 @@ pub enum Opcode {
     CHAINID,
     SELFBALANCE,
     BASEFEE,
+    BLOBHASH,
+    BLOBBASEFEE,
     POP,
     MLOAD,
     MSTORE,
@@ -75,6 +83,7 @@ pub enum Opcode {
     MSIZE,
     GAS,
     JUMPDEST,
+    PUSH0,
     PUSH1,
     PUSH2,
     PUSH3,
@@ -213,6 +222,8 @@ impl TryFrom<u8> for Opcode {
             0x46 => Ok(Opcode::CHAINID),
             0x47 => Ok(Opcode::SELFBALANCE),
             0x48 => Ok(Opcode::BASEFEE),
+            0x49 => Ok(Opcode::BLOBHASH),
+            0x4A => Ok(Opcode::BLOBBASEFEE),
             0x50 => Ok(Opcode::POP),
             0x51 => Ok(Opcode::MLOAD),
             0x52 => Ok(Opcode::MSTORE),
@@ -225,6 +236,7 @@ impl TryFrom<u8> for Opcode {
             0x59 => Ok(Opcode::MSIZE),
             0x5A => Ok(Opcode::GAS),
             0x5B => Ok(Opcode::JUMPDEST),
+            0x5F => Ok(Opcode::PUSH0),
             0x60 => Ok(Opcode::PUSH1),
             0x61 => Ok(Opcode::PUSH2),
             0x62 => Ok(Opcode::PUSH3),
@@ -311,24 +323,141 @@ impl TryFrom<u8> for Opcode {
 }
 
 impl Opcode {
-    pub fn execute(&self, ctx: &mut ExecutionContext) -> bool {
+    // Decodes an opcode under the rules of `spec`. Unassigned bytes and the
+    // opcodes introduced by a later fork decode as INVALID.
+    pub fn parse(byte: u8, spec: SpecId) -> Opcode {
+        match Opcode::try_from(byte) {
+            Ok(opcode) if spec.is_enabled_in(opcode.since()) => opcode,
+            _ => Opcode::INVALID,
+        }
+    }
+
+    // Byte encoding of the opcode
+    pub fn byte(&self) -> u8 {
+        (0..=u8::MAX)
+            .find(|byte| Opcode::try_from(*byte).is_ok_and(|opcode| opcode == *self))
+            .unwrap()
+    }
+
+    // Number of immediate bytes following the opcode (only PUSHn has any)
+    pub fn immediate_size(&self) -> usize {
+        match self.byte() {
+            byte @ 0x60..=0x7F => (byte - 0x5F) as usize,
+            _ => 0,
+        }
+    }
+
+    // Fork that introduced the opcode
+    pub fn since(&self) -> SpecId {
+        match self {
+            Opcode::DELEGATECALL => SpecId::Homestead,
+            Opcode::RETURNDATASIZE | Opcode::RETURNDATACOPY | Opcode::STATICCALL | Opcode::REVERT => SpecId::Byzantium,
+            Opcode::SHL | Opcode::SHR | Opcode::SAR | Opcode::EXTCODEHASH | Opcode::CREATE2 => SpecId::Constantinople,
+            Opcode::CHAINID | Opcode::SELFBALANCE => SpecId::Istanbul,
+            Opcode::BASEFEE => SpecId::London,
+            Opcode::PUSH0 => SpecId::Shanghai,
+            Opcode::BLOBHASH | Opcode::BLOBBASEFEE => SpecId::Cancun,
+            _ => SpecId::Frontier,
+        }
+    }
+
+    // Name of the opcode in `spec`, for the opcodes renamed by a fork: 0x44 is
+    // DIFFICULTY before the Merge and PREVRANDAO after it (EIP-4399)
+    pub fn name(&self, spec: SpecId) -> String {
+        match self {
+            Opcode::PREVRANDAO if spec < SpecId::Merge => "DIFFICULTY".to_string(),
+            _ => format!("{:?}", self),
+        }
+    }
+
+    // Number of items the opcode pops from and pushes to the stack
+    pub fn stack_io(&self) -> (usize, usize) {
+        match self {
+            Opcode::STOP | Opcode::JUMPDEST | Opcode::INVALID => (0, 0),
+            Opcode::ADD | Opcode::MUL | Opcode::SUB | Opcode::DIV | Opcode::SDIV | Opcode::MOD
+                | Opcode::SMOD | Opcode::EXP | Opcode::SIGNEXTEND | Opcode::LT | Opcode::GT | Opcode::SLT
+                | Opcode::SGT | Opcode::EQ | Opcode::AND | Opcode::OR | Opcode::XOR | Opcode::BYTE
+                | Opcode::SHL | Opcode::SHR | Opcode::SAR | Opcode::SHA3 => (2, 1),
+            Opcode::ADDMOD | Opcode::MULMOD => (3, 1),
+            Opcode::ISZERO | Opcode::NOT | Opcode::BALANCE | Opcode::CALLDATALOAD | Opcode::EXTCODESIZE | Opcode::EXTCODEHASH
+                | Opcode::BLOCKHASH | Opcode::BLOBHASH | Opcode::MLOAD | Opcode::SLOAD => (1, 1),
+            Opcode::ADDRESS | Opcode::ORIGIN | Opcode::CALLER | Opcode::CALLVALUE | Opcode::CALLDATASIZE | Opcode::CODESIZE
+                | Opcode::GASPRICE | Opcode::RETURNDATASIZE | Opcode::COINBASE | Opcode::TIMESTAMP | Opcode::NUMBER | Opcode::PREVRANDAO
+                | Opcode::GASLIMIT | Opcode::CHAINID | Opcode::SELFBALANCE | Opcode::BASEFEE | Opcode::BLOBBASEFEE | Opcode::PC
+                | Opcode::MSIZE
+                | Opcode::GAS | Opcode::PUSH0 | Opcode::PUSH1 | Opcode::PUSH2 | Opcode::PUSH3 | Opcode::PUSH4
+                | Opcode::PUSH5 | Opcode::PUSH6 | Opcode::PUSH7 | Opcode::PUSH8 | Opcode::PUSH9 | Opcode::PUSH10
+                | Opcode::PUSH11 | Opcode::PUSH12 | Opcode::PUSH13 | Opcode::PUSH14 | Opcode::PUSH15 | Opcode::PUSH16
+                | Opcode::PUSH17 | Opcode::PUSH18 | Opcode::PUSH19 | Opcode::PUSH20 | Opcode::PUSH21 | Opcode::PUSH22
+                | Opcode::PUSH23 | Opcode::PUSH24 | Opcode::PUSH25 | Opcode::PUSH26 | Opcode::PUSH27 | Opcode::PUSH28
+                | Opcode::PUSH29 | Opcode::PUSH30 | Opcode::PUSH31 | Opcode::PUSH32 => (0, 1),
+            Opcode::POP | Opcode::JUMP | Opcode::SELFDESTRUCT => (1, 0),
+            Opcode::MSTORE | Opcode::MSTORE8 | Opcode::SSTORE | Opcode::JUMPI | Opcode::RETURN | Opcode::REVERT => (2, 0),
+            Opcode::CALLDATACOPY | Opcode::CODECOPY | Opcode::RETURNDATACOPY => (3, 0),
+            Opcode::EXTCODECOPY => (4, 0),
+            Opcode::DUP1 => (1, 2),
+            Opcode::DUP2 => (2, 3),
+            Opcode::DUP3 => (3, 4),
+            Opcode::DUP4 => (4, 5),
+            Opcode::DUP5 => (5, 6),
+            Opcode::DUP6 => (6, 7),
+            Opcode::DUP7 => (7, 8),
+            Opcode::DUP8 => (8, 9),
+            Opcode::DUP9 => (9, 10),
+            Opcode::DUP10 => (10, 11),
+            Opcode::DUP11 => (11, 12),
+            Opcode::DUP12 => (12, 13),
+            Opcode::DUP13 => (13, 14),
+            Opcode::DUP14 => (14, 15),
+            Opcode::DUP15 => (15, 16),
+            Opcode::DUP16 => (16, 17),
+            Opcode::SWAP1 => (2, 2),
+            Opcode::SWAP2 => (3, 3),
+            Opcode::SWAP3 => (4, 4),
+            Opcode::SWAP4 => (5, 5),
+            Opcode::SWAP5 => (6, 6),
+            Opcode::SWAP6 => (7, 7),
+            Opcode::SWAP7 => (8, 8),
+            Opcode::SWAP8 => (9, 9),
+            Opcode::SWAP9 => (10, 10),
+            Opcode::SWAP10 => (11, 11),
+            Opcode::SWAP11 => (12, 12),
+            Opcode::SWAP12 => (13, 13),
+            Opcode::SWAP13 => (14, 14),
+            Opcode::SWAP14 => (15, 15),
+            Opcode::SWAP15 => (16, 16),
+            Opcode::SWAP16 => (17, 17),
+            Opcode::LOG0 => (2, 0),
+            Opcode::LOG1 => (3, 0),
+            Opcode::LOG2 => (4, 0),
+            Opcode::LOG3 => (5, 0),
+            Opcode::LOG4 => (6, 0),
+            Opcode::CREATE => (3, 1),
+            Opcode::CREATE2 => (4, 1),
+            Opcode::CALL | Opcode::CALLCODE => (7, 1),
+            Opcode::DELEGATECALL | Opcode::STATICCALL => (6, 1),
+        }
+    }
+
+    pub fn execute(&self, ctx: &mut ExecutionContext) -> Result<(), Halt> {
+        // GAS (the fixed part, the dynamic one is charged by each opcode and
+        // checked once it is done)
+        ctx.gas += self.fix_gas(ctx.env.spec);
+        ctx.check_gas()?;
+
         match self {
             Opcode::STOP => {
-                // GAS
-                ctx.gas += self.fix_gas();
                 // OPERATION
                 ctx.stopped = true;
                 // PC
                 ctx.pc += 1;
                 // SUCCESS
-                true
+                Ok(())
             },
             Opcode::ADD => {
                 // STACK
                 let a = ctx.stack.pop().to_u256();
                 let b = ctx.stack.pop().to_u256();
-                // GAS
-                ctx.gas += self.fix_gas();
                 // OPERATION
                 // rely on U256 overflowing_add to handle overflow
                 let (result, _) = a.overflowing_add(b);
@@ -336,14 +465,12 @@ impl Opcode {
                 // PC
                 ctx.pc += 1;
                 // SUCCESS
-                true
+                Ok(())
             },
             Opcode::MUL => {
                 // STACK
                 let a = ctx.stack.pop().to_u256();
                 let b = ctx.stack.pop().to_u256();
-                // GAS
-                ctx.gas += self.fix_gas();
                 // OPERATION
                 // rely on U256 overflowing_mul to handle overflow
                 let (result, _) = a.overflowing_mul(b);
@@ -351,14 +478,12 @@ impl Opcode {
                 // PC
                 ctx.pc += 1;
                 // SUCCESS
-                true
+                Ok(())
             },
             Opcode::SUB => {
                 // STACK
                 let a = ctx.stack.pop().to_u256();
                 let b = ctx.stack.pop().to_u256();
-                // GAS
-                ctx.gas += self.fix_gas();
                 // OPERATION
                 // rely on U256 overflowing_sub to handle underflow
                 let (result, _) = a.overflowing_sub(b);
@@ -366,31 +491,31 @@ impl Opcode {
                 // PC
                 ctx.pc += 1;
                 // SUCCESS
-                true
+                Ok(())
             },
             Opcode::DIV => {
                 // STACK
                 let a = ctx.stack.pop().to_u256();
                 let b = ctx.stack.pop().to_u256();
-                // GAS
-                ctx.gas += self.fix_gas();
                 // OPERATION
                 let result = if b.is_zero() { U256::zero() } else { a / b };
                 ctx.stack.push_u256(result);
                 // PC
                 ctx.pc += 1;
                 // SUCCESS
-                true
+                Ok(())
             },
             Opcode::SDIV => {
                 // STACK
                 let a = ctx.stack.pop().to_u256();
                 let b = ctx.stack.pop().to_u256();
-                // GAS
-                ctx.gas += self.fix_gas();
                 // OPERATION
+                let int256_min = U256::one() << 255;
                 let result = if b.is_zero() {
                     U256::zero()
+                } else if a == int256_min && b == U256::MAX {
+                    // -2^255 / -1: the only quotient that doesn't fit, which wraps
+                    int256_min
                 } else {
                     let (a_neg, a_twos) = (a.bit(255), a.not().overflowing_add(U256::one()).0);
                     let (b_neg, b_twos) = (b.bit(255), b.not().overflowing_add(U256::one()).0);
@@ -405,28 +530,24 @@ impl Opcode {
                 // PC
                 ctx.pc += 1;
                 // SUCCESS
-                true
+                Ok(())
             },
             Opcode::MOD => {
                 // STACK
                 let a = ctx.stack.pop().to_u256();
                 let b = ctx.stack.pop().to_u256();
-                // GAS
-                ctx.gas += self.fix_gas();
                 // OPERATION
                 let result = if b.is_zero() { U256::zero() } else { a % b };
                 ctx.stack.push_u256(result);
                 // PC
                 ctx.pc += 1;
                 // SUCCESS
-                true
+                Ok(())
             },
             Opcode::SMOD => {
                 // STACK
                 let a = ctx.stack.pop().to_u256();
                 let b = ctx.stack.pop().to_u256();
-                // GAS
-                ctx.gas += self.fix_gas();
                 // OPERATION
                 let result = if b.is_zero() {
                     U256::zero()
@@ -434,7 +555,8 @@ impl Opcode {
                     let (a_neg, a_twos) = (a.bit(255), a.not().overflowing_add(U256::one()).0);
                     let (b_neg, b_twos) = (b.bit(255), b.not().overflowing_add(U256::one()).0);
                     let div = if a_neg { a_twos } else { a } % if b_neg { b_twos } else { b };
-                    if a_neg | b_neg {
+                    // The remainder takes the sign of the dividend
+                    if a_neg {
                         div.not().overflowing_add(U256::one()).0
                     } else {
                         div
@@ -444,15 +566,13 @@ impl Opcode {
                 // PC
                 ctx.pc += 1;
                 // SUCCESS
-                true
+                Ok(())
             },
             Opcode::ADDMOD => {
                 // STACK
                 let a = ctx.stack.pop().to_u512();
                 let b = ctx.stack.pop().to_u512();
                 let c = ctx.stack.pop().to_u256();
-                // GAS
-                ctx.gas += self.fix_gas();
                 // OPERATION
                 let result = if c.is_zero() {
                     U256::zero()
@@ -463,15 +583,13 @@ impl Opcode {
                 // PC
                 ctx.pc += 1;
                 // SUCCESS
-                true
+                Ok(())
             },
             Opcode::MULMOD => {
                 // STACK
                 let a = ctx.stack.pop().to_u512();
                 let b = ctx.stack.pop().to_u512();
                 let c = ctx.stack.pop().to_u256();
-                // GAS
-                ctx.gas += self.fix_gas();
                 // OPERATION
                 let result = if c.is_zero() {
                     U256::zero()
@@ -482,37 +600,37 @@ impl Opcode {
                 // PC
                 ctx.pc += 1;
                 // SUCCESS
-                true
+                Ok(())
             },
             Opcode::EXP => {
                 // STACK
                 let a = ctx.stack.pop().to_u256();
                 let b = ctx.stack.pop().to_u256();
                 // GAS
-                let var_gas = if b != U256::zero() {
-                    50 * (b.bits() + 7)
-                } else {
-                    0
-                };
-                ctx.gas += self.fix_gas() + var_gas;
+                ctx.gas += exp_gas(ctx.env.spec, b);
                 // OPERATION
                 let (result, _) = a.overflowing_pow(b);
                 ctx.stack.push_u256(result);
                 // PC
                 ctx.pc += 1;
                 // SUCCESS
-                true
+                Ok(())
             },
             Opcode::SIGNEXTEND => {
                 // STACK
-                let exp = ctx.stack.pop().as_usize();
+                let index = saturated_index(ctx.stack.pop());
                 let num = ctx.stack.pop().to_u256();
-                // GAS
-                ctx.gas += self.fix_gas();
                 // OPERATION
-                let id = (exp + 1) * 8;
-                let result = if num.bit(id - 1) {
-                    U256::MAX.shl(id).bitor(num)
+                // Extends the sign bit of the byte `index` (counting from the
+                // right), which leaves the word as is from the 32nd byte
+                let result = if index < 31 {
+                    let sign_bit = index * 8 + 7;
+                    let mask = U256::one().shl(sign_bit + 1) - 1;
+                    if num.bit(sign_bit) {
+                        num.bitor(mask.not())
+                    } else {
+                        num.bitand(mask)
+                    }
                 } else {
                     num
                 };
@@ -520,46 +638,40 @@ impl Opcode {
                 // PC
                 ctx.pc += 1;
                 // SUCCESS
-                true
+                Ok(())
             },
             Opcode::LT => {
                 // STACK
                 let a = ctx.stack.pop().to_u256();
                 let b = ctx.stack.pop().to_u256();
-                // GAS
-                ctx.gas += self.fix_gas();
                 // OPERATION
                 let result = if a < b { U256::one() } else { U256::zero() };
                 ctx.stack.push_u256(result);
                 // PC
                 ctx.pc += 1;
                 // SUCCESS
-                true
+                Ok(())
             },
             Opcode::GT => {
                 // STACK
                 let a = ctx.stack.pop().to_u256();
                 let b = ctx.stack.pop().to_u256();
-                // GAS
-                ctx.gas += self.fix_gas();
                 // OPERATION
                 let result = if a > b { U256::one() } else { U256::zero() };
                 ctx.stack.push_u256(result);
                 // PC
                 ctx.pc += 1;
                 // SUCCESS
-                true
+                Ok(())
             },
             Opcode::SLT => {
                 // STACK
                 let a = ctx.stack.pop().to_u256();
                 let b = ctx.stack.pop().to_u256();
-                // GAS
-                ctx.gas += self.fix_gas();
                 // OPERATION
-                let (a_twos, _) = a.not().overflowing_add(U256::one());
-                let (b_twos, _) = b.not().overflowing_add(U256::one());
-                let result = if a_twos > b_twos {
+                // Flipping the sign bit maps the signed order to the unsigned one
+                let sign = U256::one().shl(255);
+                let result = if a.bitxor(sign) < b.bitxor(sign) {
                     U256::one()
                 } else {
                     U256::zero()
@@ -568,18 +680,16 @@ impl Opcode {
                 // PC
                 ctx.pc += 1;
                 // SUCCESS
-                true
+                Ok(())
             },
             Opcode::SGT => {
                 // STACK
                 let a = ctx.stack.pop().to_u256();
                 let b = ctx.stack.pop().to_u256();
-                // GAS
-                ctx.gas += self.fix_gas();
                 // OPERATION
-                let (a_twos, _) = a.not().overflowing_add(U256::one());
-                let (b_twos, _) = b.not().overflowing_add(U256::one());
-                let result = if a_twos < b_twos {
+                // Flipping the sign bit maps the signed order to the unsigned one
+                let sign = U256::one().shl(255);
+                let result = if a.bitxor(sign) > b.bitxor(sign) {
                     U256::one()
                 } else {
                     U256::zero()
@@ -588,14 +698,12 @@ impl Opcode {
                 // PC
                 ctx.pc += 1;
                 // SUCCESS
-                true
+                Ok(())
             },
             Opcode::EQ => {
                 // STACK
                 let a = ctx.stack.pop();
                 let b = ctx.stack.pop();
-                // GAS
-                ctx.gas += self.fix_gas();
                 // OPERATION
                 ctx.stack.push(if a == b {
                     Bytes32::one()
@@ -605,7 +713,7 @@ impl Opcode {
                 // PC
                 ctx.pc += 1;
                 // SUCCESS
-                true
+                Ok(())
             },
             Opcode::ISZERO => {
                 // STACK
@@ -618,125 +726,95 @@ impl Opcode {
                 // PC
                 ctx.pc += 1;
                 // SUCCESS
-                true
+                Ok(())
             },
             Opcode::AND => {
                 // STACK
                 let a = ctx.stack.pop();
                 let b = ctx.stack.pop();
-                // GAS
-                ctx.gas += self.fix_gas();
                 // OPERATION
                 let result = a.bitand(b);
                 ctx.stack.push(result);
                 // PC
                 ctx.pc += 1;
                 // SUCCESS
-                true
+                Ok(())
             },
             Opcode::OR => {
                 // STACK
                 let a = ctx.stack.pop();
                 let b = ctx.stack.pop();
-                // GAS
-                ctx.gas += self.fix_gas();
                 // OPERATION
                 let result = a.bitor(b);
                 ctx.stack.push(result);
                 // PC
                 ctx.pc += 1;
                 // SUCCESS
-                true
+                Ok(())
             },
             Opcode::XOR => {
                 // STACK
                 let a = ctx.stack.pop();
                 let b = ctx.stack.pop();
-                // GAS
-                ctx.gas += self.fix_gas();
                 // OPERATION
                 let result = a.bitxor(b);
                 ctx.stack.push(result);
                 // PC
                 ctx.pc += 1;
                 // SUCCESS
-                true
+                Ok(())
             },
             Opcode::NOT => {
                 // STACK
                 let a = ctx.stack.pop();
-                // GAS
-                ctx.gas += self.fix_gas();
                 // OPERATION
                 let result = a.not();
                 ctx.stack.push(result);
                 // PC
                 ctx.pc += 1;
                 // SUCCESS
-                true
+                Ok(())
             },
             Opcode::BYTE => {
                 // STACK
-                let index = ctx.stack.pop().as_usize();
+                let index = saturated_index(ctx.stack.pop());
                 let word = ctx.stack.pop();
-                // GAS
-                ctx.gas += self.fix_gas();
                 // OPERATION
-                let result = Bytes32::from_vec(vec![word.get_byte(index)]);
+                let result = Bytes32::from_slice(&[word.get_byte(index)]);
                 ctx.stack.push(result);
                 // PC
                 ctx.pc += 1;
                 // SUCCESS
-                true
+                Ok(())
             },
             Opcode::SHL => {
                 // STACK
-                // let index = ctx.stack.pop().as_usize();
-                let index = ctx.stack.pop();
-                println!("index.as_usize(): {:#X}", index.as_usize());
-                println!("index.to_u256(): {:#X}", index.to_u256());
-                println!(
-                    "index.to_u256.as_usize(): {:#X}",
-                    index.to_u256().as_usize()
-                );
+                let index = saturated_index(ctx.stack.pop());
                 let word = ctx.stack.pop().to_u256();
-                // GAS
-                ctx.gas += self.fix_gas();
                 // OPERATION
-                let result = word.shl(index.as_usize());
+                let result = if index > 255 { U256::zero() } else { word.shl(index) };
                 ctx.stack.push_u256(result);
                 // PC
                 ctx.pc += 1;
                 // SUCCESS
-                true
+                Ok(())
             },
             Opcode::SHR => {
                 // STACK
-                // let index = ctx.stack.pop().as_usize();
-                let index = ctx.stack.pop();
-                println!("index.as_usize(): {:#X}", index.as_usize());
-                println!("index.to_u256(): {:#X}", index.to_u256());
-                println!(
-                    "index.to_u256.as_usize(): {:#X}",
-                    index.to_u256().as_usize()
-                );
+                let index = saturated_index(ctx.stack.pop());
                 let word = ctx.stack.pop().to_u256();
-                // GAS
-                ctx.gas += self.fix_gas();
                 // OPERATION
-                let result = word.shr(index.as_usize());
+                let result = if index > 255 { U256::zero() } else { word.shr(index) };
                 ctx.stack.push_u256(result);
                 // PC
                 ctx.pc += 1;
                 // SUCCESS
-                true
+                Ok(())
             },
             Opcode::SAR => {
                 // STACK
-                let index = ctx.stack.pop().as_usize();
+                let index = saturated_index(ctx.stack.pop());
                 let word = ctx.stack.pop().to_u256();
-                // GAS
-                ctx.gas += self.fix_gas();
                 // OPERATION
                 let result = if word.bit(255) {
                     if index > 255 {
@@ -752,285 +830,236 @@ impl Opcode {
                 // PC
                 ctx.pc += 1;
                 // SUCCESS
-                true
+                Ok(())
             },
             Opcode::SHA3 => {
                 // STACK
-                let offset = ctx.stack.pop().as_usize();
-                let size = ctx.stack.pop().as_usize();
+                let (offset, size) = match memory_range(ctx.stack.pop(), ctx.stack.pop(), ctx.env.cfg.memory_limit) {
+                    Some(range) => range,
+                    None => return Err(Halt::MemoryLimitExceeded),
+                };
                 // GAS
-                ctx.gas += self.fix_gas() + 6 * (size + 31) / 32;
-                // // OPERATION
-                let result = Bytes32::from_slice(
-                    Keccak256::digest(ctx.memory.load(offset, size).as_slice()).as_slice(),
-                );
+                let words = size.div_ceil(32);
+                trace!(target: "evm::opcode", "SHA3 of {} words", words);
+                charge_memory(ctx, sha3_gas(words).ok_or(Halt::OutOfGas)?, offset, size)?;
+                // OPERATION
+                let data = ctx.memory.load(offset, size);
+                let result = match ctx.env.cfg.keccak_cache {
+                    true => ctx.keccak_cache.get_or_insert_with(Box::default).hash(data.as_slice()),
+                    false => keccak256(data.as_slice()),
+                };
                 ctx.stack.push(result);
                 // PC
                 ctx.pc += 1;
                 // SUCCESS
-                true
+                Ok(())
             },
             Opcode::ADDRESS => {
-                // GAS
-                ctx.gas += self.fix_gas();
                 // OPERATION
                 ctx.stack.push_address(ctx.env.call.recipient);
                 // PC
                 ctx.pc += 1;
                 // SUCCESS
-                true
+                Ok(())
             },
             Opcode::BALANCE => {
                 // STACK
                 let address = ctx.stack.pop().to_address();
                 // GAS
-                ctx.gas += self.fix_gas();
+                let (balance, is_cold) = ctx.state.load_balance(&address);
+                ctx.gas += account_access_gas(ctx.env.spec, is_cold);
                 // OPERATION
-                ctx.stack.push_u256(ctx.state.balance(&address));
+                ctx.stack.push_u256(balance);
                 // PC
                 ctx.pc += 1;
                 // SUCCESS
-                true
+                Ok(())
             },
             Opcode::ORIGIN => {
-                // GAS
-                ctx.gas += self.fix_gas();
                 // OPERATION
-                ctx.stack.push_address(ctx.env.call.originator);
+                ctx.stack.push_address(ctx.env.tx.originator);
                 // PC
                 ctx.pc += 1;
                 // SUCCESS
-                true
+                Ok(())
             },
             Opcode::CALLER => {
-                // GAS
-                ctx.gas += self.fix_gas();
                 // OPERATION
                 ctx.stack.push_address(ctx.env.call.sender);
                 // PC
                 ctx.pc += 1;
                 // SUCCESS
-                true
+                Ok(())
             },
             Opcode::CALLVALUE => {
-                // GAS
-                ctx.gas += self.fix_gas();
                 // OPERATION
                 ctx.stack.push_u256(ctx.env.call.value);
                 // PC
                 ctx.pc += 1;
                 // SUCCESS
-                true
+                Ok(())
             },
             Opcode::CALLDATALOAD => {
                 // STACK
-                let offset = ctx.stack.pop().as_usize();
-                // GAS
-                ctx.gas += self.fix_gas();
+                let offset = saturated_offset(ctx.stack.pop());
                 // OPERATION
-                let mut result = [0u8; 32];
-                let calldata = ctx.env.call.data();
-                let (end, len) = if offset + 32 > calldata.len() {
-                    (32, 32 - offset)
-                } else {
-                    (offset + 32, 32)
-                };
-                if len == 32 {
-                    result.copy_from_slice(&calldata[offset..end]);
-                } else {
-                    result[..len].copy_from_slice(&calldata[offset..end]);
-                }
-                ctx.stack.push(Bytes32::from_slice(&result));
+                let result = ctx.env.call.data().padded_word(offset);
+                ctx.stack.push(result);
                 // PC
                 ctx.pc += 1;
                 // SUCCESS
-                true
+                Ok(())
             },
             Opcode::CALLDATASIZE => {
-                // GAS
-                ctx.gas += self.fix_gas();
                 // OPERATION
-                let result = ctx.env.call.data_size();
+                let result = ctx.env.call.data_len();
                 ctx.stack.push_usize(result);
                 // PC
                 ctx.pc += 1;
                 // SUCCESS
-                true
+                Ok(())
             },
             Opcode::CALLDATACOPY => {
                 // STACK
-                let memory_offset = ctx.stack.pop().as_usize();
-                let offset = ctx.stack.pop().as_usize();
-                let size = ctx.stack.pop().as_usize();
+                let memory_offset = ctx.stack.pop();
+                let offset = ctx.stack.pop();
+                let (memory_offset, size) = match memory_range(memory_offset, ctx.stack.pop(), ctx.env.cfg.memory_limit) {
+                    Some(range) => range,
+                    None => return Err(Halt::MemoryLimitExceeded),
+                };
+                let offset = saturated_offset(offset);
                 // GAS
-                ctx.gas += self.fix_gas();
+                charge_memory(ctx, copy_gas(size), memory_offset, size)?;
                 // OPERATION
-                let mut result = vec![0u8; size];
-                let calldata = ctx.env.call.data();
-                let (end, len) = if offset + size > calldata.len() {
-                    (size, size - offset)
-                } else {
-                    (offset + size, size)
-                };
-                if len == size {
-                    result.copy_from_slice(&calldata[offset..end]);
-                } else {
-                    result[..len].copy_from_slice(&calldata[offset..end]);
-                }
-                ctx.memory.store(memory_offset, Bytes::from_vec(result));
+                let result = ctx.env.call.data().padded_slice(offset, size);
+                ctx.gas += ctx.memory.store(memory_offset, result);
                 // PC
                 ctx.pc += 1;
                 // SUCCESS
-                true
+                Ok(())
             },
             Opcode::CODESIZE => {
-                // GAS
-                ctx.gas += self.fix_gas();
                 // OPERATION
                 ctx.stack.push_usize(ctx.code_size());
                 // PC
                 ctx.pc += 1;
                 // SUCCESS
-                true
+                Ok(())
             },
             Opcode::CODECOPY => {
                 // STACK
-                let memory_offset = ctx.stack.pop().as_usize();
-                let offset = ctx.stack.pop().as_usize();
-                let mut size = ctx.stack.pop().as_usize();
-                if size > ctx.code_size() {
-                    size = ctx.code_size()
-                }
+                let memory_offset = ctx.stack.pop();
+                let offset = ctx.stack.pop();
+                let (memory_offset, size) = match memory_range(memory_offset, ctx.stack.pop(), ctx.env.cfg.memory_limit) {
+                    Some(range) => range,
+                    None => return Err(Halt::MemoryLimitExceeded),
+                };
+                let offset = saturated_offset(offset);
                 // GAS
-                ctx.gas += self.fix_gas();
+                charge_memory(ctx, copy_gas(size), memory_offset, size)?;
                 // OPERATION
-                let mut result = vec![0u8; size];
-                let (end, len) = if offset + size > ctx.code_size() {
-                    (size, size - offset)
-                } else {
-                    (offset + size, size)
-                };
-                if len == size {
-                    result.copy_from_slice(&ctx.code[offset..end]);
-                } else {
-                    result[..len].copy_from_slice(&ctx.code[offset..end]);
-                }
-                ctx.memory.store(memory_offset, Bytes::from_vec(result));
+                // The executing code (e.g. init code, or a library under DELEGATECALL),
+                // which may not be the one stored at the frame's address
+                let result = ctx.code.padded_slice(offset, size);
+                ctx.gas += ctx.memory.store(memory_offset, result);
                 // PC
                 ctx.pc += 1;
                 // SUCCESS
-                true
+                Ok(())
             },
             Opcode::GASPRICE => {
-                // GAS
-                ctx.gas += self.fix_gas();
                 // OPERATION
-                ctx.stack.push_u256(ctx.env.call.gas_price);
+                ctx.stack.push_u256(ctx.env.tx.gas_price);
                 // PC
                 ctx.pc += 1;
                 // SUCCESS
-                true
+                Ok(())
             },
             Opcode::EXTCODESIZE => {
                 // STACK
                 let address = ctx.stack.pop().to_address();
                 // GAS
-                ctx.gas += self.fix_gas();
+                let (code_size, is_cold) = ctx.state.load_code_size(&address);
+                ctx.gas += account_access_gas(ctx.env.spec, is_cold);
                 // OPERATION
-                ctx.stack.push_usize(ctx.state.code_size(&address));
+                ctx.stack.push_usize(code_size);
                 // PC
                 ctx.pc += 1;
                 // SUCCESS
-                true
+                Ok(())
             },
             Opcode::EXTCODECOPY => {
                 // STACK
                 let address = ctx.stack.pop().to_address();
-                let memory_offset = ctx.stack.pop().as_usize();
-                let offset = ctx.stack.pop().as_usize();
-                let size = ctx.stack.pop().as_usize();
+                let memory_offset = ctx.stack.pop();
+                let offset = ctx.stack.pop();
+                let (memory_offset, size) = match memory_range(memory_offset, ctx.stack.pop(), ctx.env.cfg.memory_limit) {
+                    Some(range) => range,
+                    None => return Err(Halt::MemoryLimitExceeded),
+                };
+                let offset = saturated_offset(offset);
                 // GAS
-                ctx.gas += self.fix_gas();
+                let (code, is_cold) = ctx.state.load_code(&address);
+                ctx.gas += account_access_gas(ctx.env.spec, is_cold);
+                charge_memory(ctx, copy_gas(size), memory_offset, size)?;
                 // OPERATION
-                let code = ctx.state.code(&address);
-                let mut result = vec![0u8; size];
-                let (end, len) = if size > code.len() {
-                    (code.len(), code.len() - offset)
-                } else {
-                    if offset + size > code.len() {
-                        (size, size - offset)
-                    } else {
-                        (offset + size, size)
-                    }
-                };
-                if len == size {
-                    result.copy_from_slice(&code[offset..end]);
-                } else {
-                    result[..len].copy_from_slice(&code[offset..end]);
-                };
-                ctx.memory.store(memory_offset, Bytes::from_vec(result));
+                let result = code.padded_slice(offset, size);
+                ctx.gas += ctx.memory.store(memory_offset, result);
                 // PC
                 ctx.pc += 1;
                 // SUCCESS
-                true
+                Ok(())
             },
             Opcode::RETURNDATASIZE => {
-                // GAS
-                ctx.gas += self.fix_gas();
                 // OPERATION
                 let data = ctx.return_data();
                 ctx.stack.push_usize(data.len());
                 // PC
                 ctx.pc += 1;
                 // SUCCESS
-                true
+                Ok(())
             },
             Opcode::RETURNDATACOPY => {
                 // STACK
-                let memory_offset = ctx.stack.pop().as_usize();
-                let offset = ctx.stack.pop().as_usize();
-                let mut size = ctx.stack.pop().as_usize();
+                let memory_offset = ctx.stack.pop();
+                let offset = ctx.stack.pop();
+                let (memory_offset, size) = match memory_range(memory_offset, ctx.stack.pop(), ctx.env.cfg.memory_limit) {
+                    Some(range) => range,
+                    None => return Err(Halt::MemoryLimitExceeded),
+                };
                 // GAS
-                ctx.gas += self.fix_gas();
-                // OPERATION
+                charge_memory(ctx, copy_gas(size), memory_offset, size)?;
+                // Unlike the other copies, reading past the end of the return
+                // data halts instead of padding it (EIP-211)
                 let data = ctx.return_data();
-                if size > data.len() {
-                    size = data.len()
-                }
-                let mut result = vec![0u8; size];
-                let (end, len) = if offset + size > data.len() {
-                    (size, size - offset)
-                } else {
-                    (offset + size, size)
-                };
-                if len == size {
-                    result.copy_from_slice(&data[offset..end]);
-                } else {
-                    result[..len].copy_from_slice(&data[offset..end]);
+                let (end, overflow) = offset.to_u256().overflowing_add(U256::from(size));
+                if overflow || end > U256::from(data.len()) {
+                    return Err(Halt::ReturnDataOutOfBounds);
                 }
-                ctx.memory.store(memory_offset, Bytes::from_vec(result));
+                // OPERATION
+                let result = data.padded_slice(saturated_offset(offset), size);
+                ctx.gas += ctx.memory.store(memory_offset, result);
                 // PC
                 ctx.pc += 1;
                 // SUCCESS
-                true
+                Ok(())
             },
             Opcode::EXTCODEHASH => {
                 // STACK
                 let address = ctx.stack.pop().to_address();
                 // GAS
-                ctx.gas += self.fix_gas();
+                let (code_hash, is_cold) = ctx.state.load_code_hash(&address);
+                ctx.gas += account_access_gas(ctx.env.spec, is_cold);
                 // OPERATION
-                ctx.stack.push(ctx.state.code_hash(&address));
+                ctx.stack.push(code_hash);
                 // PC
                 ctx.pc += 1;
                 // SUCCESS
-                true
+                Ok(())
             },
             Opcode::BLOCKHASH => {
                 // STACK
                 let _block_number = ctx.stack.pop();
-                // GAS
-                ctx.gas += self.fix_gas();
                 // OPERATION
                 let result = Bytes32::zero();
                 // let result = match ctx.env.block.block_hash(block_number) {
@@ -1041,11 +1070,9 @@ impl Opcode {
                 // PC
                 ctx.pc += 1;
                 // SUCCESS
-                true
+                Ok(())
             },
             Opcode::COINBASE => {
-                // GAS
-                ctx.gas += self.fix_gas();
                 // OPERATION
                 let result = match ctx.env.block.beneficiary {
                     Some(coinbase) => coinbase.to_u256(),
@@ -1055,21 +1082,17 @@ impl Opcode {
                 // PC
                 ctx.pc += 1;
                 // SUCCESS
-                true
+                Ok(())
             },
             Opcode::TIMESTAMP => {
-                // GAS
-                ctx.gas += self.fix_gas();
                 // OPERATION
                 ctx.stack.push_u256(ctx.env.block.timestamp);
                 // PC
                 ctx.pc += 1;
                 // SUCCESS
-                true
+                Ok(())
             },
             Opcode::NUMBER => {
-                // GAS
-                ctx.gas += self.fix_gas();
                 // OPERATION
                 let result = match ctx.env.block.number {
                     Some(number) => Bytes32::from_u64(number).to_u256(),
@@ -1079,62 +1102,48 @@ impl Opcode {
                 // PC
                 ctx.pc += 1;
                 // SUCCESS
-                true
+                Ok(())
             },
             Opcode::PREVRANDAO => {
-                // GAS
-                ctx.gas += self.fix_gas();
-                // OPERATION
-                let result = match ctx.env.block.prev_randao {
-                    Some(number) => number,
-                    // If block.prev_randao is None, use block.difficulty instead
-                    None => match ctx.env.block.difficulty {
-                        Some(number) => number,
-                        None => U256::zero(),
-                    },
+                // OPERATION (DIFFICULTY before the Merge)
+                let result = match ctx.env.spec < SpecId::Merge {
+                    true => ctx.env.block.difficulty.unwrap_or_default(),
+                    false => ctx.env.block.randao(),
                 };
                 ctx.stack.push_u256(result);
                 // PC
                 ctx.pc += 1;
                 // SUCCESS
-                true
+                Ok(())
             },
             Opcode::GASLIMIT => {
-                // GAS
-                ctx.gas += self.fix_gas();
                 // OPERATION
                 ctx.stack.push_u256(ctx.env.block.gas_limit);
                 // PC
                 ctx.pc += 1;
                 // SUCCESS
-                true
+                Ok(())
             },
             Opcode::CHAINID => {
-                // GAS
-                ctx.gas += self.fix_gas();
                 // OPERATION
-                let chain_id = Bytes32::from_u64(ctx.env.block.chain_id).to_u256();
+                let chain_id = U256::from(ctx.env.cfg.chain_id);
                 ctx.stack.push_u256(chain_id);
                 // PC
                 ctx.pc += 1;
                 // SUCCESS
-                true
+                Ok(())
             },
             Opcode::SELFBALANCE => {
-                // STACK
-                let address = ctx.env.call.recipient;
-                // GAS
-                ctx.gas += self.fix_gas();
                 // OPERATION
-                ctx.stack.push_u256(ctx.state.balance(&address));
+                // Balance of the current context, i.e. of the caller under
+                // DELEGATECALL/CALLCODE (like ADDRESS and the storage opcodes)
+                ctx.stack.push_u256(ctx.state.balance(&ctx.target));
                 // PC
                 ctx.pc += 1;
                 // SUCCESS
-                true
+                Ok(())
             },
             Opcode::BASEFEE => {
-                // GAS
-                ctx.gas += self.fix_gas();
                 // OPERATION
                 let base_fee = match ctx.env.block.base_fee {
                     Some(base_fee) => base_fee,
@@ -1144,1017 +1153,898 @@ impl Opcode {
                 // PC
                 ctx.pc += 1;
                 // SUCCESS
-                true
+                Ok(())
+            },
+            Opcode::BLOBHASH => {
+                // STACK
+                let index = ctx.stack.pop();
+                // OPERATION
+                // Zero past the blobs of the tx
+                let hash = match index.to_u256() < ctx.env.tx.blob_hashes.len().into() {
+                    true => ctx.env.tx.blob_hashes[index.as_usize()],
+                    false => Bytes32::zero(),
+                };
+                ctx.stack.push(hash);
+                // PC
+                ctx.pc += 1;
+                // SUCCESS
+                Ok(())
+            },
+            Opcode::BLOBBASEFEE => {
+                // OPERATION
+                let blob_base_fee = match ctx.env.block.blob_base_fee {
+                    Some(blob_base_fee) => blob_base_fee,
+                    None => U256::zero(),
+                };
+                ctx.stack.push_u256(blob_base_fee);
+                // PC
+                ctx.pc += 1;
+                // SUCCESS
+                Ok(())
             },
             Opcode::POP => {
-                // GAS
-                ctx.gas += self.fix_gas();
                 // OPERATION
                 ctx.stack.pop();
                 // PC
                 ctx.pc += 1;
                 // SUCCESS
-                true
+                Ok(())
             },
             Opcode::MLOAD => {
                 // STACK
-                let offset = ctx.stack.pop();
+                let offset = match checked_range(ctx.stack.pop().to_u256(), U256::from(32), ctx.env.cfg.memory_limit) {
+                    Some((offset, _)) => offset,
+                    None => return Err(Halt::MemoryLimitExceeded),
+                };
                 // GAS
-                ctx.gas += self.fix_gas() * ctx.memory.expansion(offset.as_usize(), 32);
+                charge_memory(ctx, 0, offset, 32)?;
                 // OPERATION
-                let value = ctx.memory.load(offset.as_usize(), 32);
-                ctx.stack.push(value.as_bytes32());
+                let value = ctx.memory.get_word(offset);
+                ctx.stack.push(value);
                 // PC
                 ctx.pc += 1;
                 // SUCCESS
-                true
+                Ok(())
             },
             Opcode::MSTORE => {
                 // STACK
                 let offset = ctx.stack.pop();
                 let value = ctx.stack.pop();
-                println!(" > MSTORE\n   - offset: {:#X}\n   -  value: {:#X}", offset, value);
                 // GAS
-                ctx.gas += self.fix_gas() * ctx.memory.expansion(offset.as_usize(), 32);
+                let offset = match checked_range(offset.to_u256(), U256::from(32), ctx.env.cfg.memory_limit) {
+                    Some((offset, _)) => offset,
+                    None => return Err(Halt::MemoryLimitExceeded),
+                };
+                charge_memory(ctx, 0, offset, 32)?;
                 // OPERATION
-                ctx.memory
-                    .store(offset.as_usize(), Bytes::from_bytes32(value));
+                ctx.gas += ctx.memory.set_word(offset, &value);
                 // PC
                 ctx.pc += 1;
                 // SUCCESS
-                true
+                Ok(())
             },
             Opcode::MSTORE8 => {
                 // STACK
                 let offset = ctx.stack.pop();
                 let value = ctx.stack.pop();
                 // GAS
-                ctx.gas += self.fix_gas() * ctx.memory.expansion(offset.as_usize(), 1);
+                let offset = match checked_range(offset.to_u256(), U256::one(), ctx.env.cfg.memory_limit) {
+                    Some((offset, _)) => offset,
+                    None => return Err(Halt::MemoryLimitExceeded),
+                };
+                // Expanding the memory to a whole word
+                charge_memory(ctx, 0, offset, 1)?;
                 // OPERATION
-                ctx.memory
-                    .store(offset.as_usize(), Bytes::from_byte(value.get_byte(31)));
+                ctx.gas += ctx.memory.set_byte(offset, value.get_byte(31));
                 // PC
                 ctx.pc += 1;
                 // SUCCESS
-                true
+                Ok(())
             },
             Opcode::SLOAD => {
                 // STACK
                 let key = ctx.stack.pop().to_u256();
                 // GAS
-                ctx.gas += self.fix_gas(); //+ self.state_access_gas(key);
+                let (value, is_cold) = ctx.state.sload(&ctx.target, key);
+                ctx.gas += cold_access_gas(ctx.env.spec, is_cold, COLD_SLOAD_COST - WARM_STORAGE_READ_COST);
                 // OPERATION
-                let value = ctx.state.storage_load(&ctx.target, key);
                 ctx.stack.push(value);
                 // PC
                 ctx.pc += 1;
                 // SUCCESS
-                true
+                Ok(())
             },
             Opcode::SSTORE => {
                 // CHECK REVERT CONDITION
-                if ctx.env.call.is_static() { return false; }
+                if ctx.env.call.is_static() { return Err(Halt::StaticCallViolation); }
                 // STACK
                 let key = ctx.stack.pop().to_u256();
                 let value = ctx.stack.pop();
                 // GAS
-                ctx.gas += self.fix_gas(); //+ self.state_access_gas(key);
+                if ctx.env.spec.is_enabled_in(SpecId::Istanbul) && ctx.gas_limit.saturating_sub(ctx.gas) <= SSTORE_SENTRY {
+                    return Err(Halt::OutOfGas);
+                }
+                let original = ctx.state.storage_original(&ctx.target, key);
+                let current = ctx.state.storage_load(&ctx.target, key);
+                let is_cold = ctx.state.access_slot(&ctx.target, key);
+                ctx.gas += cold_access_gas(ctx.env.spec, is_cold, COLD_SLOAD_COST);
+                ctx.gas += sstore_gas(ctx.env.spec, original, current, value);
+                // REFUND
+                ctx.refund += sstore_refund(ctx.env.spec, original, current, value);
                 // OPERATION
                 ctx.state.storage_store(&ctx.target, key, value);
                 // PC
                 ctx.pc += 1;
                 // SUCCESS
-                true
+                Ok(())
             },
             Opcode::JUMP => {
                 // STACK
-                let jumpdest = ctx.stack.pop().as_usize();
-                // GAS
-                ctx.gas += self.fix_gas();
+                let jumpdest = ctx.stack.pop().to_u256();
                 // OPERATION
-                match validate_jumpdest(&ctx.code, jumpdest) {
-                    true => {
+                match jump_destination(&ctx.code, jumpdest) {
+                    Some(jumpdest) => {
                         // PC
                         ctx.pc = jumpdest;
                         // SUCCESS
-                        true
+                        Ok(())
                     }
-                    false => false,
+                    None => Err(Halt::InvalidJump),
                 }
             },
             Opcode::JUMPI => {
                 // STACK
-                let jumpdest = ctx.stack.pop().as_usize();
+                let jumpdest = ctx.stack.pop().to_u256();
                 let condition = ctx.stack.pop().to_u256();
-                // GAS
-                ctx.gas += self.fix_gas();
                 // OPERATION
                 match condition.is_zero() {
                     true => {
                         // PC
                         ctx.pc += 1;
                         // SUCCESS
-                        true
+                        Ok(())
                     }
                     false => {
-                        match validate_jumpdest(&ctx.code, jumpdest) {
-                            true => {
+                        match jump_destination(&ctx.code, jumpdest) {
+                            Some(jumpdest) => {
                                 // PC
                                 ctx.pc = jumpdest;
                                 // SUCCESS
-                                true
+                                Ok(())
                             }
-                            false => false,
+                            None => Err(Halt::InvalidJump),
                         }
                     }
                 }
             },
             Opcode::PC => {
-                // GAS
-                ctx.gas += self.fix_gas();
                 // OPERATION
                 ctx.stack.push_usize(ctx.pc);
                 // PC
                 ctx.pc += 1;
                 // SUCCESS
-                true
+                Ok(())
             },
             Opcode::MSIZE => {
-                // GAS
-                ctx.gas += self.fix_gas();
                 // OPERATION
                 ctx.stack.push_usize(ctx.memory.size());
                 // PC
                 ctx.pc += 1;
                 // SUCCESS
-                true
+                Ok(())
             },
             Opcode::GAS => {
-                // GAS
-                ctx.gas += self.fix_gas();
                 // OPERATION
-                // TODO
-                // ctx.stack.push(U256::from(ctx.gas));
-                ctx.stack.push_u256(U256::max_value());
+                ctx.stack.push_usize(ctx.gas_limit.saturating_sub(ctx.gas));
                 // PC
                 ctx.pc += 1;
                 // SUCCESS
-                true
+                Ok(())
             },
             Opcode::JUMPDEST => {
-                // GAS
-                ctx.gas += self.fix_gas();
                 // PC
                 ctx.pc += 1;
                 // SUCCESS
-                true
+                Ok(())
+            },
+            Opcode::PUSH0 => {
+                // OPERATION
+                ctx.stack.push(Bytes32::zero());
+                // PC
+                ctx.pc += 1;
+                // SUCCESS
+                Ok(())
             },
             Opcode::PUSH1 => {
-                // GAS
-                ctx.gas += self.fix_gas();
                 // OPERATION
                 let value = &[ctx.code[ctx.pc + 1]];
                 ctx.stack.push(Bytes32::from_slice(value));
                 // PC
                 ctx.pc += 2;
                 // SUCCESS
-                true
+                Ok(())
             },
             Opcode::PUSH2 => {
-                // GAS
-                ctx.gas += self.fix_gas();
                 // OPERATION
                 let value = &ctx.code[ctx.pc + 1..ctx.pc + 3];
                 ctx.stack.push(Bytes32::from_slice(value));
                 // PC
                 ctx.pc += 3;
                 // SUCCESS
-                true
+                Ok(())
             },
             Opcode::PUSH3 => {
-                // GAS
-                ctx.gas += self.fix_gas();
                 // OPERATION
                 let value = &ctx.code[ctx.pc + 1..ctx.pc + 4];
                 ctx.stack.push(Bytes32::from_slice(value));
                 // PC
                 ctx.pc += 4;
                 // SUCCESS
-                true
+                Ok(())
             },
             Opcode::PUSH4 => {
-                // GAS
-                ctx.gas += self.fix_gas();
                 // OPERATION
                 let value = &ctx.code[ctx.pc + 1..ctx.pc + 5];
                 ctx.stack.push(Bytes32::from_slice(value));
                 // PC
                 ctx.pc += 5;
                 // SUCCESS
-                true
+                Ok(())
             },
             Opcode::PUSH5 => {
-                // GAS
-                ctx.gas += self.fix_gas();
                 // OPERATION
                 let value = &ctx.code[ctx.pc + 1..ctx.pc + 6];
                 ctx.stack.push(Bytes32::from_slice(value));
                 // PC
                 ctx.pc += 6;
                 // SUCCESS
-                true
+                Ok(())
             },
             Opcode::PUSH6 => {
-                // GAS
-                ctx.gas += self.fix_gas();
                 // OPERATION
                 let value = &ctx.code[ctx.pc + 1..ctx.pc + 7];
                 ctx.stack.push(Bytes32::from_slice(value));
                 // PC
                 ctx.pc += 7;
                 // SUCCESS
-                true
+                Ok(())
             },
             Opcode::PUSH7 => {
-                // GAS
-                ctx.gas += self.fix_gas();
                 // OPERATION
                 let value = &ctx.code[ctx.pc + 1..ctx.pc + 8];
                 ctx.stack.push(Bytes32::from_slice(value));
                 // PC
                 ctx.pc += 8;
                 // SUCCESS
-                true
+                Ok(())
             },
             Opcode::PUSH8 => {
-                // GAS
-                ctx.gas += self.fix_gas();
                 // OPERATION
                 let value = &ctx.code[ctx.pc + 1..ctx.pc + 9];
                 ctx.stack.push(Bytes32::from_slice(value));
                 // PC
                 ctx.pc += 9;
                 // SUCCESS
-                true
+                Ok(())
             },
             Opcode::PUSH9 => {
-                // GAS
-                ctx.gas += self.fix_gas();
                 // OPERATION
                 let value = &ctx.code[ctx.pc + 1..ctx.pc + 10];
                 ctx.stack.push(Bytes32::from_slice(value));
                 // PC
                 ctx.pc += 10;
                 // SUCCESS
-                true
+                Ok(())
             },
             Opcode::PUSH10 => {
-                // GAS
-                ctx.gas += self.fix_gas();
                 // OPERATION
                 let value = &ctx.code[ctx.pc + 1..ctx.pc + 11];
                 ctx.stack.push(Bytes32::from_slice(value));
                 // PC
                 ctx.pc += 11;
                 // SUCCESS
-                true
+                Ok(())
             },
             Opcode::PUSH11 => {
-                // GAS
-                ctx.gas += self.fix_gas();
                 // OPERATION
                 let value = &ctx.code[ctx.pc + 1..ctx.pc + 12];
                 ctx.stack.push(Bytes32::from_slice(value));
                 // PC
                 ctx.pc += 12;
                 // SUCCESS
-                true
+                Ok(())
             },
             Opcode::PUSH12 => {
-                // GAS
-                ctx.gas += self.fix_gas();
                 // OPERATION
                 let value = &ctx.code[ctx.pc + 1..ctx.pc + 13];
                 ctx.stack.push(Bytes32::from_slice(value));
                 // PC
                 ctx.pc += 13;
                 // SUCCESS
-                true
+                Ok(())
             },
             Opcode::PUSH13 => {
-                // GAS
-                ctx.gas += self.fix_gas();
                 // OPERATION
                 let value = &ctx.code[ctx.pc + 1..ctx.pc + 14];
                 ctx.stack.push(Bytes32::from_slice(value));
                 // PC
                 ctx.pc += 14;
                 // SUCCESS
-                true
+                Ok(())
             },
             Opcode::PUSH14 => {
-                // GAS
-                ctx.gas += self.fix_gas();
                 // OPERATION
                 let value = &ctx.code[ctx.pc + 1..ctx.pc + 15];
                 ctx.stack.push(Bytes32::from_slice(value));
                 // PC
                 ctx.pc += 15;
                 // SUCCESS
-                true
+                Ok(())
             },
             Opcode::PUSH15 => {
-                // GAS
-                ctx.gas += self.fix_gas();
                 // OPERATION
                 let value = &ctx.code[ctx.pc + 1..ctx.pc + 16];
                 ctx.stack.push(Bytes32::from_slice(value));
                 // PC
                 ctx.pc += 16;
                 // SUCCESS
-                true
+                Ok(())
             },
             Opcode::PUSH16 => {
-                // GAS
-                ctx.gas += self.fix_gas();
                 // OPERATION
                 let value = &ctx.code[ctx.pc + 1..ctx.pc + 17];
                 ctx.stack.push(Bytes32::from_slice(value));
                 // PC
                 ctx.pc += 17;
                 // SUCCESS
-                true
+                Ok(())
             },
             Opcode::PUSH17 => {
-                // GAS
-                ctx.gas += self.fix_gas();
                 // OPERATION
                 let value = &ctx.code[ctx.pc + 1..ctx.pc + 18];
                 ctx.stack.push(Bytes32::from_slice(value));
                 // PC
                 ctx.pc += 18;
                 // SUCCESS
-                true
+                Ok(())
             },
             Opcode::PUSH18 => {
-                // GAS
-                ctx.gas += self.fix_gas();
                 // OPERATION
                 let value = &ctx.code[ctx.pc + 1..ctx.pc + 19];
                 ctx.stack.push(Bytes32::from_slice(value));
                 // PC
                 ctx.pc += 19;
                 // SUCCESS
-                true
+                Ok(())
             },
             Opcode::PUSH19 => {
-                // GAS
-                ctx.gas += self.fix_gas();
                 // OPERATION
                 let value = &ctx.code[ctx.pc + 1..ctx.pc + 20];
                 ctx.stack.push(Bytes32::from_slice(value));
                 // PC
                 ctx.pc += 20;
                 // SUCCESS
-                true
+                Ok(())
             },
             Opcode::PUSH20 => {
-                // GAS
-                ctx.gas += self.fix_gas();
                 // OPERATION
                 let value = &ctx.code[ctx.pc + 1..ctx.pc + 21];
                 ctx.stack.push(Bytes32::from_slice(value));
                 // PC
                 ctx.pc += 21;
                 // SUCCESS
-                true
+                Ok(())
             },
             Opcode::PUSH21 => {
-                // GAS
-                ctx.gas += self.fix_gas();
                 // OPERATION
                 let value = &ctx.code[ctx.pc + 1..ctx.pc + 22];
                 ctx.stack.push(Bytes32::from_slice(value));
                 // PC
                 ctx.pc += 22;
                 // SUCCESS
-                true
+                Ok(())
             },
             Opcode::PUSH22 => {
-                // GAS
-                ctx.gas += self.fix_gas();
                 // OPERATION
                 let value = &ctx.code[ctx.pc + 1..ctx.pc + 23];
                 ctx.stack.push(Bytes32::from_slice(value));
                 // PC
                 ctx.pc += 23;
                 // SUCCESS
-                true
+                Ok(())
             },
             Opcode::PUSH23 => {
-                // GAS
-                ctx.gas += self.fix_gas();
                 // OPERATION
                 let value = &ctx.code[ctx.pc + 1..ctx.pc + 24];
                 ctx.stack.push(Bytes32::from_slice(value));
                 // PC
                 ctx.pc += 24;
                 // SUCCESS
-                true
+                Ok(())
             },
             Opcode::PUSH24 => {
-                // GAS
-                ctx.gas += self.fix_gas();
                 // OPERATION
                 let value = &ctx.code[ctx.pc + 1..ctx.pc + 25];
                 ctx.stack.push(Bytes32::from_slice(value));
                 // PC
                 ctx.pc += 25;
                 // SUCCESS
-                true
+                Ok(())
             },
             Opcode::PUSH25 => {
-                // GAS
-                ctx.gas += self.fix_gas();
                 // OPERATION
                 let value = &ctx.code[ctx.pc + 1..ctx.pc + 26];
                 ctx.stack.push(Bytes32::from_slice(value));
                 // PC
                 ctx.pc += 26;
                 // SUCCESS
-                true
+                Ok(())
             },
             Opcode::PUSH26 => {
-                // GAS
-                ctx.gas += self.fix_gas();
                 // OPERATION
                 let value = &ctx.code[ctx.pc + 1..ctx.pc + 27];
                 ctx.stack.push(Bytes32::from_slice(value));
                 // PC
                 ctx.pc += 27;
                 // SUCCESS
-                true
+                Ok(())
             },
             Opcode::PUSH27 => {
-                // GAS
-                ctx.gas += self.fix_gas();
                 // OPERATION
                 let value = &ctx.code[ctx.pc + 1..ctx.pc + 28];
                 ctx.stack.push(Bytes32::from_slice(value));
                 // PC
                 ctx.pc += 28;
                 // SUCCESS
-                true
+                Ok(())
             },
             Opcode::PUSH28 => {
-                // GAS
-                ctx.gas += self.fix_gas();
                 // OPERATION
                 let value = &ctx.code[ctx.pc + 1..ctx.pc + 29];
                 ctx.stack.push(Bytes32::from_slice(value));
                 // PC
                 ctx.pc += 29;
                 // SUCCESS
-                true
+                Ok(())
             },
             Opcode::PUSH29 => {
-                // GAS
-                ctx.gas += self.fix_gas();
                 // OPERATION
                 let value = &ctx.code[ctx.pc + 1..ctx.pc + 30];
                 ctx.stack.push(Bytes32::from_slice(value));
                 // PC
                 ctx.pc += 30;
                 // SUCCESS
-                true
+                Ok(())
             },
             Opcode::PUSH30 => {
-                // GAS
-                ctx.gas += self.fix_gas();
                 // OPERATION
                 let value = &ctx.code[ctx.pc + 1..ctx.pc + 31];
                 ctx.stack.push(Bytes32::from_slice(value));
                 // PC
                 ctx.pc += 31;
                 // SUCCESS
-                true
+                Ok(())
             },
             Opcode::PUSH31 => {
-                // GAS
-                ctx.gas += self.fix_gas();
                 // OPERATION
                 let value = &ctx.code[ctx.pc + 1..ctx.pc + 32];
                 ctx.stack.push(Bytes32::from_slice(value));
                 // PC
                 ctx.pc += 32;
                 // SUCCESS
-                true
+                Ok(())
             },
             Opcode::PUSH32 => {
-                // GAS
-                ctx.gas += self.fix_gas();
                 // OPERATION
                 let value = &ctx.code[ctx.pc + 1..ctx.pc + 33];
                 ctx.stack.push(Bytes32::from_slice(value));
                 // PC
                 ctx.pc += 33;
                 // SUCCESS
-                true
+                Ok(())
             },
             Opcode::DUP1 => {
-                // GAS
-                ctx.gas += self.fix_gas();
                 // OPERATION
-                let value = ctx.stack.get_item(ctx.stack.depth() - 1);
-                match value {
+                match ctx.stack.peek(0) {
                     Some(value) => ctx.stack.push(value),
-                    None => panic!("Stack underflow"),
+                    None => return Err(Halt::StackUnderflow),
                 };
                 // PC
                 ctx.pc += 1;
                 // SUCCESS
-                true
+                Ok(())
             },
             Opcode::DUP2 => {
-                // GAS
-                ctx.gas += self.fix_gas();
                 // OPERATION
-                let value = ctx.stack.get_item(ctx.stack.depth() - 2);
-                match value {
+                match ctx.stack.peek(1) {
                     Some(value) => ctx.stack.push(value),
-                    None => panic!("Stack underflow"),
+                    None => return Err(Halt::StackUnderflow),
                 };
                 // PC
                 ctx.pc += 1;
                 // SUCCESS
-                true
+                Ok(())
             },
             Opcode::DUP3 => {
-                // GAS
-                ctx.gas += self.fix_gas();
                 // OPERATION
-                let value = ctx.stack.get_item(ctx.stack.depth() - 3);
-                match value {
+                match ctx.stack.peek(2) {
                     Some(value) => ctx.stack.push(value),
-                    None => panic!("Stack underflow"),
+                    None => return Err(Halt::StackUnderflow),
                 };
                 // PC
                 ctx.pc += 1;
                 // SUCCESS
-                true
+                Ok(())
             },
             Opcode::DUP4 => {
-                // GAS
-                ctx.gas += self.fix_gas();
                 // OPERATION
-                let value = ctx.stack.get_item(ctx.stack.depth() - 4);
-                match value {
+                match ctx.stack.peek(3) {
                     Some(value) => ctx.stack.push(value),
-                    None => panic!("Stack underflow"),
+                    None => return Err(Halt::StackUnderflow),
                 };
                 // PC
                 ctx.pc += 1;
                 // SUCCESS
-                true
+                Ok(())
             },
             Opcode::DUP5 => {
-                // GAS
-                ctx.gas += self.fix_gas();
                 // OPERATION
-                let value = ctx.stack.get_item(ctx.stack.depth() - 5);
-                match value {
+                match ctx.stack.peek(4) {
                     Some(value) => ctx.stack.push(value),
-                    None => panic!("Stack underflow"),
+                    None => return Err(Halt::StackUnderflow),
                 };
                 // PC
                 ctx.pc += 1;
                 // SUCCESS
-                true
+                Ok(())
             },
             Opcode::DUP6 => {
-                // GAS
-                ctx.gas += self.fix_gas();
                 // OPERATION
-                let value = ctx.stack.get_item(ctx.stack.depth() - 6);
-                match value {
+                match ctx.stack.peek(5) {
                     Some(value) => ctx.stack.push(value),
-                    None => panic!("Stack underflow"),
+                    None => return Err(Halt::StackUnderflow),
                 };
                 // PC
                 ctx.pc += 1;
                 // SUCCESS
-                true
+                Ok(())
             },
             Opcode::DUP7 => {
-                // GAS
-                ctx.gas += self.fix_gas();
                 // OPERATION
-                let value = ctx.stack.get_item(ctx.stack.depth() - 7);
-                match value {
+                match ctx.stack.peek(6) {
                     Some(value) => ctx.stack.push(value),
-                    None => panic!("Stack underflow"),
+                    None => return Err(Halt::StackUnderflow),
                 };
                 // PC
                 ctx.pc += 1;
                 // SUCCESS
-                true
+                Ok(())
             },
             Opcode::DUP8 => {
-                // GAS
-                ctx.gas += self.fix_gas();
                 // OPERATION
-                let value = ctx.stack.get_item(ctx.stack.depth() - 8);
-                match value {
+                match ctx.stack.peek(7) {
                     Some(value) => ctx.stack.push(value),
-                    None => panic!("Stack underflow"),
+                    None => return Err(Halt::StackUnderflow),
                 };
                 // PC
                 ctx.pc += 1;
                 // SUCCESS
-                true
+                Ok(())
             },
             Opcode::DUP9 => {
-                // GAS
-                ctx.gas += self.fix_gas();
                 // OPERATION
-                let value = ctx.stack.get_item(ctx.stack.depth() - 9);
-                match value {
+                match ctx.stack.peek(8) {
                     Some(value) => ctx.stack.push(value),
-                    None => panic!("Stack underflow"),
+                    None => return Err(Halt::StackUnderflow),
                 };
                 // PC
                 ctx.pc += 1;
                 // SUCCESS
-                true
+                Ok(())
             },
             Opcode::DUP10 => {
-                // GAS
-                ctx.gas += self.fix_gas();
                 // OPERATION
-                let value = ctx.stack.get_item(ctx.stack.depth() - 10);
-                match value {
+                match ctx.stack.peek(9) {
                     Some(value) => ctx.stack.push(value),
-                    None => panic!("Stack underflow"),
+                    None => return Err(Halt::StackUnderflow),
                 };
                 // PC
                 ctx.pc += 1;
                 // SUCCESS
-                true
+                Ok(())
             },
             Opcode::DUP11 => {
-                // GAS
-                ctx.gas += self.fix_gas();
                 // OPERATION
-                let value = ctx.stack.get_item(ctx.stack.depth() - 11);
-                match value {
+                match ctx.stack.peek(10) {
                     Some(value) => ctx.stack.push(value),
-                    None => panic!("Stack underflow"),
+                    None => return Err(Halt::StackUnderflow),
                 };
                 // PC
                 ctx.pc += 1;
                 // SUCCESS
-                true
+                Ok(())
             },
             Opcode::DUP12 => {
-                // GAS
-                ctx.gas += self.fix_gas();
                 // OPERATION
-                let value = ctx.stack.get_item(ctx.stack.depth() - 12);
-                match value {
+                match ctx.stack.peek(11) {
                     Some(value) => ctx.stack.push(value),
-                    None => panic!("Stack underflow"),
+                    None => return Err(Halt::StackUnderflow),
                 };
                 // PC
                 ctx.pc += 1;
                 // SUCCESS
-                true
+                Ok(())
             },
             Opcode::DUP13 => {
-                // GAS
-                ctx.gas += self.fix_gas();
                 // OPERATION
-                let value = ctx.stack.get_item(ctx.stack.depth() - 13);
-                match value {
+                match ctx.stack.peek(12) {
                     Some(value) => ctx.stack.push(value),
-                    None => panic!("Stack underflow"),
+                    None => return Err(Halt::StackUnderflow),
                 };
                 // PC
                 ctx.pc += 1;
                 // SUCCESS
-                true
+                Ok(())
             },
             Opcode::DUP14 => {
-                // GAS
-                ctx.gas += self.fix_gas();
                 // OPERATION
-                let value = ctx.stack.get_item(ctx.stack.depth() - 14);
-                match value {
+                match ctx.stack.peek(13) {
                     Some(value) => ctx.stack.push(value),
-                    None => panic!("Stack underflow"),
+                    None => return Err(Halt::StackUnderflow),
                 };
                 // PC
                 ctx.pc += 1;
                 // SUCCESS
-                true
+                Ok(())
             },
             Opcode::DUP15 => {
-                // GAS
-                ctx.gas += self.fix_gas();
                 // OPERATION
-                let value = ctx.stack.get_item(ctx.stack.depth() - 15);
-                match value {
+                match ctx.stack.peek(14) {
                     Some(value) => ctx.stack.push(value),
-                    None => panic!("Stack underflow"),
+                    None => return Err(Halt::StackUnderflow),
                 };
                 // PC
                 ctx.pc += 1;
                 // SUCCESS
-                true
+                Ok(())
             },
             Opcode::DUP16 => {
-                // GAS
-                ctx.gas += self.fix_gas();
                 // OPERATION
-                let value = ctx.stack.get_item(ctx.stack.depth() - 16);
-                match value {
+                match ctx.stack.peek(15) {
                     Some(value) => ctx.stack.push(value),
-                    None => panic!("Stack underflow"),
+                    None => return Err(Halt::StackUnderflow),
                 };
                 // PC
                 ctx.pc += 1;
                 // SUCCESS
-                true
+                Ok(())
             },
             Opcode::SWAP1 => {
-                // GAS
-                ctx.gas += self.fix_gas();
                 // OPERATION
-                ctx.stack.swap(1);
+                if ctx.stack.swap_top(1).is_err() {
+                    return Err(Halt::StackUnderflow);
+                }
                 // PC
                 ctx.pc += 1;
                 // SUCCESS
-                true
+                Ok(())
             },
             Opcode::SWAP2 => {
-                // GAS
-                ctx.gas += self.fix_gas();
                 // OPERATION
-                ctx.stack.swap(2);
+                if ctx.stack.swap_top(2).is_err() {
+                    return Err(Halt::StackUnderflow);
+                }
                 // PC
                 ctx.pc += 1;
                 // SUCCESS
-                true
+                Ok(())
             },
             Opcode::SWAP3 => {
-                // GAS
-                ctx.gas += self.fix_gas();
                 // OPERATION
-                ctx.stack.swap(3);
+                if ctx.stack.swap_top(3).is_err() {
+                    return Err(Halt::StackUnderflow);
+                }
                 // PC
                 ctx.pc += 1;
                 // SUCCESS
-                true
+                Ok(())
             },
             Opcode::SWAP4 => {
-                // GAS
-                ctx.gas += self.fix_gas();
                 // OPERATION
-                ctx.stack.swap(4);
+                if ctx.stack.swap_top(4).is_err() {
+                    return Err(Halt::StackUnderflow);
+                }
                 // PC
                 ctx.pc += 1;
                 // SUCCESS
-                true
+                Ok(())
             },
             Opcode::SWAP5 => {
-                // GAS
-                ctx.gas += self.fix_gas();
                 // OPERATION
-                ctx.stack.swap(5);
+                if ctx.stack.swap_top(5).is_err() {
+                    return Err(Halt::StackUnderflow);
+                }
                 // PC
                 ctx.pc += 1;
                 // SUCCESS
-                true
+                Ok(())
             },
             Opcode::SWAP6 => {
-                // GAS
-                ctx.gas += self.fix_gas();
                 // OPERATION
-                ctx.stack.swap(6);
+                if ctx.stack.swap_top(6).is_err() {
+                    return Err(Halt::StackUnderflow);
+                }
                 // PC
                 ctx.pc += 1;
                 // SUCCESS
-                true
+                Ok(())
             },
             Opcode::SWAP7 => {
-                // GAS
-                ctx.gas += self.fix_gas();
                 // OPERATION
-                ctx.stack.swap(7);
+                if ctx.stack.swap_top(7).is_err() {
+                    return Err(Halt::StackUnderflow);
+                }
                 // PC
                 ctx.pc += 1;
                 // SUCCESS
-                true
+                Ok(())
             },
             Opcode::SWAP8 => {
-                // GAS
-                ctx.gas += self.fix_gas();
                 // OPERATION
-                ctx.stack.swap(8);
+                if ctx.stack.swap_top(8).is_err() {
+                    return Err(Halt::StackUnderflow);
+                }
                 // PC
                 ctx.pc += 1;
                 // SUCCESS
-                true
+                Ok(())
             },
             Opcode::SWAP9 => {
-                // GAS
-                ctx.gas += self.fix_gas();
                 // OPERATION
-                ctx.stack.swap(9);
+                if ctx.stack.swap_top(9).is_err() {
+                    return Err(Halt::StackUnderflow);
+                }
                 // PC
                 ctx.pc += 1;
                 // SUCCESS
-                true
+                Ok(())
             },
             Opcode::SWAP10 => {
-                // GAS
-                ctx.gas += self.fix_gas();
                 // OPERATION
-                ctx.stack.swap(10);
+                if ctx.stack.swap_top(10).is_err() {
+                    return Err(Halt::StackUnderflow);
+                }
                 // PC
                 ctx.pc += 1;
                 // SUCCESS
-                true
+                Ok(())
             },
             Opcode::SWAP11 => {
-                // GAS
-                ctx.gas += self.fix_gas();
                 // OPERATION
-                ctx.stack.swap(11);
+                if ctx.stack.swap_top(11).is_err() {
+                    return Err(Halt::StackUnderflow);
+                }
                 // PC
                 ctx.pc += 1;
                 // SUCCESS
-                true
+                Ok(())
             },
             Opcode::SWAP12 => {
-                // GAS
-                ctx.gas += self.fix_gas();
                 // OPERATION
-                ctx.stack.swap(12);
+                if ctx.stack.swap_top(12).is_err() {
+                    return Err(Halt::StackUnderflow);
+                }
                 // PC
                 ctx.pc += 1;
                 // SUCCESS
-                true
+                Ok(())
             },
             Opcode::SWAP13 => {
-                // GAS
-                ctx.gas += self.fix_gas();
                 // OPERATION
-                ctx.stack.swap(13);
+                if ctx.stack.swap_top(13).is_err() {
+                    return Err(Halt::StackUnderflow);
+                }
                 // PC
                 ctx.pc += 1;
                 // SUCCESS
-                true
+                Ok(())
             },
             Opcode::SWAP14 => {
-                // GAS
-                ctx.gas += self.fix_gas();
                 // OPERATION
-                ctx.stack.swap(14);
+                if ctx.stack.swap_top(14).is_err() {
+                    return Err(Halt::StackUnderflow);
+                }
                 // PC
                 ctx.pc += 1;
                 // SUCCESS
-                true
+                Ok(())
             },
             Opcode::SWAP15 => {
-                // GAS
-                ctx.gas += self.fix_gas();
                 // OPERATION
-                ctx.stack.swap(15);
+                if ctx.stack.swap_top(15).is_err() {
+                    return Err(Halt::StackUnderflow);
+                }
                 // PC
                 ctx.pc += 1;
                 // SUCCESS
-                true
+                Ok(())
             },
             Opcode::SWAP16 => {
-                // GAS
-                ctx.gas += self.fix_gas();
                 // OPERATION
-                ctx.stack.swap(16);
-                // PC
-                ctx.pc += 1;
-                // SUCCESS
-                true
-            },
-            Opcode::LOG0 => {
-                // STACK
-                let offset = ctx.stack.pop().as_usize();
-                let size = ctx.stack.pop().as_usize();
-                // GAS
-                ctx.gas += self.fix_gas();// + self.log_gas(offset, size);
-                // OPERATION
-                let data = ctx.memory.load(offset, size);
-                let log = Log::new(ctx.target, data);
-                ctx.add_log(log);
-                // PC
-                ctx.pc += 1;
-                // SUCCESS
-                true
-            },
-            Opcode::LOG1 => {
-                // STACK
-                let offset = ctx.stack.pop().as_usize();
-                let size = ctx.stack.pop().as_usize();
-                let topic1 = ctx.stack.pop();
-                // GAS
-                ctx.gas += self.fix_gas();// + self.log_gas(offset, size);
-                // OPERATION
-                let data = ctx.memory.load(offset, size);
-                let mut log = Log::new(ctx.target, data);
-                log.add_topic(topic1);
-                ctx.add_log(log);
-                // PC
-                ctx.pc += 1;
-                // SUCCESS
-                true
-            },
-            Opcode::LOG2 => {
-                // STACK
-                let offset = ctx.stack.pop().as_usize();
-                let size = ctx.stack.pop().as_usize();
-                let topic1 = ctx.stack.pop();
-                let topic2 = ctx.stack.pop();
-                // GAS
-                ctx.gas += self.fix_gas();// + self.log_gas(offset, size);
-                // OPERATION
-                let data = ctx.memory.load(offset, size);
-                let mut log = Log::new(ctx.target, data);
-                log.add_topics(vec![topic1, topic2]);
-                ctx.add_log(log);
-                // PC
-                ctx.pc += 1;
-                // SUCCESS
-                true
-            },
-            Opcode::LOG3 => {
-                // STACK
-                let offset = ctx.stack.pop().as_usize();
-                let size = ctx.stack.pop().as_usize();
-                let topic1 = ctx.stack.pop();
-                let topic2 = ctx.stack.pop();
-                let topic3 = ctx.stack.pop();
-                // GAS
-                ctx.gas += self.fix_gas();// + self.log_gas(offset, size);
-                // OPERATION
-                let data = ctx.memory.load(offset, size);
-                let mut log = Log::new(ctx.target, data);
-                log.add_topics(vec![topic1, topic2, topic3]);
-                ctx.add_log(log);
+                if ctx.stack.swap_top(16).is_err() {
+                    return Err(Halt::StackUnderflow);
+                }
                 // PC
                 ctx.pc += 1;
                 // SUCCESS
-                true
+                Ok(())
             },
-            Opcode::LOG4 => {
+            Opcode::LOG0 | Opcode::LOG1 | Opcode::LOG2 | Opcode::LOG3 | Opcode::LOG4 => {
+                // CHECK REVERT CONDITION
+                if ctx.env.call.is_static() {
+                    return Err(Halt::StaticCallViolation);
+                }
                 // STACK
-                let offset = ctx.stack.pop().as_usize();
-                let size = ctx.stack.pop().as_usize();
-                let topic1 = ctx.stack.pop();
-                let topic2 = ctx.stack.pop();
-                let topic3 = ctx.stack.pop();
-                let topic4 = ctx.stack.pop();
+                let (offset, size) = match memory_range(ctx.stack.pop(), ctx.stack.pop(), ctx.env.cfg.memory_limit) {
+                    Some(range) => range,
+                    None => return Err(Halt::MemoryLimitExceeded),
+                };
+                let topics: Vec<Bytes32> = (0..self.log_topics()).map(|_| ctx.stack.pop()).collect();
                 // GAS
-                ctx.gas += self.fix_gas();// + self.log_gas(offset, size);
+                charge_memory(ctx, log_gas(topics.len(), size), offset, size)?;
                 // OPERATION
                 let data = ctx.memory.load(offset, size);
                 let mut log = Log::new(ctx.target, data);
-                log.add_topics(vec![topic1, topic2, topic3, topic4]);
+                log.add_topics(topics);
                 ctx.add_log(log);
                 // PC
                 ctx.pc += 1;
                 // SUCCESS
-                true
+                Ok(())
             },
-            Opcode::CREATE => {
+            // CREATE2 only differs in the address of the contract, derived
+            // from a salt and the hash of the init code (EIP-1014)
+            Opcode::CREATE | Opcode::CREATE2 => {
                 // STACK
                 let value = ctx.stack.pop().to_u256();
-                let offset = ctx.stack.pop().as_usize();
-                let size = ctx.stack.pop().as_usize();
-                // CHECK REVERT CONDITION
-                if ctx.env.call.is_static() & !value.is_zero() {
-                    return false;
+                let (offset, size) = match memory_range(ctx.stack.pop(), ctx.stack.pop(), ctx.env.cfg.memory_limit) {
+                    Some(range) => range,
+                    None => return Err(Halt::MemoryLimitExceeded),
+                };
+                let salt = match self {
+                    Opcode::CREATE2 => Some(ctx.stack.pop()),
+                    _ => None,
+                };
+                // CHECK REVERT CONDITION (creating is a state change, even
+                // without value)
+                if ctx.env.call.is_static() {
+                    return Err(Halt::StaticCallViolation);
                 }
-                if !ctx.env.call.is_static() & (ctx.state.balance(&ctx.env.call.originator) < value) {
-                    return false;
+                // GAS (the init code gets all the gas left). CREATE2 also pays
+                // for hashing the init code.
+                let hash_gas = match salt {
+                    Some(_) => 6 * size.div_ceil(32),
+                    None => 0,
+                };
+                charge_memory(ctx, initcode_gas(ctx.env.spec, size) + hash_gas, offset, size)?;
+                let gas = forwarded_gas(ctx, U256::MAX)?;
+                // Oversized init code, or a value the contract can't afford,
+                // fails the creation before it touches the state (no nonce bump
+                // nor value transfer)
+                if is_initcode_too_large(ctx.env.spec, size) || ctx.state.balance(&ctx.target) < value {
+                    ctx.return_data = Bytes::new();
+                    ctx.stack.push(Bytes32::zero());
+                    ctx.pc += 1;
+                    return Ok(());
                 }
-                // GAS
-                ctx.gas += self.fix_gas();
                 // OPERATION
                 let data = ctx.memory.load(offset, size);
-                let mut rlp_encoded1 = rlp_encode(ctx.target.as_slice());
-                let mut rlp_encoded2 = rlp_encode(Bytes32::from_u256(ctx.state.nonce(&ctx.target)).as_slice());
-                rlp_encoded1.append(&mut rlp_encoded2);
-                let address = Address::from_slice(Keccak256::digest(rlp_encoded1).as_slice());
-                let call_result = ctx.create_call(address, value, data);
+                let (address, kind) = match salt {
+                    Some(salt) => (create2_address(&ctx.target, &salt, data.as_slice()), CallKind::Create2),
+                    None => (create_address(&ctx.target, ctx.state.nonce(&ctx.target)), CallKind::Create),
+                };
+                let call_result = ctx.create_call(address, value, data, gas, kind);
+                ctx.gas += call_result.gas_used;
                 if !call_result.success.is_zero() {
                     ctx.stack.push_address(address);
                 } else {
@@ -2163,233 +2053,614 @@ impl Opcode {
                 // PC
                 ctx.pc += 1;
                 // SUCCESS
-                true
+                Ok(())
             }
             Opcode::CALL => {
                 // STACK
                 let gas = ctx.stack.pop().to_u256();
                 let address = ctx.stack.pop().to_address();
                 let value = ctx.stack.pop().to_u256();
-                let args_offset = ctx.stack.pop().as_usize();
-                let args_size = ctx.stack.pop().as_usize();
-                let ret_offset = ctx.stack.pop().as_usize();
-                let ret_size = ctx.stack.pop().as_usize();
+                let (args_offset, args_size) = match memory_range(ctx.stack.pop(), ctx.stack.pop(), ctx.env.cfg.memory_limit) {
+                    Some(range) => range,
+                    None => return Err(Halt::MemoryLimitExceeded),
+                };
+                let (ret_offset, ret_size) = match memory_range(ctx.stack.pop(), ctx.stack.pop(), ctx.env.cfg.memory_limit) {
+                    Some(range) => range,
+                    None => return Err(Halt::MemoryLimitExceeded),
+                };
                 // CHECK REVERT CONDITION
                 if ctx.env.call.is_static() & !value.is_zero() {
-                    return false;
-                }
-                if !ctx.env.call.is_static() & (ctx.state.balance(&ctx.env.call.originator) < value) {
-                    return false;
+                    return Err(Halt::StaticCallViolation);
                 }
                 // GAS
-                ctx.gas += self.fix_gas();
+                let is_cold = ctx.state.access_account(&address);
+                let new_account = is_new_account(ctx, &address, value);
+                ctx.gas += call_gas(ctx.env.spec, !value.is_zero(), new_account, is_cold);
+                // The memory of the arguments and of the return data is paid
+                // up front (so the return data is stored for free)
+                charge_memory(ctx, 0, args_offset, args_size)?;
+                charge_memory(ctx, 0, ret_offset, ret_size)?;
+                let gas = forwarded_gas(ctx, gas)?;
+                let stipend = call_stipend(value);
                 // OPERATION
                 let data = ctx.memory.load(args_offset, args_size);
+                // Frames called from a static one are static too
                 let call = Call::new(
                     ctx.target,
                     address,
-                    ctx.env.call.originator,
-                    gas,
-                    U256::from(ctx.gas_left()),
                     address,
                     data,
                     value,
-                    false
+                    ctx.env.call.is_static()
                 );
-                let call_result = ctx.execute_call(call);
-                let mut data = vec![0u8; ret_size];
-                data.copy_from_slice(&call_result.result[0..ret_size]);
-                ctx.memory.store(ret_offset, Bytes::from_vec(data));
+                let call_result = ctx.execute_call(call, gas + stipend, CallKind::Call);
+                // The stipend is free for the caller
+                ctx.gas = (ctx.gas + call_result.gas_used).saturating_sub(stipend);
+                store_return_data(ctx, ret_offset, ret_size, &call_result.result);
                 ctx.stack.push(call_result.success);
                 // PC
                 ctx.pc += 1;
                 // SUCCESS
-                true
+                Ok(())
             },
             Opcode::CALLCODE => {
                 // STACK
                 let gas = ctx.stack.pop().to_u256();
                 let address = ctx.stack.pop().to_address();
                 let value = ctx.stack.pop().to_u256();
-                let args_offset = ctx.stack.pop().as_usize();
-                let args_size = ctx.stack.pop().as_usize();
-                let ret_offset = ctx.stack.pop().as_usize();
-                let ret_size = ctx.stack.pop().as_usize();
+                let (args_offset, args_size) = match memory_range(ctx.stack.pop(), ctx.stack.pop(), ctx.env.cfg.memory_limit) {
+                    Some(range) => range,
+                    None => return Err(Halt::MemoryLimitExceeded),
+                };
+                let (ret_offset, ret_size) = match memory_range(ctx.stack.pop(), ctx.stack.pop(), ctx.env.cfg.memory_limit) {
+                    Some(range) => range,
+                    None => return Err(Halt::MemoryLimitExceeded),
+                };
                 // GAS
-                ctx.gas += self.fix_gas();
+                let is_cold = ctx.state.access_account(&address);
+                // The value stays in the calling account, which exists
+                ctx.gas += call_gas(ctx.env.spec, !value.is_zero(), false, is_cold);
+                // The memory of the arguments and of the return data is paid
+                // up front (so the return data is stored for free)
+                charge_memory(ctx, 0, args_offset, args_size)?;
+                charge_memory(ctx, 0, ret_offset, ret_size)?;
+                let gas = forwarded_gas(ctx, gas)?;
+                let stipend = call_stipend(value);
                 // OPERATION
                 let data = ctx.memory.load(args_offset, args_size);
+                // Only the code comes from `address`: it runs against the
+                // storage and balance of the caller, which sends the value to
+                // itself
                 let call = Call::new(
                     ctx.target,
-                    address,
-                    ctx.env.call.originator,
-                    gas,
-                    U256::from(ctx.gas_left()),
+                    ctx.target,
                     address,
                     data,
                     value,
-                    false
+                    ctx.env.call.is_static()
                 );
-                let call_result = ctx.execute_call(call);
-                let mut data = vec![0u8; ret_size];
-                data.copy_from_slice(&call_result.result[0..ret_size]);
-                ctx.memory.store(ret_offset, Bytes::from_vec(data));
+                let call_result = ctx.execute_call(call, gas + stipend, CallKind::CallCode);
+                ctx.gas = (ctx.gas + call_result.gas_used).saturating_sub(stipend);
+                store_return_data(ctx, ret_offset, ret_size, &call_result.result);
                 ctx.stack.push(call_result.success);
                 // PC
                 ctx.pc += 1;
                 // SUCCESS
-                true
+                Ok(())
             },
             Opcode::RETURN => {
                 // STACK
-                let offset = ctx.stack.pop().as_usize();
-                let size = ctx.stack.pop().as_usize();
+                let (offset, size) = match memory_range(ctx.stack.pop(), ctx.stack.pop(), ctx.env.cfg.memory_limit) {
+                    Some(range) => range,
+                    None => return Err(Halt::MemoryLimitExceeded),
+                };
                 // GAS
-                ctx.gas += self.fix_gas() * ctx.memory.expansion(offset, size);
+                charge_memory(ctx, 0, offset, size)?;
                 // OPERATION
                 let value = ctx.memory.load(offset, size);
                 ctx.env.call.set_result(value.clone());
+                // RETURN halts the frame, so any data after it is never executed
+                ctx.stopped = true;
                 // PC
                 ctx.pc += 1;
                 // SUCCESS
-                true
+                Ok(())
             },
             Opcode::DELEGATECALL => {
                 // STACK
                 let gas = ctx.stack.pop().to_u256();
                 let address = ctx.stack.pop().to_address();
-                let args_offset = ctx.stack.pop().as_usize();
-                let args_size = ctx.stack.pop().as_usize();
-                let ret_offset = ctx.stack.pop().as_usize();
-                let ret_size = ctx.stack.pop().as_usize();
+                let (args_offset, args_size) = match memory_range(ctx.stack.pop(), ctx.stack.pop(), ctx.env.cfg.memory_limit) {
+                    Some(range) => range,
+                    None => return Err(Halt::MemoryLimitExceeded),
+                };
+                let (ret_offset, ret_size) = match memory_range(ctx.stack.pop(), ctx.stack.pop(), ctx.env.cfg.memory_limit) {
+                    Some(range) => range,
+                    None => return Err(Halt::MemoryLimitExceeded),
+                };
                 // GAS
-                ctx.gas += self.fix_gas();
+                let is_cold = ctx.state.access_account(&address);
+                ctx.gas += account_access_gas(ctx.env.spec, is_cold);
+                // The memory of the arguments and of the return data is paid
+                // up front (so the return data is stored for free)
+                charge_memory(ctx, 0, args_offset, args_size)?;
+                charge_memory(ctx, 0, ret_offset, ret_size)?;
+                let gas = forwarded_gas(ctx, gas)?;
                 // OPERATION
                 let data = ctx.memory.load(args_offset, args_size);
+                // The code of `address` runs as the frame itself: same
+                // context, caller and value (which isn't sent again)
                 let call = Call::new(
+                    ctx.env.call.sender,
                     ctx.target,
-                    ctx.target,
-                    ctx.env.call.originator,
-                    gas,
-                    U256::from(ctx.gas_left()),
                     address,
                     data,
-                    U256::zero(),
-                    false
+                    ctx.env.call.value,
+                    ctx.env.call.is_static()
                 );
-                let call_result = ctx.execute_call(call);
-                if !call_result.success.is_zero() {
-                    let mut data = vec![0u8; ret_size];
-                    data.copy_from_slice(&call_result.result[0..ret_size]);
-                    ctx.memory.store(ret_offset, Bytes::from_vec(data));
-                }
+                let call_result = ctx.execute_call(call, gas, CallKind::DelegateCall);
+                ctx.gas += call_result.gas_used;
+                store_return_data(ctx, ret_offset, ret_size, &call_result.result);
                 ctx.stack.push(call_result.success);
                 // PC
                 ctx.pc += 1;
                 // SUCCESS
-                true
-            },
-            Opcode::CREATE2 => {
-                todo!()
+                Ok(())
             },
             Opcode::STATICCALL => {
                 // STACK
                 let gas = ctx.stack.pop().to_u256();
                 let address = ctx.stack.pop().to_address();
-                let args_offset = ctx.stack.pop().as_usize();
-                let args_size = ctx.stack.pop().as_usize();
-                let ret_offset = ctx.stack.pop().as_usize();
-                let ret_size = ctx.stack.pop().as_usize();
+                let (args_offset, args_size) = match memory_range(ctx.stack.pop(), ctx.stack.pop(), ctx.env.cfg.memory_limit) {
+                    Some(range) => range,
+                    None => return Err(Halt::MemoryLimitExceeded),
+                };
+                let (ret_offset, ret_size) = match memory_range(ctx.stack.pop(), ctx.stack.pop(), ctx.env.cfg.memory_limit) {
+                    Some(range) => range,
+                    None => return Err(Halt::MemoryLimitExceeded),
+                };
                 // GAS
-                ctx.gas += self.fix_gas();
+                let is_cold = ctx.state.access_account(&address);
+                ctx.gas += account_access_gas(ctx.env.spec, is_cold);
+                // The memory of the arguments and of the return data is paid
+                // up front (so the return data is stored for free)
+                charge_memory(ctx, 0, args_offset, args_size)?;
+                charge_memory(ctx, 0, ret_offset, ret_size)?;
+                let gas = forwarded_gas(ctx, gas)?;
                 // OPERATION
                 let data = ctx.memory.load(args_offset, args_size);
                 let call = Call::new(
                     ctx.target,
                     address,
-                    ctx.env.call.originator,
-                    gas,
-                    U256::from(ctx.gas_left()),
                     address,
                     data,
                     U256::zero(),
                     true
                 );
-                let call_result = ctx.execute_call(call);
-                if !call_result.success.is_zero() {
-                    let mut data = vec![0u8; ret_size];
-                    data.copy_from_slice(&call_result.result[0..ret_size]);
-                    ctx.memory.store(ret_offset, Bytes::from_vec(data));
-                }
+                let call_result = ctx.execute_call(call, gas, CallKind::StaticCall);
+                ctx.gas += call_result.gas_used;
+                store_return_data(ctx, ret_offset, ret_size, &call_result.result);
                 ctx.stack.push(call_result.success);
                 // PC
                 ctx.pc += 1;
                 // SUCCESS
-                true
+                Ok(())
             },
             Opcode::REVERT => {
                 // STACK
-                let offset = ctx.stack.pop().as_usize();
-                let size = ctx.stack.pop().as_usize();
+                let (offset, size) = match memory_range(ctx.stack.pop(), ctx.stack.pop(), ctx.env.cfg.memory_limit) {
+                    Some(range) => range,
+                    None => return Err(Halt::MemoryLimitExceeded),
+                };
                 // GAS
-                ctx.gas += self.fix_gas() * ctx.memory.expansion(offset, size);
+                charge_memory(ctx, 0, offset, size)?;
                 // OPERATION
                 let value = ctx.memory.load(offset, size);
                 ctx.env.call.set_result(value);
                 // PC
                 ctx.pc += 1;
                 // SUCCESS
-                false
+                Err(Halt::Revert)
             },
             Opcode::INVALID => {
-                // PC
-                ctx.pc += 1;
-                // SUCCESS
-                false
+                // The pc stays at the INVALID, where the execution halted
+                Err(Halt::InvalidOpcode)
             },
             Opcode::SELFDESTRUCT => {
                 // STACK
                 let address = ctx.stack.pop().to_address();
                 // CHECK REVERT CONDITION
                 if ctx.env.call.is_static() {
-                    return false;
+                    return Err(Halt::StaticCallViolation);
                 }
                 // GAS
-                ctx.gas += self.fix_gas();
+                let is_cold = ctx.state.access_account(&address);
+                ctx.gas += cold_access_gas(ctx.env.spec, is_cold, COLD_ACCOUNT_ACCESS_COST);
+                let balance = ctx.state.balance(&ctx.target);
+                ctx.gas += selfdestruct_new_account_gas(ctx, &address, balance);
                 // OPERATION
-                match ctx.state.transfer(&ctx.target, &address, ctx.state.balance(&ctx.target)) {
+                match ctx.state.transfer(&ctx.target, &address, balance) {
                     Ok(_) => {
-                        ctx.selfdestruct();
+                        // Since Cancun, only accounts created in the same
+                        // transaction are deleted, the others just send their
+                        // balance (EIP-6780)
+                        if !ctx.env.spec.is_enabled_in(SpecId::Cancun) || ctx.state.is_created(&ctx.target) {
+                            // Refunded once per account until London (EIP-3529)
+                            if !ctx.env.spec.is_enabled_in(SpecId::London) && !ctx.to_delete.contains(&ctx.target) {
+                                ctx.refund += SELFDESTRUCT_REFUND;
+                            }
+                            ctx.selfdestruct();
+                        }
                         // PC
                         ctx.pc += 1;
                         // SUCCESS
-                        true
+                        Ok(())
                     }
-                    Err(_) => return false,
+                    Err(error) => Err(error.into()),
                 }
             }
         }
     }
 }
 
+// Gas: Access sets (EIP-2929)
+pub const WARM_STORAGE_READ_COST: usize = 100;
+pub const COLD_ACCOUNT_ACCESS_COST: usize = 2600;
+pub const COLD_SLOAD_COST: usize = 2100;
+// Gas: Per entry of the access list of a transaction, charged up front
+// (EIP-2930)
+pub const ACCESS_LIST_ADDRESS_COST: usize = 2400;
+pub const ACCESS_LIST_STORAGE_KEY_COST: usize = 1900;
+
+// Gas: Sending funds to an account that didn't exist (EIP-150)
+pub const NEW_ACCOUNT_COST: usize = 25000;
+// Gas: Sending a non-zero value in a CALL or CALLCODE
+pub const CALL_VALUE_COST: usize = 9000;
+// Gas: Given to the callee on top of the forwarded gas when value is sent
+pub const CALL_STIPEND: usize = 2300;
+
+// Size limit of init code since Shanghai (EIP-3860), twice the code size limit
+// of EIP-170
+pub const MAX_INITCODE_SIZE: usize = 2 * 24576;
+// Gas: Per word of init code since Shanghai (EIP-3860)
+pub const INITCODE_WORD_COST: usize = 2;
+
+// Gas: SSTORE of a new value in a slot, depending on whether the slot was
+// empty (set) or not (reset). Since Berlin, resetting costs 2100 less, the
+// price of the cold access that comes with it (EIP-2929).
+pub const SSTORE_SET_COST: usize = 20000;
+pub const SSTORE_RESET_COST: usize = 5000;
+// Gas: SSTORE halts when it is left with the stipend of a CALL or less
+// (EIP-2200)
+pub const SSTORE_SENTRY: usize = 2300;
+// Gas: Cost of a warm SLOAD, before Berlin and since Istanbul (EIP-1884)
+pub const ISTANBUL_SLOAD_COST: usize = 800;
+
+// Gas: Refunds. Clearing a slot earned 15000 until London (EIP-3529), and
+// SELFDESTRUCT 24000. SSTOREs restoring the original value of a slot get back
+// what setting or resetting it costs, minus a warm read (EIP-2200).
+pub const SSTORE_CLEARS_REFUND: i64 = 15000;
+pub const SSTORE_CLEARS_REFUND_LONDON: i64 = 4800;
+pub const SELFDESTRUCT_REFUND: i64 = 24000;
+// Gas: The refund of a transaction is capped to a fifth of the gas it used since
+// London (EIP-3529), to half of it before
+pub const MAX_REFUND_QUOTIENT: usize = 5;
+pub const MAX_REFUND_QUOTIENT_FRONTIER: usize = 2;
+
+// Memory range of `size` bytes from `offset`, as popped from the stack. Empty
+// ranges never touch the memory, so their offset is irrelevant (and may not fit
+// a usize). None if a non-empty range ends beyond `limit`.
+fn memory_range(offset: Bytes32, size: Bytes32, limit: usize) -> Option<(usize, usize)> {
+    checked_range(offset.to_u256(), size.to_u256(), limit)
+}
+
+fn checked_range(offset: U256, size: U256, limit: usize) -> Option<(usize, usize)> {
+    if size.is_zero() {
+        return Some((0, 0));
+    }
+    let (end, overflow) = offset.overflowing_add(size);
+    if overflow || end > U256::from(limit) {
+        return None;
+    }
+    Some((offset.as_usize(), size.as_usize()))
+}
+
+// Byte or bit index of BYTE, SIGNEXTEND and the shifts. Every index above
+// 255 has the same effect, so huge ones saturate instead of overflowing.
+fn saturated_index(word: Bytes32) -> usize {
+    word.to_u256().min(U256::from(256)).as_usize()
+}
+
+// Offset into a buffer (call data, code, return data), as popped from the
+// stack. Offsets beyond usize are past the end of any buffer, so they saturate.
+fn saturated_offset(word: Bytes32) -> usize {
+    word.to_u256().min(U256::from(usize::MAX)).as_usize()
+}
+
+// Gas of `size` bytes of init code, charged since Shanghai (EIP-3860)
+pub fn initcode_gas(spec: SpecId, size: usize) -> usize {
+    match spec.is_enabled_in(SpecId::Shanghai) {
+        true => INITCODE_WORD_COST * size.div_ceil(32),
+        false => 0,
+    }
+}
+
+// Whether `size` bytes of init code exceed the limit, enforced since Shanghai
+// (EIP-3860)
+pub fn is_initcode_too_large(spec: SpecId, size: usize) -> bool {
+    spec.is_enabled_in(SpecId::Shanghai) && size > MAX_INITCODE_SIZE
+}
+
+// Nonce a new contract starts with: 1 since Spurious Dragon (EIP-161)
+pub fn created_nonce(spec: SpecId) -> U256 {
+    match spec.is_enabled_in(SpecId::SpuriousDragon) {
+        true => U256::one(),
+        false => U256::zero(),
+    }
+}
+
+// Gas of the entries of an access list, since Berlin (EIP-2930)
+pub fn access_list_gas(spec: SpecId, access_list: &[(Address, Vec<U256>)]) -> usize {
+    if !spec.is_enabled_in(SpecId::Berlin) {
+        return 0;
+    }
+    access_list
+        .iter()
+        .map(|(_, keys)| ACCESS_LIST_ADDRESS_COST + ACCESS_LIST_STORAGE_KEY_COST * keys.len())
+        .sum()
+}
+
+// Refund of an SSTORE writing `new` over `current`, for a slot that held
+// `original` at the start of the transaction. Negative when it takes back an
+// earlier refund (a cleared slot being set again). Net metering (EIP-2200)
+// since Istanbul, before which only clearing a slot is refunded.
+pub fn sstore_refund(spec: SpecId, original: Bytes32, current: Bytes32, new: Bytes32) -> i64 {
+    let clears_refund = match spec.is_enabled_in(SpecId::London) {
+        true => SSTORE_CLEARS_REFUND_LONDON,
+        false => SSTORE_CLEARS_REFUND,
+    };
+    if !spec.is_enabled_in(SpecId::Istanbul) {
+        return match !current.is_zero() && new.is_zero() {
+            true => clears_refund,
+            false => 0,
+        };
+    }
+    if current == new {
+        return 0;
+    }
+    // First write of the slot in the transaction
+    if original == current {
+        return match !original.is_zero() && new.is_zero() {
+            true => clears_refund,
+            false => 0,
+        };
+    }
+    let mut refund = 0;
+    if !original.is_zero() {
+        if current.is_zero() {
+            refund -= clears_refund;
+        } else if new.is_zero() {
+            refund += clears_refund;
+        }
+    }
+    // Back to the original value: what the first write cost is refunded, but
+    // for a warm read
+    if original == new {
+        let first_write = sstore_gas(spec, original, original, current);
+        let warm_read = sstore_gas(spec, original, original, original);
+        refund += (first_write - warm_read) as i64;
+    }
+    refund
+}
+
+// Gas of an SSTORE writing `new` over `current`, for a slot that held
+// `original` at the start of the transaction (the cold surcharge aside). Since
+// Istanbul, only the first write of a new value in the transaction is priced
+// as a set or a reset, the others as a warm read (EIP-2200).
+pub fn sstore_gas(spec: SpecId, original: Bytes32, current: Bytes32, new: Bytes32) -> usize {
+    if !spec.is_enabled_in(SpecId::Istanbul) {
+        return match current.is_zero() && !new.is_zero() {
+            true => SSTORE_SET_COST,
+            false => SSTORE_RESET_COST,
+        };
+    }
+    let berlin = spec.is_enabled_in(SpecId::Berlin);
+    if current == new || original != current {
+        return match berlin {
+            true => WARM_STORAGE_READ_COST,
+            false => ISTANBUL_SLOAD_COST,
+        };
+    }
+    match (original.is_zero(), berlin) {
+        (true, _) => SSTORE_SET_COST,
+        (false, true) => SSTORE_RESET_COST - COLD_SLOAD_COST,
+        (false, false) => SSTORE_RESET_COST,
+    }
+}
+
+// Largest refund of a transaction that used `gas_used` gas
+pub fn max_refund(spec: SpecId, gas_used: usize) -> usize {
+    match spec.is_enabled_in(SpecId::London) {
+        true => gas_used / MAX_REFUND_QUOTIENT,
+        false => gas_used / MAX_REFUND_QUOTIENT_FRONTIER,
+    }
+}
+
+// Surcharge of a cold access, since Berlin (EIP-2929)
+fn cold_access_gas(spec: SpecId, is_cold: bool, surcharge: usize) -> usize {
+    if is_cold && spec.is_enabled_in(SpecId::Berlin) {
+        surcharge
+    } else {
+        0
+    }
+}
+
+// Surcharge on top of the (warm) fixed gas of an opcode accessing an account
+fn account_access_gas(spec: SpecId, is_cold: bool) -> usize {
+    cold_access_gas(spec, is_cold, COLD_ACCOUNT_ACCESS_COST - WARM_STORAGE_READ_COST)
+}
+
+// Dynamic gas of a CALL (or CALLCODE) besides the memory expansion: the access
+// to the account, the value transfer and the creation of the recipient
+pub fn call_gas(spec: SpecId, value_transfer: bool, new_account: bool, is_cold: bool) -> usize {
+    let mut gas = account_access_gas(spec, is_cold);
+    if value_transfer {
+        gas += CALL_VALUE_COST;
+    }
+    if new_account {
+        gas += NEW_ACCOUNT_COST;
+    }
+    gas
+}
+
+// Charges `gas` along with the expansion of the memory to cover `size` bytes
+// from `offset`, and then expands it. A frame that can't afford it halts
+// before the memory grows, so unpayable sizes are never allocated.
+fn charge_memory(ctx: &mut ExecutionContext, gas: usize, offset: usize, size: usize) -> Result<(), Halt> {
+    let total = ctx
+        .memory
+        .checked_expansion_cost(offset, size)
+        .and_then(|expansion| expansion.checked_add(gas))
+        .and_then(|cost| ctx.gas.checked_add(cost));
+    match total {
+        Some(total) if total <= ctx.gas_limit => {
+            ctx.gas = total;
+            // Charged above
+            let _ = ctx.memory.expand(offset, size);
+            Ok(())
+        }
+        _ => Err(Halt::OutOfGas),
+    }
+}
+
+// Gas given to a subcall asking for `requested`: at most what the frame has
+// left (the 63/64 rule of EIP-150 isn't applied yet)
+fn forwarded_gas(ctx: &ExecutionContext, requested: U256) -> Result<usize, Halt> {
+    let available = match ctx.gas_limit.checked_sub(ctx.gas) {
+        Some(available) => available,
+        None => return Err(Halt::OutOfGas),
+    };
+    Ok(match requested > U256::from(available) {
+        true => available,
+        false => requested.as_usize(),
+    })
+}
+
+// Copies the return data of a subcall into the caller's return buffer: no more
+// than the data holds, the rest of the buffer keeps what it had
+fn store_return_data(ctx: &mut ExecutionContext, ret_offset: usize, ret_size: usize, data: &Bytes) {
+    let size = ret_size.min(data.len());
+    ctx.gas += ctx.memory.store(ret_offset, data.padded_slice(0, size));
+}
+
+// Extra gas given to the callee of a CALL (or CALLCODE) sending value
+fn call_stipend(value: U256) -> usize {
+    match value.is_zero() {
+        true => 0,
+        false => CALL_STIPEND,
+    }
+}
+
+// Whether sending `value` to `address` pays for a new account: any missing
+// account before Spurious Dragon, and only a non-zero value sent to an empty
+// account since then (EIP-161)
+fn is_new_account(ctx: &ExecutionContext, address: &Address, value: U256) -> bool {
+    match ctx.env.spec.is_enabled_in(SpecId::SpuriousDragon) {
+        true => !value.is_zero() && ctx.state.is_empty(address),
+        false => !ctx.state.exists(address),
+    }
+}
+
+// Surcharge of a SELFDESTRUCT that sends funds to a new account, since
+// Tangerine (EIP-150)
+fn selfdestruct_new_account_gas(ctx: &ExecutionContext, beneficiary: &Address, balance: U256) -> usize {
+    match is_new_account(ctx, beneficiary, balance) && ctx.env.spec.is_enabled_in(SpecId::Tangerine) {
+        true => NEW_ACCOUNT_COST,
+        false => 0,
+    }
+}
+
+// Gas of the latest fork in `schedule` (sorted by fork) enabled in `spec`
+fn gas_since(spec: SpecId, schedule: &[(SpecId, usize)]) -> usize {
+    schedule
+        .iter()
+        .rev()
+        .find(|(fork, _)| spec.is_enabled_in(*fork))
+        .map_or(0, |(_, gas)| *gas)
+}
+
+// Dynamic gas of a SHA3 hashing `words` words (without the memory expansion)
+pub fn sha3_gas(words: usize) -> Option<usize> {
+    words.checked_mul(6)
+}
+
+// Dynamic gas of an EXP: per byte of the exponent, 50 since Spurious Dragon
+// (EIP-160) and 10 before
+pub fn exp_gas(spec: SpecId, exponent: U256) -> usize {
+    let byte_gas = match spec.is_enabled_in(SpecId::SpuriousDragon) {
+        true => 50,
+        false => 10,
+    };
+    byte_gas * exponent.bits().div_ceil(8)
+}
+
+// Dynamic gas of copying `size` bytes to memory (CALLDATACOPY, CODECOPY,
+// EXTCODECOPY, RETURNDATACOPY), without the memory expansion
+pub fn copy_gas(size: usize) -> usize {
+    3 * size.div_ceil(32)
+}
+
+// Dynamic gas of a LOG with `topic_count` topics and `size` bytes of data
+// (without the memory expansion)
+fn log_gas(topic_count: usize, size: usize) -> usize {
+    375 * topic_count + 8 * size
+}
+
 impl Opcode {
-    pub fn fix_gas(&self) -> usize {
+    // Number of topics of a LOG opcode
+    pub fn log_topics(&self) -> usize {
+        match self {
+            Opcode::LOG1 => 1,
+            Opcode::LOG2 => 2,
+            Opcode::LOG3 => 3,
+            Opcode::LOG4 => 4,
+            _ => 0,
+        }
+    }
+
+    pub fn fix_gas(&self, spec: SpecId) -> usize {
         match self {
-            // Gas: Zero
+            // Gas: Zero (the cost of these is either nil or fully dynamic)
             Opcode::STOP => 0,
+            Opcode::RETURN => 0,
+            Opcode::REVERT => 0,
             Opcode::INVALID => 0,
+            Opcode::SSTORE => 0,
             // Gas: Jumpdest
             Opcode::JUMPDEST => 1,
             // Gas: Base
             Opcode::ADDRESS => 2,
+            Opcode::ORIGIN => 2,
+            Opcode::CALLER => 2,
+            Opcode::CALLVALUE => 2,
+            Opcode::CALLDATASIZE => 2,
+            Opcode::CODESIZE => 2,
+            Opcode::GASPRICE => 2,
+            Opcode::RETURNDATASIZE => 2,
+            Opcode::COINBASE => 2,
+            Opcode::TIMESTAMP => 2,
+            Opcode::NUMBER => 2,
+            Opcode::PREVRANDAO => 2,
+            Opcode::GASLIMIT => 2,
+            Opcode::CHAINID => 2,
+            Opcode::BASEFEE => 2,
+            Opcode::BLOBBASEFEE => 2,
             Opcode::POP => 2,
             Opcode::PC => 2,
             Opcode::MSIZE => 2,
             Opcode::GAS => 2,
+            Opcode::PUSH0 => 2,
             // Gas: Verylow
-            Opcode::MLOAD => 3,
-            Opcode::MSTORE => 3,
-            Opcode::MSTORE8 => 3,
             Opcode::ADD => 3,
             Opcode::SUB => 3,
             Opcode::LT => 3,
@@ -2406,6 +2677,10 @@ impl Opcode {
             Opcode::SHL => 3,
             Opcode::SHR => 3,
             Opcode::SAR => 3,
+            Opcode::CALLDATALOAD => 3,
+            Opcode::MLOAD => 3,
+            Opcode::MSTORE => 3,
+            Opcode::MSTORE8 => 3,
             Opcode::PUSH1 => 3,
             Opcode::PUSH2 => 3,
             Opcode::PUSH3 => 3,
@@ -2470,6 +2745,11 @@ impl Opcode {
             Opcode::SWAP14 => 3,
             Opcode::SWAP15 => 3,
             Opcode::SWAP16 => 3,
+            Opcode::BLOBHASH => 3,
+            // Gas: Copy (plus 3 per copied word)
+            Opcode::CALLDATACOPY => 3,
+            Opcode::CODECOPY => 3,
+            Opcode::RETURNDATACOPY => 3,
             // Gas: Low
             Opcode::MUL => 5,
             Opcode::DIV => 5,
@@ -2477,6 +2757,7 @@ impl Opcode {
             Opcode::MOD => 5,
             Opcode::SMOD => 5,
             Opcode::SIGNEXTEND => 5,
+            Opcode::SELFBALANCE => 5,
             // Gas: Mid
             Opcode::ADDMOD => 8,
             Opcode::MULMOD => 8,
@@ -2484,58 +2765,49 @@ impl Opcode {
             // Gas: High
             Opcode::EXP => 10,
             Opcode::JUMPI => 10,
-            // Gas: Copy
-            // Gas: Call
-            // Gas: Extaccount
-            // Gas: Keccak
+            // Gas: Blockhash
+            Opcode::BLOCKHASH => 20,
+            // Gas: Keccak (plus 6 per hashed word)
             Opcode::SHA3 => 30,
-            // TODO:
-            _ => 0,
+            // Gas: Warm access (plus the cold surcharge of EIP-2929). Repriced by
+            // EIP-150 (Tangerine), EIP-1884 (Istanbul) and EIP-2929 (Berlin)
+            Opcode::BALANCE => gas_since(spec, &[
+                (SpecId::Frontier, 20), (SpecId::Tangerine, 400), (SpecId::Istanbul, 700), (SpecId::Berlin, 100),
+            ]),
+            Opcode::EXTCODESIZE | Opcode::EXTCODECOPY => gas_since(spec, &[
+                (SpecId::Frontier, 20), (SpecId::Tangerine, 700), (SpecId::Berlin, 100),
+            ]),
+            Opcode::EXTCODEHASH => gas_since(spec, &[
+                (SpecId::Constantinople, 400), (SpecId::Istanbul, 700), (SpecId::Berlin, 100),
+            ]),
+            Opcode::SLOAD => gas_since(spec, &[
+                (SpecId::Frontier, 50), (SpecId::Tangerine, 200), (SpecId::Istanbul, 800), (SpecId::Berlin, 100),
+            ]),
+            Opcode::CALL | Opcode::CALLCODE | Opcode::DELEGATECALL | Opcode::STATICCALL => gas_since(spec, &[
+                (SpecId::Frontier, 40), (SpecId::Tangerine, 700), (SpecId::Berlin, 100),
+            ]),
+            // Gas: Log (plus 375 per topic and 8 per byte of data)
+            Opcode::LOG0 => 375,
+            Opcode::LOG1 => 375,
+            Opcode::LOG2 => 375,
+            Opcode::LOG3 => 375,
+            Opcode::LOG4 => 375,
+            // Gas: Selfdestruct
+            Opcode::SELFDESTRUCT => gas_since(spec, &[(SpecId::Frontier, 0), (SpecId::Tangerine, 5000)]),
+            // Gas: Create
+            Opcode::CREATE => 32000,
+            Opcode::CREATE2 => 32000,
         }
     }
 }
 
-fn validate_jumpdest(code: &Bytes, pc_new: usize) -> bool {
-    // Ensure informed jump destination
-    match code[pc_new].try_into().unwrap() {
-        Opcode::JUMPDEST => {
-            // Ensure valid jump destination
-            !matches!(
-                code[pc_new - 1].try_into().unwrap(),
-                Opcode::PUSH1
-                    | Opcode::PUSH2
-                    | Opcode::PUSH3
-                    | Opcode::PUSH4
-                    | Opcode::PUSH5
-                    | Opcode::PUSH6
-                    | Opcode::PUSH7
-                    | Opcode::PUSH8
-                    | Opcode::PUSH9
-                    | Opcode::PUSH10
-                    | Opcode::PUSH11
-                    | Opcode::PUSH12
-                    | Opcode::PUSH13
-                    | Opcode::PUSH14
-                    | Opcode::PUSH15
-                    | Opcode::PUSH16
-                    | Opcode::PUSH17
-                    | Opcode::PUSH18
-                    | Opcode::PUSH19
-                    | Opcode::PUSH20
-                    | Opcode::PUSH21
-                    | Opcode::PUSH22
-                    | Opcode::PUSH23
-                    | Opcode::PUSH24
-                    | Opcode::PUSH25
-                    | Opcode::PUSH26
-                    | Opcode::PUSH27
-                    | Opcode::PUSH28
-                    | Opcode::PUSH29
-                    | Opcode::PUSH30
-                    | Opcode::PUSH31
-                    | Opcode::PUSH32
-            )
-        }
-        _ => false,
+// Offset of a valid jump destination: a JUMPDEST within the code, which isn't
+// part of the immediate of a PUSHn. None for anything else, including
+// destinations past the end of the code (however large).
+fn jump_destination(code: &AnalyzedCode, destination: U256) -> Option<usize> {
+    if destination >= U256::from(code.len()) {
+        return None;
     }
+    let destination = destination.as_usize();
+    code.is_jumpdest(destination).then_some(destination)
 }
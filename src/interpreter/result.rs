@@ -0,0 +1,63 @@
+use crate::prelude::*;
+use crate::halt::Halt;
+use crate::logs::Log;
+use crate::types::{Address, Bytes, Bytes32};
+
+// Outcome of an execution, a frame run on its own or a whole transaction.
+// Also exported at the root of the crate.
+#[derive(Debug, Clone, Default)]
+pub struct EvmResult {
+    // Resulting stack after the EVM execution
+    pub stack: Vec<Bytes32>,
+    // Resulting logs after the EVM execution
+    pub logs: Vec<Log>,
+    // Whether the transaction was successful or not
+    pub success: bool,
+    // Result of the transaction execution
+    pub result: Bytes,
+    // Gas consumed by the execution
+    pub gas_used: usize,
+    // Gas refunded at the end of the transaction, already taken off
+    // `gas_used` by the transact path (which caps it, see `max_refund`). For
+    // a frame run on its own, the refunds it earned, uncapped.
+    pub gas_refunded: usize,
+    // Gas limit of the transaction (saturated to usize::MAX)
+    pub gas_limit: usize,
+    // Why the execution didn't succeed, if it didn't
+    pub halt: Option<Halt>,
+    // Contract deployed by a successful creation transaction
+    pub created_address: Option<Address>,
+    // Largest memory of a single frame (in bytes)
+    pub peak_memory_bytes: usize,
+    // Largest memory of all the simultaneously live frames (in bytes)
+    pub peak_live_memory_bytes: usize,
+    // Largest number of items in the stack of a single frame
+    pub peak_stack_depth: usize,
+    // Deepest nested call (0 if no call was made)
+    pub max_call_depth: usize,
+    // Instructions executed, subcalls included
+    pub step_count: u64,
+}
+
+impl EvmResult {
+    // Execution that returned `output`, with everything else zero or empty
+    pub fn success(output: Bytes) -> Self {
+        Self { success: true, result: output, ..Self::default() }
+    }
+
+    // Execution that reverted with `output`
+    pub fn revert(output: Bytes) -> Self {
+        Self { result: output, halt: Some(Halt::Revert), ..Self::default() }
+    }
+
+    // Execution stopped by `halt`: a REVERT without data, or an exceptional
+    // halt, which returns none
+    pub fn failure(halt: Halt) -> Self {
+        Self { halt: Some(halt), ..Self::default() }
+    }
+
+    // Gas of the limit that the execution didn't use
+    pub fn gas_left(&self) -> usize {
+        self.gas_limit.saturating_sub(self.gas_used)
+    }
+}
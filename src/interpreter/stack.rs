@@ -1,3 +1,4 @@
+use crate::prelude::*;
 use crate::types::{U256, Address, Bytes32};
 
 const MAX_STACK_DEPTH: usize = 1024;
@@ -7,6 +8,8 @@ const MAX_STACK_DEPTH: usize = 1024;
 pub struct Stack {
     items: Vec<Bytes32>,
     max_depth: usize,
+    // Highest number of items held at once
+    peak_depth: usize,
 }
 
 impl Stack {
@@ -14,6 +17,7 @@ impl Stack {
         Self {
             items: Vec::new(),
             max_depth: MAX_STACK_DEPTH,
+            peak_depth: 0,
         }
     }
 
@@ -23,8 +27,8 @@ impl Stack {
         if self.items.len() == self.max_depth {
             panic!("Stack overflow");
         }
-        println!(" > PUSH {:#X}", value);
         self.items.push(value);
+        self.peak_depth = self.peak_depth.max(self.items.len());
     }
 
     pub fn push_u256(&mut self, number: U256) {
@@ -47,34 +51,54 @@ impl Stack {
         self.items.pop().unwrap()
     }
 
+    // Swaps the top item with the one `depth` positions below it (SWAPn swaps
+    // with depth n). Panics if there are not enough items.
     pub fn swap(&mut self, depth: usize) {
+        self.swap_top(depth).unwrap();
+    }
+
+    // Swaps the top item with the one `depth` positions below it (SWAPn swaps
+    // with depth n). Fails, leaving the stack untouched, if there are not
+    // enough items.
+    pub fn swap_top(&mut self, depth: usize) -> Result<(), String> {
         let stack_depth = self.depth();
         if depth >= stack_depth {
-            panic!("Stack underflow");
+            return Err("Stack underflow".to_string());
         }
 
-        let index = stack_depth - depth - 1;
-        self.items.swap(index, stack_depth - 1);
+        self.items.swap(stack_depth - depth - 1, stack_depth - 1);
+        Ok(())
     }
 
     // Stack Getters
 
-    pub fn items(&self) -> &Vec<Bytes32> {
+    // Items from the bottom to the top of the stack (the order of EIP-3155
+    // traces)
+    pub fn items_bottom_first(&self) -> &[Bytes32] {
         &self.items
     }
 
-    pub fn get_item(&self, depth: usize) -> Option<Bytes32> {
-        if depth >= self.items.len() {
-            None
-        } else {
-            Some(self.items[depth].clone())
-        }
+    // Items from the top to the bottom of the stack (the order of the expected
+    // stacks in `evm.json`, and of `EvmResult::stack`)
+    pub fn items_top_first(&self) -> Vec<Bytes32> {
+        self.items.iter().rev().copied().collect()
+    }
+
+    // Item at `index`, counting from the bottom of the stack (0 is the first
+    // item pushed). See `peek` to count from the top.
+    pub fn get_item(&self, index: usize) -> Option<Bytes32> {
+        self.items.get(index).copied()
     }
 
-    pub fn deref_items(&self) -> Vec<Bytes32> {
-        let mut items = self.items.clone();
-        items.reverse();
-        items
+    // Item `depth` positions below the top of the stack, without popping it
+    // (0 is the top, so DUPn duplicates `peek(n - 1)`)
+    pub fn peek(&self, depth: usize) -> Option<Bytes32> {
+        let index = self.depth().checked_sub(depth + 1)?;
+        self.get_item(index)
+    }
+
+    pub fn top(&self) -> Option<Bytes32> {
+        self.peek(0)
     }
 
     pub fn max_depth(&self) -> usize {
@@ -84,4 +108,15 @@ impl Stack {
     pub fn depth(&self) -> usize {
         self.items.len()
     }
+
+    pub fn peak_depth(&self) -> usize {
+        self.peak_depth
+    }
+}
+
+// Renders the stack as `[top, ..., bottom]`, each item in hex without leading
+// zeros (e.g. `[0x3, 0x0, 0xff]`)
+pub fn fmt_stack(stack: &Stack) -> String {
+    let items: Vec<String> = stack.items.iter().rev().map(|item| format!("{:#x}", item.to_u256())).collect();
+    format!("[{}]", items.join(", "))
 }
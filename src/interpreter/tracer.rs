@@ -0,0 +1,152 @@
+use serde::{Serialize, Serializer};
+
+use crate::prelude::*;
+use crate::env::Call;
+use crate::halt::Halt;
+use crate::types::{Address, Bytes, U256};
+
+// How a frame was entered
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum CallKind {
+    Call,
+    CallCode,
+    DelegateCall,
+    StaticCall,
+    Create,
+    Create2,
+}
+
+// Call tree of an execution, collected by the `ExecutionContext` when enabled
+// with `with_call_tracer`. Serializes like the `callTracer` of geth.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CallTrace {
+    #[serde(rename = "type")]
+    pub kind: CallKind,
+    pub from: Address,
+    // Account whose code runs (the created one for creations)
+    pub to: Address,
+    // None for DELEGATECALL and STATICCALL, which carry no value of their own
+    #[serde(serialize_with = "optional_quantity", skip_serializing_if = "Option::is_none")]
+    pub value: Option<U256>,
+    // Gas given to the frame (and gas it used)
+    #[serde(serialize_with = "quantity")]
+    pub gas: usize,
+    #[serde(serialize_with = "quantity")]
+    pub gas_used: usize,
+    #[serde(serialize_with = "hex")]
+    pub input: Bytes,
+    #[serde(serialize_with = "hex")]
+    pub output: Bytes,
+    // Why the frame failed, if it did
+    #[serde(rename = "error", serialize_with = "error", skip_serializing_if = "Option::is_none")]
+    pub halt: Option<Halt>,
+    // Subcalls, in execution order
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub calls: Vec<CallTrace>,
+}
+
+impl CallTrace {
+    // Trace of a frame entered with `call` and `gas`, until it finishes
+    pub fn new(kind: CallKind, call: &Call, gas: usize) -> Self {
+        let input = match kind {
+            // The init code isn't the call data of the frame
+            CallKind::Create | CallKind::Create2 => Bytes::new(),
+            _ => call.data(),
+        };
+        let value = match kind {
+            CallKind::DelegateCall | CallKind::StaticCall => None,
+            _ => Some(call.value),
+        };
+        // The caller of a DELEGATECALL frame is inherited: the call comes from
+        // the delegating contract, which is the frame's context
+        let from = match kind {
+            CallKind::DelegateCall => call.recipient,
+            _ => call.sender,
+        };
+        Self {
+            kind,
+            from,
+            to: call.code_target,
+            value,
+            gas,
+            gas_used: 0,
+            input,
+            output: Bytes::new(),
+            halt: None,
+            calls: Vec::new(),
+        }
+    }
+
+    // For creations, which carry their init code as input
+    pub fn with_input(mut self, input: Bytes) -> Self {
+        self.input = input;
+        self
+    }
+
+    pub fn finish(&mut self, gas_used: usize, output: Bytes, halt: Option<Halt>) {
+        self.gas_used = gas_used;
+        self.output = output;
+        self.halt = halt;
+    }
+
+    pub fn success(&self) -> bool {
+        self.halt.is_none()
+    }
+}
+
+// State of a frame before an instruction, collected by the `ExecutionContext`
+// when enabled with `with_step_tracer`. Serializes as a line of an EIP-3155
+// trace.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Step {
+    pub pc: usize,
+    pub op: u8,
+    // Gas left before the instruction, and gas it cost (subcalls included)
+    #[serde(serialize_with = "quantity")]
+    pub gas: usize,
+    #[serde(serialize_with = "quantity")]
+    pub gas_cost: usize,
+    pub mem_size: usize,
+    // From the bottom to the top
+    #[serde(serialize_with = "quantities")]
+    pub stack: Vec<U256>,
+    // 1 for the top-level frame
+    pub depth: usize,
+    pub refund: i64,
+    pub op_name: String,
+    // Why the instruction halted the frame, if it did
+    #[serde(serialize_with = "error", skip_serializing_if = "Option::is_none")]
+    pub error: Option<Halt>,
+}
+
+fn quantity<S: Serializer, T: Into<U256> + Copy>(value: &T, serializer: S) -> Result<S::Ok, S::Error> {
+    let value: U256 = (*value).into();
+    serializer.serialize_str(&format!("{:#x}", value))
+}
+
+fn optional_quantity<S: Serializer>(value: &Option<U256>, serializer: S) -> Result<S::Ok, S::Error> {
+    match value {
+        Some(value) => quantity(value, serializer),
+        None => serializer.serialize_none(),
+    }
+}
+
+fn quantities<S: Serializer>(values: &[U256], serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.collect_seq(values.iter().map(|value| format!("{:#x}", value)))
+}
+
+fn hex<S: Serializer>(bytes: &Bytes, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(&format!("0x{}", hex::encode(bytes.as_slice())))
+}
+
+// Worded like geth for reverts
+fn error<S: Serializer>(halt: &Option<Halt>, serializer: S) -> Result<S::Ok, S::Error> {
+    match halt {
+        Some(Halt::Revert) => serializer.serialize_str("execution reverted"),
+        Some(halt) => serializer.serialize_str(&halt.to_string()),
+        None => serializer.serialize_none(),
+    }
+}
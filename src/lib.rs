@@ -2,24 +2,36 @@
 // Project:      EVM from scrustch
 // Description:  A minimal implementation of the Ethereum Virtual Machine, from scratch.
 
+// Without the `std` feature, only the interpreter core is built, on `alloc`
+#![cfg_attr(not(feature = "std"), no_std)]
+
+#[cfg_attr(not(feature = "std"), macro_use)]
+extern crate alloc;
+
+mod prelude;
 pub mod utils;
 pub mod primitives;
 pub mod interpreter;
+#[cfg(feature = "std")]
+pub mod evmtest;
+#[cfg(feature = "std")]
+pub mod statetest;
+pub mod asm;
+pub mod abi;
+pub mod code_analysis;
+pub mod evm;
+#[cfg(feature = "fork")]
+pub mod fork;
+#[cfg(feature = "wasm")]
+pub mod wasm;
 
 pub use primitives::*;
 pub use interpreter::*;
+pub use evm::{Evm, SnapshotId};
 
-#[derive(Debug, Clone)]
-pub struct EvmResult {
-    // Resulting stack after the EVM execution
-    pub stack: Vec<Bytes32>,
-    // Resulting logs after the EVM execution
-    pub logs: Vec<Log>,
-    // Whether the transaction was successful or not
-    pub success: bool,
-    // Result of the transaction execution
-    pub result: Bytes,
-}
+use log::{debug, trace};
+use prelude::*;
+use utils::{create_address, sorted_addresses};
 
 #[derive(Debug, Clone)]
 pub struct CallResult {
@@ -27,6 +39,8 @@ pub struct CallResult {
     pub success: Bytes32,
     // Result of the transaction execution
     pub result: Bytes,
+    // Gas used by the subframe
+    pub gas_used: usize,
 }
 
 #[derive(Debug, Clone)]
@@ -36,7 +50,10 @@ pub struct ExecutionContext {
     // Address targeted by the current execution
     pub target: Address,
     // Code to be executed in the current execution
-    pub code: Bytes,
+    pub code: Arc<AnalyzedCode>,
+    // Code of the accounts called so far in the transaction, handed down to
+    // (and back from) the subcalls
+    pub code_cache: CodeCache,
     // Program counter of the current execution
     pub pc: usize,
     // Stack of the current execution
@@ -47,6 +64,12 @@ pub struct ExecutionContext {
     pub memory: Memory,
     // Gas consumed by the current execution
     pub gas: usize,
+    // Gas available to the current execution. Using more halts it.
+    pub gas_limit: usize,
+    // Gas to refund at the end of the transaction, so far (SSTOREs can take
+    // back earlier refunds). Subcalls start from their caller's, and hand it
+    // back unless they fail.
+    pub refund: i64,
     // Return data resulting from the execution
     pub return_data: Bytes,
     // Logs of the current execution
@@ -55,38 +78,229 @@ pub struct ExecutionContext {
     pub stopped: bool,
     // Addresses to be deleted at the end of the execurion
     pub to_delete: Vec<Address>,
+    // Call depth of the current execution (0 for the top-level one)
+    pub depth: usize,
+    // Memory held by the calling frames (in bytes)
+    pub parent_memory: usize,
+    // Resource high-water marks of the current execution and its subcalls
+    pub peak_memory_bytes: usize,
+    pub peak_live_memory_bytes: usize,
+    pub peak_stack_depth: usize,
+    pub max_call_depth: usize,
+    // Instructions executed so far by the transaction, checked against
+    // `env.cfg.max_steps`. Subcalls start from (and hand back) their caller's count.
+    pub step_count: u64,
+    // Whether the frame or one of its subcalls ran out of steps
+    pub out_of_steps: bool,
+    // Opcode-level profile, when enabled with `with_metrics`
+    pub metrics: Option<Box<Metrics>>,
+    // Results of SHA3, when enabled with `env.cfg.keccak_cache`. Created by the
+    // first SHA3 and handed down to (and back from) the subcalls.
+    pub keccak_cache: Option<Box<KeccakCache>>,
+    // Trace of the frame and its subcalls, when enabled with `with_call_tracer`
+    pub call_trace: Option<Box<CallTrace>>,
+    // Instructions executed by the frame and its subcalls, in order, when
+    // enabled with `with_step_tracer`
+    pub steps: Option<Vec<Step>>,
 }
 
 impl ExecutionContext {
-    pub fn new(call: Call, block: Block, state: State, code: Bytes) -> Self {
+    pub fn new(mut tx: TxEnv, call: Call, block: Block, state: State, code: Bytes) -> Self {
         let target = call.recipient;
+        let gas_limit = saturated_usize(tx.gas_limit);
+        // Resolved once, for GASPRICE
+        tx.gas_price = tx.effective_gas_price(block.base_fee);
 
         Self {
-            env: Env::new(call, block),
+            env: Env::new(tx, call, block),
             state,
-            code,
+            code: Arc::new(AnalyzedCode::new(code)),
+            code_cache: CodeCache::new(),
             stack: Stack::new(),
             memory: Memory::new(),
             pc: 0,
             gas: 0,
+            gas_limit,
+            refund: 0,
             target,
             return_data: Bytes::new(),
             stopped: false,
             to_delete: Vec::new(),
             logs: Vec::new(),
+            depth: 0,
+            parent_memory: 0,
+            peak_memory_bytes: 0,
+            peak_live_memory_bytes: 0,
+            peak_stack_depth: 0,
+            max_call_depth: 0,
+            step_count: 0,
+            out_of_steps: false,
+            metrics: None,
+            keccak_cache: None,
+            call_trace: None,
+            steps: None,
         }
     }
 
-    pub fn sub_ctx(&self, code: Bytes, call: Call) -> Self {
+    // Collects the metrics of every executed opcode (see `metrics`)
+    pub fn with_metrics(mut self) -> Self {
+        self.metrics = Some(Box::default());
+        self
+    }
+
+    pub fn metrics(&self) -> Option<&Metrics> {
+        self.metrics.as_deref()
+    }
+
+    // Records the call tree of the execution (see `call_trace`), entered as a
+    // CALL
+    pub fn with_call_tracer(mut self) -> Self {
+        self.call_trace = Some(Box::new(CallTrace::new(CallKind::Call, &self.env.call, self.gas_limit)));
+        self
+    }
+
+    pub fn call_trace(&self) -> Option<&CallTrace> {
+        self.call_trace.as_deref()
+    }
+
+    // Records every executed instruction (see `steps`), like an EIP-3155 trace
+    pub fn with_step_tracer(mut self) -> Self {
+        self.steps = Some(Vec::new());
+        self
+    }
+
+    pub fn steps(&self) -> Option<&[Step]> {
+        self.steps.as_deref()
+    }
+
+    // Executes under the rules of `spec` (Shanghai by default)
+    pub fn with_spec(mut self, spec: SpecId) -> Self {
+        self.env.spec = spec;
+        self
+    }
+
+    // Halts any frame expanding its memory beyond `memory_limit` bytes
+    pub fn with_memory_limit(mut self, memory_limit: usize) -> Self {
+        self.env.cfg.memory_limit = memory_limit;
+        self
+    }
+
+    // Halts the execution after `max_steps` instructions (subcalls included),
+    // as a guard against untrusted code looping forever
+    pub fn with_max_steps(mut self, max_steps: u64) -> Self {
+        self.env.cfg.max_steps = Some(max_steps);
+        self
+    }
+
+    // Chain settings, limits and checks of the transaction (see `CfgEnv`)
+    pub fn with_cfg(mut self, cfg: CfgEnv) -> Self {
+        self.env.cfg = cfg;
+        self
+    }
+
+    // Frame running `code` for `call`, with `gas_limit` gas of its own
+    pub fn sub_ctx(&self, code: Arc<AnalyzedCode>, call: Call, gas_limit: usize) -> Self {
         let mut sub_ctx = self.clone();
         // Update the execution subcontext for the call
         sub_ctx.target = call.recipient;
         sub_ctx.code = code;
         sub_ctx.env.call = call;
         sub_ctx.pc = 0;
+        sub_ctx.stack = Stack::new();
+        sub_ctx.memory = Memory::new();
+        sub_ctx.gas = 0;
+        sub_ctx.gas_limit = gas_limit;
+        sub_ctx.depth = self.depth + 1;
+        sub_ctx.parent_memory = self.parent_memory + self.memory.size();
+        // Merged into the caller's by `record_usage`
+        sub_ctx.metrics = self.metrics.as_ref().map(|_| Box::default());
+        // Set by the caller, which knows how the frame is entered
+        sub_ctx.call_trace = None;
+        // Appended to the caller's by `record_usage`
+        sub_ctx.steps = self.steps.as_ref().map(|_| Vec::new());
+        // Appended to the caller's if the frame succeeds
+        sub_ctx.logs = Vec::new();
         sub_ctx
     }
 
+    // Checks that the stack holds the items popped by `opcode`, and has room
+    // for the ones it pushes
+    fn check_stack(&self, opcode: Opcode) -> Result<(), Halt> {
+        let (inputs, outputs) = opcode.stack_io();
+        let depth = self.stack.depth();
+        if depth < inputs {
+            return Err(Halt::StackUnderflow);
+        }
+        if depth - inputs + outputs > self.stack.max_depth() {
+            return Err(Halt::StackOverflow);
+        }
+        Ok(())
+    }
+
+    // Checks that the execution is within its gas limit
+    pub fn check_gas(&self) -> Result<(), Halt> {
+        match self.gas > self.gas_limit {
+            true => Err(Halt::OutOfGas),
+            false => Ok(()),
+        }
+    }
+
+    // A subcall that ran out of steps halts its callers too
+    fn check_steps(&self) -> Result<(), Halt> {
+        match self.out_of_steps {
+            true => Err(Halt::StepLimit),
+            false => Ok(()),
+        }
+    }
+
+    // Updates the resource high-water marks with the ones of a finished subcall
+    fn record_usage(&mut self, sub_ctx: &ExecutionContext) {
+        self.peak_memory_bytes = self.peak_memory_bytes.max(sub_ctx.peak_memory_bytes);
+        self.peak_live_memory_bytes = self.peak_live_memory_bytes.max(sub_ctx.peak_live_memory_bytes);
+        self.peak_stack_depth = self.peak_stack_depth.max(sub_ctx.peak_stack_depth);
+        self.max_call_depth = self.max_call_depth.max(sub_ctx.max_call_depth);
+        self.step_count = sub_ctx.step_count;
+        self.out_of_steps = sub_ctx.out_of_steps;
+        self.keccak_cache.clone_from(&sub_ctx.keccak_cache);
+        self.code_cache = sub_ctx.code_cache.clone();
+        if let (Some(metrics), Some(sub_metrics)) = (self.metrics.as_mut(), sub_ctx.metrics.as_ref()) {
+            metrics.merge(sub_metrics);
+        }
+        if let (Some(steps), Some(sub_steps)) = (self.steps.as_mut(), sub_ctx.steps.as_ref()) {
+            steps.extend(sub_steps.iter().cloned());
+        }
+    }
+
+    // Trace of a subcall about to be entered, if the execution is traced
+    fn sub_trace(&self, kind: CallKind, call: &Call, gas_limit: usize) -> Option<CallTrace> {
+        self.call_trace.as_ref().map(|_| CallTrace::new(kind, call, gas_limit))
+    }
+
+    // Appends the trace of a finished subcall to the frame's
+    fn record_trace(&mut self, sub_trace: Option<CallTrace>) {
+        if let (Some(trace), Some(sub_trace)) = (self.call_trace.as_mut(), sub_trace) {
+            trace.calls.push(sub_trace);
+        }
+    }
+
+    // Step of the instruction at the program counter, and where it goes in
+    // the steps (its cost and outcome are only known after it)
+    fn step(&self, byte: u8, opcode: Opcode) -> (usize, Step) {
+        let step = Step {
+            pc: self.pc,
+            op: byte,
+            gas: self.gas_limit.saturating_sub(self.gas),
+            gas_cost: 0,
+            mem_size: self.memory.size(),
+            stack: self.stack.items_bottom_first().iter().map(|item| item.to_u256()).collect(),
+            depth: self.depth + 1,
+            refund: self.refund,
+            op_name: opcode.name(self.env.spec),
+            error: None,
+        };
+        (self.steps.as_ref().map_or(0, |steps| steps.len()), step)
+    }
+
     pub fn add_log(&mut self, log: Log) {
         self.logs.push(log);
     }
@@ -100,119 +314,483 @@ impl ExecutionContext {
     }
 
     pub fn run(&mut self) -> EvmResult {
-        let mut success = true;
-        loop {
-            // Check execution conditions
-            if !success || self.stopped || self.pc >= self.code.len() {
+        let mut halt = None;
+        while !self.stopped && self.pc < self.code.len() {
+            if self.env.cfg.max_steps.is_some_and(|max_steps| self.step_count >= max_steps) {
+                debug!(target: "evm::call", "step limit reached at pc {} (depth {})", self.pc, self.depth);
+                self.out_of_steps = true;
+                halt = Some(Halt::StepLimit);
                 break;
             }
-
+            self.step_count += 1;
             // Process the next opcode
-            let opcode: Opcode = self.code[self.pc].try_into().unwrap();
-            let opcode_success = opcode.execute(self);
-
-            // Update control variables
-            success = opcode_success;
+            let byte = self.code[self.pc];
+            let opcode = Opcode::parse(byte, self.env.spec);
+            trace!(
+                target: "evm::opcode",
+                "{} at pc {} (depth {}), stack {}",
+                opcode.name(self.env.spec),
+                self.pc,
+                self.depth,
+                fmt_stack(&self.stack)
+            );
+            let gas = self.gas;
+            // Recorded before the instruction, and completed after it (its
+            // subcalls' steps come in between)
+            let step = self.steps.is_some().then(|| self.step(byte, opcode));
+            let outcome = self
+                .check_stack(opcode)
+                .and_then(|_| opcode.execute(self))
+                .and_then(|_| self.check_gas())
+                .and_then(|_| self.check_steps());
+            if let Some(metrics) = self.metrics.as_mut() {
+                metrics.record(byte, opcode, self.gas.saturating_sub(gas), self.memory.size(), self.depth);
+            }
+            if let (Some((index, mut step)), Some(steps)) = (step, self.steps.as_mut()) {
+                step.gas_cost = self.gas.saturating_sub(gas);
+                step.error = outcome.clone().err();
+                steps.insert(index, step);
+            }
+            if let Err(reason) = outcome {
+                debug!(target: "evm::call", "halted at pc {} (depth {}): {}", self.pc, self.depth, reason);
+                halt = Some(reason);
+                break;
+            }
         }
+        let success = halt.is_none();
 
-        if success {
-            self.to_delete.iter().for_each(|address| {
-                self.state.delete(&address);
-            });
+        // Memory never shrinks, so the frame's peak is its final size
+        let memory = self.memory.size();
+        self.peak_memory_bytes = self.peak_memory_bytes.max(memory);
+        self.peak_live_memory_bytes = self.peak_live_memory_bytes.max(self.parent_memory + memory);
+        self.peak_stack_depth = self.peak_stack_depth.max(self.stack.peak_depth());
+        self.max_call_depth = self.max_call_depth.max(self.depth);
+
+        // Only an explicit REVERT returns data
+        let result = match halt {
+            None | Some(Halt::Revert) => self.env.call.result(),
+            Some(_) => Bytes::new(),
+        };
+        // Exceptional halts (running out of gas, INVALID…) use all of it
+        let gas_used = match &halt {
+            Some(halt) if halt.consumes_all_gas() => self.gas_limit,
+            _ => self.gas,
+        };
+        if let Some(trace) = self.call_trace.as_mut() {
+            trace.finish(gas_used, result.clone(), halt.clone());
         }
 
+        // The logs of a failed frame (its subcalls' included) are dropped
+        // along with its state changes
+        let logs = match success {
+            true => self.logs.clone(),
+            false => Vec::new(),
+        };
+
+        let outcome = match halt {
+            None => EvmResult::success(result),
+            Some(Halt::Revert) => EvmResult::revert(result),
+            Some(halt) => EvmResult::failure(halt),
+        };
         EvmResult {
-            stack: self.stack.deref_items(),
-            logs: self.logs.clone(),
-            success,
-            result: self.env.call.result(),
+            stack: self.stack.items_top_first(),
+            logs,
+            gas_used,
+            gas_refunded: match success {
+                true => usize::try_from(self.refund).unwrap_or(0),
+                false => 0,
+            },
+            gas_limit: self.gas_limit,
+            peak_memory_bytes: self.peak_memory_bytes,
+            peak_live_memory_bytes: self.peak_live_memory_bytes,
+            peak_stack_depth: self.peak_stack_depth,
+            max_call_depth: self.max_call_depth,
+            step_count: self.step_count,
+            ..outcome
         }
     }
 
-    pub fn execute_call(&mut self, call: Call) -> CallResult {
-        match self.state.transfer(&call.originator, &call.recipient, call.value) {
-            Err(error) => {
-                println!("{:?}\n", error);
-                CallResult{success: Bytes32::zero(), result: Bytes::new()}
-            },
-            _ => {
-                let code = self.state.code(&call.code_target);
-                if code.is_empty() {
-                    return CallResult{success: Bytes32::one(), result: Bytes::new()};
+    // Executes a top-level transaction on top of `state`. Returns the execution
+    // result, the receipt of the transaction and the resulting state (in which
+    // a failed transaction only bumps the nonce of the sender).
+    pub fn transact(tx: TxEnv, call: Call, block: Block, spec: SpecId, state: State, cumulative_gas_used: usize) -> (EvmResult, Receipt, State) {
+        let env = Env { spec, ..Env::new(tx, call, block) };
+        Self::transact_env(env, state, cumulative_gas_used)
+    }
+
+    // Like `transact`, with every setting (including the memory limit) taken
+    // from `env`
+    pub fn transact_env(env: Env, state: State, cumulative_gas_used: usize) -> (EvmResult, Receipt, State) {
+        let (result, receipt, state, _) = Self::execute_tx(env, state, cumulative_gas_used, false, true);
+        (result, receipt, state)
+    }
+
+    // Like `transact_env`, without bumping the nonce of the sender: for
+    // simulations, whose state is thrown away (see `Evm::call`)
+    pub fn simulate_env(env: Env, state: State, cumulative_gas_used: usize) -> (EvmResult, Receipt, State) {
+        let (result, receipt, state, _) = Self::execute_tx(env, state, cumulative_gas_used, false, false);
+        (result, receipt, state)
+    }
+
+    // Like `transact_env`, also returning the call tree of the transaction
+    pub fn transact_traced(env: Env, state: State, cumulative_gas_used: usize) -> (EvmResult, Receipt, State, CallTrace) {
+        let (result, receipt, state, trace) = Self::execute_tx(env, state, cumulative_gas_used, true, true);
+        (result, receipt, state, trace.expect("traced transaction"))
+    }
+
+    fn execute_tx(env: Env, mut state: State, cumulative_gas_used: usize, traced: bool, bump_nonce: bool) -> (EvmResult, Receipt, State, Option<CallTrace>) {
+        let Env { tx, call, block, spec, cfg } = env;
+        let kind = match call.is_create() {
+            true => CallKind::Create,
+            false => CallKind::Call,
+        };
+        let init_code = call.data();
+        // Contract creations execute the call data as init code
+        let (call, code, contract_address) = if call.is_create() {
+            let address = create_address(&call.sender, state.nonce(&call.sender));
+            let init_call = Call::new(
+                call.sender,
+                address,
+                address,
+                Bytes::new(),
+                call.value,
+                false
+            );
+            (init_call, call.data(), Some(address))
+        } else {
+            let code = state.executable_code(&call.recipient);
+            (call, code, None)
+        };
+
+        // Outcome of a transaction rejected before running
+        let rejected = |halt: Halt, call: &Call| {
+            let receipt = Receipt::new(false, 0, cumulative_gas_used, Vec::new(), None);
+            let result = unexecuted(&tx, halt);
+            let trace = traced.then(|| unexecuted_trace(kind, call, &result));
+            (result, receipt, trace)
+        };
+
+        // Oversized init code makes the transaction invalid, so it doesn't even
+        // bump the nonce
+        if contract_address.is_some() && is_initcode_too_large(spec, code.len()) {
+            let (result, receipt, trace) = rejected(Halt::InitCodeSizeLimit, &call);
+            return (result, receipt, state, trace);
+        }
+        // So does a wrong nonce, or a gas limit over the block's (unless these
+        // checks are disabled)
+        let expected_nonce = state.nonce(&call.sender);
+        if !cfg.disable_nonce_check && tx.nonce != expected_nonce {
+            let (result, receipt, trace) = rejected(Halt::InvalidNonce { expected: expected_nonce, actual: tx.nonce }, &call);
+            return (result, receipt, state, trace);
+        }
+        if !cfg.disable_block_gas_limit && tx.gas_limit > block.gas_limit {
+            let (result, receipt, trace) = rejected(Halt::BlockGasLimitExceeded, &call);
+            return (result, receipt, state, trace);
+        }
+        // Or a max fee that doesn't cover the base fee (EIP-1559)
+        let base_fee = block.base_fee.unwrap_or_default();
+        if tx.tx_type.has_dynamic_fee() && tx.max_fee_per_gas < base_fee {
+            let (result, receipt, trace) = rejected(Halt::MaxFeeBelowBaseFee { max_fee: tx.max_fee_per_gas, base_fee }, &call);
+            return (result, receipt, state, trace);
+        }
+        // Or not being able to pay for its access list, and the words of its
+        // init code for a creation (EIP-3860)
+        let init_code_gas = match contract_address {
+            Some(_) => initcode_gas(spec, code.len()),
+            None => 0,
+        };
+        let intrinsic_gas = access_list_gas(spec, &tx.access_list) + init_code_gas;
+        if U256::from(intrinsic_gas) > tx.gas_limit {
+            let (result, receipt, trace) = rejected(Halt::OutOfGas, &call);
+            return (result, receipt, state, trace);
+        }
+        // Or a sender whose balance doesn't cover its gas at the max fee, along
+        // with the value (unless the balance check is disabled, in which case
+        // gas isn't paid for)
+        let fees = !cfg.disable_balance_check;
+        let max_gas_price = match tx.tx_type.has_dynamic_fee() {
+            true => tx.max_fee_per_gas,
+            false => tx.gas_price,
+        };
+        let max_cost = tx.gas_limit.checked_mul(max_gas_price).and_then(|fee| fee.checked_add(call.value));
+        let balance = state.balance(&call.sender);
+        if fees && max_cost.is_none_or(|cost| balance < cost) {
+            let halt = Halt::InsufficientBalance { address: call.sender, balance, value: max_cost.unwrap_or(U256::MAX) };
+            let (result, receipt, trace) = rejected(halt, &call);
+            return (result, receipt, state, trace);
+        }
+
+        if bump_nonce {
+            state.increment_nonce(&call.sender);
+        }
+        // The gas is bought up front at the effective price, and what's left of
+        // it refunded at the end. The coinbase gets the tip, and the base fee is
+        // burnt (EIP-1559).
+        let sender = call.sender;
+        let coinbase = block.beneficiary.unwrap_or_default();
+        let gas_price = tx.effective_gas_price(block.base_fee);
+        let tip = gas_price - base_fee.min(gas_price);
+        let gas_limit = tx.gas_limit;
+        let mut post_state = state.clone();
+        if fees {
+            post_state.set_balance(&sender, balance - gas_limit * gas_price);
+        }
+        post_state.clear_accesses();
+        prepare_transaction_access(&mut post_state, &tx, &call, &block, spec);
+        post_state.clear_created();
+        post_state.clear_touched();
+        // The contract exists, without code, while its init code runs
+        if let Some(address) = contract_address {
+            post_state.mark_created(address);
+            post_state.set_nonce(&address, created_nonce(spec));
+        }
+
+        // Without the balance check, the sender is credited what it lacks to
+        // send the value (gas isn't paid for)
+        if cfg.disable_balance_check && post_state.balance(&call.sender) < call.value {
+            post_state.set_balance(&call.sender, call.value);
+        }
+        if let Err(error) = post_state.transfer(&call.sender, &call.recipient, call.value) {
+            debug!(target: "evm::state", "{}", error);
+            let (result, receipt, trace) = rejected(error.into(), &call);
+            return (result, receipt, state, trace);
+        }
+
+        let mut ctx = ExecutionContext::new(tx, call, block, post_state, code)
+            .with_spec(spec)
+            .with_cfg(cfg);
+        ctx.gas = intrinsic_gas;
+        if traced {
+            let trace = CallTrace::new(kind, &ctx.env.call, ctx.gas_limit);
+            ctx.call_trace = Some(Box::new(match kind {
+                CallKind::Create => trace.with_input(init_code),
+                _ => trace,
+            }));
+        }
+        let mut result = ctx.run();
+        let trace = ctx.call_trace.take().map(|trace| *trace);
+        result.gas_refunded = result.gas_refunded.min(max_refund(spec, result.gas_used));
+        result.gas_used -= result.gas_refunded;
+        let gas_used = result.gas_used;
+        let cumulative_gas_used = cumulative_gas_used + gas_used;
+
+        let reward = U256::from(gas_used) * tip;
+        let reward_coinbase = |state: &mut State| {
+            if fees && !reward.is_zero() {
+                state.set_balance(&coinbase, state.balance(&coinbase) + reward);
+            }
+        };
+
+        if !result.success {
+            // Of the state before the execution, which hasn't paid for the gas
+            if fees {
+                state.set_balance(&sender, balance - U256::from(gas_used) * gas_price);
+            }
+            reward_coinbase(&mut state);
+            let receipt = Receipt::new(false, gas_used, cumulative_gas_used, Vec::new(), None);
+            return (result, receipt, state, trace);
+        }
+
+        if let Some(address) = contract_address {
+            ctx.state.set_code(&address, result.result.clone());
+            result.created_address = Some(address);
+        }
+        ctx.delete_selfdestructed();
+        if spec.is_enabled_in(SpecId::SpuriousDragon) {
+            ctx.state.delete_touched_empty();
+        }
+        if fees {
+            let refund = (gas_limit - U256::from(gas_used)) * gas_price;
+            ctx.state.set_balance(&sender, ctx.state.balance(&sender) + refund);
+        }
+        reward_coinbase(&mut ctx.state);
+        ctx.state.commit_tx();
+        let receipt = Receipt::new(true, gas_used, cumulative_gas_used, result.logs.clone(), contract_address);
+        (result, receipt, ctx.state, trace)
+    }
+
+    // Runs `call` in a subframe with `gas_limit` gas. The caller is charged the
+    // gas used, returned in the result.
+    pub fn execute_call(&mut self, call: Call, gas_limit: usize, kind: CallKind) -> CallResult {
+        let mut trace = self.sub_trace(kind, &call, gas_limit);
+        // Snapshot to revert the value transfer if the call fails
+        let snapshot = self.state.clone();
+        // Whatever the outcome, the return data of a previous call is gone
+        self.return_data = Bytes::new();
+
+        // A failed transfer leaves the state untouched. Otherwise, the recipient
+        // gets the value (and is created if it didn't exist yet). The value of
+        // a DELEGATECALL is the one its caller already received.
+        let transfer = match kind {
+            CallKind::DelegateCall => Ok(()),
+            _ => self.state.transfer(&call.sender, &call.recipient, call.value),
+        };
+        if let Err(error) = transfer {
+            debug!(target: "evm::call", "{}", error);
+            if let Some(trace) = trace.as_mut() {
+                trace.finish(0, Bytes::new(), Some(error.into()));
+            }
+            self.record_trace(trace);
+            return CallResult{success: Bytes32::zero(), result: Bytes::new(), gas_used: 0};
+        }
+
+        // Delegated EOAs also access their delegate (EIP-7702)
+        #[cfg(feature = "eip7702")]
+        if let Some(delegate) = self.state.delegation(&call.code_target) {
+            self.gas += match self.state.access_account(&delegate) {
+                true => COLD_ACCOUNT_ACCESS_COST,
+                false => WARM_STORAGE_READ_COST,
+            };
+        }
+
+        // Nothing to execute, the call only transfers value
+        let code = self.load_code(&call.code_target);
+        if code.is_empty() {
+            self.record_trace(trace);
+            return CallResult{success: Bytes32::one(), result: Bytes::new(), gas_used: 0};
+        }
+
+        let mut sub_ctx = self.sub_ctx(code, call.clone(), gas_limit);
+        sub_ctx.call_trace = trace.map(Box::new);
+        let call_result = sub_ctx.run();
+        self.record_usage(&sub_ctx);
+        self.record_trace(sub_ctx.call_trace.take().map(|trace| *trace));
+        match call_result.success {
+            true => {
+                // Update the execution context
+                // A static frame can't change the state, but what it accessed
+                // stays warm
+                match call.is_static() {
+                    true => self.state.merge_accesses(&sub_ctx.state),
+                    false => self.state = sub_ctx.state,
                 }
-        
-                let mut sub_ctx = self.sub_ctx(code, call.clone());
-                let call_result = sub_ctx.run();
-                match call_result.success {
-                    true => {
-                        // Update the execution context
-                        self.stack = sub_ctx.stack;
-                        self.memory = sub_ctx.memory;
-                        if !call.is_static() { self.state = sub_ctx.state };
-                        self.return_data = call_result.result.clone();
-        
-                        CallResult {
-                            success: Bytes32::one(),
-                            result: call_result.result,
-                        }
-                    },
-                    false => {
-                        CallResult {
-                            success: Bytes32::zero(),
-                            result: call_result.result,
-                        }
-                    },
+                self.refund = sub_ctx.refund;
+                // Which holds the caller's pending deletions too (a failed
+                // frame's are dropped with it)
+                self.to_delete = sub_ctx.to_delete;
+                self.logs.extend(call_result.logs);
+                self.return_data = call_result.result.clone();
+
+                CallResult {
+                    success: Bytes32::one(),
+                    result: call_result.result,
+                    gas_used: call_result.gas_used,
+                }
+            },
+            false => {
+                // Nothing of the failed frame leaks but its revert data (empty
+                // after an exceptional halt, like a static call violation).
+                // A REVERT hands back the gas it didn't use, an exceptional
+                // halt none of it.
+                self.state = snapshot;
+                self.return_data = call_result.result.clone();
+                CallResult {
+                    success: Bytes32::zero(),
+                    result: call_result.result,
+                    gas_used: call_result.gas_used,
                 }
             },
         }
     }
 
-    pub fn create_call(&mut self, address: Address, value: U256, code: Bytes) -> CallResult {
-        match self.state.transfer(&self.env.call.originator, &self.env.call.recipient, value) {
+    // Code run by a call to `address`, analyzed once per transaction
+    fn load_code(&mut self, address: &Address) -> Arc<AnalyzedCode> {
+        let address = self.state.executable_address(address);
+        let code_hash = self.state.code_hash(&address);
+        if let Some(code) = self.code_cache.get(&code_hash) {
+            return code;
+        }
+        let code = Arc::new(AnalyzedCode::new(self.state.code(&address)));
+        self.code_cache.insert(code_hash, code.clone());
+        code
+    }
+
+    // Runs the init `code` of a contract at `address`, with `gas_limit` gas
+    // (`kind` tells CREATE from CREATE2)
+    pub fn create_call(&mut self, address: Address, value: U256, code: Bytes, gas_limit: usize, kind: CallKind) -> CallResult {
+        // Snapshot to revert the creation if the init code fails
+        let snapshot = self.state.clone();
+        // Whatever the outcome, the return data of a previous call is gone
+        self.return_data = Bytes::new();
+
+        let call = Call::new(self.target, address, address, Bytes::new(), value, false);
+        let mut trace = self.sub_trace(kind, &call, gas_limit).map(|trace| trace.with_input(code.clone()));
+
+        // Nothing can be deployed over an account with code or a nonce (a
+        // CREATE2 with a used salt): the creation fails with all its gas, after
+        // using up the nonce of the creator (EIP-684)
+        if !self.state.nonce(&address).is_zero() || self.state.code_size(&address) > 0 {
+            debug!(target: "evm::call", "address collision at {:#X}", address);
+            self.state.increment_nonce(&self.target);
+            if let Some(trace) = trace.as_mut() {
+                trace.finish(gas_limit, Bytes::new(), Some(Halt::CreateCollision));
+            }
+            self.record_trace(trace);
+            return CallResult{success: Bytes32::zero(), result: Bytes::new(), gas_used: gas_limit};
+        }
+
+        // The value comes from the creating contract
+        match self.state.transfer(&self.target, &address, value) {
             Err(error) => {
-                println!("{:?}\n", error);
-                CallResult{success: Bytes32::zero(), result: Bytes::new()}
+                debug!(target: "evm::call", "{}", error);
+                if let Some(trace) = trace.as_mut() {
+                    trace.finish(0, Bytes::new(), Some(error.into()));
+                }
+                self.record_trace(trace);
+                CallResult{success: Bytes32::zero(), result: Bytes::new(), gas_used: 0}
             },
             _ => {
-                println!("\nCreating contract at address: {:#X}", address);
-                println!("with code: {:#X}\n", code);
+                debug!(target: "evm::call", "creating contract at {:#X} with code {:#X}", address, code);
+                // The next creation must get another address
+                self.state.increment_nonce(&self.target);
+                self.state.mark_created(address);
+                // Until the init code returns, the contract exists without
+                // code: EXTCODESIZE sees 0 and EXTCODEHASH the hash of no code
+                let nonce = created_nonce(self.env.spec);
+                self.state.set_nonce(&address, nonce);
                 if code.is_empty() {
-                    self.state.create(address, Bytes::zero(), value);
-                    return CallResult{success: Bytes32::one(), result: Bytes::new()};
+                    let balance = self.state.balance(&address);
+                    self.state.create(address, Bytes::new(), balance);
+                    self.state.set_nonce(&address, nonce);
+                    self.record_trace(trace);
+                    return CallResult{success: Bytes32::one(), result: Bytes::new(), gas_used: 0};
                 }
 
-                let call = Call::new(
-                    self.target,
-                    address,
-                    self.env.call.originator,
-                    U256::zero(),
-                    U256::from(self.gas_left()),
-                    address,
-                    Bytes::zero(),
-                    value,
-                    false
-                );
-
-                let mut sub_ctx = self.sub_ctx(code, call.clone());
+                // Init code isn't in the state (nor run again): analyzed from
+                // its bytes, and not cached
+                let mut sub_ctx = self.sub_ctx(Arc::new(AnalyzedCode::new(code)), call.clone(), gas_limit);
+                sub_ctx.call_trace = trace.map(Box::new);
                 let call_result = sub_ctx.run();
+                self.record_usage(&sub_ctx);
+                self.record_trace(sub_ctx.call_trace.take().map(|trace| *trace));
                 match call_result.success {
                     true => {
                         // Update the execution context
-                        self.stack = sub_ctx.stack;
-                        self.memory = sub_ctx.memory;
                         if !call.is_static() { self.state = sub_ctx.state };
-                        self.return_data = call_result.result.clone();
-                        self.state.create(address, call_result.result.clone(), value);
+                        self.refund = sub_ctx.refund;
+                        self.to_delete = sub_ctx.to_delete;
+                        self.logs.extend(call_result.logs);
+                        // Deployed on top of what the init code left (balance,
+                        // storage)
+                        self.state.set_code(&address, call_result.result.clone());
 
                         CallResult {
                             success: Bytes32::one(),
                             result: call_result.result,
+                            gas_used: call_result.gas_used,
                         }
                     },
                     false => {
+                        // Neither the account nor the value transfer survive
+                        // the failed init code, only the nonce bump of the
+                        // creator and the revert data
+                        self.state = snapshot;
+                        self.state.increment_nonce(&self.target);
+                        self.return_data = call_result.result.clone();
                         CallResult {
                             success: Bytes32::zero(),
                             result: call_result.result,
+                            gas_used: call_result.gas_used,
                         }
                     },
                 }
@@ -224,7 +802,121 @@ impl ExecutionContext {
         self.to_delete.push(self.target);
     }
 
+    // Deletes the accounts that self-destructed in the frames that succeeded.
+    // Only once the transaction is over: until then, they keep their code and
+    // storage.
+    pub fn delete_selfdestructed(&mut self) {
+        for address in sorted_addresses(&self.to_delete) {
+            self.state.delete(&address);
+        }
+        self.to_delete.clear();
+    }
+
     pub fn gas_left(&self) -> usize {
         self.gas
     }
-}
\ No newline at end of file
+}
+// Executes the transactions of a block in order, threading the state and the
+// cumulative gas through them.
+#[derive(Debug, Clone)]
+pub struct BlockExecutor {
+    // Block in which the transactions are executed
+    block: Block,
+    // Fork whose rules apply
+    spec: SpecId,
+    // State on top of which the next transaction is executed
+    state: State,
+    // Gas consumed by the transactions executed so far
+    cumulative_gas_used: usize,
+}
+
+impl BlockExecutor {
+    pub fn new(block: Block, state: State) -> Self {
+        Self {
+            block,
+            spec: SpecId::default(),
+            state,
+            cumulative_gas_used: 0,
+        }
+    }
+
+    pub fn with_spec(mut self, spec: SpecId) -> Self {
+        self.spec = spec;
+        self
+    }
+
+    // Executes the transactions, returning their receipts and the final state.
+    // A failed transaction still produces a receipt, and doesn't prevent the
+    // execution of the following ones.
+    pub fn execute(mut self, txs: Vec<(TxEnv, Call)>) -> (Vec<Receipt>, State) {
+        let mut receipts = Vec::with_capacity(txs.len());
+        for (tx, call) in txs {
+            let (_, receipt, state) = ExecutionContext::transact(
+                tx,
+                call,
+                self.block.clone(),
+                self.spec,
+                self.state,
+                self.cumulative_gas_used,
+            );
+            self.state = state;
+            self.cumulative_gas_used = receipt.cumulative_gas_used;
+            receipts.push(receipt);
+        }
+        (receipts, self.state)
+    }
+
+    pub fn cumulative_gas_used(&self) -> usize {
+        self.cumulative_gas_used
+    }
+}
+
+// Accounts and slots that are warm from the start of a transaction: its
+// origin, sender and recipient (the created contract for a creation), the
+// precompiles (EIP-2929), the coinbase since Shanghai (EIP-3651) and the
+// entries of its access list (EIP-2930)
+fn prepare_transaction_access(state: &mut State, tx: &TxEnv, call: &Call, block: &Block, spec: SpecId) {
+    state.access_account(&tx.originator);
+    state.access_account(&call.sender);
+    state.access_account(&call.recipient);
+    for address in precompiles(spec) {
+        state.access_account(&address);
+    }
+    if let Some(coinbase) = block.beneficiary.filter(|_| spec.is_enabled_in(SpecId::Shanghai)) {
+        state.access_account(&coinbase);
+    }
+    for (address, keys) in &tx.access_list {
+        state.access_account(address);
+        for key in keys {
+            state.access_slot(address, *key);
+        }
+    }
+}
+
+// Addresses of the precompiled contracts (not implemented yet, but warm all
+// the same)
+fn precompiles(spec: SpecId) -> Vec<Address> {
+    let count = match spec {
+        spec if spec.is_enabled_in(SpecId::Cancun) => 10,
+        spec if spec.is_enabled_in(SpecId::Istanbul) => 9,
+        spec if spec.is_enabled_in(SpecId::Byzantium) => 8,
+        _ => 4,
+    };
+    (1..=count).map(|index| Address::from_slice(&[index])).collect()
+}
+
+// Trace of a transaction rejected before running
+fn unexecuted_trace(kind: CallKind, call: &Call, result: &EvmResult) -> CallTrace {
+    let mut trace = CallTrace::new(kind, call, result.gas_limit);
+    trace.finish(0, Bytes::new(), result.halt.clone());
+    trace
+}
+
+// Result of a transaction stopped by `halt` before any execution
+fn unexecuted(tx: &TxEnv, halt: Halt) -> EvmResult {
+    EvmResult { gas_limit: saturated_usize(tx.gas_limit), ..EvmResult::failure(halt) }
+}
+
+fn saturated_usize(value: U256) -> usize {
+    value.min(U256::from(usize::MAX)).as_usize()
+}
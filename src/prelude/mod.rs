@@ -0,0 +1,13 @@
+// What the modules take from the std prelude and collections, which without
+// std come from alloc (and hashbrown for the hash maps). Imported with
+// `use crate::prelude::*` wherever they are used.
+pub use alloc::borrow::Cow;
+pub use alloc::boxed::Box;
+pub use alloc::string::{String, ToString};
+pub use alloc::sync::Arc;
+pub use alloc::vec::Vec;
+
+#[cfg(feature = "std")]
+pub use std::collections::{HashMap, HashSet};
+#[cfg(not(feature = "std"))]
+pub use hashbrown::{HashMap, HashSet};
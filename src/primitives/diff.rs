@@ -0,0 +1,135 @@
+use core::fmt;
+
+use crate::prelude::*;
+use crate::state::{AccountState, State};
+use crate::types::{Address, Bytes, Bytes32, U256};
+
+// Difference between two states: the changes that turn the first one into the
+// second. Accounts missing from a state are the same as empty ones (EIP-161),
+// and missing storage slots the same as zero ones. Only the accounts held in
+// memory are compared.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct StateDiff {
+    // Accounts with changes, ordered by address
+    pub accounts: Vec<AccountDiff>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AccountDiff {
+    pub address: Address,
+    pub status: AccountStatus,
+    // Balance, nonce and code changes, then the storage ones ordered by slot
+    pub changes: Vec<Change>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AccountStatus {
+    // Empty (or missing) in the first state only
+    Added,
+    // Empty (or missing) in the second state only
+    Removed,
+    Changed,
+}
+
+// A change of a field, from its value in the first state to the one in the
+// second
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Change {
+    Balance(U256, U256),
+    Nonce(U256, U256),
+    Code(Bytes, Bytes),
+    Storage(U256, Bytes32, Bytes32),
+}
+
+impl StateDiff {
+    pub fn is_empty(&self) -> bool {
+        self.accounts.is_empty()
+    }
+
+    // Number of changes, over all the accounts
+    pub fn len(&self) -> usize {
+        self.accounts.iter().map(|account| account.changes.len()).sum()
+    }
+}
+
+impl State {
+    pub fn diff(&self, other: &State) -> StateDiff {
+        let mut addresses: Vec<Address> = self.iter_sorted().chain(other.iter_sorted()).map(|(address, _)| *address).collect();
+        addresses.sort();
+        addresses.dedup();
+
+        let empty = AccountState::default();
+        let accounts = addresses
+            .into_iter()
+            .filter_map(|address| {
+                let from = self.get(&address).unwrap_or(&empty);
+                let to = other.get(&address).unwrap_or(&empty);
+                let changes = account_changes(from, to);
+                if changes.is_empty() {
+                    return None;
+                }
+                let status = match (is_empty(from), is_empty(to)) {
+                    (true, false) => AccountStatus::Added,
+                    (false, true) => AccountStatus::Removed,
+                    _ => AccountStatus::Changed,
+                };
+                Some(AccountDiff { address, status, changes })
+            })
+            .collect();
+        StateDiff { accounts }
+    }
+}
+
+fn is_empty(account: &AccountState) -> bool {
+    account.balance().is_zero() && account.nonce().is_zero() && account.code().is_empty()
+}
+
+fn account_changes(from: &AccountState, to: &AccountState) -> Vec<Change> {
+    let mut changes = Vec::new();
+    if from.balance() != to.balance() {
+        changes.push(Change::Balance(from.balance(), to.balance()));
+    }
+    if from.nonce() != to.nonce() {
+        changes.push(Change::Nonce(from.nonce(), to.nonce()));
+    }
+    let (from_code, to_code) = (from.code(), to.code());
+    if from_code != to_code {
+        changes.push(Change::Code(from_code, to_code));
+    }
+
+    let mut keys: Vec<U256> = from.storage().slots().chain(to.storage().slots()).map(|(key, _)| *key).collect();
+    keys.sort();
+    keys.dedup();
+    for key in keys {
+        let (from_value, to_value) = (from.storage().load(key), to.storage().load(key));
+        if from_value != to_value {
+            changes.push(Change::Storage(key, from_value, to_value));
+        }
+    }
+    changes
+}
+
+// One line per account, followed by one indented line per change
+impl fmt::Display for StateDiff {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for account in &self.accounts {
+            let status = match account.status {
+                AccountStatus::Added => "added",
+                AccountStatus::Removed => "removed",
+                AccountStatus::Changed => "changed",
+            };
+            writeln!(f, "{:#X} ({})", account.address, status)?;
+            for change in &account.changes {
+                match change {
+                    Change::Balance(from, to) => writeln!(f, "  balance: {:#x} -> {:#x}", from, to)?,
+                    Change::Nonce(from, to) => writeln!(f, "  nonce: {:#x} -> {:#x}", from, to)?,
+                    Change::Code(from, to) => writeln!(f, "  code: {:#X} -> {:#X}", from, to)?,
+                    Change::Storage(key, from, to) => {
+                        writeln!(f, "  storage[{:#x}]: {:#x} -> {:#x}", key, from.to_u256(), to.to_u256())?
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
@@ -1,58 +1,134 @@
 use serde::Deserialize;
+use sha3::{Digest, Keccak256};
 
+use crate::prelude::*;
 use crate::types::{
     Bytes,
+    Bytes32,
     Address,
     U64, U256,
     hex_string_to_bytes,
     hex_string_to_address, 
-    hex_string_to_address_option
+    hex_string_to_address_option,
+    deserialize_bytes32_vec,
+    deserialize_u64_option,
+    deserialize_u256,
+    deserialize_u256_option,
+    deserialize_u256_vec,
 };
+use crate::spec::SpecId;
 
-#[derive(Debug, Default, Deserialize, Clone)]
+// Default size limit of the memory of a frame (4 GiB)
+pub const DEFAULT_MEMORY_LIMIT: usize = 1 << 32;
+
+#[derive(Debug, Deserialize, Clone)]
 pub struct Env {
+    /// Transaction
+    #[serde(default)]
+    pub tx: TxEnv,
     /// Call
     #[serde(default)]
     pub call: Call,
     /// Block
     #[serde(default)]
     pub block: Block,
+    /// Fork whose rules apply
+    #[serde(default)]
+    pub spec: SpecId,
+    /// Chain settings, limits and checks of the transaction
+    #[serde(default)]
+    pub cfg: CfgEnv,
 }
 
 impl Env {
-    pub fn new(call: Call, block: Block) -> Self {
-        Self { call, block }
+    pub fn new(tx: TxEnv, call: Call, block: Block) -> Self {
+        Self { tx, call, block, spec: SpecId::default(), cfg: CfgEnv::default() }
+    }
+}
+
+/// Settings of the chain and of the interpreter, and the checks of the
+/// transactions that simulations (eth_call-style) can turn off
+#[derive(Debug, Deserialize, Clone, PartialEq, Eq)]
+#[serde(default, rename_all = "camelCase")]
+pub struct CfgEnv {
+    /// Chain ID, as read by CHAINID
+    pub chain_id: u64,
+    /// Let senders without the funds for the value of their transactions
+    /// send it anyway (they are credited the missing amount)
+    pub disable_balance_check: bool,
+    /// Accept transactions with a gas limit over the one of the block
+    pub disable_block_gas_limit: bool,
+    /// Accept transactions whose nonce isn't the one of their sender
+    pub disable_nonce_check: bool,
+    /// Size limit of the memory of a frame, in bytes. Expanding the memory
+    /// beyond it halts the frame.
+    pub memory_limit: usize,
+    /// Instructions the execution (all its frames included) may run before
+    /// halting. Unlimited by default.
+    pub max_steps: Option<u64>,
+    /// Cache the results of SHA3 for the length of the transaction (see
+    /// `KeccakCache`), for code hashing the same bytes over and over
+    pub keccak_cache: bool,
+}
+
+impl Default for CfgEnv {
+    fn default() -> Self {
+        Self {
+            chain_id: DEFAULT_CHAIN_ID,
+            disable_balance_check: false,
+            disable_block_gas_limit: false,
+            disable_nonce_check: false,
+            memory_limit: DEFAULT_MEMORY_LIMIT,
+            max_steps: None,
+            keccak_cache: false,
+        }
+    }
+}
+
+impl Default for Env {
+    fn default() -> Self {
+        Self::new(TxEnv::default(), Call::default(), Block::default())
     }
 }
 
-#[derive(Debug, Default, Deserialize, Clone)]
+// Mainnet-like defaults (block 18,000,000) for blocks built in code
+pub const DEFAULT_CHAIN_ID: u64 = 1;
+pub const DEFAULT_BLOCK_NUMBER: u64 = 18_000_000;
+pub const DEFAULT_TIMESTAMP: u64 = 1_693_066_895;
+pub const DEFAULT_GAS_LIMIT: u64 = 30_000_000;
+pub const DEFAULT_BASE_FEE: u64 = 7;
+// The minimum of EIP-4844
+pub const DEFAULT_BLOB_BASE_FEE: u64 = 1;
+
+#[derive(Debug, Deserialize, Clone)]
 pub struct Block {
-    /// Chain ID
-    #[serde(default, rename = "chainId")]
-    pub chain_id: U64,
     /// Block number. None if pending.
+    #[serde(default, deserialize_with = "deserialize_u64_option")]
     pub number: Option<U64>,
     /// Miner/author's address. None if pending.
     #[serde(default, rename = "miner")]
     pub author: Option<Address>,
     /// Gas Used
-    #[serde(default, rename = "gasUsed")]
+    #[serde(default, rename = "gasUsed", deserialize_with = "deserialize_u256")]
     pub gas_used: U256,
     /// Gas Limit
-    #[serde(default, rename = "gasLimit")]
+    #[serde(default, rename = "gasLimit", deserialize_with = "deserialize_u256")]
     pub gas_limit: U256,
     /// Timestamp
-    #[serde(default)]
+    #[serde(default, deserialize_with = "deserialize_u256")]
     pub timestamp: U256,
     /// Previous RANDAO
-    #[serde(default, rename = "prevRandao")]
+    #[serde(default, rename = "prevRandao", deserialize_with = "deserialize_u256_option")]
     pub prev_randao: Option<U256>,
     /// Difficulty
-    #[serde(default)]
+    #[serde(default, deserialize_with = "deserialize_u256_option")]
     pub difficulty: Option<U256>,
     /// Base fee per unit of gas (if past London)
-    #[serde(rename = "baseFee")]
+    #[serde(default, rename = "baseFee", deserialize_with = "deserialize_u256_option")]
     pub base_fee: Option<U256>,
+    /// Base fee per unit of blob gas (if past Cancun)
+    #[serde(default, rename = "blobBaseFee", deserialize_with = "deserialize_u256_option")]
+    pub blob_base_fee: Option<U256>,
     /// Beneficiary address (if past London)
     #[serde(
         default,
@@ -62,12 +138,267 @@ pub struct Block {
     pub beneficiary: Option<Address>,
 }
 
+// Fields missing from a JSON block are still zero (or None): only blocks
+// built in code get these defaults.
+impl Default for Block {
+    fn default() -> Self {
+        Self {
+            number: Some(DEFAULT_BLOCK_NUMBER.into()),
+            author: None,
+            gas_used: U256::zero(),
+            gas_limit: DEFAULT_GAS_LIMIT.into(),
+            timestamp: DEFAULT_TIMESTAMP.into(),
+            prev_randao: Some(U256::zero()),
+            difficulty: Some(U256::zero()),
+            base_fee: Some(DEFAULT_BASE_FEE.into()),
+            blob_base_fee: Some(DEFAULT_BLOB_BASE_FEE.into()),
+            beneficiary: None,
+        }
+    }
+}
+
 impl Block {
     pub fn new() -> Self {
         Self::default()
     }
+
+    pub fn builder() -> BlockBuilder {
+        BlockBuilder::new()
+    }
+
+    // Cheatcodes: move the block to another time, height or base fee
+
+    pub fn warp(&mut self, timestamp: U256) {
+        self.timestamp = timestamp;
+    }
+
+    pub fn roll(&mut self, number: u64) {
+        self.number = Some(number.into());
+    }
+
+    pub fn fee(&mut self, base_fee: U256) {
+        self.base_fee = Some(base_fee);
+    }
+
+    // PREVRANDAO of the block. Blocks without one get a stand-in derived from
+    // their number, so that executions stay reproducible: the Keccak of the
+    // number as a 32-byte word (of 0 without a number).
+    pub fn randao(&self) -> U256 {
+        match self.prev_randao {
+            Some(prev_randao) => prev_randao,
+            None => {
+                let number = Bytes32::from_u64(self.number.unwrap_or_default());
+                U256::from_big_endian(&Keccak256::digest(number.as_slice()))
+            }
+        }
+    }
+}
+
+// Builds a `Block` field by field, starting from the defaults
+#[derive(Debug, Default, Clone)]
+pub struct BlockBuilder {
+    block: Block,
+}
+
+impl BlockBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn number(mut self, number: u64) -> Self {
+        self.block.number = Some(number.into());
+        self
+    }
+
+    pub fn timestamp(mut self, timestamp: impl Into<U256>) -> Self {
+        self.block.timestamp = timestamp.into();
+        self
+    }
+
+    pub fn gas_limit(mut self, gas_limit: impl Into<U256>) -> Self {
+        self.block.gas_limit = gas_limit.into();
+        self
+    }
+
+    pub fn base_fee(mut self, base_fee: impl Into<U256>) -> Self {
+        self.block.base_fee = Some(base_fee.into());
+        self
+    }
+
+    pub fn blob_base_fee(mut self, blob_base_fee: impl Into<U256>) -> Self {
+        self.block.blob_base_fee = Some(blob_base_fee.into());
+        self
+    }
+
+    pub fn coinbase(mut self, coinbase: Address) -> Self {
+        self.block.beneficiary = Some(coinbase);
+        self
+    }
+
+    // Also accepts a `Bytes32`, as PREVRANDAO pushes it as a word
+    pub fn prev_randao(mut self, prev_randao: impl Into<U256>) -> Self {
+        self.block.prev_randao = Some(prev_randao.into());
+        self
+    }
+
+    pub fn difficulty(mut self, difficulty: impl Into<U256>) -> Self {
+        self.block.difficulty = Some(difficulty.into());
+        self
+    }
+
+    pub fn build(self) -> Block {
+        self.block
+    }
+}
+
+// Envelope of a transaction, which decides how its gas is priced
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TxType {
+    // Gas priced at `gas_price`
+    #[default]
+    Legacy,
+    // With an access list (EIP-2930), priced like legacy ones
+    Eip2930,
+    // Gas priced from `max_fee_per_gas` and `max_priority_fee_per_gas`, given
+    // the base fee of the block (EIP-1559)
+    Eip1559,
+    // With blobs (EIP-4844), priced like EIP-1559 ones
+    Eip4844,
+}
+
+impl TxType {
+    // Whether the gas price depends on the base fee of the block
+    pub fn has_dynamic_fee(&self) -> bool {
+        matches!(self, TxType::Eip1559 | TxType::Eip4844)
+    }
+}
+
+// As the `type` of the JSON-RPC transactions
+impl<'de> Deserialize<'de> for TxType {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let tx_type = deserialize_u256(deserializer)?;
+        match tx_type {
+            _ if tx_type == 0.into() => Ok(TxType::Legacy),
+            _ if tx_type == 1.into() => Ok(TxType::Eip2930),
+            _ if tx_type == 2.into() => Ok(TxType::Eip1559),
+            _ if tx_type == 3.into() => Ok(TxType::Eip4844),
+            _ => Err(serde::de::Error::custom(format!("unknown transaction type {}", tx_type))),
+        }
+    }
+}
+
+// Transaction-wide environment, shared by all the frames of the execution
+#[derive(Debug, Clone, Deserialize)]
+pub struct TxEnv {
+    // Envelope of the tx
+    #[serde(default, rename = "type")]
+    pub tx_type: TxType,
+    // Transaction originator (in solidity `tx.origin`)
+    #[serde(default, rename = "origin", deserialize_with = "hex_string_to_address")]
+    pub originator: Address,
+    // Gas price of the tx. For the dynamic-fee types, the effective gas price
+    // once the execution resolves it against the base fee of the block.
+    #[serde(default, rename = "gasprice", deserialize_with = "deserialize_u256")]
+    pub gas_price: U256,
+    // Most the sender pays per unit of gas, base fee included (EIP-1559)
+    #[serde(default, rename = "maxFeePerGas", deserialize_with = "deserialize_u256")]
+    pub max_fee_per_gas: U256,
+    // Most the sender pays per unit of gas to the coinbase (EIP-1559)
+    #[serde(default, rename = "maxPriorityFeePerGas", deserialize_with = "deserialize_u256")]
+    pub max_priority_fee_per_gas: U256,
+    // Gas limit of the tx
+    #[serde(default = "default_gas_limit", rename = "gas", deserialize_with = "deserialize_u256")]
+    pub gas_limit: U256,
+    // Nonce of the tx
+    #[serde(default, deserialize_with = "deserialize_u256")]
+    pub nonce: U256,
+    // Versioned hashes of the blobs carried by the tx (EIP-4844)
+    #[serde(default, rename = "blobVersionedHashes", deserialize_with = "deserialize_bytes32_vec")]
+    pub blob_hashes: Vec<Bytes32>,
+    // Accounts and storage keys warmed before the execution (EIP-2930)
+    #[serde(default, rename = "accessList", deserialize_with = "deserialize_access_list")]
+    pub access_list: Vec<(Address, Vec<U256>)>,
+}
+
+// Entry of an access list, as in the JSON-RPC transactions
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct AccessListItem {
+    #[serde(deserialize_with = "hex_string_to_address")]
+    address: Address,
+    #[serde(default, deserialize_with = "deserialize_u256_vec")]
+    storage_keys: Vec<U256>,
+}
+
+fn deserialize_access_list<'de, D>(deserializer: D) -> Result<Vec<(Address, Vec<U256>)>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let items = Vec::<AccessListItem>::deserialize(deserializer)?;
+    Ok(items.into_iter().map(|item| (item.address, item.storage_keys)).collect())
 }
 
+// Transactions get the gas limit of a whole block by default
+impl Default for TxEnv {
+    fn default() -> Self {
+        Self::new(Address::zero(), U256::zero(), default_gas_limit(), U256::zero())
+    }
+}
+
+fn default_gas_limit() -> U256 {
+    DEFAULT_GAS_LIMIT.into()
+}
+
+impl TxEnv {
+    pub fn new(originator: Address, gas_price: U256, gas_limit: U256, nonce: U256) -> Self {
+        Self {
+            tx_type: TxType::Legacy,
+            originator,
+            gas_price,
+            max_fee_per_gas: U256::zero(),
+            max_priority_fee_per_gas: U256::zero(),
+            gas_limit,
+            nonce,
+            blob_hashes: Vec::new(),
+            access_list: Vec::new(),
+        }
+    }
+
+    pub fn with_access_list(mut self, access_list: Vec<(Address, Vec<U256>)>) -> Self {
+        self.access_list = access_list;
+        self
+    }
+
+    pub fn with_blob_hashes(mut self, blob_hashes: Vec<Bytes32>) -> Self {
+        self.blob_hashes = blob_hashes;
+        self
+    }
+
+    // Makes it an EIP-1559 transaction
+    pub fn with_max_fees(mut self, max_fee_per_gas: U256, max_priority_fee_per_gas: U256) -> Self {
+        self.tx_type = TxType::Eip1559;
+        self.max_fee_per_gas = max_fee_per_gas;
+        self.max_priority_fee_per_gas = max_priority_fee_per_gas;
+        self
+    }
+
+    // Price paid per unit of gas in a block with `base_fee` (zero before
+    // London): the base fee plus the tip the max fee leaves room for, for the
+    // dynamic-fee types
+    pub fn effective_gas_price(&self, base_fee: Option<U256>) -> U256 {
+        if !self.tx_type.has_dynamic_fee() {
+            return self.gas_price;
+        }
+        let base_fee = base_fee.unwrap_or_default();
+        let tip = self.max_priority_fee_per_gas.min(self.max_fee_per_gas.saturating_sub(base_fee));
+        base_fee.saturating_add(tip)
+    }
+}
+
+// Per-frame call data
 #[derive(Debug, Default, Clone, Deserialize)]
 pub struct Call {
     // Call sender (in solidity `msg.from`)
@@ -76,15 +407,6 @@ pub struct Call {
     // Call receiver
     #[serde(default, rename = "to", deserialize_with = "hex_string_to_address")]
     pub recipient: Address,
-    // Transaction originator (in solidity `tx.origin`)
-    #[serde(default, rename = "origin", deserialize_with = "hex_string_to_address")]
-    pub originator: Address,
-    // Gas price of the tx
-    #[serde(default, rename = "gasprice")]
-    pub gas_price: U256,
-    // Available gas of the tx
-    #[serde(default)]
-    pub available_gas: U256,
     // Contract address of the code to be executed
     #[serde(default)]
     pub code_target: Address,
@@ -92,7 +414,7 @@ pub struct Call {
     #[serde(default, deserialize_with = "hex_string_to_bytes")]
     pub data: Bytes,
     // Value transferred in the call
-    #[serde(default)]
+    #[serde(default, deserialize_with = "deserialize_u256")]
     pub value: U256,
     // Whether it is a view only call or not
     #[serde(default)]
@@ -106,9 +428,6 @@ impl Call {
     pub fn new(
         sender: Address,
         recipient: Address,
-        originator: Address,
-        gas_price: U256,
-        available_gas: U256,
         code_target: Address,
         data: Bytes,
         value: U256,
@@ -117,10 +436,7 @@ impl Call {
         Self {
             sender,
             recipient,
-            originator,
             code_target,
-            available_gas,
-            gas_price,
             data,
             value,
             view,
@@ -133,8 +449,14 @@ impl Call {
         self.data.clone()
     }
 
-    pub fn data_size(&self) -> usize {
-        (&self.data.len() + 31) / 32 * 32
+    // Exact length of the call data (in bytes)
+    pub fn data_len(&self) -> usize {
+        self.data.len()
+    }
+
+    // A call without recipient (zero address) is a contract creation
+    pub fn is_create(&self) -> bool {
+        self.recipient == Address::zero()
     }
 
     pub fn is_static(&self) -> bool {
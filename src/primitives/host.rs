@@ -0,0 +1,93 @@
+use core::fmt::Debug;
+
+use crate::prelude::*;
+use crate::types::{Address, Bytes32, U256};
+use crate::state::AccountState;
+
+// Host. Backing store of the accounts that are not held in memory by the
+// `State` (e.g. the accounts of a forked chain).
+//
+// The state looks up its host the first time an account or a storage slot it
+// doesn't hold is read, and loads the account into memory before writing to
+// it, so every write is kept local. Hosts are expected to cache their results,
+// since the same account or slot may be requested several times.
+pub trait Host: Debug + Send + Sync {
+    // Account at `address` (its storage is loaded separately through
+    // `storage`). `None` if the account doesn't exist.
+    fn account(&self, address: &Address) -> Option<AccountState>;
+
+    // Value of the storage slot `key` of the account at `address`. Values are
+    // words (like the stack items), and keys the full 256-bit numbers.
+    fn storage(&self, address: &Address, key: U256) -> Bytes32;
+
+    // Whether an access to the account at `address` is cold, which costs the
+    // EIP-2929 surcharge. `first_access` tells whether the transaction hasn't
+    // accessed the account yet (the precompiles, the origin, the recipient and
+    // the access list entries are accessed before the transaction runs). The
+    // default is EIP-2929's: cold on the first access only, warm afterwards.
+    fn is_cold_account(&self, _address: &Address, first_access: bool) -> bool {
+        first_access
+    }
+
+    // Same for the storage slot `key` of the account at `address`
+    fn is_cold_slot(&self, _address: &Address, _key: U256, first_access: bool) -> bool {
+        first_access
+    }
+}
+
+// Hosts behind a pointer are hosts too, so that generic code taking a
+// `H: Host` can be handed a `Box<dyn Host>`, an `Arc<dyn Host>` or a borrowed
+// one (the trait is object-safe: no generic methods, nor `Self` by value)
+impl<H: Host + ?Sized> Host for &H {
+    fn account(&self, address: &Address) -> Option<AccountState> {
+        (**self).account(address)
+    }
+
+    fn storage(&self, address: &Address, key: U256) -> Bytes32 {
+        (**self).storage(address, key)
+    }
+
+    fn is_cold_account(&self, address: &Address, first_access: bool) -> bool {
+        (**self).is_cold_account(address, first_access)
+    }
+
+    fn is_cold_slot(&self, address: &Address, key: U256, first_access: bool) -> bool {
+        (**self).is_cold_slot(address, key, first_access)
+    }
+}
+
+impl<H: Host + ?Sized> Host for Box<H> {
+    fn account(&self, address: &Address) -> Option<AccountState> {
+        (**self).account(address)
+    }
+
+    fn storage(&self, address: &Address, key: U256) -> Bytes32 {
+        (**self).storage(address, key)
+    }
+
+    fn is_cold_account(&self, address: &Address, first_access: bool) -> bool {
+        (**self).is_cold_account(address, first_access)
+    }
+
+    fn is_cold_slot(&self, address: &Address, key: U256, first_access: bool) -> bool {
+        (**self).is_cold_slot(address, key, first_access)
+    }
+}
+
+impl<H: Host + ?Sized> Host for Arc<H> {
+    fn account(&self, address: &Address) -> Option<AccountState> {
+        (**self).account(address)
+    }
+
+    fn storage(&self, address: &Address, key: U256) -> Bytes32 {
+        (**self).storage(address, key)
+    }
+
+    fn is_cold_account(&self, address: &Address, first_access: bool) -> bool {
+        (**self).is_cold_account(address, first_access)
+    }
+
+    fn is_cold_slot(&self, address: &Address, key: U256, first_access: bool) -> bool {
+        (**self).is_cold_slot(address, key, first_access)
+    }
+}
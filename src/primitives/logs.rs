@@ -1,5 +1,6 @@
+use crate::prelude::*;
 use crate::types::{hex_string_to_address, hex_string_to_bytes, hex_string_to_bytes_vec, Bytes, Bytes32, Address};
-use serde::Deserialize;
+use serde::{Deserialize, Serialize, Serializer};
 
 #[derive(Debug, PartialEq, Clone)]
 pub struct Log {
@@ -29,18 +30,6 @@ impl Log {
         }
     }
 
-    pub fn default() -> Self {
-        Self {
-            address: Address::zero(),
-            data: Bytes::new(),
-            topic1: None,
-            topic2: None,
-            topic3: None,
-            topic4: None,
-            topic_count: 0,
-        }
-    }
-
     pub fn add_topic(&mut self, topic: Bytes32) {
         match self.topic_count {
             0 => self.topic1 = Some(topic),
@@ -63,28 +52,46 @@ impl Log {
         self.data = data;
     }
 
-    // Parsing
+    pub fn topics(&self) -> Vec<Bytes32> {
+        [&self.topic1, &self.topic2, &self.topic3, &self.topic4]
+            .into_iter()
+            .flatten()
+            .cloned()
+            .collect()
+    }
+
+    // Parsing. Empty topics ("" or "0x") are absent ones, so that a log
+    // without topics compares equal to the one emitted by LOG0.
     pub fn from_json(json_log: &JsonLog) -> Result<Self, String> {
-        let topics = json_log.topics.clone();
-        let topics: Vec<Option<Bytes32>> = topics.into_iter()
-            .map(|bytes| Some(Bytes32::from_bytes(bytes)))
-            .collect();
-
-        // Handling possible absence of topics
-        let topic1 = topics.get(0).cloned().flatten();
-        let topic2 = topics.get(1).cloned().flatten();
-        let topic3 = topics.get(2).cloned().flatten();
-        let topic4 = topics.get(3).cloned().flatten();
-
-        Ok(Log {
-            address: json_log.address,
-            data: json_log.data.clone(),
-            topic1,
-            topic2,
-            topic3,
-            topic4,
-            topic_count: topics.len(),
-        })
+        let topics: Vec<Bytes32> = json_log.topics.iter()
+            .filter(|bytes| !bytes.is_empty())
+            .map(|bytes| Bytes32::try_from_slice(bytes.as_slice()).map_err(|error| error.to_string()))
+            .collect::<Result<_, _>>()?;
+        if topics.len() > 4 {
+            return Err(format!("{} topics, at most 4 expected", topics.len()));
+        }
+
+        let mut log = Log::new(json_log.address, json_log.data.clone());
+        log.add_topics(topics);
+        Ok(log)
+    }
+
+    // As returned by eth_getLogs, emitted by the `tx_index`th transaction of
+    // its block and `log_index`th log
+    pub fn to_rpc(&self, tx_index: usize, log_index: usize) -> RpcLog {
+        RpcLog {
+            address: self.address,
+            topics: self.topics(),
+            data: self.data.clone(),
+            log_index,
+            transaction_index: tx_index,
+        }
+    }
+}
+
+impl Default for Log {
+    fn default() -> Self {
+        Self::new(Address::zero(), Bytes::new())
     }
 }
 
@@ -93,8 +100,41 @@ impl Log {
 pub struct JsonLog {
     #[serde(deserialize_with = "hex_string_to_address")]
     address: Address,
-    #[serde(deserialize_with = "hex_string_to_bytes")]
+    #[serde(default, deserialize_with = "hex_string_to_bytes")]
     data: Bytes,
-    #[serde(deserialize_with = "hex_string_to_bytes_vec")]
+    #[serde(default, deserialize_with = "hex_string_to_bytes_vec")]
     topics: Vec<Bytes>,
-}
\ No newline at end of file
+}
+
+// Log object of the JSON-RPC API, with 0x-prefixed hex strings and lowercase
+// addresses
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RpcLog {
+    #[serde(serialize_with = "lowercase_address")]
+    pub address: Address,
+    #[serde(serialize_with = "hex_words")]
+    pub topics: Vec<Bytes32>,
+    #[serde(serialize_with = "hex_bytes")]
+    pub data: Bytes,
+    #[serde(serialize_with = "quantity")]
+    pub log_index: usize,
+    #[serde(serialize_with = "quantity")]
+    pub transaction_index: usize,
+}
+
+fn lowercase_address<S: Serializer>(address: &Address, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(&format!("0x{}", hex::encode(address.as_slice())))
+}
+
+fn hex_words<S: Serializer>(words: &[Bytes32], serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.collect_seq(words.iter().map(|word| format!("0x{}", hex::encode(word.as_slice()))))
+}
+
+fn hex_bytes<S: Serializer>(bytes: &Bytes, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(&format!("0x{}", hex::encode(bytes.as_slice())))
+}
+
+fn quantity<S: Serializer>(value: &usize, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(&format!("{:#x}", value))
+}
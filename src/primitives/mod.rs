@@ -2,8 +2,18 @@ pub mod types;
 pub mod state;
 pub mod logs;
 pub mod env;
+pub mod receipt;
+pub mod host;
+pub mod spec;
+pub mod diff;
+pub mod overrides;
 
 pub use crate::types::*;
 pub use crate::state::*;
+pub use crate::diff::*;
+pub use crate::overrides::*;
 pub use crate::logs::*;
-pub use crate::env::*;
\ No newline at end of file
+pub use crate::env::*;
+pub use crate::receipt::*;
+pub use crate::host::*;
+pub use crate::spec::*;
\ No newline at end of file
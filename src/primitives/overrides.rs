@@ -0,0 +1,91 @@
+use core::fmt;
+use serde::Deserialize;
+
+use crate::prelude::*;
+use crate::state::State;
+use crate::types::{deserialize_u256_option, hex_string_to_bytes_option, Address, Bytes, Bytes32, U256};
+
+// State overrides of a simulated call, as in the `stateOverride` parameter of
+// eth_call: accounts whose balance, nonce, code or storage are replaced for the
+// duration of the call only.
+#[derive(Debug, Default, Clone, Deserialize)]
+pub struct Overrides(pub HashMap<Address, AccountOverride>);
+
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AccountOverride {
+    #[serde(default, deserialize_with = "deserialize_u256_option")]
+    pub balance: Option<U256>,
+    #[serde(default, deserialize_with = "deserialize_u256_option")]
+    pub nonce: Option<U256>,
+    #[serde(default, deserialize_with = "hex_string_to_bytes_option")]
+    pub code: Option<Bytes>,
+    // Replacement of the whole storage: the slots left out read as zero
+    #[serde(default)]
+    pub state: Option<HashMap<U256, U256>>,
+    // Slots to override, the others keeping their values
+    #[serde(default)]
+    pub state_diff: Option<HashMap<U256, U256>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum OverrideError {
+    // Both `state` and `stateDiff` were given for the account
+    StateAndStateDiff(Address),
+}
+
+impl fmt::Display for OverrideError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            OverrideError::StateAndStateDiff(address) => {
+                write!(f, "StateAndStateDiff({:#X}): state and stateDiff are exclusive", address)
+            }
+        }
+    }
+}
+
+impl Overrides {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Overrides of `address`, to be filled in
+    pub fn account(&mut self, address: Address) -> &mut AccountOverride {
+        self.0.entry(address).or_default()
+    }
+
+    // Writes the overrides into `state`, in address order. Checks every
+    // account first, so that a conflict leaves the state untouched.
+    pub fn apply(&self, state: &mut State) -> Result<(), OverrideError> {
+        let mut addresses: Vec<&Address> = self.0.keys().collect();
+        addresses.sort();
+        for address in &addresses {
+            let account = &self.0[*address];
+            if account.state.is_some() && account.state_diff.is_some() {
+                return Err(OverrideError::StateAndStateDiff(**address));
+            }
+        }
+
+        for address in addresses {
+            let account = &self.0[address];
+            if let Some(balance) = account.balance {
+                state.set_balance(address, balance);
+            }
+            if let Some(nonce) = account.nonce {
+                state.set_nonce(address, nonce);
+            }
+            if let Some(code) = &account.code {
+                state.set_code(address, code.clone());
+            }
+            if let Some(slots) = &account.state {
+                state.replace_storage(address, slots.iter().map(|(key, value)| (*key, Bytes32::from_u256(*value))));
+            }
+            if let Some(slots) = &account.state_diff {
+                for (key, value) in slots {
+                    state.set_storage(address, *key, Bytes32::from_u256(*value));
+                }
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,63 @@
+pub use ethereum_types::{Bloom, BloomInput};
+
+use crate::prelude::*;
+use crate::types::Address;
+use crate::logs::{Log, RpcLog};
+
+// Transaction receipt. The outcome of a transaction executed within a block.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Receipt {
+    // Whether the transaction was successful (1) or not (0)
+    pub status: u8,
+    // Gas consumed by the transaction
+    pub gas_used: usize,
+    // Gas consumed by the block up to (and including) the transaction
+    pub cumulative_gas_used: usize,
+    // Logs emitted by the transaction
+    pub logs: Vec<Log>,
+    // Bloom filter of the addresses and topics of the logs
+    pub logs_bloom: Bloom,
+    // Address of the deployed contract (only for contract creations)
+    pub contract_address: Option<Address>,
+}
+
+impl Receipt {
+    pub fn new(
+        success: bool,
+        gas_used: usize,
+        cumulative_gas_used: usize,
+        logs: Vec<Log>,
+        contract_address: Option<Address>,
+    ) -> Self {
+        Self {
+            status: success as u8,
+            gas_used,
+            cumulative_gas_used,
+            logs_bloom: logs_bloom(&logs),
+            logs,
+            contract_address,
+        }
+    }
+
+    pub fn is_success(&self) -> bool {
+        self.status == 1
+    }
+
+    // Logs of the transaction as JSON-RPC log objects, numbered from 0 in the
+    // order they were emitted, for the `tx_index`th transaction of the block
+    pub fn rpc_logs(&self, tx_index: usize) -> Vec<RpcLog> {
+        self.logs.iter().enumerate().map(|(log_index, log)| log.to_rpc(tx_index, log_index)).collect()
+    }
+}
+
+// Bloom filter over the address and topics of every log
+pub fn logs_bloom(logs: &[Log]) -> Bloom {
+    let mut bloom = Bloom::zero();
+    for log in logs {
+        bloom.accrue(BloomInput::Raw(log.address.as_slice()));
+        for topic in log.topics() {
+            bloom.accrue(BloomInput::Raw(topic.as_slice()));
+        }
+    }
+    bloom
+}
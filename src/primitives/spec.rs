@@ -0,0 +1,79 @@
+use serde::{de, Deserialize, Deserializer};
+use core::fmt;
+use core::str::FromStr;
+
+use crate::prelude::*;
+
+// Ethereum hard forks, in chronological order. The fork determines which
+// opcodes are available and the gas rules of the execution.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum SpecId {
+    Frontier,
+    Homestead,
+    // EIP-150
+    Tangerine,
+    // EIP-158
+    SpuriousDragon,
+    Byzantium,
+    Constantinople,
+    Petersburg,
+    Istanbul,
+    Berlin,
+    London,
+    // Paris
+    Merge,
+    #[default]
+    Shanghai,
+    Cancun,
+}
+
+impl SpecId {
+    // Most recent fork supported
+    pub const LATEST: SpecId = SpecId::Cancun;
+
+    // Whether the rules introduced by `fork` apply
+    pub fn is_enabled_in(self, fork: SpecId) -> bool {
+        self >= fork
+    }
+}
+
+impl FromStr for SpecId {
+    type Err = String;
+
+    // Parses the fork names used by `ethereum/tests` and `execution-spec-tests`
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name {
+            "Frontier" => Ok(SpecId::Frontier),
+            "Homestead" => Ok(SpecId::Homestead),
+            "Tangerine" | "EIP150" => Ok(SpecId::Tangerine),
+            "SpuriousDragon" | "EIP158" => Ok(SpecId::SpuriousDragon),
+            "Byzantium" => Ok(SpecId::Byzantium),
+            "Constantinople" => Ok(SpecId::Constantinople),
+            "Petersburg" | "ConstantinopleFix" => Ok(SpecId::Petersburg),
+            "Istanbul" => Ok(SpecId::Istanbul),
+            "Berlin" => Ok(SpecId::Berlin),
+            "London" => Ok(SpecId::London),
+            "Merge" | "Paris" => Ok(SpecId::Merge),
+            "Shanghai" => Ok(SpecId::Shanghai),
+            "Cancun" => Ok(SpecId::Cancun),
+            _ => Err(format!("Unknown fork: {}", name)),
+        }
+    }
+}
+
+impl fmt::Display for SpecId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Debug::fmt(self, f)
+    }
+}
+
+impl<'de> Deserialize<'de> for SpecId {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(de::Error::custom)
+    }
+}
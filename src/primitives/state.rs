@@ -1,14 +1,68 @@
 use core::result::Result::Err;
 use serde::Deserialize;
 use sha3::{Digest, Keccak256};
-use std::collections::HashMap;
+use core::fmt;
+// Accounts are shared between threads with std. Without it, the cache of the
+// code hash is a plain cell.
+#[cfg(feature = "std")]
+use std::sync::OnceLock;
+#[cfg(not(feature = "std"))]
+use core::cell::OnceCell as OnceLock;
+
+use crate::prelude::*;
+use crate::host::Host;
+use crate::utils::sorted_addresses;
+use crate::types::{deserialize_u256, hex_string_to_address, hex_string_to_bytes, Address, Bytes, Bytes32, U256, Code};
+
+// Error of a value transfer between accounts, which leaves the state untouched
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TransferError {
+    // The sender can't afford to transfer `value`
+    InsufficientBalance { address: Address, balance: U256, value: U256 },
+    // The balance of the recipient would overflow with `value`
+    BalanceOverflow { address: Address, balance: U256, value: U256 },
+}
 
-use crate::types::{hex_string_to_address, hex_string_to_bytes, Address, Bytes, Bytes32, U256, Code};
+impl fmt::Display for TransferError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TransferError::InsufficientBalance { address, balance, value } => {
+                write!(f, "InsufficientBalance({:#X}): {:#X} < {:#X}", address, balance, value)
+            }
+            TransferError::BalanceOverflow { address, balance, value } => {
+                write!(f, "BalanceOverflow({:#X}): {:#X} + {:#X}", address, balance, value)
+            }
+        }
+    }
+}
+
+// Prefix of the delegation designators of EIP-7702 (`0xef0100 ++ address`)
+pub const DELEGATION_PREFIX: [u8; 3] = [0xef, 0x01, 0x00];
 
 // EVM State. A key-value pair of account states.
 #[derive(Debug, Default, Deserialize, Clone)]
-#[serde(default)]
-pub struct State(HashMap<Address, AccountState>);
+#[serde(transparent)]
+pub struct State {
+    // Account states
+    accounts: HashMap<Address, AccountState>,
+    // Accounts accessed during the current transaction (EIP-2929)
+    #[serde(skip)]
+    warm_accounts: HashSet<Address>,
+    // Storage slots accessed during the current transaction (EIP-2929)
+    #[serde(skip)]
+    warm_slots: HashSet<(Address, U256)>,
+    // Accounts created during the current transaction (EIP-6780)
+    #[serde(skip)]
+    created: HashSet<Address>,
+    // Accounts touched during the current transaction: sent value (even zero)
+    // or sending it, as call recipients and SELFDESTRUCT beneficiaries are
+    // (EIP-161)
+    #[serde(skip)]
+    touched: HashSet<Address>,
+    // Backing store of the accounts that are not held in memory
+    #[serde(skip)]
+    host: Option<Arc<dyn Host>>,
+}
 
 // Account state. The state of an account in the EVM.
 #[derive(Debug, Default, Deserialize, Clone)]
@@ -17,10 +71,10 @@ pub struct AccountState {
     #[serde(default, deserialize_with = "hex_string_to_address")]
     address: Address,
     // Balance of the account
-    #[serde(default)]
+    #[serde(default, deserialize_with = "deserialize_u256")]
     balance: U256,
     // Nonce of the account
-    #[serde(default)]
+    #[serde(default, deserialize_with = "deserialize_u256")]
     nonce: U256,
     // Code of the account (in bytes)
     #[serde(default, deserialize_with = "hex_string_to_bytes")]
@@ -28,9 +82,17 @@ pub struct AccountState {
     // Code of the account (in a test suite compatible format)
     #[serde(default, rename = "code")]
     code_test: Code,
+    // Keccak of the code, set along with it (and computed on first use for the
+    // accounts loaded from a test suite)
+    #[serde(skip)]
+    code_hash: OnceLock<Bytes32>,
     // Storage of the account
     #[serde(default)]
     storage: Storage,
+    // Whether the account was loaded from the host, in which case the storage
+    // slots that are not held in memory are looked up there
+    #[serde(skip)]
+    from_host: bool,
 }
 
 // Storage of an account. A key-value pair of storage slots.
@@ -38,98 +100,199 @@ pub struct AccountState {
 pub struct Storage {
     // Storage map
     map: HashMap<U256, Bytes32>,
-    // Warm slots
-    warm_slots: Vec<U256>,
+    // Values of the slots written during the current transaction, as they
+    // were before its first write (EIP-2200)
+    #[serde(skip)]
+    original: HashMap<U256, Bytes32>,
 }
 
 // State implementation.
 impl State {
     pub fn new() -> Self {
-        Self(HashMap::new())
+        Self::default()
     }
 
+    // State backed by a host, which is looked up for the accounts and storage
+    // slots that are not held in memory. Writes are always kept in memory.
+    pub fn with_host(host: Arc<dyn Host>) -> Self {
+        Self {
+            host: Some(host),
+            ..Default::default()
+        }
+    }
+
+    pub fn set_host(&mut self, host: Arc<dyn Host>) {
+        self.host = Some(host);
+    }
+
+    pub fn host(&self) -> Option<&Arc<dyn Host>> {
+        self.host.as_ref()
+    }
+
+    // Account held in memory
     pub fn get(&self, address: &Address) -> Option<&AccountState> {
-        self.0.get(address)
+        self.accounts.get(address)
     }
 
+    // Account held in memory, loading it from the host first if needed
     pub fn get_mut(&mut self, address: &Address) -> Option<&mut AccountState> {
-        self.0.get_mut(address)
+        if !self.accounts.contains_key(address) {
+            if let Some(mut account_state) = self.host_account(address) {
+                account_state.from_host = true;
+                self.accounts.insert(*address, account_state);
+            }
+        }
+        self.accounts.get_mut(address)
+    }
+
+    // Account either held in memory or provided by the host
+    fn load(&self, address: &Address) -> Option<Cow<'_, AccountState>> {
+        match self.accounts.get(address) {
+            Some(account_state) => Some(Cow::Borrowed(account_state)),
+            None => self.host_account(address).map(Cow::Owned),
+        }
+    }
+
+    fn host_account(&self, address: &Address) -> Option<AccountState> {
+        self.host.as_ref().and_then(|host| host.account(address))
+    }
+
+    fn host_storage(&self, address: &Address, key: U256) -> Bytes32 {
+        match &self.host {
+            Some(host) => host.storage(address, key),
+            None => Bytes32::zero(),
+        }
     }
 
     pub fn insert(&mut self, address: Address, account_state: AccountState) {
-        self.0.insert(address, account_state);
+        self.accounts.insert(address, account_state);
+    }
+
+    pub fn exists(&self, address: &Address) -> bool {
+        self.load(address).is_some()
+    }
+
+    // Whether the account has no code, nonce nor balance (EIP-161)
+    pub fn is_empty(&self, address: &Address) -> bool {
+        self.load(address).is_none_or(|account_state| account_state.is_empty())
+    }
+
+    // Marks an account as created in the current transaction
+    pub fn mark_created(&mut self, address: Address) {
+        self.created.insert(address);
+    }
+
+    pub fn is_created(&self, address: &Address) -> bool {
+        self.created.contains(address)
+    }
+
+    // Forgets the created accounts. To be called at the start of every transaction.
+    pub fn clear_created(&mut self) {
+        self.created.clear();
+    }
+
+    pub fn touch(&mut self, address: &Address) {
+        self.touched.insert(*address);
+    }
+
+    pub fn is_touched(&self, address: &Address) -> bool {
+        self.touched.contains(address)
+    }
+
+    // Forgets the touched accounts. To be called at the start of every
+    // transaction.
+    pub fn clear_touched(&mut self) {
+        self.touched.clear();
+    }
+
+    // Deletes the accounts touched by the transaction that ended up empty
+    // (EIP-161). To be called at its end, since Spurious Dragon.
+    pub fn delete_touched_empty(&mut self) {
+        for address in sorted_addresses(self.touched.iter()) {
+            if self.is_empty(&address) && self.exists(&address) {
+                self.delete(&address);
+            }
+        }
+        self.touched.clear();
     }
 
     pub fn delete(&mut self, address: &Address) {
-        self.0.remove(address);
+        match self.host {
+            // Keep an empty account so that the host's one isn't looked up again
+            Some(_) => self.insert(*address, AccountState::new(*address)),
+            None => {
+                self.accounts.remove(address);
+            }
+        }
+    }
+
+    // Accounts held in memory, ordered by address
+    pub fn iter_sorted(&self) -> impl Iterator<Item = (&Address, &AccountState)> {
+        let mut accounts: Vec<_> = self.accounts.iter().collect();
+        accounts.sort_by_key(|(address, _)| **address);
+        accounts.into_iter()
+    }
+
+    // Addresses of all the accounts, sorted
+    pub fn addresses(&self) -> Vec<Address> {
+        sorted_addresses(self.accounts.keys())
     }
 
     pub fn create(&mut self, address: Address, code: Bytes, balance: U256) {
-        let account_state = AccountState {
-            address: address.clone(),
-            code_bytes: code,
-            balance,
-            ..Default::default()
-        };
-        self.0.insert(address, account_state);
-    }
-
-    pub fn transfer(&mut self, from: &Address, to: &Address, value: U256) -> Result<(), String>{
-        if value.is_zero() {return Ok(())};
-
-        let state_from = self.get_mut(from);
-        match state_from {
-            Some(state_from) => {
-                if state_from.balance < value {
-                    return Err(format!("InsufficientBalance({:#X}): {:#X} < {:#X}",
-                        from,
-                        state_from.balance,
-                        value
-                    ));
-                }
-                state_from.balance -= value;
-            },
-            _ => return Err(format!("InsufficientBalance({:#X}): {:#X} < {:#X}",
-                    from,
-                    U256::zero(),
-                    value
-                )),
-        }
+        let account_state = AccountState::from_parts(address, balance, U256::zero(), code);
+        self.accounts.insert(address, account_state);
+    }
 
-        let state_to = self.get_mut(to);
-        match state_to {
-            Some(state_to) => {
-                state_to.balance += value;
-            },
-            _ => {
-                let account_state = AccountState {
-                    address: to.clone(),
-                    balance: value,
-                    ..Default::default()
-                };
-                self.0.insert(to.clone(), account_state);
-            },
+    // Moves `value` from `from` to `to`, creating the recipient if needed.
+    // Everything is checked before touching either balance, so a failed
+    // transfer changes nothing. A successful one touches both accounts, even
+    // without value (EIP-161).
+    pub fn transfer(&mut self, from: &Address, to: &Address, value: U256) -> Result<(), TransferError> {
+        let balance = self.balance(from);
+        if balance < value {
+            return Err(TransferError::InsufficientBalance { address: *from, balance, value });
+        }
+        // Sending nothing, or to oneself, only needs the funds
+        if !value.is_zero() && from != to {
+            let recipient_balance = self.balance(to);
+            let new_balance = match recipient_balance.checked_add(value) {
+                Some(new_balance) => new_balance,
+                None => return Err(TransferError::BalanceOverflow { address: *to, balance: recipient_balance, value }),
+            };
+
+            // The sender exists, as it holds funds
+            self.get_mut(from).expect("sender with a balance").balance -= value;
+            self.get_or_create(to).balance = new_balance;
         }
 
+        self.touch(from);
+        self.touch(to);
         Ok(())
     }
 
+    pub fn increment_nonce(&mut self, address: &Address) {
+        self.get_or_create(address).nonce += U256::one();
+    }
+
+    // Reads. Missing accounts read as empty ones, and are never created: only
+    // writes materialize an account (see `get_or_create`).
+
     pub fn balance(&self, address: &Address) -> U256 {
-        match self.get(address) {
+        match self.load(address) {
             Some(account_state) => account_state.balance(),
             None => U256::zero(),
         }
     }
 
     pub fn nonce(&self, address: &Address) -> U256 {
-        match self.get(address) {
+        match self.load(address) {
             Some(account_state) => account_state.nonce(),
             None => U256::zero(),
         }
     }
 
     pub fn code(&self, address: &Address) -> Bytes {
-        match self.get(address) {
+        match self.load(address) {
             Some(account_state) => account_state.code(),
             None => Bytes::new(),
         }
@@ -139,34 +302,220 @@ impl State {
         self.code(address).len()
     }
 
+    // Zero for non-existent and empty accounts (EIP-1052), the hash of no code
+    // for other accounts without code
     pub fn code_hash(&self, address: &Address) -> Bytes32 {
+        match self.load(address) {
+            Some(account_state) if !account_state.is_empty() => account_state.code_hash(),
+            _ => Bytes32::zero(),
+        }
+    }
+
+    // Code executed when `address` is called. With EIP-7702, the code of the
+    // delegate if the account carries a delegation designator.
+    pub fn executable_code(&self, address: &Address) -> Bytes {
+        self.code(&self.executable_address(address))
+    }
+
+    // Account whose code is executed when `address` is called (see
+    // `executable_code`)
+    pub fn executable_address(&self, address: &Address) -> Address {
+        #[cfg(feature = "eip7702")]
+        if let Some(delegate) = self.delegation(address) {
+            return delegate;
+        }
+        *address
+    }
+
+    // Account that `address` delegates its code to (EIP-7702)
+    #[cfg(feature = "eip7702")]
+    pub fn delegation(&self, address: &Address) -> Option<Address> {
         let code = self.code(address);
-        if code.is_empty() {
-            Bytes32::from_vec(vec![0])
+        if code.len() == DELEGATION_PREFIX.len() + 20 && code.as_slice().starts_with(&DELEGATION_PREFIX) {
+            Some(Address::from_slice(&code.as_slice()[DELEGATION_PREFIX.len()..]))
         } else {
-            Bytes32::from_vec(Keccak256::digest(self.code(address).as_slice()).to_vec())
+            None
         }
     }
 
+    // Sets the delegation designator of an EOA (EIP-7702). Delegating to the
+    // zero address removes the delegation.
+    #[cfg(feature = "eip7702")]
+    pub fn set_delegation(&mut self, eoa: &Address, target: &Address) {
+        let code = if *target == Address::zero() {
+            Bytes::new()
+        } else {
+            Bytes::from_vec([DELEGATION_PREFIX.as_slice(), target.as_slice()].concat())
+        };
+        self.get_or_create(eoa).set_code(code);
+    }
+
     pub fn storage_load(&self, address: &Address, key: U256) -> Bytes32 {
         match self.get(address) {
-            Some(account_state) => account_state.storage().load(key),
-            None => Bytes32::zero(),
+            Some(account_state) => match account_state.storage().get(key) {
+                Some(value) => value,
+                None if account_state.from_host => self.host_storage(address, key),
+                None => Bytes32::zero(),
+            },
+            None => self.host_storage(address, key),
         }
     }
 
-    pub fn storage_store(&mut self, address: &Address, key: U256, value: Bytes32) {
-        match self.get_mut(address) {
-            Some(account_state) => account_state.storage_mut().store(key, value),
-            None => {
-                self.insert(
-                    address.clone(),
-                    AccountState::new(address.clone()),
-                );
-                self.storage_store(address, key, value);
+    // Value of a slot at the start of the current transaction (EIP-2200)
+    pub fn storage_original(&self, address: &Address, key: U256) -> Bytes32 {
+        match self.get(address) {
+            Some(account_state) => match account_state.storage().original(key) {
+                Some(value) => value,
+                None if account_state.from_host => self.host_storage(address, key),
+                None => Bytes32::zero(),
+            },
+            None => self.host_storage(address, key),
+        }
+    }
+
+    // Makes the current values of the slots their original ones. To be called
+    // at the end of every transaction. Slots cleared to zero are removed, but
+    // for accounts backed by a host, where the zero hides the host's value.
+    pub fn commit_tx(&mut self) {
+        for account_state in self.accounts.values_mut() {
+            account_state.storage.commit_tx();
+            if !account_state.from_host {
+                account_state.storage.remove_zeros();
             }
         }
     }
+
+    // Nonzero slots of an account, ordered by key
+    pub fn storage_dump(&self, address: &Address) -> Vec<(U256, Bytes32)> {
+        self.get(address).map(|account_state| account_state.storage().dump()).unwrap_or_default()
+    }
+
+    // Warm/cold access tracking (EIP-2929).
+    //
+    // The `load_*` methods below are the ones used by the interpreter to read
+    // the state. Besides the value, they return whether the access was cold
+    // (first access to the account/slot in the current transaction, unless the
+    // host reports otherwise), which determines the gas surcharge of the
+    // opcode. Any access marks the account/slot as warm for the rest of the
+    // transaction, regardless of whether the account exists.
+
+    // Marks an account as accessed. Returns whether it was cold, as the host
+    // (if any) has it.
+    pub fn access_account(&mut self, address: &Address) -> bool {
+        let first_access = self.warm_accounts.insert(*address);
+        match &self.host {
+            Some(host) => host.is_cold_account(address, first_access),
+            None => first_access,
+        }
+    }
+
+    // Marks a storage slot as accessed. Returns whether it was cold, as the
+    // host (if any) has it.
+    pub fn access_slot(&mut self, address: &Address, key: U256) -> bool {
+        let first_access = self.warm_slots.insert((*address, key));
+        match &self.host {
+            Some(host) => host.is_cold_slot(address, key, first_access),
+            None => first_access,
+        }
+    }
+
+    pub fn is_warm_account(&self, address: &Address) -> bool {
+        self.warm_accounts.contains(address)
+    }
+
+    pub fn is_warm_slot(&self, address: &Address, key: U256) -> bool {
+        self.warm_slots.contains(&(*address, key))
+    }
+
+    // Takes the accesses of `other` as well, like those of a static frame,
+    // whose state is otherwise discarded
+    pub fn merge_accesses(&mut self, other: &State) {
+        self.warm_accounts.extend(other.warm_accounts.iter().copied());
+        self.warm_slots.extend(other.warm_slots.iter().copied());
+    }
+
+    // Forgets all the accesses. To be called at the start of every transaction.
+    pub fn clear_accesses(&mut self) {
+        self.warm_accounts.clear();
+        self.warm_slots.clear();
+    }
+
+    pub fn load_balance(&mut self, address: &Address) -> (U256, bool) {
+        (self.balance(address), self.access_account(address))
+    }
+
+    pub fn load_code(&mut self, address: &Address) -> (Bytes, bool) {
+        (self.code(address), self.access_account(address))
+    }
+
+    pub fn load_code_size(&mut self, address: &Address) -> (usize, bool) {
+        (self.code_size(address), self.access_account(address))
+    }
+
+    pub fn load_code_hash(&mut self, address: &Address) -> (Bytes32, bool) {
+        (self.code_hash(address), self.access_account(address))
+    }
+
+    pub fn sload(&mut self, address: &Address, key: U256) -> (Bytes32, bool) {
+        (self.storage_load(address, key), self.access_slot(address, key))
+    }
+
+    // Cheatcodes: direct writes, creating the account if needed. Like any other
+    // write, they are undone with the frame (or snapshot) they happen in.
+
+    pub fn set_balance(&mut self, address: &Address, balance: U256) {
+        self.get_or_create(address).balance = balance;
+    }
+
+    pub fn set_nonce(&mut self, address: &Address, nonce: U256) {
+        self.get_or_create(address).nonce = nonce;
+    }
+
+    // The code hash follows the code
+    pub fn set_code(&mut self, address: &Address, code: Bytes) {
+        self.get_or_create(address).set_code(code);
+    }
+
+    // Unlike `storage_store`, the slot is taken to have held `value` since the
+    // start of the transaction
+    pub fn set_storage(&mut self, address: &Address, key: U256, value: Bytes32) {
+        self.get_or_create(address).storage_mut().store(key, value);
+    }
+
+    // Replaces the whole storage of the account with `slots`. The slots left
+    // out read as zero, even if the host holds a value for them.
+    pub fn replace_storage(&mut self, address: &Address, slots: impl IntoIterator<Item = (U256, Bytes32)>) {
+        let account_state = self.get_or_create(address);
+        account_state.from_host = false;
+        account_state.storage = Storage::new();
+        for (key, value) in slots {
+            account_state.storage.store(key, value);
+        }
+    }
+
+    // Account held in memory, loading it from the host or creating an empty
+    // one if needed. Every implicit creation (value sent to a new address,
+    // SELFDESTRUCT beneficiary, nonce bump, cheatcode…) goes through it, so
+    // reverting the frame it happens in removes the account with the rest of
+    // the frame's writes.
+    pub fn get_or_create(&mut self, address: &Address) -> &mut AccountState {
+        if self.get_mut(address).is_none() {
+            self.insert(*address, AccountState::new(*address));
+        }
+        self.get_mut(address).unwrap()
+    }
+
+    // Writes a slot as SSTORE does, keeping track of its original value
+    pub fn storage_store(&mut self, address: &Address, key: U256, value: Bytes32) {
+        let current = self.storage_load(address, key);
+        let storage = self.get_or_create(address).storage_mut();
+        // A slot of the host is held in memory first, so that its value is
+        // the original one
+        if storage.get(key).is_none() {
+            storage.store(key, current);
+        }
+        storage.write(key, value);
+    }
 }
 
 // Account state implementation.
@@ -178,7 +527,19 @@ impl AccountState {
             nonce: U256::zero(),
             code_bytes: Bytes::new(),
             code_test: Code::default(),
+            code_hash: OnceLock::new(),
             storage: Storage::new(),
+            from_host: false,
+        }
+    }
+
+    pub fn from_parts(address: Address, balance: U256, nonce: U256, code: Bytes) -> Self {
+        Self {
+            balance,
+            nonce,
+            code_hash: OnceLock::from(hash_code(&code)),
+            code_bytes: code,
+            ..Self::new(address)
         }
     }
 
@@ -194,6 +555,12 @@ impl AccountState {
         self.nonce
     }
 
+    // No code, nonce nor balance (EIP-161). The code may still be in the test
+    // suite format.
+    pub fn is_empty(&self) -> bool {
+        self.balance.is_zero() && self.nonce.is_zero() && self.code_bytes.is_empty() && self.code_test.bin.is_empty()
+    }
+
     pub fn code(&self) -> Bytes {
         if !self.code_bytes.is_empty() {
             self.code_bytes.clone()
@@ -202,6 +569,18 @@ impl AccountState {
         }
     }
 
+    pub fn set_code(&mut self, code: Bytes) {
+        self.code_hash = OnceLock::from(hash_code(&code));
+        self.code_bytes = code;
+        self.code_test = Code::default();
+    }
+
+    pub fn code_hash(&self) -> Bytes32 {
+        let code_hash = *self.code_hash.get_or_init(|| hash_code(&self.code()));
+        debug_assert_eq!(code_hash, hash_code(&self.code()), "stale code hash of {:?}", self.address);
+        code_hash
+    }
+
     pub fn storage(&self) -> &Storage {
         &self.storage
     }
@@ -214,36 +593,69 @@ impl AccountState {
 // Storage implementation.
 impl Storage {
     pub fn new() -> Self {
-        Self {
-            map: HashMap::new(),
-            warm_slots: Vec::new(),
-        }
+        Self::default()
     }
 
     pub fn load(&self, key: U256) -> Bytes32 {
         match self.map.get(&key) {
-            Some(value) => value.clone(),
+            Some(value) => *value,
             None => Bytes32::zero(),
         }
     }
 
+    pub fn get(&self, key: U256) -> Option<Bytes32> {
+        self.map.get(&key).cloned()
+    }
+
     pub fn store(&mut self, key: U256, value: Bytes32) {
         self.map.insert(key, value);
     }
 
+    // Like `store`, remembering the value the slot held before its first
+    // write in the transaction
+    pub fn write(&mut self, key: U256, value: Bytes32) {
+        let current = self.load(key);
+        self.original.entry(key).or_insert(current);
+        self.map.insert(key, value);
+    }
+
+    // Value of a slot at the start of the transaction. None if the slot isn't
+    // held (nor was written).
+    pub fn original(&self, key: U256) -> Option<Bytes32> {
+        self.original.get(&key).copied().or_else(|| self.get(key))
+    }
+
+    // Makes the current values the original ones, at the end of a transaction
+    pub fn commit_tx(&mut self) {
+        self.original.clear();
+    }
+
     pub fn delete(&mut self, key: U256) {
         self.map.remove(&key);
     }
 
-    pub fn warm_slots(&self) -> &Vec<U256> {
-        &self.warm_slots
+    // Removes the slots holding zero, which read the same as absent ones
+    pub fn remove_zeros(&mut self) {
+        self.map.retain(|_, value| !value.is_zero());
     }
 
-    pub fn clear_warm_slots(&mut self) {
-        self.warm_slots.clear();
+    // Nonzero slots, ordered by key
+    pub fn dump(&self) -> Vec<(U256, Bytes32)> {
+        self.iter_sorted().filter(|(_, value)| !value.is_zero()).map(|(key, value)| (*key, *value)).collect()
+    }
+
+    pub fn slots(&self) -> impl Iterator<Item = (&U256, &Bytes32)> {
+        self.map.iter()
     }
 
-    pub fn access_slot(&mut self, key: U256) {
-        self.warm_slots.push(key);
+    // Slots ordered by key
+    pub fn iter_sorted(&self) -> impl Iterator<Item = (&U256, &Bytes32)> {
+        let mut slots: Vec<_> = self.map.iter().collect();
+        slots.sort_by_key(|(key, _)| **key);
+        slots.into_iter()
     }
-}
\ No newline at end of file
+}
+// Keccak-256 of the code, even empty
+fn hash_code(code: &Bytes) -> Bytes32 {
+    Bytes32::from_slice(&Keccak256::digest(code.as_slice()))
+}
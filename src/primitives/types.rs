@@ -1,9 +1,13 @@
 pub use ethereum_types::{H160, H256, U64, U256, U512};
 use serde::de::{self, Deserializer};
-use serde::Deserialize;
-use std::fmt;
-use std::ops::{BitAnd, BitOr, BitXor, Not};
-use std::ops::{Index, IndexMut, Range};
+use serde::{Deserialize, Serialize, Serializer};
+use sha3::{Digest, Keccak256};
+use core::fmt;
+use core::str::FromStr;
+use core::ops::{BitAnd, BitOr, BitXor, Not};
+use core::ops::{Index, IndexMut, Range};
+
+use crate::prelude::*;
 
 // Code struct used in the test suite
 #[derive(Debug, Deserialize, Default, Clone)]
@@ -11,8 +15,8 @@ pub struct Code {
     // Opcode representation of the code
     #[serde(default)]
     pub asm: Option<String>,
-    // Bytecode representation of the code
-    #[serde(default)]
+    // Bytecode representation of the code, as hex (empty if missing)
+    #[serde(default, deserialize_with = "deserialize_hex")]
     pub bin: String,
 }
 
@@ -41,6 +45,11 @@ impl Bytes {
         Bytes(vec![byte])
     }
 
+    // Bytecode of the mnemonics in `source` (see `asm::assemble`)
+    pub fn from_asm(source: &str) -> Result<Bytes, crate::asm::AsmError> {
+        crate::asm::assemble(source)
+    }
+
     pub fn as_slice(&self) -> &[u8] {
         &self.0
     }
@@ -62,6 +71,28 @@ impl Bytes {
         self.0.resize(new_size, value);
     }
 
+    // `size` bytes from `offset`, zero-padded past the end. Any offset is
+    // fine, even one whose sum with `size` overflows.
+    pub fn padded_slice(&self, offset: usize, size: usize) -> Bytes {
+        let mut result = vec![0u8; size];
+        if offset < self.0.len() {
+            let len = size.min(self.0.len() - offset);
+            result[..len].copy_from_slice(&self.0[offset..offset + len]);
+        }
+        Bytes(result)
+    }
+
+    // The word at `offset`, zero-padded past the end (like CALLDATALOAD)
+    pub fn padded_word(&self, offset: usize) -> Bytes32 {
+        let mut word = [0u8; 32];
+        if offset < self.0.len() {
+            let len = word.len().min(self.0.len() - offset);
+            word[..len].copy_from_slice(&self.0[offset..offset + len]);
+        }
+        Bytes32(word)
+    }
+
+    // A single 0x00 byte. No data (no return value, no code) is `Bytes::new()`.
     pub fn zero() -> Bytes {
         Bytes::from_byte(0)
     }
@@ -74,17 +105,9 @@ impl Bytes {
         self.0.iter().all(|&x| x == 0)
     }
 
-    // Conversion from/to Bytes32
+    // Conversion from/to Bytes32 (panics if longer than 32 bytes)
     pub fn as_bytes32(&self) -> Bytes32 {
-        let vec = self.0.to_vec();
-        let len = vec.len();
-        let mut bytes = [0u8; 32];
-        if len < 32 {
-            bytes[32 - len..32].copy_from_slice(&vec);
-        } else {
-            bytes.copy_from_slice(&vec[len - 32..len]);
-        }
-        Bytes32::from_vec(bytes.to_vec())
+        Bytes32::from_slice(&self.0)
     }
 
     pub fn from_bytes32(bytes: Bytes32) -> Bytes {
@@ -100,31 +123,30 @@ impl Bytes {
     }
 
     pub fn from_u512(number: U512) -> Bytes32 {
-        let mut bytes = [0u8; 32];
-        number.to_big_endian(&mut bytes);
-        Bytes32::from_vec(bytes.to_vec())
+        Bytes32::from_u512(number)
     }
 }
 
 // -- TYPE: BYTES32 -----------------------------------------------------------
-//  A wrapper around Vec<u8> that represents an 32 bytes.
+//  A wrapper around [u8; 32] that represents an 32-byte word, big-endian.
 //  Implements bitwise operations and conversion from/to other types that are
 //  used in EVM.
 
-#[derive(Debug, Default, Clone, Deserialize, PartialEq, Eq, Hash)]
-pub struct Bytes32(Vec<u8>);
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Bytes32([u8; 32]);
 
 impl Bytes32 {
     pub fn new() -> Bytes32 {
-        Bytes32::from_vec(Vec::new())
+        Bytes32::zero()
     }
 
     pub fn len(&self) -> usize {
         self.0.len()
     }
 
+    // A word is never empty (it is always 32 bytes long)
     pub fn is_empty(&self) -> bool {
-        self.0.len() == 0
+        false
     }
 
     pub fn as_usize(&self) -> usize {
@@ -139,19 +161,32 @@ impl Bytes32 {
         }
     }
 
-    pub fn from_vec(vec: Vec<u8>) -> Bytes32 {
+    // Shorter inputs are left-padded with zeros, and longer ones keep their
+    // last 32 bytes. Only for callers that want to drop the extra bytes.
+    pub fn from_vec_truncate(vec: Vec<u8>) -> Bytes32 {
         let len = vec.len();
-        let mut bytes = [0u8; 32];
-        if len < 32 {
-            bytes[32 - len..32].copy_from_slice(&vec);
-        } else {
-            bytes.copy_from_slice(&vec[len - 32..len]);
+        Bytes32::from_slice(&vec[len.saturating_sub(32)..])
+    }
+
+    // Shorter inputs are left-padded with zeros, and longer ones are an error
+    pub fn try_from_slice(slice: &[u8]) -> Result<Bytes32, InvalidLength> {
+        let len = slice.len();
+        if len > 32 {
+            return Err(InvalidLength(len));
         }
-        Bytes32(bytes.to_vec())
+        let mut bytes = [0u8; 32];
+        bytes[32 - len..32].copy_from_slice(slice);
+        Ok(Bytes32(bytes))
     }
 
+    // Like `try_from_slice`, for inputs known to fit. Panics on longer ones.
     pub fn from_slice(slice: &[u8]) -> Bytes32 {
-        Bytes32::from_vec(slice.to_vec())
+        Bytes32::try_from_slice(slice).unwrap_or_else(|error| panic!("{}", error))
+    }
+
+    // Like `from_slice`
+    pub fn from_vec(vec: Vec<u8>) -> Bytes32 {
+        Bytes32::from_slice(&vec)
     }
 
     pub fn as_slice(&self) -> &[u8] {
@@ -159,14 +194,13 @@ impl Bytes32 {
     }
 
     pub fn zero() -> Bytes32 {
-        let bytes = [0u8; 32];
-        Bytes32::from_slice(&bytes)
+        Bytes32([0u8; 32])
     }
 
     pub fn one() -> Bytes32 {
         let mut bytes = [0u8; 32];
         bytes[31] = 1;
-        Bytes32::from_slice(&bytes)
+        Bytes32(bytes)
     }
 
     pub fn is_zero(&self) -> bool {
@@ -175,7 +209,7 @@ impl Bytes32 {
 
     // Conversion from/to Bytes
     pub fn as_bytes(&self) -> Bytes {
-        Bytes(self.0.clone())
+        Bytes(self.0.to_vec())
     }
 
     pub fn from_bytes(bytes: Bytes) -> Bytes32 {
@@ -184,70 +218,49 @@ impl Bytes32 {
 
     // Conversion from/to U512
     pub fn to_u512(&self) -> U512 {
-        let len = self.0.len();
         let mut bytes = [0u8; 64];
-        bytes[64 - len..64].copy_from_slice(&self.0);
+        bytes[32..64].copy_from_slice(&self.0);
         U512::from_big_endian(&bytes)
     }
 
+    // Keeps the low 256 bits
     pub fn from_u512(number: U512) -> Bytes32 {
-        let mut bytes = [0u8; 32];
+        let mut bytes = [0u8; 64];
         number.to_big_endian(&mut bytes);
-        Bytes32::from_vec(bytes.to_vec())
+        Bytes32::from_slice(&bytes[32..])
     }
 
     // Conversion from/to U256
     pub fn to_u256(&self) -> U256 {
-        let len = self.0.len();
-        let mut bytes = [0u8; 32];
-        if len < 32 {
-            bytes[32 - len..32].copy_from_slice(&self.0);
-        } else {
-            bytes.copy_from_slice(&self.0[len - 32..len]);
-        }
-        U256::from_big_endian(&bytes)
+        U256::from_big_endian(&self.0)
     }
 
     pub fn from_u256(number: U256) -> Bytes32 {
         let mut bytes = [0u8; 32];
         number.to_big_endian(&mut bytes);
-        Bytes32::from_vec(bytes.to_vec())
+        Bytes32(bytes)
     }
 
     // Conversion from/to U64
     pub fn to_u64(&self) -> U64 {
-        let len = self.0.len();
-        let mut bytes = [0u8; 8];
-        if len < 8 {
-            bytes[8 - len..8].copy_from_slice(&self.0);
-        } else {
-            bytes.copy_from_slice(&self.0[len - 8..len]);
-        }
-        U64::from_big_endian(&bytes)
+        U64::from_big_endian(&self.0[24..32])
     }
 
     pub fn from_u64(number: U64) -> Bytes32 {
         let mut bytes = [0u8; 8];
         number.to_big_endian(&mut bytes);
-        Bytes32::from_vec(bytes.to_vec())
+        Bytes32::from_slice(&bytes)
     }
 
     // Conversion from/to H160
     pub fn to_h160(&self) -> H160 {
-        let len = self.0.len();
-        let mut bytes = [0u8; 20];
-        if len < 20 {
-            bytes[20 - len..20].copy_from_slice(&self.0);
-        } else {
-            bytes.copy_from_slice(&self.0[len - 20..len]);
-        }
-        H160::from(&bytes)
+        H160::from_slice(&self.0[12..32])
     }
 
     pub fn from_h160(address: H160) -> Bytes32 {
         let mut bytes = [0u8; 32];
         bytes[12..32].copy_from_slice(address.as_bytes());
-        Bytes32::from_vec(bytes.to_vec())
+        Bytes32(bytes)
     }
 
     // Conversion from/to Address
@@ -260,9 +273,34 @@ impl Bytes32 {
     }
 }
 
+// Deserialized from a sequence of at most 32 bytes, left-padded like
+// `try_from_slice`
+impl<'de> Deserialize<'de> for Bytes32 {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let bytes = Vec::<u8>::deserialize(deserializer)?;
+        Bytes32::try_from_slice(&bytes).map_err(de::Error::custom)
+    }
+}
+
+// Error of building a Bytes32 from more than 32 bytes (holds their number)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InvalidLength(pub usize);
+
+impl fmt::Display for InvalidLength {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Invalid word length: {} bytes", self.0)
+    }
+}
+
 // -- TYPE: ADDRESS -----------------------------------------------------------
 
-#[derive(Debug, Default, Clone, Copy, Deserialize, PartialEq, Eq, Hash)]
+//  A 20-byte account address. Parsed from 0x-prefixed hex (validating the
+//  EIP-55 checksum of mixed-case inputs) and displayed in checksum form.
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct Address(H160);
 
 impl Address {
@@ -270,8 +308,34 @@ impl Address {
         Address(H160::zero())
     }
 
+    // Shorter slices are left-padded with zeros (like a big-endian number),
+    // and longer ones keep their last 20 bytes (like addresses derived from a
+    // hash)
     pub fn from_slice(slice: &[u8]) -> Self {
-        Bytes32::from_vec(slice.to_vec()).to_address()
+        let len = slice.len();
+        let mut bytes = [0u8; 20];
+        if len < 20 {
+            bytes[20 - len..20].copy_from_slice(slice);
+        } else {
+            bytes.copy_from_slice(&slice[len - 20..len]);
+        }
+        Address(H160(bytes))
+    }
+
+    // EIP-55 form: a hex letter is uppercase iff the matching nibble of the
+    // Keccak-256 hash of the lowercase hex is 8 or more
+    pub fn to_checksum_string(&self) -> String {
+        let lower = hex::encode(self.as_slice());
+        let hash = Keccak256::digest(lower.as_bytes());
+        let checksummed: String = lower
+            .chars()
+            .enumerate()
+            .map(|(i, c)| {
+                let nibble = (hash[i / 2] >> (4 * (1 - i % 2))) & 0xf;
+                if nibble >= 8 { c.to_ascii_uppercase() } else { c }
+            })
+            .collect();
+        format!("0x{}", checksummed)
     }
 
     pub fn from_u256(number: U256) -> Self {
@@ -295,6 +359,51 @@ impl Address {
     }
 }
 
+impl FromStr for Address {
+    type Err = String;
+
+    // Accepts 40 hex digits, with or without the 0x prefix. Mixed-case inputs
+    // must carry a valid EIP-55 checksum.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let digits = s.strip_prefix("0x").unwrap_or(s);
+        if digits.len() != 40 {
+            return Err(format!("Invalid address length: {}", s));
+        }
+        let bytes = hex::decode(digits).map_err(|error| format!("Invalid address {}: {}", s, error))?;
+        let address = Address::from_slice(&bytes);
+
+        let mixed_case = digits.chars().any(|c| c.is_ascii_lowercase()) && digits.chars().any(|c| c.is_ascii_uppercase());
+        if mixed_case && address.to_checksum_string()[2..] != *digits {
+            return Err(format!("Invalid address checksum: {}", s));
+        }
+        Ok(address)
+    }
+}
+
+impl fmt::Display for Address {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.to_checksum_string())
+    }
+}
+
+impl Serialize for Address {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_str(&self.to_checksum_string())
+    }
+}
+
+impl<'de> Deserialize<'de> for Address {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        String::deserialize(deserializer)?.parse().map_err(de::Error::custom)
+    }
+}
+
 // -- COMMON TRAITS -----------------------------------------------------------
 
 // Immutable indexing
@@ -409,7 +518,7 @@ fn bitnot(a: Vec<u8>) -> Vec<u8> {
 }
 
 fn bitwise_operation(a: Vec<u8>, b: Vec<u8>, operator: fn(x: u8, y: u8) -> u8) -> Vec<u8> {
-    let len = std::cmp::max(a.len(), b.len());
+    let len = core::cmp::max(a.len(), b.len());
     let mut result = Vec::with_capacity(len);
     for i in 0..len {
         let x = *a.get(i).unwrap_or(&0);
@@ -444,7 +553,7 @@ impl BitAnd for Bytes32 {
     type Output = Self;
 
     fn bitand(self, rhs: Self) -> Self::Output {
-        Bytes32(bitand(self.0, rhs.0))
+        Bytes32(core::array::from_fn(|i| self.0[i] & rhs.0[i]))
     }
 }
 
@@ -460,7 +569,7 @@ impl BitOr for Bytes32 {
     type Output = Self;
 
     fn bitor(self, rhs: Self) -> Self::Output {
-        Bytes32(bitor(self.0, rhs.0))
+        Bytes32(core::array::from_fn(|i| self.0[i] | rhs.0[i]))
     }
 }
 
@@ -476,7 +585,7 @@ impl BitXor for Bytes32 {
     type Output = Self;
 
     fn bitxor(self, rhs: Self) -> Self::Output {
-        Bytes32(bitxor(self.0, rhs.0))
+        Bytes32(core::array::from_fn(|i| self.0[i] ^ rhs.0[i]))
     }
 }
 
@@ -492,7 +601,138 @@ impl Not for Bytes32 {
     type Output = Self;
 
     fn not(self) -> Self::Output {
-        Bytes32(bitnot(self.0))
+        Bytes32(self.0.map(|x| !x))
+    }
+}
+
+// -- CONVERSIONS -------------------------------------------------------------
+//
+//  Lossless conversions from/to the primitives of ethereum_types and (with the
+//  `alloy` feature) alloy. Words are big-endian. Converting a slice into a
+//  Bytes32 left-pads shorter slices with zeros and fails on slices longer than
+//  32 bytes (see `try_from_slice`).
+
+impl From<H160> for Address {
+    fn from(address: H160) -> Self {
+        Address(address)
+    }
+}
+
+impl From<Address> for H160 {
+    fn from(address: Address) -> Self {
+        address.0
+    }
+}
+
+impl From<U256> for Bytes32 {
+    fn from(number: U256) -> Self {
+        Bytes32::from_u256(number)
+    }
+}
+
+impl From<Bytes32> for U256 {
+    fn from(word: Bytes32) -> Self {
+        word.to_u256()
+    }
+}
+
+impl From<H256> for Bytes32 {
+    fn from(hash: H256) -> Self {
+        Bytes32(hash.0)
+    }
+}
+
+impl From<Bytes32> for H256 {
+    fn from(word: Bytes32) -> Self {
+        H256(word.0)
+    }
+}
+
+impl From<[u8; 32]> for Bytes32 {
+    fn from(bytes: [u8; 32]) -> Self {
+        Bytes32(bytes)
+    }
+}
+
+impl TryFrom<&[u8]> for Bytes32 {
+    type Error = InvalidLength;
+
+    fn try_from(slice: &[u8]) -> Result<Self, Self::Error> {
+        Bytes32::try_from_slice(slice)
+    }
+}
+
+impl From<Vec<u8>> for Bytes {
+    fn from(vec: Vec<u8>) -> Self {
+        Bytes(vec)
+    }
+}
+
+impl From<&[u8]> for Bytes {
+    fn from(slice: &[u8]) -> Self {
+        Bytes::from_slice(slice)
+    }
+}
+
+impl From<Bytes> for Vec<u8> {
+    fn from(bytes: Bytes) -> Self {
+        bytes.0
+    }
+}
+
+#[cfg(feature = "alloy")]
+impl From<alloy_primitives::Address> for Address {
+    fn from(address: alloy_primitives::Address) -> Self {
+        Address(H160(address.into_array()))
+    }
+}
+
+#[cfg(feature = "alloy")]
+impl From<Address> for alloy_primitives::Address {
+    fn from(address: Address) -> Self {
+        alloy_primitives::Address::new(address.0 .0)
+    }
+}
+
+#[cfg(feature = "alloy")]
+impl From<alloy_primitives::B256> for Bytes32 {
+    fn from(word: alloy_primitives::B256) -> Self {
+        Bytes32(word.0)
+    }
+}
+
+#[cfg(feature = "alloy")]
+impl From<Bytes32> for alloy_primitives::B256 {
+    fn from(word: Bytes32) -> Self {
+        alloy_primitives::B256::new(word.0)
+    }
+}
+
+#[cfg(feature = "alloy")]
+impl From<alloy_primitives::U256> for Bytes32 {
+    fn from(number: alloy_primitives::U256) -> Self {
+        Bytes32(number.to_be_bytes())
+    }
+}
+
+#[cfg(feature = "alloy")]
+impl From<Bytes32> for alloy_primitives::U256 {
+    fn from(word: Bytes32) -> Self {
+        alloy_primitives::U256::from_be_bytes(word.0)
+    }
+}
+
+#[cfg(feature = "alloy")]
+impl From<alloy_primitives::Bytes> for Bytes {
+    fn from(bytes: alloy_primitives::Bytes) -> Self {
+        Bytes(bytes.to_vec())
+    }
+}
+
+#[cfg(feature = "alloy")]
+impl From<Bytes> for alloy_primitives::Bytes {
+    fn from(bytes: Bytes) -> Self {
+        alloy_primitives::Bytes::from(bytes.0)
     }
 }
 
@@ -500,6 +740,131 @@ impl Not for Bytes32 {
 
 // Custom deserializers to convert hex strings from EVM Test
 
+// Numbers are given as JSON numbers, decimal strings or 0x-prefixed hex
+// strings, depending on the fixture (and sometimes on the field)
+pub fn parse_u256(s: &str) -> Result<U256, InvalidNumber> {
+    let invalid = || InvalidNumber::Format(s.to_string());
+    match s.strip_prefix("0x").or_else(|| s.strip_prefix("0X")) {
+        // "0x" alone is zero, like in the RLP-style encodings of the fixtures
+        Some("") => Ok(U256::zero()),
+        Some(digits) if digits.bytes().all(|b| b.is_ascii_hexdigit()) => {
+            U256::from_str_radix(digits, 16).map_err(|_| InvalidNumber::Overflow(s.to_string(), 256))
+        }
+        None if !s.is_empty() && s.bytes().all(|b| b.is_ascii_digit()) => {
+            U256::from_dec_str(s).map_err(|_| InvalidNumber::Overflow(s.to_string(), 256))
+        }
+        _ => Err(invalid()),
+    }
+}
+
+pub fn parse_u64(s: &str) -> Result<U64, InvalidNumber> {
+    let number = parse_u256(s)?;
+    if number > U256::from(u64::MAX) {
+        return Err(InvalidNumber::Overflow(s.to_string(), 64));
+    }
+    Ok(number.as_u64().into())
+}
+
+// Error of parsing a number from a string
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InvalidNumber {
+    // Neither decimal nor 0x-prefixed hex
+    Format(String),
+    // Doesn't fit in the given number of bits
+    Overflow(String, usize),
+}
+
+impl fmt::Display for InvalidNumber {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            InvalidNumber::Format(s) => {
+                write!(f, "Invalid number `{}`: expected a decimal or a 0x-prefixed hex string", s)
+            }
+            InvalidNumber::Overflow(s, bits) => write!(f, "Number `{}` doesn't fit in {} bits", s, bits),
+        }
+    }
+}
+
+// A U256 in any of the encodings of `parse_u256`, or a JSON number
+struct Number(U256);
+
+impl<'de> Deserialize<'de> for Number {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_any(NumberVisitor).map(Number)
+    }
+}
+
+struct NumberVisitor;
+
+impl<'de> de::Visitor<'de> for NumberVisitor {
+    type Value = U256;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "a non-negative JSON integer, a decimal string or a 0x-prefixed hex string")
+    }
+
+    fn visit_u64<E: de::Error>(self, value: u64) -> Result<U256, E> {
+        Ok(value.into())
+    }
+
+    fn visit_str<E: de::Error>(self, value: &str) -> Result<U256, E> {
+        parse_u256(value).map_err(E::custom)
+    }
+}
+
+fn to_u64<E: de::Error>(number: U256) -> Result<U64, E> {
+    if number > U256::from(u64::MAX) {
+        return Err(E::custom(format!("Number `{:#x}` doesn't fit in 64 bits", number)));
+    }
+    Ok(number.as_u64().into())
+}
+
+pub fn deserialize_u256<'de, D>(deserializer: D) -> Result<U256, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    Ok(Number::deserialize(deserializer)?.0)
+}
+
+pub fn deserialize_u256_option<'de, D>(deserializer: D) -> Result<Option<U256>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    Ok(Option::<Number>::deserialize(deserializer)?.map(|number| number.0))
+}
+
+pub fn deserialize_u256_vec<'de, D>(deserializer: D) -> Result<Vec<U256>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    Ok(Vec::<Number>::deserialize(deserializer)?.into_iter().map(|number| number.0).collect())
+}
+
+// Words given as hex strings, like the versioned hashes of blobs
+pub fn deserialize_bytes32_vec<'de, D>(deserializer: D) -> Result<Vec<Bytes32>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    Ok(deserialize_u256_vec(deserializer)?.into_iter().map(Bytes32::from_u256).collect())
+}
+
+pub fn deserialize_u64<'de, D>(deserializer: D) -> Result<U64, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    to_u64(Number::deserialize(deserializer)?.0)
+}
+
+pub fn deserialize_u64_option<'de, D>(deserializer: D) -> Result<Option<U64>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    Option::<Number>::deserialize(deserializer)?.map(|number| to_u64(number.0)).transpose()
+}
+
 pub fn hex_string_to_u64<'de, D>(deserializer: D) -> Result<Vec<u8>, D::Error>
 where
     D: Deserializer<'de>,
@@ -512,6 +877,17 @@ where
     hex::decode(trimmed).map_err(de::Error::custom)
 }
 
+// Hex string without its 0x prefix, which is checked to decode
+pub fn deserialize_hex<'de, D>(deserializer: D) -> Result<String, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let s = String::deserialize(deserializer)?;
+    let trimmed = s.strip_prefix("0x").unwrap_or(&s);
+    hex::decode(trimmed).map_err(|error| de::Error::custom(format!("invalid hex `{}`: {}", s, error)))?;
+    Ok(trimmed.to_string())
+}
+
 pub fn hex_string_to_bytes<'de, D>(deserializer: D) -> Result<Bytes, D::Error>
 where
     D: Deserializer<'de>,
@@ -525,6 +901,14 @@ where
     Ok(Bytes::from_vec(bytes))
 }
 
+pub fn hex_string_to_bytes_option<'de, D>(deserializer: D) -> Result<Option<Bytes>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let s: Option<String> = Option::deserialize(deserializer)?;
+    s.map(|s| hex_string_to_bytes_temp(s.as_str()).map_err(de::Error::custom)).transpose()
+}
+
 pub fn hex_string_to_bytes_vec<'de, D>(deserializer: D) -> Result<Vec<Bytes>, D::Error>
 where
     D: Deserializer<'de>,
@@ -538,12 +922,12 @@ where
         .collect()
 }
 
-fn hex_string_to_bytes_temp(s: &str) -> Result<Bytes, serde_json::Error> {
+fn hex_string_to_bytes_temp(s: &str) -> Result<Bytes, hex::FromHexError> {
     let trimmed = match s.strip_prefix("0x") {
         Some(stripped) => stripped,
         None => s,
     };
-    let bytes = hex::decode(trimmed).map_err(de::Error::custom)?;
+    let bytes = hex::decode(trimmed)?;
     Ok(Bytes::from_vec(bytes))
 }
 
@@ -0,0 +1,256 @@
+// Runner of the GeneralStateTests fixtures of `ethereum/tests` (in the format
+// filled by `execution-spec-tests`).
+//
+// Every test is a pre-state and a transaction whose data, gas limit and value
+// are given as arrays. Each post-state entry of a fork selects one element of
+// each array through its `indexes`, and the resulting transaction is executed
+// through `ExecutionContext::transact` on top of the pre-state.
+//
+// State roots aren't computed, so the post-state is compared account by
+// account instead (balances, nonces, code and storage), and the entries that
+// only carry a state root are skipped. So are the ones expecting the
+// transaction to be rejected, and the transaction types that aren't supported.
+// Gas fees aren't charged either, so only fixtures with a zero gas price can
+// match the expected balances.
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashMap};
+use std::panic::{catch_unwind, AssertUnwindSafe};
+
+use crate::evmtest::Outcome;
+use crate::primitives::*;
+use crate::ExecutionContext;
+
+// Fork whose post-states are checked by default
+pub const DEFAULT_FORK: &str = "Cancun";
+
+// A state test
+#[derive(Debug, Deserialize)]
+pub struct StateTest {
+    // Name of the test (the key of the fixture)
+    #[serde(skip)]
+    pub name: String,
+    // Block environment
+    env: TestEnv,
+    // Accounts before the transaction
+    pre: HashMap<Address, TestAccount>,
+    // Transaction, with the alternative data, gas limits and values
+    transaction: TestTransaction,
+    // Expected outcomes per fork
+    post: BTreeMap<String, Vec<PostState>>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TestEnv {
+    #[serde(deserialize_with = "hex_string_to_address")]
+    current_coinbase: Address,
+    #[serde(deserialize_with = "deserialize_u256")]
+    current_gas_limit: U256,
+    #[serde(deserialize_with = "deserialize_u64")]
+    current_number: U64,
+    #[serde(deserialize_with = "deserialize_u256")]
+    current_timestamp: U256,
+    #[serde(default, deserialize_with = "deserialize_u256_option")]
+    current_difficulty: Option<U256>,
+    #[serde(default, deserialize_with = "deserialize_u256_option")]
+    current_random: Option<U256>,
+    #[serde(default, deserialize_with = "deserialize_u256_option")]
+    current_base_fee: Option<U256>,
+}
+
+#[derive(Debug, Deserialize)]
+struct TestAccount {
+    #[serde(default, deserialize_with = "deserialize_u256")]
+    balance: U256,
+    #[serde(default, deserialize_with = "deserialize_u256")]
+    nonce: U256,
+    #[serde(default, deserialize_with = "hex_string_to_bytes")]
+    code: Bytes,
+    #[serde(default)]
+    storage: HashMap<U256, U256>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TestTransaction {
+    #[serde(deserialize_with = "hex_string_to_address")]
+    sender: Address,
+    // Recipient, empty for contract creations
+    #[serde(deserialize_with = "hex_string_to_address")]
+    to: Address,
+    #[serde(default, deserialize_with = "deserialize_u256")]
+    nonce: U256,
+    #[serde(default, deserialize_with = "deserialize_u256_option")]
+    gas_price: Option<U256>,
+    #[serde(default, deserialize_with = "deserialize_u256_option")]
+    max_fee_per_gas: Option<U256>,
+    #[serde(default, deserialize_with = "deserialize_u256_option")]
+    max_priority_fee_per_gas: Option<U256>,
+    #[serde(deserialize_with = "hex_string_to_bytes_vec")]
+    data: Vec<Bytes>,
+    #[serde(deserialize_with = "deserialize_u256_vec")]
+    gas_limit: Vec<U256>,
+    #[serde(deserialize_with = "deserialize_u256_vec")]
+    value: Vec<U256>,
+    // Fields of the transaction types that aren't supported
+    #[serde(default)]
+    blob_versioned_hashes: Option<serde_json::Value>,
+    #[serde(default)]
+    authorization_list: Option<serde_json::Value>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PostState {
+    // Elements of the transaction arrays used by this entry
+    indexes: Indexes,
+    // Expected accounts after the transaction (not present in older fixtures)
+    #[serde(default)]
+    state: Option<HashMap<Address, TestAccount>>,
+    // Expected rejection of the transaction
+    #[serde(default)]
+    expect_exception: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Indexes {
+    data: usize,
+    gas: usize,
+    value: usize,
+}
+
+// Loads the state tests of the fixture at `path`, sorted by name
+pub fn load(path: &str) -> Result<Vec<StateTest>, String> {
+    let text = std::fs::read_to_string(path).map_err(|error| format!("{}: {}", path, error))?;
+    let tests: BTreeMap<String, StateTest> =
+        serde_json::from_str(&text).map_err(|error| format!("{}: {}", path, error))?;
+    Ok(tests
+        .into_iter()
+        .map(|(name, test)| StateTest { name, ..test })
+        .collect())
+}
+
+impl StateTest {
+    // Runs every post-state entry of `fork`, returning their labels and outcomes
+    pub fn run(&self, fork: &str) -> Vec<(String, Outcome)> {
+        let posts = match self.post.get(fork) {
+            Some(posts) => posts,
+            None => return vec![(self.name.clone(), Outcome::Skip(format!("no post-state for {}", fork)))],
+        };
+        let spec: SpecId = match fork.parse() {
+            Ok(spec) => spec,
+            Err(error) => return vec![(self.name.clone(), Outcome::Skip(error))],
+        };
+
+        posts
+            .iter()
+            .map(|post| {
+                let label = format!(
+                    "{} [{}] d{}g{}v{}",
+                    self.name, fork, post.indexes.data, post.indexes.gas, post.indexes.value
+                );
+                (label, self.run_post(spec, post))
+            })
+            .collect()
+    }
+
+    fn run_post(&self, spec: SpecId, post: &PostState) -> Outcome {
+        if self.transaction.blob_versioned_hashes.is_some() {
+            return Outcome::Skip("blob transactions aren't supported".to_string());
+        }
+        if self.transaction.authorization_list.is_some() {
+            return Outcome::Skip("set-code transactions aren't supported".to_string());
+        }
+        if let Some(exception) = &post.expect_exception {
+            return Outcome::Skip(format!("transaction validation isn't supported ({})", exception));
+        }
+        let expected = match &post.state {
+            Some(expected) => expected,
+            None => return Outcome::Skip("only the state root is given".to_string()),
+        };
+
+        let (tx, call) = match self.transaction(&post.indexes) {
+            Some(transaction) => transaction,
+            None => return Outcome::Fail(format!("Indexes out of bounds: {:?}", post.indexes)),
+        };
+        let execution = catch_unwind(AssertUnwindSafe(|| {
+            ExecutionContext::transact(tx, call, self.block(), spec, self.pre_state(), 0).2
+        }));
+
+        match execution {
+            Ok(state) => match compare(&state, expected) {
+                Some(report) => Outcome::Fail(report),
+                None => Outcome::Pass,
+            },
+            Err(panic) => Outcome::Fail(format!("Panicked: {:?}", panic.downcast_ref::<&str>())),
+        }
+    }
+
+    fn transaction(&self, indexes: &Indexes) -> Option<(TxEnv, Call)> {
+        let transaction = &self.transaction;
+        let tx = TxEnv::new(
+            transaction.sender,
+            transaction.gas_price.unwrap_or_default(),
+            *transaction.gas_limit.get(indexes.gas)?,
+            transaction.nonce,
+        );
+        let tx = match transaction.max_fee_per_gas {
+            Some(max_fee) => tx.with_max_fees(max_fee, transaction.max_priority_fee_per_gas.unwrap_or_default()),
+            None => tx,
+        };
+        let call = Call::new(
+            transaction.sender,
+            transaction.to,
+            transaction.to,
+            transaction.data.get(indexes.data)?.clone(),
+            *transaction.value.get(indexes.value)?,
+            false,
+        );
+        Some((tx, call))
+    }
+
+    fn block(&self) -> Block {
+        Block {
+            number: Some(self.env.current_number),
+            gas_limit: self.env.current_gas_limit,
+            timestamp: self.env.current_timestamp,
+            prev_randao: self.env.current_random,
+            difficulty: self.env.current_difficulty,
+            base_fee: self.env.current_base_fee,
+            beneficiary: Some(self.env.current_coinbase),
+            ..Default::default()
+        }
+    }
+
+    fn pre_state(&self) -> State {
+        let mut state = State::new();
+        for (address, account) in &self.pre {
+            let account_state = AccountState::from_parts(*address, account.balance, account.nonce, account.code.clone());
+            state.insert(*address, account_state);
+            for (key, value) in &account.storage {
+                state.set_storage(address, *key, Bytes32::from_u256(*value));
+            }
+        }
+        state
+    }
+}
+
+// Report of the differences between the state and the expected accounts.
+// None if they match.
+fn compare(state: &State, expected: &HashMap<Address, TestAccount>) -> Option<String> {
+    let mut expected_state = State::new();
+    for (address, account) in expected {
+        let mut account_state = AccountState::from_parts(*address, account.balance, account.nonce, account.code.clone());
+        for (key, value) in &account.storage {
+            account_state.storage_mut().store(*key, Bytes32::from_u256(*value));
+        }
+        expected_state.insert(*address, account_state);
+    }
+
+    let diff = expected_state.diff(state);
+    if diff.is_empty() {
+        None
+    } else {
+        Some(format!("expected -> actual:\n{}", diff))
+    }
+}
@@ -1,3 +1,8 @@
+use sha3::{Digest, Keccak256};
+
+use crate::prelude::*;
+use crate::types::{Address, Bytes32, U256};
+
 pub fn rlp_encode(input: &[u8]) -> Vec<u8> {
     if input.len() == 1 && input[0] < 0x80 {
         vec![input[0]]
@@ -28,4 +33,29 @@ fn to_binary(mut x: usize) -> Vec<u8> {
         x /= 256;
     }
     result
-}
\ No newline at end of file
+}
+// Address of a contract created by `sender` with the given nonce
+pub fn create_address(sender: &Address, nonce: U256) -> Address {
+    let mut encoded = rlp_encode(sender.as_slice());
+    encoded.append(&mut rlp_encode(Bytes32::from_u256(nonce).as_slice()));
+    Address::from_slice(Keccak256::digest(encoded).as_slice())
+}
+
+// Address of a contract created by `sender` with CREATE2: it only depends on
+// the salt and the init code, not on the nonce (EIP-1014)
+pub fn create2_address(sender: &Address, salt: &Bytes32, init_code: &[u8]) -> Address {
+    let mut encoded = vec![0xff];
+    encoded.extend_from_slice(sender.as_slice());
+    encoded.extend_from_slice(salt.as_slice());
+    encoded.extend_from_slice(Keccak256::digest(init_code).as_slice());
+    Address::from_slice(Keccak256::digest(encoded).as_slice())
+}
+
+// Sorted and deduplicated copy of a set of addresses, so that any processing
+// over them happens in a deterministic order
+pub fn sorted_addresses<'a>(addresses: impl IntoIterator<Item = &'a Address>) -> Vec<Address> {
+    let mut sorted: Vec<Address> = addresses.into_iter().copied().collect();
+    sorted.sort();
+    sorted.dedup();
+    sorted
+}
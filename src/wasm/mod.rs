@@ -0,0 +1,67 @@
+// JavaScript bindings, for running the interpreter in a browser. Requires the
+// `wasm` feature.
+//
+// `run` executes hex code with hex call data against a state given in the
+// JSON format of `evm.json`, through the `Evm` facade (like `evm::eval`), and
+// returns the outcome as a plain object. Bad inputs and panics of the
+// interpreter are thrown as JS errors. On `wasm32-unknown-unknown`, which
+// can't unwind, a panic still traps, as a `RuntimeError` whose message is
+// logged to the console first.
+
+use serde_json::{json, Value};
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use wasm_bindgen::prelude::*;
+
+use crate::evm::Evm;
+use crate::primitives::*;
+
+// Executes `code_hex` (deployed at 0xeeee…ee) with `calldata_hex`, on top of
+// `state_json` (empty for an empty state). Returns an object with `success`,
+// `stack` (bottom to top), `logs`, `returnData` and `gasUsed`.
+#[wasm_bindgen]
+pub fn run(code_hex: &str, calldata_hex: &str, state_json: &str) -> Result<JsValue, JsValue> {
+    console_error_panic_hook::set_once();
+    let output = catch_unwind(AssertUnwindSafe(|| run_json(code_hex, calldata_hex, state_json)))
+        .unwrap_or_else(|panic| Err(panic_message(panic.as_ref())))
+        .map_err(|error| JsValue::from(JsError::new(&error)))?;
+    js_sys::JSON::parse(&output.to_string())
+}
+
+// `run`, without the JS values
+pub fn run_json(code_hex: &str, calldata_hex: &str, state_json: &str) -> Result<Value, String> {
+    let code = decode_hex(code_hex).map_err(|error| format!("Invalid code: {}", error))?;
+    let calldata = decode_hex(calldata_hex).map_err(|error| format!("Invalid call data: {}", error))?;
+    let state = match state_json.trim() {
+        "" => State::new(),
+        state_json => serde_json::from_str(state_json).map_err(|error| format!("Invalid state: {}", error))?,
+    };
+
+    let contract = Address::from_slice(&[0xee; 20]);
+    let mut evm = Evm::new(Block::default(), state).disable_nonce_check(true);
+    evm.set_code(&contract, Bytes::from_vec(code));
+    let call = Call::new(Address::zero(), contract, contract, Bytes::from_vec(calldata), U256::zero(), false);
+    let result = evm.transact(TxEnv::default(), call);
+
+    let stack: Vec<String> = result.stack.iter().map(|item| format!("{:#x}", item.to_u256())).collect();
+    let logs = serde_json::to_value(evm.receipts()[0].rpc_logs(0)).map_err(|error| error.to_string())?;
+    Ok(json!({
+        "success": result.success,
+        "stack": stack,
+        "logs": logs,
+        "returnData": format!("0x{}", hex::encode(result.result.as_slice())),
+        "gasUsed": result.gas_used,
+    }))
+}
+
+fn decode_hex(hex: &str) -> Result<Vec<u8>, hex::FromHexError> {
+    let hex = hex.trim();
+    hex::decode(hex.strip_prefix("0x").unwrap_or(hex))
+}
+
+fn panic_message(panic: &(dyn std::any::Any + Send)) -> String {
+    match (panic.downcast_ref::<&str>(), panic.downcast_ref::<String>()) {
+        (Some(message), _) => format!("Panicked: {}", message),
+        (_, Some(message)) => format!("Panicked: {}", message),
+        _ => "Panicked".to_string(),
+    }
+}
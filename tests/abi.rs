@@ -0,0 +1,89 @@
+// Encoding calls and decoding return values with the `abi` helpers
+use evm_from_scrust::abi::*;
+use evm_from_scrust::asm::Assembler;
+use evm_from_scrust::*;
+use sha3::{Digest, Keccak256};
+use Opcode::*;
+
+fn word(hex: &str) -> Bytes32 {
+    Bytes32::from_slice(&hex::decode(hex).unwrap())
+}
+
+#[test]
+fn selectors() {
+    assert_eq!(selector("transfer(address,uint256)"), [0xa9, 0x05, 0x9c, 0xbb]);
+    assert_eq!(selector("balanceOf(address)"), [0x70, 0xa0, 0x82, 0x31]);
+}
+
+#[test]
+fn encoding() {
+    let address = Address::from_slice(&[0x11; 20]);
+    let data = encode_call("transfer(address,uint256)", &[AbiValue::Address(address), AbiValue::Uint(1000.into())]);
+    assert_eq!(data.len(), 4 + 2 * 32);
+    assert_eq!(&data.as_slice()[..4], &[0xa9, 0x05, 0x9c, 0xbb]);
+    assert_eq!(decode_words(&Bytes::from_slice(&data.as_slice()[4..])), [Bytes32::from_address(address), Bytes32::from_u256(1000.into())]);
+
+    assert_eq!(AbiValue::Bool(true).encode(), Bytes32::one());
+    assert_eq!(AbiValue::Bool(false).encode(), Bytes32::zero());
+    // Fixed-size byte arrays are left-aligned, unlike numbers
+    let bytes4 = AbiValue::FixedBytes(vec![0xde, 0xad, 0xbe, 0xef]).encode();
+    assert_eq!(bytes4, word("deadbeef00000000000000000000000000000000000000000000000000000000"));
+    let hash = Bytes32::from([0x42; 32]);
+    assert_eq!(AbiValue::Bytes32(hash).encode(), hash);
+}
+
+#[test]
+#[should_panic(expected = "bytesN holds at most 32 bytes, got 33")]
+fn fixed_bytes_too_long() {
+    AbiValue::FixedBytes(vec![0; 33]).encode();
+}
+
+#[test]
+fn decoding() {
+    assert!(decode_words(&Bytes::new()).is_empty());
+    let data = Bytes::from_vec([[0x01; 32].as_slice(), &[0x02]].concat());
+    assert_eq!(decode_words(&data), [Bytes32::from([0x01; 32]), word("0200000000000000000000000000000000000000000000000000000000000000")]);
+}
+
+#[test]
+fn erc20_balance_of() {
+    // balanceOf(address), with the balances in a mapping at slot 0
+    let token = Address::from_slice(&[0xcc; 20]);
+    let code = Assembler::new()
+        .push(4)
+        .op(CALLDATALOAD)
+        .push(0)
+        .op(MSTORE)
+        .push(0)
+        .push(32)
+        .op(MSTORE)
+        .push(64)
+        .push(0)
+        .op(SHA3)
+        .op(SLOAD)
+        .push(0)
+        .op(MSTORE)
+        .push(32)
+        .push(0)
+        .op(RETURN)
+        .build();
+    let holder = Address::from_slice(&[0x11; 20]);
+    let slot = Keccak256::digest([Bytes32::from_address(holder).as_slice(), &[0; 32]].concat());
+
+    let mut evm = Evm::new(Block::default(), State::new());
+    evm.set_code(&token, code);
+    evm.set_storage(&token, U256::from_big_endian(&slot), Bytes32::from_u256(1000.into()));
+
+    let words = evm.call_fn(token, "balanceOf(address)", &[AbiValue::Address(holder)]).unwrap();
+    assert_eq!(words, [Bytes32::from_u256(1000.into())]);
+    let words = evm.call_fn(token, "balanceOf(address)", &[AbiValue::Address(token)]).unwrap();
+    assert_eq!(words, [Bytes32::zero()]);
+}
+
+#[test]
+fn call_fn_halt() {
+    let reverter = Address::from_slice(&[0xcc; 20]);
+    let mut evm = Evm::new(Block::default(), State::new());
+    evm.set_code(&reverter, Assembler::new().push(0).push(0).op(REVERT).build());
+    assert_eq!(evm.call_fn(reverter, "f()", &[]), Err(Halt::Revert));
+}
@@ -0,0 +1,124 @@
+// Accounts and storage slots warmed before a transaction runs: those of its
+// access list (EIP-2930), paid up front, and those that are always warm
+use evm_from_scrust::asm::Assembler;
+use evm_from_scrust::*;
+use Opcode::*;
+
+mod common;
+use common::{contract, sender};
+
+fn coinbase() -> Address {
+    Address::from_slice(&[0xcb; 20])
+}
+
+fn transact(spec: SpecId, code: Bytes, access_list: Vec<(Address, Vec<U256>)>) -> EvmResult {
+    let block = Block::builder().coinbase(coinbase()).build();
+    let mut evm = Evm::new(block, State::new()).with_spec(spec);
+    evm.set_code(&contract(), code);
+    let call = Call::new(sender(), contract(), contract(), Bytes::new(), U256::zero(), false);
+    evm.transact(TxEnv::default().with_access_list(access_list), call)
+}
+
+fn sload(key: u64) -> Bytes {
+    Assembler::new().push(key).op(SLOAD).build()
+}
+
+#[test]
+fn listed_slot() {
+    let listed = vec![(contract(), vec![U256::from(1)])];
+    let result = transact(SpecId::Shanghai, sload(1), listed.clone());
+    assert!(result.success);
+    assert_eq!(result.gas_used, ACCESS_LIST_ADDRESS_COST + ACCESS_LIST_STORAGE_KEY_COST + 3 + WARM_STORAGE_READ_COST);
+
+    // Other keys of a listed account are still cold
+    let result = transact(SpecId::Shanghai, sload(2), listed);
+    assert_eq!(result.gas_used, ACCESS_LIST_ADDRESS_COST + ACCESS_LIST_STORAGE_KEY_COST + 3 + COLD_SLOAD_COST);
+
+    let result = transact(SpecId::Shanghai, sload(1), Vec::new());
+    assert_eq!(result.gas_used, 3 + COLD_SLOAD_COST);
+}
+
+#[test]
+fn listed_account() {
+    let other = Address::from_slice(&[0x0b; 20]);
+    let code = Assembler::new().push(other.to_u256()).op(BALANCE).build();
+    let result = transact(SpecId::Shanghai, code.clone(), vec![(other, Vec::new())]);
+    assert_eq!(result.gas_used, ACCESS_LIST_ADDRESS_COST + 3 + WARM_STORAGE_READ_COST);
+    let result = transact(SpecId::Shanghai, code, Vec::new());
+    assert_eq!(result.gas_used, 3 + COLD_ACCOUNT_ACCESS_COST);
+}
+
+#[test]
+fn intrinsic_gas() {
+    let keys = |count: u64| (0..count).map(U256::from).collect::<Vec<_>>();
+    let list = vec![(contract(), keys(2)), (sender(), Vec::new()), (coinbase(), keys(3))];
+    let expected = 3 * ACCESS_LIST_ADDRESS_COST + 5 * ACCESS_LIST_STORAGE_KEY_COST;
+    assert_eq!(access_list_gas(SpecId::Shanghai, &list), expected);
+    assert_eq!(transact(SpecId::Shanghai, Bytes::new(), list.clone()).gas_used, expected);
+    assert_eq!(transact(SpecId::Berlin, Bytes::new(), list.clone()).gas_used, expected);
+    // Access lists came with Berlin
+    assert_eq!(transact(SpecId::Istanbul, Bytes::new(), list).gas_used, 0);
+}
+
+#[test]
+fn unaffordable_access_list() {
+    let mut evm = Evm::new(Block::default(), State::new());
+    let call = Call::new(sender(), contract(), contract(), Bytes::new(), U256::zero(), false);
+    let tx = TxEnv::new(sender(), U256::zero(), 2399.into(), U256::zero()).with_access_list(vec![(contract(), Vec::new())]);
+    let result = evm.transact(tx, call);
+    assert_eq!(result.halt, Some(Halt::OutOfGas));
+    assert_eq!(result.gas_used, 0);
+    assert!(evm.state().nonce(&sender()).is_zero());
+}
+
+// BALANCE of each address
+fn balances(addresses: &[Address]) -> Bytes {
+    addresses
+        .iter()
+        .fold(Assembler::new(), |code, address| code.push(address.to_u256()).op(BALANCE))
+        .build()
+}
+
+#[test]
+fn always_warm() {
+    let warm = [sender(), contract(), Address::from_slice(&[0x01]), Address::from_slice(&[0x09]), coinbase()];
+    let result = transact(SpecId::Shanghai, balances(&warm), Vec::new());
+    assert_eq!(result.gas_used, warm.len() * (3 + WARM_STORAGE_READ_COST));
+
+    // The coinbase only since Shanghai (EIP-3651), and 0x0a since Cancun
+    let result = transact(SpecId::Merge, balances(&[coinbase()]), Vec::new());
+    assert_eq!(result.gas_used, 3 + COLD_ACCOUNT_ACCESS_COST);
+    let point_evaluation = [Address::from_slice(&[0x0a])];
+    assert_eq!(transact(SpecId::Shanghai, balances(&point_evaluation), Vec::new()).gas_used, 3 + COLD_ACCOUNT_ACCESS_COST);
+    assert_eq!(transact(SpecId::Cancun, balances(&point_evaluation), Vec::new()).gas_used, 3 + WARM_STORAGE_READ_COST);
+}
+
+// BALANCE(COINBASE) and BALANCE(ORIGIN) as the first instructions
+#[test]
+fn coinbase_and_origin() {
+    let code = Assembler::new().op(COINBASE).op(BALANCE).build();
+    assert_eq!(transact(SpecId::Shanghai, code.clone(), Vec::new()).gas_used, 2 + WARM_STORAGE_READ_COST);
+    assert_eq!(transact(SpecId::London, code, Vec::new()).gas_used, 2 + COLD_ACCOUNT_ACCESS_COST);
+
+    // Even when the origin isn't the sender
+    let origin = Address::from_slice(&[0x0e; 20]);
+    for spec in [SpecId::Berlin, SpecId::London, SpecId::Shanghai] {
+        let mut evm = Evm::new(Block::default(), State::new()).with_spec(spec);
+        evm.set_code(&contract(), Assembler::new().op(ORIGIN).op(BALANCE).build());
+        let call = Call::new(sender(), contract(), contract(), Bytes::new(), U256::zero(), false);
+        let tx = TxEnv { originator: origin, ..TxEnv::default() };
+        assert_eq!(evm.transact(tx, call).gas_used, 2 + WARM_STORAGE_READ_COST, "{:?}", spec);
+    }
+}
+
+#[test]
+fn json() {
+    let tx: TxEnv = serde_json::from_str(
+        r#"{"accessList": [
+            {"address": "0xcccccccccccccccccccccccccccccccccccccccc", "storageKeys": ["0x01", "0x0000000000000000000000000000000000000000000000000000000000000002"]},
+            {"address": "0xaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa", "storageKeys": []}
+        ]}"#,
+    )
+    .unwrap();
+    assert_eq!(tx.access_list, [(contract(), vec![U256::from(1), U256::from(2)]), (sender(), Vec::new())]);
+}
@@ -0,0 +1,62 @@
+// Parsing and formatting of addresses, against the test vectors of EIP-55
+use evm_from_scrust::*;
+
+const CHECKSUMMED: [&str; 8] = [
+    // All caps
+    "0x52908400098527886E0F7030069857D2E4169EE7",
+    "0x8617E340B3D01FA5F11F306F4090FD50E238070D",
+    // All lower
+    "0xde709f2102306220921060314715629080e2fb77",
+    "0x27b1fdb04752bbc536007a920d24acb045561c26",
+    // Mixed
+    "0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed",
+    "0xfB6916095ca1df60bB79Ce92cE3Ea74c37c5d359",
+    "0xdbF03B407c01E7cD3CBea99509d93f8DDDC8C6FB",
+    "0xD1220A0cf47c7B9Be7A2E6BA89F429762e7b9aDb",
+];
+
+#[test]
+fn checksum() {
+    for expected in CHECKSUMMED {
+        let address: Address = expected.to_lowercase().parse().unwrap();
+        assert_eq!(address.to_checksum_string(), expected);
+        assert_eq!(address.to_string(), expected);
+        assert_eq!(expected.parse::<Address>(), Ok(address));
+    }
+}
+
+#[test]
+fn parse() {
+    let address: Address = "0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed".parse().unwrap();
+    // Without prefix, and in a single case (no checksum to validate)
+    assert_eq!("5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed".parse(), Ok(address));
+    assert_eq!("0x5AAEB6053F3E94C9B9A09F33669435E7EF1BEAED".parse(), Ok(address));
+    assert_eq!("0x5aaeb6053f3e94c9b9a09f33669435e7ef1beaed".parse(), Ok(address));
+
+    // Bad checksum (the case of the last letter is flipped)
+    assert!("0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAeD".parse::<Address>().is_err());
+    // Not 20 bytes
+    assert!("0x5aaeb6053f3e94c9b9a09f33669435e7ef1bea".parse::<Address>().is_err());
+    assert!("0x5aaeb6053f3e94c9b9a09f33669435e7ef1beaed00".parse::<Address>().is_err());
+    // Not hex
+    assert!("0x5aaeb6053f3e94c9b9a09f33669435e7ef1beaeg".parse::<Address>().is_err());
+}
+
+#[test]
+fn serde() {
+    let expected = "0xfB6916095ca1df60bB79Ce92cE3Ea74c37c5d359";
+    let address: Address = serde_json::from_str(&format!("\"{}\"", expected.to_lowercase())).unwrap();
+    assert_eq!(serde_json::to_string(&address).unwrap(), format!("\"{}\"", expected));
+    assert!(serde_json::from_str::<Address>("\"0xfB6916095ca1df60bB79Ce92cE3Ea74c37c5d35A\"").is_err());
+}
+
+#[test]
+fn from_slice() {
+    let bytes: Vec<u8> = (1..=32).collect();
+    // Longer slices keep their last 20 bytes, shorter ones are left-padded
+    assert_eq!(Address::from_slice(&bytes).as_slice(), &bytes[12..]);
+    assert_eq!(Address::from_slice(&bytes[12..]).as_slice(), &bytes[12..]);
+    let mut padded = [0u8; 20];
+    padded[18..].copy_from_slice(&bytes[..2]);
+    assert_eq!(Address::from_slice(&bytes[..2]).as_slice(), &padded);
+}
@@ -0,0 +1,165 @@
+// Building bytecode with the assembler and reading it back with the disassembler
+use evm_from_scrust::asm::{disassemble, AsmError, Assembler};
+use evm_from_scrust::*;
+use Opcode::*;
+
+fn run(code: Bytes) -> EvmResult {
+    let mut evm = ExecutionContext::new(TxEnv::default(), Call::default(), Block::default(), State::default(), code);
+    evm.run()
+}
+
+fn text(code: &Bytes) -> Vec<String> {
+    disassemble(code.as_slice()).iter().map(|instruction| instruction.to_string()).collect()
+}
+
+#[test]
+fn push_width() {
+    let code = Assembler::new().push(0).push(0xff).push(0x100).push(U256::MAX).build();
+    let max = format!("PUSH32 0x{}", "ff".repeat(32));
+    assert_eq!(text(&code), ["PUSH1 0x00", "PUSH1 0xff", "PUSH2 0x0100", max.as_str()]);
+}
+
+// A forward jump over `padding` bytes lands at 3 + `padding` with a PUSH1, which
+// stops fitting at 256 (and then the PUSH2 itself moves the label to 257)
+#[test]
+fn label_push_boundary() {
+    for padding in 250..260 {
+        let code = Assembler::new()
+            .jump_to("end")
+            .bytes(&vec![STOP.byte(); padding])
+            .jumpdest_label("end")
+            .push(1)
+            .build();
+        let instructions = disassemble(code.as_slice());
+        let expected = match 3 + padding {
+            offset if offset <= 0xff => offset,
+            offset => offset + 1,
+        };
+        let width = if expected <= 0xff { 1 } else { 2 };
+        assert_eq!(instructions[0].immediate.len(), width, "padding {}", padding);
+        let target = instructions[0].immediate.iter().fold(0, |offset, byte| offset << 8 | *byte as usize);
+        assert_eq!(target, expected, "padding {}", padding);
+        assert_eq!(code[target], JUMPDEST.byte());
+
+        let result = run(code);
+        assert!(result.success);
+        assert_eq!(result.stack, [Bytes32::one()]);
+    }
+}
+
+// A loop whose body pushes its JUMPDEST past offset 255
+#[test]
+fn backward_label() {
+    let mut asm = Assembler::new().push(3).bytes(&vec![JUMPDEST.byte(); 0x100]).jumpdest_label("loop");
+    asm = asm.push(1).ops(&[SWAP1, SUB, DUP1]).jumpi_to("loop");
+    let code = asm.build();
+    assert!(text(&code).contains(&"PUSH2 0x0102".to_string()));
+
+    let result = run(code);
+    assert!(result.success);
+    assert_eq!(result.stack, [Bytes32::zero()]);
+}
+
+#[test]
+fn round_trip() {
+    let code = Assembler::new()
+        .push(0x42)
+        .jumpdest_label("loop")
+        .ops(&[DUP1, ADD])
+        .jump_to("loop")
+        .bytes(&[0x0c, 0x61, 0x01])
+        .build();
+    let instructions = disassemble(code.as_slice());
+    assert_eq!(
+        instructions.iter().map(|instruction| instruction.to_string()).collect::<Vec<_>>(),
+        // 0x0c isn't an opcode and the last PUSH2 is cut short by the end of the code
+        ["PUSH1 0x42", "JUMPDEST", "DUP1", "ADD", "PUSH1 0x02", "JUMP", "0x0c", "PUSH2 0x01"]
+    );
+    assert_eq!(instructions.iter().map(|instruction| instruction.offset).collect::<Vec<_>>(), [0, 2, 3, 4, 5, 7, 8, 9]);
+
+    let rebuilt = instructions
+        .iter()
+        .fold(Assembler::new(), |asm, instruction| asm.bytes(&[instruction.byte]).bytes(&instruction.immediate))
+        .build();
+    assert_eq!(rebuilt, code);
+}
+
+#[test]
+#[should_panic(expected = "label `end` is never defined")]
+fn undefined_label() {
+    Assembler::new().jump_to("end").build();
+}
+
+#[test]
+#[should_panic(expected = "label `start` defined twice")]
+fn duplicate_label() {
+    Assembler::new().jumpdest_label("start").jumpdest_label("start").build();
+}
+
+fn assembled(source: &str) -> Vec<u8> {
+    Bytes::from_asm(source).unwrap().as_slice().to_vec()
+}
+
+#[test]
+fn hex_immediates() {
+    assert_eq!(assembled("PUSH1 0x01\nPUSH2 0x0102"), [0x60, 0x01, 0x61, 0x01, 0x02]);
+    // Left-padded to the width of the PUSH
+    assert_eq!(assembled("PUSH4 0xff"), [0x63, 0x00, 0x00, 0x00, 0xff]);
+    assert_eq!(assembled(&format!("PUSH32 0x{}", "ff".repeat(32))), [vec![0x7f], vec![0xff; 32]].concat());
+}
+
+#[test]
+fn decimal_immediates() {
+    assert_eq!(assembled("PUSH1 10\nPUSH2 256"), [0x60, 0x0a, 0x61, 0x01, 0x00]);
+    assert_eq!(assembled("PUSH1 0"), [0x60, 0x00]);
+}
+
+#[test]
+fn immediate_width() {
+    assert_eq!(
+        Bytes::from_asm("ADD\nPUSH1 256"),
+        Err(AsmError::ImmediateTooWide { line: 2, opcode: PUSH1, immediate: "256".to_string() })
+    );
+    assert_eq!(
+        Bytes::from_asm("PUSH2 0x010000"),
+        Err(AsmError::ImmediateTooWide { line: 1, opcode: PUSH2, immediate: "0x010000".to_string() })
+    );
+    assert_eq!(Bytes::from_asm("PUSH1"), Err(AsmError::MissingImmediate { line: 1, opcode: PUSH1 }));
+    assert_eq!(
+        Bytes::from_asm("PUSH1 one"),
+        Err(AsmError::InvalidImmediate { line: 1, immediate: "one".to_string() })
+    );
+}
+
+#[test]
+fn comments_and_case() {
+    let source = "# adds two numbers\npush1 0x01 // first\nPush1 2\n\n  add  # sum\nchainId\nKECCAK256";
+    assert_eq!(assembled(source), [0x60, 0x01, 0x60, 0x02, 0x01, 0x46, 0x20]);
+}
+
+#[test]
+fn raw_bytes() {
+    // For the bytes that aren't opcodes
+    assert_eq!(assembled("PUSH1 3\nJUMP\n0x0c"), [0x60, 0x03, 0x56, 0x0c]);
+}
+
+#[test]
+fn unknown_mnemonic() {
+    let error = Bytes::from_asm("PUSH1 1\nPUSH1 2\nADDD").unwrap_err();
+    assert_eq!(error, AsmError::UnknownMnemonic { line: 3, mnemonic: "ADDD".to_string() });
+    assert_eq!(error.to_string(), "line 3: unknown mnemonic `ADDD`");
+}
+
+// Every mnemonic assembles back to the byte of its opcode
+#[test]
+fn all_mnemonics() {
+    for instruction in disassemble(&(0..=u8::MAX).collect::<Vec<_>>()) {
+        if let Some(opcode) = instruction.opcode() {
+            let source = match opcode.immediate_size() {
+                0 => format!("{:?}", opcode),
+                size => format!("{:?} 0x{}", opcode, "00".repeat(size)),
+            };
+            assert_eq!(assembled(&source)[0], opcode.byte(), "{}", source);
+        }
+    }
+}
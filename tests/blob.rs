@@ -0,0 +1,56 @@
+// The blob opcodes of Cancun (EIP-4844 and EIP-7516)
+use evm_from_scrust::asm::Assembler;
+use evm_from_scrust::*;
+use Opcode::*;
+
+fn hashes() -> Vec<Bytes32> {
+    vec![Bytes32::from([0x01; 32]), Bytes32::from([0x02; 32])]
+}
+
+fn run(spec: SpecId, code: Bytes) -> EvmResult {
+    let tx = TxEnv::default().with_blob_hashes(hashes());
+    let block = Block::builder().blob_base_fee(42).build();
+    let mut evm = ExecutionContext::new(tx, Call::default(), block, State::new(), code).with_spec(spec);
+    evm.run()
+}
+
+fn blobhash(index: U256) -> EvmResult {
+    run(SpecId::Cancun, Assembler::new().push(index).op(BLOBHASH).build())
+}
+
+#[test]
+fn blob_hashes() {
+    assert_eq!(blobhash(0.into()).stack, [hashes()[0]]);
+    assert_eq!(blobhash(1.into()).stack, [hashes()[1]]);
+    // Past the blobs of the tx
+    assert_eq!(blobhash(2.into()).stack, [Bytes32::zero()]);
+    assert_eq!(blobhash(U256::MAX).stack, [Bytes32::zero()]);
+    // A huge index that would be 1 if truncated
+    assert_eq!(blobhash((U256::one() << 64) + 1).stack, [Bytes32::zero()]);
+
+    let result = blobhash(1.into());
+    assert!(result.success);
+    assert_eq!(result.gas_used, 3 + 3);
+}
+
+#[test]
+fn blob_base_fee() {
+    let result = run(SpecId::Cancun, Assembler::new().op(BLOBBASEFEE).build());
+    assert!(result.success);
+    assert_eq!(result.stack, [Bytes32::from_u256(42.into())]);
+    assert_eq!(result.gas_used, 2);
+
+    // The EIP-4844 minimum by default
+    let mut evm = ExecutionContext::new(TxEnv::default(), Call::default(), Block::default(), State::new(), Bytes::from_byte(0x4a))
+        .with_spec(SpecId::Cancun);
+    assert_eq!(evm.run().stack, [Bytes32::from_u256(DEFAULT_BLOB_BASE_FEE.into())]);
+}
+
+#[test]
+fn before_cancun() {
+    for code in [Assembler::new().push(0).op(BLOBHASH).build(), Assembler::new().op(BLOBBASEFEE).build()] {
+        let result = run(SpecId::Shanghai, code);
+        assert!(!result.success);
+        assert_eq!(result.halt, Some(Halt::InvalidOpcode));
+    }
+}
@@ -0,0 +1,93 @@
+// Blocks built in code: the builder's values are what the block opcodes see
+use evm_from_scrust::asm::{disassemble, Assembler};
+use evm_from_scrust::*;
+use sha3::{Digest, Keccak256};
+use Opcode::*;
+
+// Runs `opcode` alone and returns the word it pushes
+fn read(block: &Block, opcode: Opcode) -> U256 {
+    read_in(SpecId::default(), block, opcode)
+}
+
+fn read_in(spec: SpecId, block: &Block, opcode: Opcode) -> U256 {
+    let code = Assembler::new().op(opcode).build();
+    let mut evm = ExecutionContext::new(TxEnv::default(), Call::default(), block.clone(), State::default(), code)
+        .with_spec(spec);
+    let result = evm.run();
+    assert!(result.success);
+    result.stack[0].to_u256()
+}
+
+#[test]
+fn builder() {
+    let coinbase = Address::from_slice(&[0xcb; 20]);
+    let prev_randao = Bytes32::from([0x42; 32]);
+    let block = Block::builder()
+        .number(19_000_000)
+        .timestamp(1_700_000_000u64)
+        .gas_limit(36_000_000u64)
+        .base_fee(U256::from(30_000_000_000u64))
+        .coinbase(coinbase)
+        .prev_randao(prev_randao)
+        .build();
+
+    assert_eq!(read(&block, NUMBER), 19_000_000.into());
+    assert_eq!(read(&block, TIMESTAMP), 1_700_000_000u64.into());
+    assert_eq!(read(&block, GASLIMIT), 36_000_000.into());
+    assert_eq!(read(&block, BASEFEE), 30_000_000_000u64.into());
+    assert_eq!(read(&block, COINBASE), U256::from_big_endian(coinbase.as_slice()));
+    assert_eq!(read(&block, PREVRANDAO), U256::from(prev_randao));
+}
+
+#[test]
+fn defaults() {
+    let block = Block::default();
+    assert_eq!(read(&block, CHAINID), DEFAULT_CHAIN_ID.into());
+    assert_eq!(read(&block, NUMBER), DEFAULT_BLOCK_NUMBER.into());
+    assert_eq!(read(&block, TIMESTAMP), DEFAULT_TIMESTAMP.into());
+    assert_eq!(read(&block, GASLIMIT), DEFAULT_GAS_LIMIT.into());
+    assert_eq!(read(&block, BASEFEE), DEFAULT_BASE_FEE.into());
+    assert!(read(&block, COINBASE).is_zero());
+
+    // The builder starts from the same block
+    let built = Block::builder().number(1).build();
+    assert_eq!(read(&built, NUMBER), 1.into());
+    assert_eq!(read(&built, TIMESTAMP), DEFAULT_TIMESTAMP.into());
+}
+
+#[test]
+fn difficulty_and_prev_randao() {
+    let block = Block::builder().difficulty(0x20000).prev_randao(0x42).build();
+    // 0x44 reads the difficulty before the Merge, and PREVRANDAO after it
+    assert_eq!(read_in(SpecId::London, &block, PREVRANDAO), 0x20000.into());
+    assert_eq!(read_in(SpecId::Merge, &block, PREVRANDAO), 0x42.into());
+    assert_eq!(read_in(SpecId::Cancun, &block, PREVRANDAO), 0x42.into());
+}
+
+#[test]
+fn missing_prev_randao() {
+    let block = Block { prev_randao: None, difficulty: Some(0x20000.into()), number: Some(7u64.into()), ..Block::default() };
+    // Not the difficulty, but a stand-in derived from the block number
+    let expected = U256::from_big_endian(&Keccak256::digest(Bytes32::from_u256(7.into()).as_slice()));
+    assert_eq!(read_in(SpecId::Merge, &block, PREVRANDAO), expected);
+    assert_eq!(block.randao(), expected);
+    let other = Block { number: Some(8u64.into()), ..block.clone() };
+    assert_ne!(read_in(SpecId::Merge, &other, PREVRANDAO), expected);
+
+    // Nor the other way around
+    let block = Block { difficulty: None, prev_randao: Some(0x42.into()), ..Block::default() };
+    assert!(read_in(SpecId::London, &block, PREVRANDAO).is_zero());
+}
+
+#[test]
+fn names() {
+    assert_eq!(PREVRANDAO.name(SpecId::London), "DIFFICULTY");
+    assert_eq!(PREVRANDAO.name(SpecId::Merge), "PREVRANDAO");
+    assert_eq!(ADD.name(SpecId::London), "ADD");
+
+    let code = Assembler::new().op(PREVRANDAO).push(1).build();
+    let instructions = disassemble(code.as_slice());
+    assert_eq!(instructions[0].text(SpecId::Istanbul), "DIFFICULTY");
+    assert_eq!(instructions[0].to_string(), "PREVRANDAO");
+    assert_eq!(instructions[1].text(SpecId::Istanbul), "PUSH1 0x01");
+}
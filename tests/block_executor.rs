@@ -0,0 +1,78 @@
+// Transactions of a block executed in order, each on top of the state left by
+// the previous ones
+use evm_from_scrust::asm::Assembler;
+use evm_from_scrust::*;
+use Opcode::*;
+
+mod common;
+use common::sender;
+
+fn counter() -> Address {
+    Address::from_slice(&[0xcc; 20])
+}
+
+// Increments the counter at slot 0 and logs its new value
+fn counter_code() -> Bytes {
+    Assembler::new()
+        .push(0)
+        .op(SLOAD)
+        .push(1)
+        .op(ADD)
+        .op(DUP1)
+        .push(0)
+        .op(SSTORE)
+        .push(0)
+        .op(MSTORE)
+        .push(32)
+        .push(0)
+        .op(LOG0)
+        .build()
+}
+
+fn increment(nonce: u64, gas_limit: u64) -> (TxEnv, Call) {
+    let tx = TxEnv::new(sender(), U256::zero(), gas_limit.into(), nonce.into());
+    let call = Call::new(sender(), counter(), counter(), Bytes::new(), U256::zero(), false);
+    (tx, call)
+}
+
+fn executor() -> BlockExecutor {
+    let mut state = State::new();
+    state.set_code(&counter(), counter_code());
+    BlockExecutor::new(Block::default(), state)
+}
+
+fn logged(receipt: &Receipt) -> Vec<U256> {
+    receipt.logs.iter().map(|log| U256::from_big_endian(log.data.as_slice())).collect()
+}
+
+#[test]
+fn reads_previous_writes() {
+    let (receipts, state) = executor().execute(vec![increment(0, 100_000), increment(1, 100_000)]);
+    assert_eq!(receipts.iter().map(|receipt| receipt.status).collect::<Vec<_>>(), [1, 1]);
+    // The second transaction saw the slot written by the first
+    assert_eq!(logged(&receipts[0]), [1.into()]);
+    assert_eq!(logged(&receipts[1]), [2.into()]);
+    assert_eq!(state.storage_load(&counter(), 0.into()), Bytes32::from_u256(2.into()));
+    assert_eq!(state.nonce(&sender()), 2.into());
+
+    // The second one is cheaper: the slot isn't set from zero anymore
+    assert!(receipts[1].gas_used < receipts[0].gas_used);
+    assert_eq!(receipts[1].cumulative_gas_used, receipts[0].gas_used + receipts[1].gas_used);
+}
+
+// The middle transaction runs out of gas: its receipt has status 0, its write
+// is dropped, and the next transaction runs as if it hadn't touched the slot
+#[test]
+fn failed_transaction() {
+    let txs = vec![increment(0, 100_000), increment(1, 5_000), increment(2, 100_000)];
+    let (receipts, state) = executor().execute(txs);
+    assert_eq!(receipts.iter().map(|receipt| receipt.status).collect::<Vec<_>>(), [1, 0, 1]);
+    assert!(receipts[1].logs.is_empty());
+    assert_eq!(receipts[1].gas_used, 5_000);
+    assert_eq!(logged(&receipts[2]), [2.into()]);
+    assert_eq!(state.storage_load(&counter(), 0.into()), Bytes32::from_u256(2.into()));
+    assert_eq!(state.nonce(&sender()), 3.into());
+
+    let total: usize = receipts.iter().map(|receipt| receipt.gas_used).sum();
+    assert_eq!(receipts[2].cumulative_gas_used, total);
+}
@@ -0,0 +1,86 @@
+// Building words from byte sequences of different lengths: shorter inputs are
+// left-padded, and longer ones are either truncated (explicitly) or an error
+use evm_from_scrust::*;
+
+const LENGTHS: [usize; 4] = [0, 20, 32, 40];
+
+fn input(len: usize) -> Vec<u8> {
+    (1..=len as u8).collect()
+}
+
+// The word of `bytes` (at most 32), left-padded
+fn padded(bytes: &[u8]) -> [u8; 32] {
+    let mut word = [0u8; 32];
+    word[32 - bytes.len()..].copy_from_slice(bytes);
+    word
+}
+
+#[test]
+fn new_is_zero() {
+    assert_eq!(Bytes32::new(), Bytes32::zero());
+    assert!(Bytes32::new().is_zero());
+}
+
+#[test]
+fn from_vec_truncate() {
+    for len in LENGTHS {
+        let bytes = input(len);
+        let expected = padded(&bytes[len.saturating_sub(32)..]);
+        assert_eq!(Bytes32::from_vec_truncate(bytes).as_slice(), &expected, "{} bytes", len);
+    }
+}
+
+#[test]
+fn try_from_slice() {
+    for len in LENGTHS {
+        let bytes = input(len);
+        let result = Bytes32::try_from_slice(&bytes);
+        if len <= 32 {
+            assert_eq!(result.unwrap().as_slice(), &padded(&bytes), "{} bytes", len);
+        } else {
+            assert_eq!(result, Err(InvalidLength(len)));
+        }
+        assert_eq!(Bytes32::try_from(bytes.as_slice()), result);
+    }
+}
+
+#[test]
+fn from_slice() {
+    for len in LENGTHS.into_iter().filter(|len| *len <= 32) {
+        let bytes = input(len);
+        assert_eq!(Bytes32::from_slice(&bytes).as_slice(), &padded(&bytes), "{} bytes", len);
+    }
+}
+
+#[test]
+#[should_panic(expected = "Invalid word length: 40 bytes")]
+fn from_slice_too_long() {
+    Bytes32::from_slice(&input(40));
+}
+
+#[test]
+fn deserialize() {
+    for len in LENGTHS {
+        let result = serde_json::from_str::<Bytes32>(&serde_json::to_string(&input(len)).unwrap());
+        assert_eq!(result.is_ok(), len <= 32, "{} bytes", len);
+    }
+}
+
+// Words are always 32 bytes, so equal numbers are equal words whichever way
+// they were built
+#[test]
+fn canonical() {
+    let one = Bytes32::one();
+    assert_eq!(Bytes32::from_u64(1.into()), one);
+    assert_eq!(Bytes32::from_vec(vec![1]), one);
+    assert_eq!(Bytes32::from_slice(&[0, 0, 1]), one);
+    assert_eq!(Bytes32::from_u256(1.into()), one);
+    assert_eq!(Bytes32::from_u512(1.into()), one);
+    assert_eq!(Bytes32::from_vec_truncate(padded(&[1]).to_vec()), one);
+    assert_eq!(Bytes32::from(U256::one()), one);
+    assert_eq!(Bytes::from_vec(vec![1]).as_bytes32(), one);
+    assert_eq!(one.len(), 32);
+
+    assert_eq!(Bytes32::new(), Bytes32::from_vec(Vec::new()));
+    assert_eq!(Bytes32::new(), Bytes32::from_u256(U256::zero()));
+}
@@ -0,0 +1,132 @@
+// Context of the frames entered by CALLCODE and DELEGATECALL: the code comes
+// from the callee, but the storage, balance and address are the caller's. The
+// transaction's context (ORIGIN, GASPRICE) is the same in every frame.
+use evm_from_scrust::asm::Assembler;
+use evm_from_scrust::*;
+use Opcode::*;
+
+mod common;
+use common::sender;
+
+fn caller() -> Address {
+    Address::from_slice(&[0x0a; 20])
+}
+
+fn library() -> Address {
+    Address::from_slice(&[0x0b; 20])
+}
+
+// Stores 1 at slot 0, then ADDRESS, CALLER, CALLVALUE and SELFBALANCE at
+// slots 1 to 4
+fn library_code() -> Bytes {
+    let code = Assembler::new().push(1).push(0).op(SSTORE);
+    [ADDRESS, CALLER, CALLVALUE, SELFBALANCE]
+        .into_iter()
+        .zip(1u64..)
+        .fold(code, |code, (opcode, slot)| code.op(opcode).push(slot).op(SSTORE))
+        .build()
+}
+
+// The caller enters the library with `opcode` (sending 3 if it takes a value),
+// from a transaction sending 5
+fn run(opcode: Opcode) -> Evm {
+    let code = Assembler::new().push(0).push(0).push(0).push(0);
+    let code = match opcode {
+        CALLCODE => code.push(3),
+        _ => code,
+    };
+    let code = code.push(library().to_u256()).op(GAS).op(opcode).build();
+
+    let mut evm = Evm::new(Block::default(), State::new());
+    evm.set_code(&caller(), code);
+    evm.set_code(&library(), library_code());
+    evm.set_balance(&caller(), 10.into());
+    evm.set_balance(&sender(), 5.into());
+    let call = Call::new(sender(), caller(), caller(), Bytes::new(), 5.into(), false);
+    let result = evm.transact(TxEnv::default(), call);
+    assert!(result.success, "{:?}", result.halt);
+    assert_eq!(result.stack, [Bytes32::one()]);
+    evm
+}
+
+fn slots(evm: &Evm, address: &Address) -> Vec<U256> {
+    (0..5u64).map(|key| evm.state().storage_load(address, key.into()).to_u256()).collect()
+}
+
+#[test]
+fn callcode() {
+    let evm = run(CALLCODE);
+    // Called by the caller itself, with the value it sends to itself
+    assert_eq!(slots(&evm, &caller()), [1.into(), caller().to_u256(), caller().to_u256(), 3.into(), 15.into()]);
+    assert_eq!(evm.state().balance(&caller()), 15.into());
+    // The library is only where the code comes from
+    assert_eq!(slots(&evm, &library()), [U256::zero(); 5]);
+    assert!(evm.state().balance(&library()).is_zero());
+}
+
+// The caller and value of the frame carry over, and the value isn't sent again
+#[test]
+fn delegatecall() {
+    let evm = run(DELEGATECALL);
+    assert_eq!(slots(&evm, &caller()), [1.into(), caller().to_u256(), sender().to_u256(), 5.into(), 15.into()]);
+    assert_eq!(evm.state().balance(&caller()), 15.into());
+    assert!(evm.state().balance(&sender()).is_zero());
+    assert_eq!(slots(&evm, &library()), [U256::zero(); 5]);
+}
+
+// Also through a DELEGATECALL nested in another one
+#[test]
+fn nested_delegatecall() {
+    let proxy = Address::from_slice(&[0x0c; 20]);
+    let delegate = |to: Address| Assembler::new().push(0).push(0).push(0).push(0).push(to.to_u256()).op(GAS).op(DELEGATECALL).build();
+    let mut evm = Evm::new(Block::default(), State::new());
+    evm.set_code(&caller(), delegate(proxy));
+    evm.set_code(&proxy, delegate(library()));
+    evm.set_code(&library(), library_code());
+    evm.set_balance(&sender(), 5.into());
+    let call = Call::new(sender(), caller(), caller(), Bytes::new(), 5.into(), false);
+    assert!(evm.transact(TxEnv::default(), call).success);
+    assert_eq!(slots(&evm, &caller()), [1.into(), caller().to_u256(), sender().to_u256(), 5.into(), 5.into()]);
+    assert_eq!(slots(&evm, &proxy), [U256::zero(); 5]);
+}
+
+#[test]
+fn callcode_unaffordable_value() {
+    let code = Assembler::new().push(0).push(0).push(0).push(0).push(11).push(library().to_u256()).op(GAS).op(CALLCODE).build();
+    let mut evm = Evm::new(Block::default(), State::new());
+    evm.set_code(&caller(), code);
+    evm.set_code(&library(), library_code());
+    evm.set_balance(&caller(), 10.into());
+    let call = Call::new(sender(), caller(), caller(), Bytes::new(), U256::zero(), false);
+    let result = evm.transact(TxEnv::default(), call);
+    assert_eq!(result.stack, [Bytes32::zero()]);
+    assert_eq!(slots(&evm, &caller()), [U256::zero(); 5]);
+    assert_eq!(evm.state().balance(&caller()), 10.into());
+}
+
+// ORIGIN and GASPRICE come from the transaction, while CALLER is each frame's
+#[test]
+fn transaction_context() {
+    // Stores ORIGIN, GASPRICE and CALLER at slots 0 to 2
+    let record = |code: Assembler| {
+        [ORIGIN, GASPRICE, CALLER]
+            .into_iter()
+            .zip(0u64..)
+            .fold(code, |code, (opcode, slot)| code.op(opcode).push(slot).op(SSTORE))
+    };
+    let code = record(Assembler::new()).push(0).push(0).push(0).push(0).push(0).push(library().to_u256()).op(GAS).op(CALL);
+
+    let mut evm = Evm::new(Block::builder().base_fee(1).build(), State::new());
+    evm.set_code(&caller(), code.build());
+    evm.set_code(&library(), record(Assembler::new()).build());
+    evm.set_balance(&sender(), U256::from(u64::MAX));
+    let tx = TxEnv::new(sender(), 7.into(), 1_000_000.into(), U256::zero());
+    let call = Call::new(sender(), caller(), caller(), Bytes::new(), U256::zero(), false);
+    let result = evm.transact(tx, call);
+    assert!(result.success, "{:?}", result.halt);
+    assert_eq!(result.stack, [Bytes32::one()]);
+
+    let slots = |address: &Address| (0..3u64).map(|key| evm.state().storage_load(address, key.into()).to_u256()).collect::<Vec<_>>();
+    assert_eq!(slots(&caller()), [sender().to_u256(), 7.into(), sender().to_u256()]);
+    assert_eq!(slots(&library()), [sender().to_u256(), 7.into(), caller().to_u256()]);
+}
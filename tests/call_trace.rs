@@ -0,0 +1,170 @@
+// Call trees recorded by the call tracer, and their geth-like JSON
+use evm_from_scrust::asm::Assembler;
+use evm_from_scrust::*;
+use serde_json::json;
+use Opcode::*;
+
+mod common;
+use common::sender;
+
+fn parent() -> Address {
+    Address::from_slice(&[0xa0; 20])
+}
+
+fn returns() -> Address {
+    Address::from_slice(&[0xb1; 20])
+}
+
+fn reverts() -> Address {
+    Address::from_slice(&[0xb2; 20])
+}
+
+// Returns (or reverts with) the word 0x2a
+fn child(halt: Opcode) -> Bytes {
+    Assembler::new().push(0x2a).push(0).op(MSTORE).push(32).push(0).op(halt).build()
+}
+
+// `opcode` to `to` with 1000 gas, the 4 bytes 0x11223344 of input and (for
+// CALL) a value of 5
+fn call(code: Assembler, opcode: Opcode, to: Address) -> Assembler {
+    let code = code.push(0).push(0).push(4).push(28);
+    let code = match opcode {
+        CALL => code.push(5),
+        _ => code,
+    };
+    code.push(to.to_u256()).push(1000).op(opcode).op(POP)
+}
+
+fn traced(code: Bytes) -> (EvmResult, CallTrace) {
+    let mut evm = Evm::new(Block::default(), State::new());
+    evm.set_code(&parent(), code);
+    evm.set_balance(&parent(), 100.into());
+    evm.set_code(&returns(), child(RETURN));
+    evm.set_code(&reverts(), child(REVERT));
+    let call = Call::new(sender(), parent(), parent(), Bytes::from_vec(vec![0xca, 0xfe]), U256::zero(), false);
+    evm.transact_traced(TxEnv::default(), call)
+}
+
+fn word(value: u8) -> Bytes {
+    let mut word = vec![0; 32];
+    word[31] = value;
+    Bytes::from_vec(word)
+}
+
+#[test]
+fn two_children() {
+    let code = Assembler::new().push(0x11223344u64).push(0).op(MSTORE);
+    let code = call(call(code, CALL, returns()), CALL, reverts()).build();
+    let (result, trace) = traced(code);
+    assert!(result.success);
+
+    assert_eq!(trace.kind, CallKind::Call);
+    assert_eq!((trace.from, trace.to), (sender(), parent()));
+    assert_eq!(trace.input, Bytes::from_vec(vec![0xca, 0xfe]));
+    assert_eq!(trace.gas_used, result.gas_used);
+    assert!(trace.success());
+    assert_eq!(trace.calls.len(), 2);
+
+    let [first, second] = &trace.calls[..] else { unreachable!() };
+    assert_eq!((first.kind, first.from, first.to), (CallKind::Call, parent(), returns()));
+    assert_eq!(first.value, Some(5.into()));
+    assert_eq!(first.input, Bytes::from_vec(vec![0x11, 0x22, 0x33, 0x44]));
+    assert_eq!(first.output, word(0x2a));
+    // The stipend comes on top of the forwarded gas
+    assert_eq!(first.gas, 1000 + CALL_STIPEND);
+    assert!(first.gas_used > 0);
+    assert!(first.success());
+
+    assert_eq!(second.to, reverts());
+    assert_eq!(second.halt, Some(Halt::Revert));
+    assert_eq!(second.output, word(0x2a));
+    assert!(second.calls.is_empty());
+}
+
+#[test]
+fn json() {
+    let code = Assembler::new();
+    let code = call(call(code, STATICCALL, returns()), CALL, reverts()).build();
+    let (_, trace) = traced(code);
+    let json = serde_json::to_value(&trace).unwrap();
+
+    assert_eq!(json["type"], "CALL");
+    assert_eq!(json["from"], json!(sender()));
+    assert_eq!(json["input"], "0xcafe");
+    assert_eq!(json["value"], "0x0");
+    assert_eq!(json["gasUsed"], format!("{:#x}", trace.gas_used));
+    assert!(json.get("error").is_none());
+
+    let calls = json["calls"].as_array().unwrap();
+    assert_eq!(calls[0]["type"], "STATICCALL");
+    assert_eq!(calls[0]["to"], json!(returns()));
+    assert_eq!(calls[0]["gas"], "0x3e8");
+    // STATICCALL carries no value, and leaf frames no calls
+    assert!(calls[0].get("value").is_none());
+    assert!(calls[0].get("calls").is_none());
+    assert_eq!(calls[0]["output"], format!("0x{:064x}", 0x2a));
+    assert_eq!(calls[1]["type"], "CALL");
+    assert_eq!(calls[1]["value"], "0x5");
+    assert_eq!(calls[1]["error"], "execution reverted");
+}
+
+#[test]
+fn nested_frames() {
+    // The parent calls a proxy that delegates to the returning child
+    let proxy = Address::from_slice(&[0xbb; 20]);
+    let mut evm = Evm::new(Block::default(), State::new());
+    evm.set_code(&parent(), call(Assembler::new(), CALL, proxy).build());
+    evm.set_balance(&parent(), 100.into());
+    evm.set_code(&proxy, call(Assembler::new(), DELEGATECALL, returns()).build());
+    evm.set_code(&returns(), child(RETURN));
+    let call = Call::new(sender(), parent(), parent(), Bytes::new(), U256::zero(), false);
+    let (_, trace) = evm.transact_traced(TxEnv::default(), call);
+
+    let proxy_trace = &trace.calls[0];
+    assert_eq!(proxy_trace.to, proxy);
+    let delegated = &proxy_trace.calls[0];
+    assert_eq!((delegated.kind, delegated.from, delegated.to), (CallKind::DelegateCall, proxy, returns()));
+    assert_eq!(delegated.value, None);
+}
+
+#[test]
+fn creation() {
+    // Init code returning nothing, at offset 0 of the memory
+    let init = Assembler::new().push(0).push(0).op(RETURN).build();
+    let mut code = Assembler::new();
+    for (offset, byte) in init.as_slice().iter().enumerate() {
+        code = code.push(*byte).push(offset).op(MSTORE8);
+    }
+    let code = code.push(init.len()).push(0).push(7).op(CREATE).push(1).push(init.len()).push(0).push(0).op(CREATE2).build();
+    let (result, trace) = traced(code);
+    assert!(result.success);
+
+    let created = &trace.calls[0];
+    assert_eq!(created.kind, CallKind::Create);
+    assert_eq!(created.to, Address::from_slice(result.stack[1].as_slice()));
+    assert_eq!(created.value, Some(7.into()));
+    assert_eq!(created.input, init);
+    assert!(created.success());
+
+    let created = &trace.calls[1];
+    assert_eq!(created.kind, CallKind::Create2);
+    assert_eq!(created.to, Address::from_slice(result.stack[0].as_slice()));
+    assert_eq!(created.input, init);
+    assert!(created.success());
+}
+
+#[test]
+fn failed_transfer() {
+    // The parent holds 100
+    let code = Assembler::new().push(0).push(0).push(0).push(0).push(101).push(returns().to_u256()).push(1000).op(CALL).build();
+    let (result, trace) = traced(code);
+    assert_eq!(result.stack, [Bytes32::zero()]);
+    assert!(matches!(trace.calls[0].halt, Some(Halt::InsufficientBalance { .. })));
+    assert_eq!(trace.calls[0].gas_used, 0);
+}
+
+#[test]
+fn untraced() {
+    let ctx = ExecutionContext::new(TxEnv::default(), Call::default(), Block::default(), State::new(), Bytes::new());
+    assert!(ctx.call_trace().is_none());
+}
@@ -0,0 +1,72 @@
+// Chain settings and the transaction checks that simulations can turn off
+use evm_from_scrust::asm::Assembler;
+use evm_from_scrust::*;
+use Opcode::*;
+
+mod common;
+use common::sender;
+
+fn recipient() -> Address {
+    Address::from_slice(&[0xbb; 20])
+}
+
+fn send(evm: &mut Evm, tx: TxEnv, value: u64) -> EvmResult {
+    let call = Call::new(sender(), recipient(), recipient(), Bytes::new(), value.into(), false);
+    evm.transact(tx, call)
+}
+
+#[test]
+fn chain_id() {
+    let code = Assembler::new().op(CHAINID).build();
+    let result = evm::eval(code.as_slice(), &[]);
+    assert_eq!(result.stack, [Bytes32::from_u256(DEFAULT_CHAIN_ID.into())]);
+    let result = evm::eval_with(code.as_slice(), &[], |env| env.cfg.chain_id = 10);
+    assert_eq!(result.stack, [Bytes32::from_u256(10.into())]);
+
+    let mut evm = Evm::new(Block::default(), State::new()).with_chain_id(137);
+    evm.set_code(&recipient(), code);
+    assert_eq!(send(&mut evm, TxEnv::default(), 0).stack, [Bytes32::from_u256(137.into())]);
+}
+
+#[test]
+fn balance_check() {
+    let mut evm = Evm::new(Block::default(), State::new());
+    let result = send(&mut evm, TxEnv::default(), 1);
+    assert!(!result.success);
+    assert_eq!(result.halt, Some(Halt::InsufficientBalance { address: sender(), balance: U256::zero(), value: 1.into() }));
+
+    // The sender is credited the missing value
+    let mut evm = Evm::new(Block::default(), State::new()).disable_balance_check(true);
+    assert!(send(&mut evm, TxEnv::default(), 1).success);
+    assert_eq!(evm.state().balance(&recipient()), 1.into());
+    assert!(evm.state().balance(&sender()).is_zero());
+}
+
+#[test]
+fn block_gas_limit() {
+    let tx = TxEnv { gas_limit: (DEFAULT_GAS_LIMIT + 1).into(), ..TxEnv::default() };
+    let mut evm = Evm::new(Block::default(), State::new());
+    let result = send(&mut evm, tx.clone(), 0);
+    assert_eq!(result.halt, Some(Halt::BlockGasLimitExceeded));
+    assert!(evm.state().nonce(&sender()).is_zero());
+
+    let mut evm = Evm::new(Block::default(), State::new()).disable_block_gas_limit(true);
+    assert!(send(&mut evm, tx, 0).success);
+}
+
+#[test]
+fn nonce_check() {
+    let mut evm = Evm::new(Block::default(), State::new());
+    assert!(send(&mut evm, TxEnv::default(), 0).success);
+    let result = send(&mut evm, TxEnv::default(), 0);
+    assert_eq!(result.halt, Some(Halt::InvalidNonce { expected: 1.into(), actual: U256::zero() }));
+    assert_eq!(result.halt.unwrap().to_string(), "InvalidNonce: expected 1, got 0");
+    let tx = TxEnv { nonce: 1.into(), ..TxEnv::default() };
+    assert!(send(&mut evm, tx, 0).success);
+    assert_eq!(evm.state().nonce(&sender()), 2.into());
+
+    let mut evm = Evm::new(Block::default(), State::new()).disable_nonce_check(true);
+    assert!(send(&mut evm, TxEnv::default(), 0).success);
+    assert!(send(&mut evm, TxEnv::default(), 0).success);
+    assert_eq!(evm.state().nonce(&sender()), 2.into());
+}
@@ -0,0 +1,104 @@
+// Cheatcodes of the `Evm` facade: direct writes to the state and the block,
+// visible to the following transactions
+use evm_from_scrust::asm::Assembler;
+use evm_from_scrust::*;
+use sha3::{Digest, Keccak256};
+use Opcode::*;
+
+mod common;
+use common::{contract, sender};
+
+fn padded(address: &Address) -> [u8; 32] {
+    let mut word = [0u8; 32];
+    word[12..].copy_from_slice(address.as_slice());
+    word
+}
+
+fn call(evm: &mut Evm, data: Vec<u8>) -> EvmResult {
+    let call = Call::new(sender(), contract(), contract(), Bytes::from(data), U256::zero(), false);
+    let tx = TxEnv { nonce: evm.state().nonce(&sender()), ..TxEnv::default() };
+    let result = evm.transact(tx, call);
+    assert!(result.success);
+    result
+}
+
+// Returns the word on top of the stack
+fn returning(code: Assembler) -> Bytes {
+    code.push(0).op(MSTORE).push(32).push(0).op(RETURN).build()
+}
+
+#[test]
+fn erc20_balance_of() {
+    // balanceOf(address), with the balances in a mapping at slot 0
+    let token = returning(
+        Assembler::new()
+            .push(4)
+            .op(CALLDATALOAD)
+            .push(0)
+            .op(MSTORE)
+            .push(0)
+            .push(32)
+            .op(MSTORE)
+            .push(64)
+            .push(0)
+            .op(SHA3)
+            .op(SLOAD),
+    );
+    let holder = Address::from_slice(&[0x11; 20]);
+    let slot = Keccak256::digest([padded(&holder), [0u8; 32]].concat());
+
+    let mut evm = Evm::new(Block::default(), State::new());
+    evm.set_code(&contract(), token.clone());
+    evm.set_storage(&contract(), U256::from_big_endian(&slot), Bytes32::from_u256(1000.into()));
+    assert_eq!(evm.state().code(&contract()), token);
+    assert_eq!(evm.state().code_hash(&contract()).as_slice(), Keccak256::digest(token.as_slice()).as_slice());
+
+    let data = [&[0x70, 0xa0, 0x82, 0x31][..], &padded(&holder)].concat();
+    let result = call(&mut evm, data);
+    assert_eq!(U256::from_big_endian(result.result.as_slice()), 1000.into());
+}
+
+#[test]
+fn balance_and_nonce() {
+    let mut evm = Evm::new(Block::default(), State::new());
+    evm.set_code(&contract(), returning(Assembler::new().op(CALLER).op(BALANCE)));
+    evm.set_balance(&sender(), 42.into());
+    evm.set_nonce(&sender(), 7.into());
+
+    let result = call(&mut evm, Vec::new());
+    assert_eq!(U256::from_big_endian(result.result.as_slice()), 42.into());
+    // The transaction bumps the nonce set by the cheatcode
+    assert_eq!(evm.state().nonce(&sender()), 8.into());
+}
+
+#[test]
+fn block() {
+    let mut evm = Evm::new(Block::default(), State::new());
+    evm.warp(1_700_000_000u64.into());
+    evm.roll(19_000_000);
+    evm.fee(7.into());
+    assert_eq!(evm.block().timestamp, 1_700_000_000u64.into());
+
+    for (opcode, expected) in [(TIMESTAMP, 1_700_000_000u64), (NUMBER, 19_000_000), (BASEFEE, 7)] {
+        evm.set_code(&contract(), returning(Assembler::new().op(opcode)));
+        let result = call(&mut evm, Vec::new());
+        assert_eq!(U256::from_big_endian(result.result.as_slice()), expected.into(), "{:?}", opcode);
+    }
+}
+
+#[test]
+fn reverted_with_snapshot() {
+    let mut evm = Evm::new(Block::default(), State::new());
+    evm.set_balance(&sender(), 1.into());
+    let timestamp = evm.block().timestamp;
+    let snapshot = evm.snapshot();
+
+    evm.set_balance(&sender(), 2.into());
+    evm.set_storage(&contract(), U256::one(), Bytes32::from_u256(3.into()));
+    evm.warp(4.into());
+
+    assert!(evm.revert_to(snapshot));
+    assert_eq!(evm.state().balance(&sender()), 1.into());
+    assert!(evm.state().storage_load(&contract(), U256::one()).is_zero());
+    assert_eq!(evm.block().timestamp, timestamp);
+}
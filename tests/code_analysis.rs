@@ -0,0 +1,111 @@
+// Static analyses of bytecode. The contracts are the ones of the benchmarks of
+// revm (MIT licensed): an ERC20 token compiled by solc 0.4, with its
+// constructor and arguments, and the runtime code of the snailtracer
+// benchmark, compiled by solc 0.7.6.
+use evm_from_scrust::asm::Assembler;
+use evm_from_scrust::code_analysis::{function_selectors, jumpdests, split_constructor};
+use evm_from_scrust::*;
+use Opcode::*;
+
+fn fixture(name: &str) -> Bytes {
+    let path = format!("{}/tests/fixtures/code/{}.hex", env!("CARGO_MANIFEST_DIR"), name);
+    Bytes::from_vec(hex::decode(std::fs::read_to_string(path).unwrap().trim()).unwrap())
+}
+
+fn selectors(hexes: &[&str]) -> Vec<[u8; 4]> {
+    hexes.iter().map(|selector| hex::decode(selector).unwrap().try_into().unwrap()).collect()
+}
+
+#[test]
+fn erc20_selectors() {
+    let (_, runtime) = split_constructor(&fixture("erc20")).unwrap();
+    let expected = selectors(&[
+        "06fdde03", // name()
+        "095ea7b3", // approve(address,uint256)
+        "18160ddd", // totalSupply()
+        "23b872dd", // transferFrom(address,address,uint256)
+        "27e235e3", // balances(address)
+        "313ce567", // decimals()
+        "5c658165", // allowed(address,address)
+        "70a08231", // balanceOf(address)
+        "95d89b41", // symbol()
+        "a9059cbb", // transfer(address,uint256)
+        "dd62ed3e", // allowance(address,address)
+    ]);
+    assert_eq!(function_selectors(&runtime), expected);
+}
+
+#[test]
+fn snailtracer_selectors() {
+    let expected = selectors(&["30627b7c", "75ac892a", "784f1366", "c2943601"]);
+    assert_eq!(function_selectors(&fixture("snailtracer")), expected);
+    // Runtime code has no constructor
+    assert_eq!(split_constructor(&fixture("snailtracer")), None);
+}
+
+#[test]
+fn erc20_constructor() {
+    let code = fixture("erc20");
+    let (init, runtime) = split_constructor(&code).unwrap();
+    // PUSH2 0x0c3a DUP1 PUSH2 0x0197 PUSH1 0 CODECOPY PUSH1 0 RETURN
+    assert_eq!(init.len(), 0x197);
+    assert_eq!(runtime.len(), 0xc3a);
+    assert_eq!(runtime.as_slice(), &code.as_slice()[0x197..0x197 + 0xc3a]);
+    // The runtime code starts over with the free memory pointer of solc
+    assert!(runtime.as_slice().starts_with(&hex::decode("6060604052").unwrap()));
+}
+
+#[test]
+fn deployed_runtime() {
+    // What the constructor deploys is the runtime code it was split from
+    let runtime = Assembler::new().push(0x2a).push(0).op(MSTORE).push(32).push(0).op(RETURN).build();
+    let trailer = |offset: usize| {
+        Assembler::new().push(runtime.len()).op(DUP1).push(offset).ops(&[PUSH0, CODECOPY, PUSH0, RETURN, INVALID]).build()
+    };
+    let offset = trailer(0).len();
+    let init = trailer(offset);
+    let code = Bytes::from_vec([init.as_slice(), runtime.as_slice()].concat());
+    assert_eq!(split_constructor(&code), Some((init, runtime.clone())));
+
+    let call = Call::new(Address::zero(), Address::zero(), Address::zero(), code, U256::zero(), false);
+    let mut evm = Evm::new(Block::default(), State::new());
+    evm.transact(TxEnv::default(), call);
+    assert_eq!(evm.receipts()[0].status, 1);
+    let address = evm.receipts()[0].contract_address.unwrap();
+    assert_eq!(evm.state().code(&address), runtime);
+}
+
+#[test]
+fn out_of_bounds_copy() {
+    let code = Assembler::new().push(0x100).op(DUP1).push(0x10).push(0).op(CODECOPY).push(0).op(RETURN).build();
+    assert_eq!(split_constructor(&code), None);
+}
+
+#[test]
+fn jumpdest_offsets() {
+    // The 0x5b immediate of the PUSH1 isn't a JUMPDEST
+    let code = Assembler::new().op(JUMPDEST).bytes(&[PUSH1.byte(), JUMPDEST.byte()]).op(JUMPDEST).push(0).op(JUMP).build();
+    assert_eq!(jumpdests(&code), [0, 3]);
+
+    let erc20 = fixture("erc20");
+    let offsets = jumpdests(&erc20);
+    assert!(offsets.iter().all(|offset| erc20[*offset] == JUMPDEST.byte()));
+    assert!(offsets.windows(2).all(|pair| pair[0] < pair[1]));
+}
+
+#[test]
+fn comparisons_are_not_selectors() {
+    // A range split of a binary search dispatcher, then a selector
+    let code = Assembler::new()
+        .bytes(&[PUSH4.byte(), 0x80, 0, 0, 0])
+        .op(GT)
+        .push(0x40)
+        .op(JUMPI)
+        .bytes(&[PUSH4.byte(), 0x12, 0x34, 0x56, 0x78])
+        .op(DUP2)
+        .op(EQ)
+        .push(0x40)
+        .op(JUMPI)
+        .build();
+    assert_eq!(function_selectors(&code), [[0x12, 0x34, 0x56, 0x78]]);
+}
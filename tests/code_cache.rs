@@ -0,0 +1,172 @@
+// The analyzed code of the called accounts, cached by code hash for the
+// length of a transaction
+use evm_from_scrust::asm::Assembler;
+use evm_from_scrust::utils::create_address;
+use evm_from_scrust::*;
+use Opcode::*;
+
+mod common;
+use common::contract;
+
+fn callee() -> Address {
+    Address::from_slice(&[0xdd; 20])
+}
+
+// CALL to `address` with 0xffff gas, keeping the first word it returns
+fn call_to(asm: Assembler, address: Address) -> Assembler {
+    asm.push(32)
+        .push(0)
+        .push(0)
+        .push(0)
+        .push(0)
+        .push(Bytes32::from_address(address).to_u256())
+        .push(0xffff)
+        .op(CALL)
+}
+
+fn run(code: Bytes, state: State) -> ExecutionContext {
+    let call = Call::new(Address::zero(), contract(), contract(), Bytes::new(), U256::zero(), false);
+    let mut evm = ExecutionContext::new(TxEnv::default(), call, Block::default(), state, code);
+    assert!(evm.run().success);
+    evm
+}
+
+#[test]
+fn jumpdests() {
+    // PUSH2 0x5b5b JUMPDEST PUSH32 (0x5b…) JUMPDEST PUSH1 (truncated)
+    let mut code = vec![0x61, 0x5b, 0x5b, 0x5b, 0x7f];
+    code.extend([0x5b; 32]);
+    code.extend([0x5b, 0x60]);
+    let code = AnalyzedCode::new(Bytes::from_vec(code));
+
+    let jumpdests: Vec<usize> = (0..code.len() + 1).filter(|offset| code.is_jumpdest(*offset)).collect();
+    assert_eq!(jumpdests, [3, 37]);
+    assert_eq!(code.len(), 39);
+}
+
+// Calling the same contract again reuses its analyzed code
+#[test]
+fn shared_by_calls() {
+    let code = call_to(call_to(Assembler::new(), callee()), callee()).build();
+    let mut state = State::new();
+    state.set_code(&callee(), Assembler::new().push(7).push(0).op(MSTORE).push(32).push(0).op(RETURN).build());
+
+    let mut evm = run(code, state);
+    assert_eq!(evm.stack.items_top_first(), [Bytes32::one(), Bytes32::one()]);
+    assert_eq!(evm.memory.load(0, 32).as_bytes32(), Bytes32::from_u256(7.into()));
+    assert_eq!(evm.code_cache.len(), 1);
+}
+
+// An account deployed to mid-transaction runs its new code
+#[test]
+fn invalidated_by_create() {
+    let address = create_address(&contract(), U256::zero());
+    // Returns 42
+    let runtime = Assembler::new().push(42).push(0).op(MSTORE).push(32).push(0).op(RETURN).build();
+    let init = Assembler::new()
+        .push(U256::from_big_endian(runtime.as_slice()))
+        .push(0)
+        .op(MSTORE)
+        .push(runtime.len())
+        .push(32 - runtime.len())
+        .op(RETURN)
+        .build();
+
+    let code = call_to(Assembler::new(), address)
+        .push(U256::from_big_endian(init.as_slice()))
+        .push(0)
+        .op(MSTORE)
+        .push(init.len())
+        .push(32 - init.len())
+        .push(0)
+        .op(CREATE);
+    let code = call_to(code, address).op(RETURNDATASIZE).build();
+
+    let mut evm = run(code, State::new());
+    let stack: Vec<U256> = evm.stack.items_top_first().iter().map(|item| item.to_u256()).collect();
+    assert_eq!(stack, [U256::from(32), U256::one(), Bytes32::from_address(address).to_u256(), U256::one()]);
+    assert_eq!(evm.memory.load(0, 32).as_bytes32(), Bytes32::from_u256(42.into()));
+    // The empty code and the deployed one
+    assert_eq!(evm.code_cache.len(), 2);
+}
+
+// Jumps to `dest` over a PUSH32 of JUMPDEST bytes, followed by a JUMPDEST:
+// valid for 36, into the immediate for 4 to 35
+fn jump_over_push32(asm: Assembler, dest: u64) -> Assembler {
+    let mut push32 = vec![0x7f];
+    push32.extend([0x5b; 32]);
+    asm.push(dest).op(JUMP).bytes(&push32).op(JUMPDEST)
+}
+
+// Init code deploying a contract returning 42. Both start with a jump over a
+// PUSH32, to `init_dest` and `runtime_dest`.
+fn init_code(init_dest: u64, runtime_dest: u64) -> Bytes {
+    let runtime = jump_over_push32(Assembler::new(), runtime_dest)
+        .push(42)
+        .push(0)
+        .op(MSTORE)
+        .push(32)
+        .push(0)
+        .op(RETURN)
+        .build();
+    // 37 bytes of jump, then 12 of copying the runtime code that follows
+    jump_over_push32(Assembler::new(), init_dest)
+        .push(runtime.len())
+        .push(37 + 12)
+        .push(0)
+        .op(CODECOPY)
+        .push(runtime.len())
+        .push(0)
+        .op(RETURN)
+        .bytes(runtime.as_slice())
+        .build()
+}
+
+// Deploys the `init` code given as call data, then calls the new contract in
+// the same transaction
+fn deploy_and_call(init: Bytes) -> ExecutionContext {
+    let factory = Assembler::new()
+        .op(CALLDATASIZE)
+        .push(0)
+        .push(0)
+        .op(CALLDATACOPY)
+        .op(CALLDATASIZE)
+        .push(0)
+        .push(0)
+        .op(CREATE)
+        .push(32)
+        .push(0)
+        .push(0)
+        .push(0)
+        .push(0)
+        .op(DUP6)
+        .push(0xffff)
+        .op(CALL)
+        .build();
+    let call = Call::new(Address::zero(), contract(), contract(), init, U256::zero(), false);
+    let mut evm = ExecutionContext::new(TxEnv::default(), call, Block::default(), State::new(), factory);
+    assert!(evm.run().success);
+    evm
+}
+
+// Code created mid-transaction is analyzed from its bytes, both as init code
+// and once deployed
+#[test]
+fn created_code() {
+    let address = Bytes32::from_address(create_address(&contract(), U256::zero()));
+    let mut evm = deploy_and_call(init_code(36, 36));
+    assert_eq!(evm.stack.items_top_first(), [Bytes32::one(), address]);
+    assert_eq!(evm.memory.load(0, 32).as_bytes32(), Bytes32::from_u256(42.into()));
+
+    // Into the immediate of the PUSH32 of the deployed code
+    let evm = deploy_and_call(init_code(36, 20));
+    assert_eq!(evm.stack.items_top_first(), [Bytes32::zero(), address]);
+
+    // Or of the init code
+    let creation = |init: Bytes| {
+        let mut evm = Evm::new(Block::default(), State::new());
+        evm.transact(TxEnv::default(), Call::new(contract(), Address::zero(), Address::zero(), init, U256::zero(), false))
+    };
+    assert!(creation(init_code(36, 36)).success);
+    assert_eq!(creation(init_code(4, 36)).halt, Some(Halt::InvalidJump));
+}
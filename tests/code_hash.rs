@@ -0,0 +1,130 @@
+// The code hash is kept with the code: set along with it, and read back by
+// EXTCODEHASH without hashing the code again
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+
+use evm_from_scrust::asm::Assembler;
+use evm_from_scrust::utils::create_address;
+use evm_from_scrust::*;
+use sha3::{Digest, Keccak256};
+use Opcode::*;
+
+fn factory() -> Address {
+    Address::from_slice(&[0xfa; 20])
+}
+
+fn keccak(code: &[u8]) -> Bytes32 {
+    Bytes32::from_slice(&Keccak256::digest(code))
+}
+
+fn call(evm: &mut Evm, to: Address) -> EvmResult {
+    let call = Call::new(Address::zero(), to, to, Bytes::new(), U256::zero(), false);
+    let result = evm.transact(TxEnv::default(), call);
+    assert!(result.success, "{:?}", result.halt);
+    result
+}
+
+// EXTCODEHASH of `address`, twice
+fn extcodehash_twice(address: Address) -> Bytes {
+    let address = U256::from_big_endian(address.as_slice());
+    Assembler::new().push(address).op(EXTCODEHASH).push(address).op(EXTCODEHASH).build()
+}
+
+#[test]
+fn created() {
+    // Deploys 0xffffffff, then reads its code hash twice
+    let code = Assembler::new()
+        .bytes(&hex::decode("6c63ffffffff6000526004601cf3").unwrap())
+        .push(0)
+        .op(MSTORE)
+        .push(13)
+        .push(19)
+        .push(0)
+        .op(CREATE)
+        .op(DUP1)
+        .op(EXTCODEHASH)
+        .op(SWAP1)
+        .op(EXTCODEHASH)
+        .build();
+    let mut evm = Evm::new(Block::default(), State::new());
+    evm.set_code(&factory(), code);
+    let result = call(&mut evm, factory());
+
+    let hash = keccak(&[0xff; 4]);
+    assert_eq!(result.stack, [hash, hash]);
+    let address = create_address(&factory(), U256::zero());
+    assert_eq!(evm.state().code_hash(&address), hash);
+}
+
+#[test]
+fn follows_the_code() {
+    let mut state = State::new();
+    state.set_code(&factory(), Bytes::from_vec(vec![0x00]));
+    assert_eq!(state.code_hash(&factory()), keccak(&[0x00]));
+    state.set_code(&factory(), Bytes::from_vec(vec![0xfe]));
+    assert_eq!(state.code_hash(&factory()), keccak(&[0xfe]));
+    // An account without code still has the hash of no code, unless it's empty
+    state.set_code(&factory(), Bytes::new());
+    assert!(state.code_hash(&factory()).is_zero());
+    state.set_nonce(&factory(), U256::one());
+    assert_eq!(state.code_hash(&factory()), keccak(&[]));
+}
+
+#[test]
+fn extcodehash_without_code() {
+    let funded = Address::from_slice(&[0xcc; 20]);
+    let empty = Address::from_slice(&[0xdd; 20]);
+    let code = Assembler::new()
+        .push(funded.to_u256())
+        .op(EXTCODEHASH)
+        .push(empty.to_u256())
+        .op(EXTCODEHASH)
+        .push(factory().to_u256())
+        .op(EXTCODEHASH)
+        .build();
+    let mut evm = Evm::new(Block::default(), State::new());
+    evm.set_code(&factory(), code.clone());
+    evm.set_balance(&funded, U256::one());
+    let result = call(&mut evm, factory());
+    assert_eq!(result.stack, [keccak(code.as_slice()), Bytes32::zero(), keccak(&[])]);
+    assert_eq!(
+        keccak(&[]),
+        Bytes32::from_slice(&hex::decode("c5d2460186f7233c927e7db2dcc703c0e500b653ca82273b7bfad8045d85a470").unwrap())
+    );
+}
+
+// Host building its account once, and counting how many times it hashed code
+// to do so
+#[derive(Debug, Default)]
+struct Counting {
+    account: Mutex<Option<AccountState>>,
+    hashes: AtomicUsize,
+}
+
+impl Host for Counting {
+    fn account(&self, address: &Address) -> Option<AccountState> {
+        let mut account = self.account.lock().unwrap();
+        let account = account.get_or_insert_with(|| {
+            self.hashes.fetch_add(1, Ordering::SeqCst);
+            AccountState::from_parts(*address, U256::zero(), U256::zero(), Bytes::from_vec(vec![0xfe; 3]))
+        });
+        Some(account.clone())
+    }
+
+    fn storage(&self, _address: &Address, _key: U256) -> Bytes32 {
+        Bytes32::zero()
+    }
+}
+
+#[test]
+fn not_hashed_again() {
+    let host = Arc::new(Counting::default());
+    let mut evm = Evm::new(Block::default(), State::with_host(host.clone()));
+    let target = Address::from_slice(&[0xcc; 20]);
+    evm.set_code(&factory(), extcodehash_twice(target));
+    let result = call(&mut evm, factory());
+
+    let hash = keccak(&[0xfe; 3]);
+    assert_eq!(result.stack, [hash, hash]);
+    assert_eq!(host.hashes.load(Ordering::SeqCst), 1);
+}
@@ -0,0 +1,40 @@
+// Helpers shared by the integration tests. Every test binary includes the
+// module, and uses only some of it.
+#![allow(dead_code)]
+use evm_from_scrust::Address;
+
+// Sender of the transactions
+pub fn sender() -> Address {
+    Address::from_slice(&[0xaa; 20])
+}
+
+// Contract they call
+pub fn contract() -> Address {
+    Address::from_slice(&[0xcc; 20])
+}
+
+// xorshift64*, so that a seed always gives the same cases
+pub struct Rng(u64);
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        // The state must not be zero
+        Rng(seed ^ 0x9e37_79b9_7f4a_7c15)
+    }
+
+    pub fn next(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545_f491_4f6c_dd1d)
+    }
+
+    // In `0..n`
+    pub fn below(&mut self, n: u64) -> u64 {
+        self.next() % n
+    }
+
+    pub fn pick<T: Copy>(&mut self, items: &[T]) -> T {
+        items[self.below(items.len() as u64) as usize]
+    }
+}
@@ -0,0 +1,73 @@
+// Conversions between the crate's types and the ethereum_types (and alloy)
+// primitives
+use evm_from_scrust::*;
+
+fn word() -> [u8; 32] {
+    std::array::from_fn(|i| i as u8 + 1)
+}
+
+#[test]
+fn address() {
+    let h160 = H160::from_slice(&word()[12..]);
+    let address = Address::from(h160);
+    assert_eq!(address.as_slice(), h160.as_bytes());
+    assert_eq!(H160::from(address), h160);
+}
+
+#[test]
+fn bytes32() {
+    let bytes32 = Bytes32::from(word());
+    assert_eq!(bytes32.as_slice(), &word());
+
+    let number = U256::from_big_endian(&word());
+    assert_eq!(Bytes32::from(number), bytes32);
+    assert_eq!(U256::from(bytes32), number);
+
+    let hash = H256(word());
+    assert_eq!(Bytes32::from(hash), bytes32);
+    assert_eq!(H256::from(bytes32), hash);
+}
+
+#[test]
+fn bytes32_from_slice() {
+    let bytes = word();
+    assert_eq!(Bytes32::try_from(&bytes[..]), Ok(Bytes32::from(bytes)));
+    // Shorter slices are left-padded
+    assert_eq!(Bytes32::try_from(&bytes[..2]), Ok(Bytes32::from_u256(0x0102.into())));
+    assert_eq!(Bytes32::try_from(&[][..]), Ok(Bytes32::zero()));
+    // Longer ones are an error, rather than truncated like `from_slice` does
+    let long = [bytes.as_slice(), &[0xff]].concat();
+    assert!(Bytes32::try_from(long.as_slice()).is_err());
+}
+
+#[test]
+fn bytes() {
+    let vec = word().to_vec();
+    let bytes = Bytes::from(vec.clone());
+    assert_eq!(bytes.as_slice(), vec.as_slice());
+    assert_eq!(Bytes::from(vec.as_slice()), bytes);
+    assert_eq!(Vec::<u8>::from(bytes), vec);
+}
+
+#[cfg(feature = "alloy")]
+#[test]
+fn alloy() {
+    let address = Address::from_slice(&word()[12..]);
+    let alloy_address = alloy_primitives::Address::from(address);
+    assert_eq!(alloy_address.as_slice(), address.as_slice());
+    assert_eq!(Address::from(alloy_address), address);
+
+    let bytes32 = Bytes32::from(word());
+    let b256 = alloy_primitives::B256::from(bytes32);
+    assert_eq!(b256.as_slice(), bytes32.as_slice());
+    assert_eq!(Bytes32::from(b256), bytes32);
+
+    let number: alloy_primitives::U256 = bytes32.into();
+    assert_eq!(number.to_be_bytes::<32>(), word());
+    assert_eq!(Bytes32::from(number), bytes32);
+
+    let bytes = Bytes::from(word().to_vec());
+    let alloy_bytes = alloy_primitives::Bytes::from(bytes.clone());
+    assert_eq!(alloy_bytes.as_ref(), bytes.as_slice());
+    assert_eq!(Bytes::from(alloy_bytes), bytes);
+}
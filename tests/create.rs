@@ -0,0 +1,461 @@
+// Contract creation, from bytecode and by transactions: what a failed
+// constructor leaves behind, the salted addresses of CREATE2 and the init code
+// size limit of EIP-3860
+use evm_from_scrust::asm::Assembler;
+use evm_from_scrust::utils::{create2_address, create_address};
+use evm_from_scrust::*;
+use Opcode::*;
+
+fn factory() -> Address {
+    Address::from_slice(&[0xfa; 20])
+}
+
+// CREATE with `value` of the 13 bytes of init code `init`
+fn create(init: &str, value: u64) -> Bytes {
+    Assembler::new()
+        .bytes(&hex::decode(format!("6c{}", init)).unwrap())
+        .push(0)
+        .op(MSTORE)
+        .push(13)
+        .push(19)
+        .push(value)
+        .op(CREATE)
+        .build()
+}
+
+// CREATE2 with `value` and `salt` of the 13 bytes of init code `init`
+fn create2(init: &str, value: u64, salt: u64) -> Bytes {
+    Assembler::new()
+        .bytes(&hex::decode(format!("6c{}", init)).unwrap())
+        .push(0)
+        .op(MSTORE)
+        .push(salt)
+        .push(13)
+        .push(19)
+        .push(value)
+        .op(CREATE2)
+        .build()
+}
+
+fn run(code: Bytes) -> (EvmResult, Evm) {
+    run_in(SpecId::default(), code)
+}
+
+fn run_in(spec: SpecId, code: Bytes) -> (EvmResult, Evm) {
+    let mut evm = Evm::new(Block::default(), State::new()).with_spec(spec);
+    evm.set_code(&factory(), code);
+    evm.set_balance(&factory(), 100.into());
+    let call = Call::new(Address::zero(), factory(), factory(), Bytes::new(), U256::zero(), false);
+    let result = evm.transact(TxEnv::default(), call);
+    assert!(result.success);
+    (result, evm)
+}
+
+#[test]
+fn reverted_constructor() {
+    // Reverts with 0xdeadbeef
+    let (result, evm) = run(create("63deadbeef6000526004601cfd", 7));
+    let address = create_address(&factory(), U256::zero());
+
+    assert_eq!(result.stack, [Bytes32::zero()]);
+    assert!(!evm.state().exists(&address));
+    assert_eq!(evm.state().balance(&factory()), 100.into());
+    // The nonce is still used up
+    assert_eq!(evm.state().nonce(&factory()), 1.into());
+}
+
+#[test]
+fn successful_constructor() {
+    // Returns 0xffffffff
+    let (result, evm) = run(create("63ffffffff6000526004601cf3", 7));
+    let address = create_address(&factory(), U256::zero());
+
+    assert_eq!(result.stack, [Bytes32::from_address(address)]);
+    assert_eq!(evm.state().code(&address), Bytes::from_vec(vec![0xff; 4]));
+    assert_eq!(evm.state().balance(&address), 7.into());
+    assert_eq!(evm.state().balance(&factory()), 93.into());
+    assert_eq!(evm.state().nonce(&factory()), 1.into());
+}
+
+// A new contract starts with a nonce of 1 since Spurious Dragon (EIP-161), so
+// its own first CREATE uses that nonce
+#[test]
+fn created_contract_nonce() {
+    // The child's init code creates an empty grandchild
+    let code = create("600060006000f0000000000000", 0);
+    let child = create_address(&factory(), U256::zero());
+    for (spec, nonce) in [(SpecId::Tangerine, 0u64), (SpecId::SpuriousDragon, 1), (SpecId::default(), 1)] {
+        let (_, evm) = run_in(spec, code.clone());
+        let grandchild = create_address(&child, nonce.into());
+        assert!(evm.state().exists(&grandchild), "{:?}", spec);
+        assert_eq!(evm.state().nonce(&grandchild), nonce.into(), "{:?}", spec);
+        assert_eq!(evm.state().nonce(&child), (nonce + 1).into(), "{:?}", spec);
+    }
+}
+
+fn address(hex: &str) -> Address {
+    Address::from_slice(&hex::decode(hex).unwrap())
+}
+
+fn word(hex: &str) -> Bytes32 {
+    Bytes32::from_slice(&hex::decode(hex).unwrap())
+}
+
+// Examples of EIP-1014
+#[test]
+fn create2_addresses() {
+    let zero = Bytes32::zero();
+    let deadbeef = address("00000000000000000000000000000000deadbeef");
+    let deadbeef_high = address("deadbeef00000000000000000000000000000000");
+    let cases = [
+        (Address::zero(), zero, "00", "4d1a2e2bb4f88f0250f26ffff098b0b30b26bf38"),
+        (deadbeef_high, zero, "00", "b928f69bb1d91cd65274e3c79d8986362984fda3"),
+        (deadbeef_high, word("000000000000000000000000feed000000000000000000000000000000000000"), "00", "d04116cdd17bebe565eb2422f2497e06cc1c9833"),
+        (deadbeef, word("00000000000000000000000000000000000000000000000000000000cafebabe"), "deadbeef", "60f3f640a8508fc6a86d45df051962668e1e8ac7"),
+        (Address::zero(), zero, "", "e33c0c7f7df4809055c3eba6c09cfe4baf1bd9e0"),
+    ];
+    for (sender, salt, init, expected) in cases {
+        assert_eq!(create2_address(&sender, &salt, &hex::decode(init).unwrap()), address(expected), "{}", expected);
+    }
+}
+
+#[test]
+fn create2_salted_address() {
+    // Returns 0xffffffff
+    let init = "63ffffffff6000526004601cf3";
+    let (result, evm) = run(create2(init, 7, 0x42));
+    let address = create2_address(&factory(), &Bytes32::from_u256(0x42.into()), &hex::decode(init).unwrap());
+
+    assert_eq!(result.stack, [Bytes32::from_address(address)]);
+    assert_eq!(evm.state().code(&address), Bytes::from_vec(vec![0xff; 4]));
+    assert_eq!(evm.state().balance(&address), 7.into());
+    assert_eq!(evm.state().balance(&factory()), 93.into());
+    // The nonce is bumped all the same, but doesn't change the address
+    assert_eq!(evm.state().nonce(&factory()), 1.into());
+    assert_ne!(address, create_address(&factory(), U256::zero()));
+}
+
+// A second CREATE2 with the same salt and init code collides with the first
+// contract, which stays as it was (EIP-684)
+#[test]
+fn create2_collision() {
+    let init = "63ffffffff6000526004601cf3";
+    let code = [create2(init, 7, 1).as_slice(), create2(init, 5, 1).as_slice()].concat();
+    let (result, evm) = run(Bytes::from_vec(code));
+    let address = create2_address(&factory(), &Bytes32::one(), &hex::decode(init).unwrap());
+
+    assert_eq!(result.stack, [Bytes32::zero(), Bytes32::from_address(address)]);
+    assert_eq!(evm.state().balance(&address), 7.into());
+    assert_eq!(evm.state().balance(&factory()), 93.into());
+    assert_eq!(evm.state().nonce(&factory()), 2.into());
+}
+
+// Valid bytecode: CREATE2 of empty init code, from the `eval` contract
+#[test]
+fn create2_eval() {
+    let result = evm::eval(&[0x60, 0, 0x60, 0, 0x60, 0, 0x60, 0, 0xf5], &[]);
+    assert!(result.success, "{:?}", result.halt);
+    let address = create2_address(&Address::from_slice(&[0xee; 20]), &Bytes32::zero(), &[]);
+    assert_eq!(result.stack, [Bytes32::from_address(address)]);
+}
+
+// CREATE with `value` of `size` zero bytes (STOP, deploying empty code)
+fn create_zeros(size: usize, value: u64) -> Bytes {
+    Assembler::new().push(size).push(0).push(value).op(CREATE).build()
+}
+
+// CREATE2 of the same, with a zero salt
+fn create2_zeros(size: usize, value: u64) -> Bytes {
+    Assembler::new().push(0).push(size).push(0).push(value).op(CREATE2).build()
+}
+
+#[test]
+fn initcode_size_limit() {
+    let (result, evm) = run(create_zeros(MAX_INITCODE_SIZE, 7));
+    let address = create_address(&factory(), U256::zero());
+    assert_eq!(result.stack, [Bytes32::from_address(address)]);
+    assert_eq!(evm.state().balance(&address), 7.into());
+
+    // One byte more fails before touching the state
+    let (result, evm) = run(create_zeros(MAX_INITCODE_SIZE + 1, 7));
+    assert_eq!(result.stack, [Bytes32::zero()]);
+    assert!(!evm.state().exists(&address));
+    assert_eq!(evm.state().balance(&factory()), 100.into());
+    assert!(evm.state().nonce(&factory()).is_zero());
+
+    // No limit before Shanghai
+    let (result, _) = run_in(SpecId::Merge, create_zeros(MAX_INITCODE_SIZE + 1, 7));
+    assert_eq!(result.stack, [Bytes32::from_address(address)]);
+}
+
+#[test]
+fn initcode_size_limit_of_create2() {
+    let init = vec![0; MAX_INITCODE_SIZE];
+    let (result, evm) = run(create2_zeros(MAX_INITCODE_SIZE, 7));
+    let address = create2_address(&factory(), &Bytes32::zero(), &init);
+    assert_eq!(result.stack, [Bytes32::from_address(address)]);
+    assert_eq!(evm.state().balance(&address), 7.into());
+
+    let (result, evm) = run(create2_zeros(MAX_INITCODE_SIZE + 1, 7));
+    assert_eq!(result.stack, [Bytes32::zero()]);
+    assert_eq!(evm.state().balance(&factory()), 100.into());
+    assert!(evm.state().nonce(&factory()).is_zero());
+
+    let (result, _) = run_in(SpecId::Merge, create2_zeros(MAX_INITCODE_SIZE + 1, 7));
+    let address = create2_address(&factory(), &Bytes32::zero(), &[0; MAX_INITCODE_SIZE + 1]);
+    assert_eq!(result.stack, [Bytes32::from_address(address)]);
+}
+
+#[test]
+fn initcode_word_gas() {
+    let words = MAX_INITCODE_SIZE / 32;
+    let (shanghai, _) = run(create_zeros(MAX_INITCODE_SIZE, 0));
+    let (merge, _) = run_in(SpecId::Merge, create_zeros(MAX_INITCODE_SIZE, 0));
+    assert_eq!(shanghai.gas_used - merge.gas_used, INITCODE_WORD_COST * words);
+    assert_eq!(initcode_gas(SpecId::Shanghai, 33), 2 * INITCODE_WORD_COST);
+
+    // CREATE2 pays the same on top of hashing the init code (6 per word)
+    let (shanghai2, _) = run(create2_zeros(MAX_INITCODE_SIZE, 0));
+    let (merge2, _) = run_in(SpecId::Merge, create2_zeros(MAX_INITCODE_SIZE, 0));
+    assert_eq!(shanghai2.gas_used - merge2.gas_used, INITCODE_WORD_COST * words);
+    // One more PUSH, and the hashing
+    assert_eq!(shanghai2.gas_used - shanghai.gas_used, 3 + 6 * words);
+}
+
+// Creation transactions pay for the words of their init code up front, as
+// intrinsic gas
+#[test]
+fn initcode_word_gas_of_transactions() {
+    let size = 1000;
+    let (shanghai, _) = transact_create(SpecId::Shanghai, size);
+    let (merge, _) = transact_create(SpecId::Merge, size);
+    assert!(shanghai.success && merge.success);
+    assert_eq!(shanghai.gas_used - merge.gas_used, INITCODE_WORD_COST * size.div_ceil(32));
+
+    // Which the gas limit must cover
+    let sender = Address::from_slice(&[0xaa; 20]);
+    let mut evm = Evm::new(Block::default(), State::new());
+    let call = Call::new(sender, Address::zero(), Address::zero(), Bytes::from_vec(vec![0; size]), U256::zero(), false);
+    let tx = TxEnv::new(sender, U256::zero(), (INITCODE_WORD_COST * size.div_ceil(32) - 1).into(), U256::zero());
+    let result = evm.transact(tx, call);
+    assert_eq!(result.halt, Some(Halt::OutOfGas));
+    assert!(evm.state().nonce(&sender).is_zero());
+}
+
+// Creation transaction with `size` zero bytes of init code
+fn transact_create(spec: SpecId, size: usize) -> (EvmResult, Evm) {
+    let sender = Address::from_slice(&[0xaa; 20]);
+    let mut evm = Evm::new(Block::default(), State::new()).with_spec(spec);
+    let call = Call::new(sender, Address::zero(), Address::zero(), Bytes::from_vec(vec![0; size]), U256::zero(), false);
+    let result = evm.transact(TxEnv::default(), call);
+    (result, evm)
+}
+
+#[test]
+fn initcode_size_limit_of_transactions() {
+    let sender = Address::from_slice(&[0xaa; 20]);
+    let (result, evm) = transact_create(SpecId::Shanghai, MAX_INITCODE_SIZE);
+    assert!(result.success);
+    assert_eq!(evm.state().nonce(&sender), 1.into());
+
+    // The transaction is invalid: it doesn't even bump the nonce
+    let (result, evm) = transact_create(SpecId::Shanghai, MAX_INITCODE_SIZE + 1);
+    assert_eq!(result.halt, Some(Halt::InitCodeSizeLimit));
+    assert_eq!(result.gas_used, 0);
+    assert!(evm.state().nonce(&sender).is_zero());
+    assert_eq!(evm.receipts()[0].status, 0);
+
+    let (result, _) = transact_create(SpecId::Merge, MAX_INITCODE_SIZE + 1);
+    assert!(result.success);
+}
+
+#[test]
+fn insufficient_balance() {
+    // The factory holds 100, the creation fails and the execution goes on
+    let code = Assembler::new().push(0).push(0).push(101).op(CREATE).push(1).build();
+    let (result, evm) = run(code);
+    assert_eq!(result.stack, [Bytes32::one(), Bytes32::zero()]);
+    assert_eq!(evm.state().balance(&factory()), 100.into());
+    assert!(evm.state().nonce(&factory()).is_zero());
+}
+
+#[test]
+fn static_frame() {
+    // Even without value
+    let creations = [
+        Assembler::new().push(0).push(0).push(0).op(CREATE).build(),
+        Assembler::new().push(0).push(0).push(0).push(0).op(CREATE2).build(),
+    ];
+    for code in creations {
+        let call = Call::new(Address::zero(), factory(), factory(), Bytes::new(), U256::zero(), true);
+        let mut evm = ExecutionContext::new(TxEnv::default(), call, Block::default(), State::default(), code);
+        let result = evm.run();
+        assert!(!result.success);
+        assert_eq!(result.halt, Some(Halt::StaticCallViolation));
+    }
+}
+
+// Stores the EXTCODESIZE and EXTCODEHASH of the address in the first word of
+// the call data at the slot in the second word and the next one, and marks
+// the one after that
+fn inspector() -> Address {
+    Address::from_slice(&[0x15; 20])
+}
+
+fn inspector_code() -> Bytes {
+    Assembler::new()
+        .push(0)
+        .op(CALLDATALOAD)
+        .op(DUP1)
+        .op(EXTCODESIZE)
+        .push(32)
+        .op(CALLDATALOAD)
+        .op(SSTORE)
+        .op(EXTCODEHASH)
+        .push(32)
+        .op(CALLDATALOAD)
+        .push(1)
+        .op(ADD)
+        .op(SSTORE)
+        .push(1)
+        .push(32)
+        .op(CALLDATALOAD)
+        .push(2)
+        .op(ADD)
+        .op(SSTORE)
+        .build()
+}
+
+// Init code asking the inspector about its own address (slots 0 to 2), then
+// deploying 0xffffffff
+fn inspected_init_code() -> Bytes {
+    Assembler::new()
+        .op(ADDRESS)
+        .push(0)
+        .op(MSTORE)
+        .push(0)
+        .push(0)
+        .push(64)
+        .push(0)
+        .push(0)
+        .push(inspector().to_u256())
+        .push(100_000)
+        .op(CALL)
+        .op(POP)
+        .push(0xffffffffu64)
+        .push(0)
+        .op(MSTORE)
+        .push(4)
+        .push(28)
+        .op(RETURN)
+        .build()
+}
+
+// Asks the inspector about `address` again (slots 3 to 5), and checks what it
+// saw during and after the creation
+fn check_inspected(evm: &mut Evm, address: Address) {
+    let data = [Bytes32::from_address(address).as_slice(), Bytes32::from_u256(3.into()).as_slice()].concat();
+    let call = Call::new(Address::zero(), inspector(), inspector(), Bytes::from_vec(data), U256::zero(), false);
+    assert!(evm.transact(TxEnv::default(), call).success);
+
+    let slot = |key: u64| evm.state().storage_load(&inspector(), key.into());
+    // Mid-creation, the contract exists without code. It already has its
+    // nonce of 1, so its code hash is the hash of no code.
+    assert_eq!(slot(2), Bytes32::one());
+    assert_eq!(slot(0), Bytes32::zero());
+    assert_eq!(slot(1), keccak256(&[]));
+    assert!(evm.state().exists(&address));
+    // The code is there once deployed
+    let code = Bytes::from_vec(vec![0xff; 4]);
+    assert_eq!(slot(3).to_u256(), U256::from(4));
+    assert_eq!(slot(4), evm.state().code_hash(&address));
+    assert_ne!(slot(4), Bytes32::zero());
+    assert_eq!(evm.state().code(&address), code);
+}
+
+#[test]
+fn mid_creation_by_create() {
+    // Copies the init code appended to the factory, and runs it with CREATE
+    let init = inspected_init_code();
+    let prefix = |offset: usize| {
+        Assembler::new()
+            .push(init.len() as u64)
+            .push(offset as u64)
+            .push(0)
+            .op(CODECOPY)
+            .push(init.len() as u64)
+            .push(0)
+            .push(0)
+            .op(CREATE)
+            .build()
+    };
+    let offset = prefix(0).len();
+    let code = Bytes::from_vec([prefix(offset).as_slice(), init.as_slice()].concat());
+    let (result, mut evm) = {
+        let mut evm = Evm::new(Block::default(), State::new()).disable_nonce_check(true);
+        evm.set_code(&inspector(), inspector_code());
+        evm.set_code(&factory(), code);
+        let call = Call::new(Address::zero(), factory(), factory(), Bytes::new(), U256::zero(), false);
+        (evm.transact(TxEnv::default(), call), evm)
+    };
+    let address = create_address(&factory(), U256::zero());
+    assert_eq!(result.stack, [Bytes32::from_address(address)]);
+    check_inspected(&mut evm, address);
+}
+
+#[test]
+fn mid_creation_by_transaction() {
+    let sender = Address::from_slice(&[0xaa; 20]);
+    let mut evm = Evm::new(Block::default(), State::new());
+    evm.set_code(&inspector(), inspector_code());
+    let call = Call::new(sender, Address::zero(), Address::zero(), inspected_init_code(), U256::zero(), false);
+    assert!(evm.transact(TxEnv::default(), call).success);
+    let address = evm.receipts()[0].contract_address.unwrap();
+    check_inspected(&mut evm, address);
+}
+
+// What the init code stores stays along with the deployed code
+#[test]
+fn constructor_storage() {
+    // SSTORE(0, 0x42), then returns 0xffffffff
+    let (_, evm) = run(create("60426000556004601cf3000000", 0));
+    let address = create_address(&factory(), U256::zero());
+    assert_eq!(evm.state().storage_load(&address, U256::zero()).to_u256(), U256::from(0x42));
+}
+
+// Init code runs without calldata
+#[test]
+fn init_code_calldata() {
+    // SSTORE(0, CALLDATASIZE + 1)
+    let (_, evm) = run(create("36600101600055000000000000", 0));
+    let address = create_address(&factory(), U256::zero());
+    assert_eq!(evm.state().storage_load(&address, U256::zero()).to_u256(), U256::one());
+}
+
+// ADDRESS, CALLER and CALLVALUE of the init code, stored in slots 0 to 2
+const ENV_INIT: &str = "30600055336001553460025500";
+
+fn stored_env(evm: &Evm, address: Address) -> [U256; 3] {
+    [0, 1, 2].map(|key| evm.state().storage_load(&address, key.into()).to_u256())
+}
+
+// Init code runs as the new contract, called by its creator with the endowment
+#[test]
+fn init_code_env() {
+    let (_, evm) = run(create(ENV_INIT, 7));
+    let address = create_address(&factory(), U256::zero());
+    assert_eq!(stored_env(&evm, address), [address.to_u256(), factory().to_u256(), 7.into()]);
+    // The endowment comes from the creator
+    assert_eq!(evm.state().balance(&address), 7.into());
+    assert_eq!(evm.state().balance(&factory()), 93.into());
+
+    // Also for creation transactions
+    let sender = Address::from_slice(&[0xaa; 20]);
+    let mut evm = Evm::new(Block::default(), State::new());
+    evm.set_balance(&sender, 10.into());
+    let init = Bytes::from_vec(hex::decode(ENV_INIT).unwrap());
+    assert!(evm.transact(TxEnv::default(), Call::new(sender, Address::zero(), Address::zero(), init, 3.into(), false)).success);
+    let address = create_address(&sender, U256::zero());
+    assert_eq!(stored_env(&evm, address), [address.to_u256(), sender.to_u256(), 3.into()]);
+    assert_eq!(evm.state().balance(&sender), 7.into());
+}
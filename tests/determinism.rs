@@ -0,0 +1,88 @@
+// Accounts deleted at the end of a transaction (self-destructed ones, and
+// touched empty ones) go in address order, so that two runs of the same
+// transaction give the same state and receipts
+use evm_from_scrust::asm::Assembler;
+use evm_from_scrust::*;
+use Opcode::*;
+
+mod common;
+use common::{contract, sender};
+
+fn sink() -> Address {
+    Address::from_slice(&[0xee; 20])
+}
+
+// Addresses spread over the whole range, so that no map happens to keep them
+// in order
+fn address(index: u8) -> Address {
+    let mut bytes = [0u8; 20];
+    bytes[0] = index.wrapping_mul(97);
+    bytes[19] = index;
+    Address::from_slice(&bytes)
+}
+
+// Contracts that self-destruct, sending their wei to the sink
+fn victims() -> Vec<Address> {
+    (1..=50).map(address).collect()
+}
+
+// Accounts left empty in the state
+fn empties() -> Vec<Address> {
+    (101..=150).map(address).collect()
+}
+
+// Calls every victim and every empty account
+fn contract_code() -> Bytes {
+    victims()
+        .into_iter()
+        .chain(empties())
+        .fold(Assembler::new(), |code, address| {
+            code.push(0).push(0).push(0).push(0).push(0).push(address.to_u256()).op(GAS).op(CALL).op(POP)
+        })
+        .build()
+}
+
+// Runs the transaction on a fresh Evm, with the accounts inserted in the given
+// order
+fn run(reversed: bool) -> (State, Evm) {
+    let mut accounts: Vec<(Address, bool)> = victims().into_iter().map(|address| (address, true)).collect();
+    accounts.extend(empties().into_iter().map(|address| (address, false)));
+    if reversed {
+        accounts.reverse();
+    }
+
+    let mut state = State::new();
+    state.set_code(&contract(), contract_code());
+    for (address, victim) in accounts {
+        match victim {
+            true => {
+                state.set_code(&address, Assembler::new().push(sink().to_u256()).op(SELFDESTRUCT).build());
+                state.set_balance(&address, U256::one());
+            }
+            false => state.insert(address, AccountState::new(address)),
+        }
+    }
+
+    let mut evm = Evm::new(Block::default(), state.clone());
+    let result = evm.transact(TxEnv::default(), Call::new(sender(), contract(), contract(), Bytes::new(), U256::zero(), false));
+    assert!(result.success, "{:?}", result.halt);
+    (state, evm)
+}
+
+#[test]
+fn deletions() {
+    let (pre, first) = run(false);
+    let (_, second) = run(true);
+
+    // Everything deleted
+    for address in victims().iter().chain(&empties()) {
+        assert!(!first.state().exists(address));
+    }
+    assert_eq!(first.state().balance(&sink()), 50.into());
+
+    let diff = pre.diff(first.state());
+    assert_eq!(diff.accounts.iter().filter(|account| account.status == AccountStatus::Removed).count(), 50);
+    assert_eq!(diff.to_string(), pre.diff(second.state()).to_string());
+    assert_eq!(first.state().addresses(), second.state().addresses());
+    assert_eq!(format!("{:?}", first.receipts()), format!("{:?}", second.receipts()));
+}
@@ -0,0 +1,279 @@
+// Differential testing against revm: random straight-line programs (pushes,
+// stack shuffles, arithmetic and memory accesses, no calls) must end with the
+// same stack, memory size, success flag and return data in both EVMs.
+//
+// `cargo test --features differential` runs the comparison. Without the
+// feature, the generator and the minimizer still run on the seed corpus.
+use evm_from_scrust::*;
+use Opcode::*;
+
+mod common;
+use common::Rng;
+
+// Seeds always run, with or without revm
+const CORPUS: [u64; 4] = [0, 1, 0xdead_beef, 0x5eed_5eed_5eed_5eed];
+
+// Instructions per program, besides the final STOP or RETURN
+const PROGRAM_LENGTH: usize = 48;
+
+// Deeper stacks only make the programs longer to read
+const MAX_DEPTH: usize = 32;
+
+// Offsets of the memory accesses are below this
+const MEMORY_RANGE: u64 = 1024;
+
+const UNARY: [Opcode; 2] = [ISZERO, NOT];
+
+const BINARY: [Opcode; 21] = [
+    ADD, MUL, SUB, DIV, SDIV, MOD, SMOD, EXP, SIGNEXTEND, LT, GT, SLT, SGT, EQ, AND, OR, XOR, BYTE, SHL, SHR, SAR,
+];
+
+const TERNARY: [Opcode; 2] = [ADDMOD, MULMOD];
+
+// An opcode with its immediate, or a PUSH of a memory offset with the access
+// using it (so that removing it while minimizing doesn't leave a huge offset)
+type Instruction = Vec<u8>;
+
+fn code(program: &[Instruction]) -> Vec<u8> {
+    program.concat()
+}
+
+fn push(value: &[u8]) -> Instruction {
+    let mut instruction = vec![PUSH1.byte() + value.len() as u8 - 1];
+    instruction.extend_from_slice(value);
+    instruction
+}
+
+// Mostly the values where arithmetic goes wrong: small numbers, the sign
+// bit and the all-ones word
+fn word(rng: &mut Rng) -> Instruction {
+    match rng.below(4) {
+        0 => push(&[rng.below(34) as u8]),
+        1 => push(&[0xff; 32]),
+        2 => {
+            let mut word = [0u8; 32];
+            word[0] = 0x80;
+            word[31] = rng.below(2) as u8;
+            push(&word)
+        }
+        _ => {
+            let size = 1 + rng.below(32) as usize;
+            let bytes: Vec<u8> = (0..size).map(|_| rng.next() as u8).collect();
+            push(&bytes)
+        }
+    }
+}
+
+fn memory_access(rng: &mut Rng, opcode: Opcode) -> Instruction {
+    let offset = rng.below(MEMORY_RANGE) as u16;
+    let mut instruction = push(&offset.to_be_bytes());
+    instruction.push(opcode.byte());
+    instruction
+}
+
+// A program that never underflows nor overflows the stack, ending with a STOP
+// or the RETURN of a slice of the memory
+fn generate(seed: u64) -> Vec<Instruction> {
+    let mut rng = Rng::new(seed);
+    let mut program = Vec::with_capacity(PROGRAM_LENGTH + 1);
+    let mut depth = 0;
+
+    while program.len() < PROGRAM_LENGTH {
+        let (instruction, pops, pushes) = match rng.below(10) {
+            0 | 1 if depth < MAX_DEPTH => (word(&mut rng), 0, 1),
+            2 if (1..MAX_DEPTH).contains(&depth) => {
+                let n = 1 + rng.below(depth.min(16) as u64) as u8;
+                (vec![DUP1.byte() + n - 1], 0, 1)
+            }
+            3 if depth >= 2 => {
+                let n = 1 + rng.below((depth - 1).min(16) as u64) as u8;
+                (vec![SWAP1.byte() + n - 1], 0, 0)
+            }
+            4 if depth >= 1 => (vec![rng.pick(&UNARY).byte()], 1, 1),
+            5 | 6 if depth >= 2 => (vec![rng.pick(&BINARY).byte()], 2, 1),
+            7 if depth >= 3 => (vec![rng.pick(&TERNARY).byte()], 3, 1),
+            8 if depth >= 1 => match rng.below(3) {
+                0 => (memory_access(&mut rng, MSTORE), 1, 0),
+                1 => (memory_access(&mut rng, MSTORE8), 1, 0),
+                _ => (vec![POP.byte()], 1, 0),
+            },
+            9 if depth < MAX_DEPTH => match rng.below(3) {
+                0 => (memory_access(&mut rng, MLOAD), 0, 1),
+                1 => (vec![MSIZE.byte()], 0, 1),
+                _ => (vec![PC.byte()], 0, 1),
+            },
+            _ => continue,
+        };
+        depth = depth - pops + pushes;
+        program.push(instruction);
+    }
+
+    if rng.below(2) == 0 {
+        let size = rng.below(65) as u8;
+        let offset = rng.below(MEMORY_RANGE) as u16;
+        program.push([push(&[size]), push(&offset.to_be_bytes()), vec![RETURN.byte()]].concat());
+    } else {
+        program.push(vec![STOP.byte()]);
+    }
+    program
+}
+
+// Removes instructions one at a time, as long as `fails` still holds
+fn minimize(mut program: Vec<Instruction>, fails: impl Fn(&[Instruction]) -> bool) -> Vec<Instruction> {
+    let mut index = 0;
+    while index < program.len() {
+        let mut candidate = program.clone();
+        candidate.remove(index);
+        if fails(&candidate) {
+            program = candidate;
+        } else {
+            index += 1;
+        }
+    }
+    program
+}
+
+// What both EVMs must agree on. The stack goes from the top to the bottom.
+#[derive(Debug, PartialEq, Eq)]
+struct Outcome {
+    success: bool,
+    stack: Vec<U256>,
+    memory_size: usize,
+    output: Vec<u8>,
+}
+
+#[cfg(feature = "differential")]
+impl Outcome {
+    // The stack and the memory of a failed execution are implementation
+    // details
+    fn matches(&self, other: &Outcome) -> bool {
+        self.success == other.success && (!self.success || self == other)
+    }
+}
+
+fn run(code: &[u8]) -> Outcome {
+    let result = evm::eval(code, &[]);
+    Outcome {
+        success: result.success,
+        stack: result.stack.iter().map(|item| item.to_u256()).collect(),
+        // Without calls, the peak is the memory of the only frame
+        memory_size: result.peak_memory_bytes,
+        output: result.result.as_slice().to_vec(),
+    }
+}
+
+#[test]
+fn seed_corpus() {
+    for seed in CORPUS {
+        let program = generate(seed);
+        assert_eq!(program, generate(seed), "seed {}", seed);
+        assert_eq!(program.len(), PROGRAM_LENGTH + 1);
+
+        // Generated programs are valid, so they only fail on bugs
+        let outcome = run(&code(&program));
+        assert!(outcome.success, "seed {}: {}", seed, hex::encode(code(&program)));
+        assert!(outcome.stack.len() <= MAX_DEPTH);
+    }
+}
+
+#[test]
+fn minimizer() {
+    // Stand-in for a divergence: any program using MULMOD
+    let uses_mulmod = |program: &[Instruction]| program.iter().any(|instruction| instruction == &[MULMOD.byte()]);
+    let seed = (0..).find(|seed| uses_mulmod(&generate(*seed))).unwrap();
+
+    let minimized = minimize(generate(seed), uses_mulmod);
+    assert_eq!(minimized, [vec![MULMOD.byte()]]);
+}
+
+#[cfg(feature = "differential")]
+mod reference {
+    use revm::interpreter::Interpreter;
+    use revm::primitives::{AccountInfo, Address, Bytecode, Bytes, ExecutionResult, Output, SpecId, TxKind};
+    use revm::{inspector_handle_register, Database, EvmContext, InMemoryDB, Inspector};
+
+    use super::Outcome;
+    use evm_from_scrust::U256;
+
+    // Stack and memory size after the last instruction
+    #[derive(Default)]
+    struct LastStep {
+        stack: Vec<revm::primitives::U256>,
+        memory_size: usize,
+    }
+
+    impl<DB: Database> Inspector<DB> for LastStep {
+        fn step_end(&mut self, interp: &mut Interpreter, _context: &mut EvmContext<DB>) {
+            self.stack = interp.stack.data().clone();
+            self.memory_size = interp.shared_memory.len();
+        }
+    }
+
+    // Same environment as `evm::eval`
+    pub fn run(code: &[u8]) -> Outcome {
+        let contract = Address::repeat_byte(0xee);
+        let mut db = InMemoryDB::default();
+        let bytecode = Bytecode::new_raw(Bytes::copy_from_slice(code));
+        let account = AccountInfo { code_hash: bytecode.hash_slow(), code: Some(bytecode), ..Default::default() };
+        db.insert_account_info(contract, account);
+
+        let mut evm = revm::Evm::builder()
+            .with_db(db)
+            .with_external_context(LastStep::default())
+            .with_spec_id(SpecId::SHANGHAI)
+            .modify_tx_env(|tx| {
+                tx.caller = Address::ZERO;
+                tx.transact_to = TxKind::Call(contract);
+                tx.gas_limit = 30_000_000;
+                tx.gas_price = revm::primitives::U256::ZERO;
+            })
+            .modify_block_env(|block| block.basefee = revm::primitives::U256::ZERO)
+            .append_handler_register(inspector_handle_register)
+            .build();
+        let result = evm.transact().expect("invalid transaction").result;
+        let last_step = evm.into_context().external;
+
+        let (success, output) = match result {
+            ExecutionResult::Success { output: Output::Call(output), .. } => (true, output.to_vec()),
+            _ => (false, Vec::new()),
+        };
+        Outcome {
+            success,
+            stack: last_step.stack.iter().rev().map(|item| U256::from_big_endian(&item.to_be_bytes::<32>())).collect(),
+            memory_size: last_step.memory_size,
+            output,
+        }
+    }
+}
+
+#[cfg(feature = "differential")]
+fn diverges(program: &[Instruction]) -> bool {
+    let code = code(program);
+    !run(&code).matches(&reference::run(&code))
+}
+
+#[cfg(feature = "differential")]
+fn check(seed: u64) {
+    let program = generate(seed);
+    if !diverges(&program) {
+        return;
+    }
+    let minimized = code(&minimize(program.clone(), diverges));
+    panic!(
+        "seed {} diverges from revm\n  minimized: {}\n  ours:      {:?}\n  revm:      {:?}\n  original:  {}",
+        seed,
+        hex::encode(&minimized),
+        run(&minimized),
+        reference::run(&minimized),
+        hex::encode(code(&program)),
+    );
+}
+
+#[cfg(feature = "differential")]
+#[test]
+fn differential() {
+    let runs = std::env::var("DIFFERENTIAL_RUNS").map_or(500, |runs| runs.parse().expect("DIFFERENTIAL_RUNS"));
+    for seed in CORPUS.into_iter().chain(1000..1000 + runs) {
+        check(seed);
+    }
+}
@@ -0,0 +1,126 @@
+// EOAs delegating their code to a contract (EIP-7702): calls to them run the
+// delegate's code in their own context
+#![cfg(feature = "eip7702")]
+use evm_from_scrust::asm::Assembler;
+use evm_from_scrust::*;
+use sha3::{Digest, Keccak256};
+use Opcode::*;
+
+mod common;
+use common::sender;
+
+fn eoa() -> Address {
+    Address::from_slice(&[0xee; 20])
+}
+
+fn delegate() -> Address {
+    Address::from_slice(&[0xdd; 20])
+}
+
+fn inspector() -> Address {
+    Address::from_slice(&[0xcc; 20])
+}
+
+// Stores ADDRESS at slot 1, and adds 1 to slot 0, leaving the new value on the
+// stack
+fn delegate_code() -> Bytes {
+    Assembler::new()
+        .op(ADDRESS)
+        .push(1)
+        .op(SSTORE)
+        .push(0)
+        .op(SLOAD)
+        .push(1)
+        .op(ADD)
+        .op(DUP1)
+        .push(0)
+        .op(SSTORE)
+        .build()
+}
+
+fn designator(target: Address) -> Bytes {
+    Bytes::from_vec([[0xef, 0x01, 0x00].as_slice(), target.as_slice()].concat())
+}
+
+fn evm() -> Evm {
+    let mut evm = Evm::new(Block::default(), State::new()).disable_nonce_check(true);
+    evm.set_code(&delegate(), delegate_code());
+    evm.set_storage(&eoa(), 0.into(), Bytes32::from_u256(41.into()));
+    evm
+}
+
+fn call_eoa(evm: &mut Evm) -> EvmResult {
+    let call = Call::new(sender(), eoa(), eoa(), Bytes::new(), U256::zero(), false);
+    let result = evm.transact(TxEnv::default(), call);
+    assert!(result.success, "{:?}", result.halt);
+    result
+}
+
+// Runs `opcode` on the EOA from another contract
+fn inspect(evm: &mut Evm, opcode: Opcode) -> EvmResult {
+    evm.set_code(&inspector(), Assembler::new().push(eoa().to_u256()).op(opcode).build());
+    let call = Call::new(sender(), inspector(), inspector(), Bytes::new(), U256::zero(), false);
+    evm.transact(TxEnv::default(), call)
+}
+
+#[test]
+fn set_delegation() {
+    let mut state = State::new();
+    state.set_delegation(&eoa(), &delegate());
+    assert_eq!(state.code(&eoa()), designator(delegate()));
+    assert_eq!(state.delegation(&eoa()), Some(delegate()));
+    assert_eq!(state.executable_code(&eoa()), Bytes::new());
+    state.set_code(&delegate(), delegate_code());
+    assert_eq!(state.executable_code(&eoa()), delegate_code());
+    assert_eq!(state.executable_address(&eoa()), delegate());
+
+    // Accounts without a designator don't delegate, even with code
+    assert_eq!(state.delegation(&delegate()), None);
+    state.set_code(&inspector(), Bytes::from_vec([designator(delegate()).as_slice(), &[0]].concat()));
+    assert_eq!(state.delegation(&inspector()), None);
+}
+
+// SLOAD and SSTORE act on the EOA's storage, not on the delegate's
+#[test]
+fn eoa_storage() {
+    let mut evm = evm();
+    evm.set_delegation(&eoa(), &delegate());
+    assert_eq!(call_eoa(&mut evm).stack, [Bytes32::from_u256(42.into())]);
+    assert_eq!(call_eoa(&mut evm).stack, [Bytes32::from_u256(43.into())]);
+
+    assert_eq!(evm.state().storage_load(&eoa(), 0.into()), Bytes32::from_u256(43.into()));
+    assert_eq!(evm.state().storage_load(&eoa(), 1.into()), Bytes32::from_address(eoa()));
+    assert!(evm.state().storage_load(&delegate(), 0.into()).is_zero());
+    assert!(evm.state().storage_load(&delegate(), 1.into()).is_zero());
+}
+
+// The EXTCODE* opcodes see the designator
+#[test]
+fn designator_code() {
+    let mut evm = evm();
+    evm.set_delegation(&eoa(), &delegate());
+    assert_eq!(inspect(&mut evm, EXTCODESIZE).stack, [Bytes32::from_u256(23.into())]);
+    let hash = Bytes32::from_slice(&Keccak256::digest(designator(delegate()).as_slice()));
+    assert_eq!(inspect(&mut evm, EXTCODEHASH).stack, [hash]);
+}
+
+// Delegating to the zero address clears the designator: the EOA has no code
+// again, and calls to it only transfer value
+#[test]
+fn removed_delegation() {
+    let mut evm = evm();
+    evm.set_delegation(&eoa(), &delegate());
+    call_eoa(&mut evm);
+    evm.set_delegation(&eoa(), &Address::zero());
+    assert!(evm.state().code(&eoa()).is_empty());
+    assert_eq!(evm.state().delegation(&eoa()), None);
+
+    evm.set_balance(&sender(), 5.into());
+    let call = Call::new(sender(), eoa(), eoa(), Bytes::new(), 5.into(), false);
+    let result = evm.transact(TxEnv::default(), call);
+    assert!(result.success, "{:?}", result.halt);
+    assert!(result.stack.is_empty());
+    assert_eq!(evm.state().balance(&eoa()), 5.into());
+    assert_eq!(evm.state().storage_load(&eoa(), 0.into()), Bytes32::from_u256(42.into()));
+    assert_eq!(inspect(&mut evm, EXTCODESIZE).stack, [Bytes32::zero()]);
+}
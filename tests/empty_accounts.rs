@@ -0,0 +1,97 @@
+// Accounts touched by a transaction that end it empty (no balance, nonce nor
+// code) are deleted since Spurious Dragon (EIP-161)
+use evm_from_scrust::asm::Assembler;
+use evm_from_scrust::*;
+use Opcode::*;
+
+mod common;
+use common::{contract, sender};
+
+fn relay() -> Address {
+    Address::from_slice(&[0xdd; 20])
+}
+
+fn fresh() -> Address {
+    Address::from_slice(&[0xee; 20])
+}
+
+// CALL to `address` sending `value`
+fn call_to(asm: Assembler, address: Address, value: u64) -> Assembler {
+    asm.push(0)
+        .push(0)
+        .push(0)
+        .push(0)
+        .push(value)
+        .push(Bytes32::from_address(address).to_u256())
+        .push(0xffff)
+        .op(CALL)
+}
+
+// Runs the contract, holding 1 wei, with `state` as the pre-state
+fn run(spec: SpecId, code: Bytes, mut state: State) -> Evm {
+    state.set_code(&contract(), code);
+    state.set_balance(&contract(), U256::one());
+    let mut evm = Evm::new(Block::default(), state).with_spec(spec);
+    let result = evm.transact(TxEnv::default(), Call::new(sender(), contract(), contract(), Bytes::new(), U256::zero(), false));
+    assert!(result.success, "{:?}", result.halt);
+    evm
+}
+
+#[test]
+fn zero_value_call() {
+    let evm = run(SpecId::default(), call_to(Assembler::new(), fresh(), 0).build(), State::new());
+    assert!(!evm.state().exists(&fresh()));
+
+    let evm = run(SpecId::default(), call_to(Assembler::new(), fresh(), 1).build(), State::new());
+    assert_eq!(evm.state().balance(&fresh()), U256::one());
+}
+
+// An empty account left over in the state goes once touched
+#[test]
+fn touched_empty_account() {
+    let mut state = State::new();
+    state.insert(fresh(), AccountState::new(fresh()));
+    let code = call_to(Assembler::new(), fresh(), 0).build();
+
+    let evm = run(SpecId::default(), code.clone(), state.clone());
+    assert!(!evm.state().exists(&fresh()));
+    // But not before Spurious Dragon
+    let evm = run(SpecId::Tangerine, code, state.clone());
+    assert!(evm.state().exists(&fresh()));
+
+    // Nor if it isn't touched
+    let evm = run(SpecId::default(), Bytes::new(), state);
+    assert!(evm.state().exists(&fresh()));
+}
+
+// A SELFDESTRUCT sending nothing touches its beneficiary
+#[test]
+fn selfdestruct_beneficiary() {
+    let mut state = State::new();
+    state.insert(fresh(), AccountState::new(fresh()));
+    state.set_code(&relay(), Assembler::new().push(Bytes32::from_address(fresh()).to_u256()).op(SELFDESTRUCT).build());
+    // Which only sends its balance since Cancun (EIP-6780)
+    let evm = run(SpecId::Cancun, call_to(Assembler::new(), relay(), 0).build(), state);
+    assert!(!evm.state().exists(&fresh()));
+    assert!(evm.state().exists(&relay()));
+}
+
+// The relay sends the value it gets on to the fresh account, then reverts:
+// the fresh account gets and loses the wei in the same transaction
+#[test]
+fn value_sent_back() {
+    let mut state = State::new();
+    state.set_code(&relay(), call_to(Assembler::new(), fresh(), 1).push(0).push(0).op(REVERT).build());
+    let evm = run(SpecId::default(), call_to(Assembler::new(), relay(), 1).build(), state);
+    assert!(!evm.state().exists(&fresh()));
+    assert_eq!(evm.state().balance(&contract()), U256::one());
+}
+
+// Contracts created empty stay (they'd have a nonce of 1)
+#[test]
+fn created_contract() {
+    let code = Assembler::new().push(0).push(0).push(0).op(CREATE).build();
+    let evm = run(SpecId::default(), code, State::new());
+    let created = evm_from_scrust::utils::create_address(&contract(), U256::zero());
+    assert!(evm.state().exists(&created));
+}
@@ -0,0 +1,154 @@
+// Runs the test suite `evm.json`. Cases can be filtered by a substring of their
+// name through `EVM_TEST_FILTER` (e.g. `EVM_TEST_FILTER=sstore cargo test`).
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+
+use evm_from_scrust::evmtest::{self, Evmtest, Outcome};
+
+#[test]
+fn evm_json() {
+    let path = format!("{}/{}", env!("CARGO_MANIFEST_DIR"), evmtest::SUITE);
+    let filter = std::env::var("EVM_TEST_FILTER").unwrap_or_default().to_lowercase();
+    let suite = evmtest::load(&path).unwrap();
+    let total = suite.len();
+    let tests: Vec<Evmtest> = suite
+        .into_iter()
+        .filter(|test| test.name.to_lowercase().contains(&filter))
+        .collect();
+
+    // Every case builds its own state, so they are run in parallel
+    let next = AtomicUsize::new(0);
+    let workers = thread::available_parallelism().map_or(1, |n| n.get());
+    let mut outcomes: Vec<(usize, Outcome)> = thread::scope(|scope| {
+        let handles: Vec<_> = (0..workers)
+            .map(|_| {
+                scope.spawn(|| {
+                    let mut outcomes = Vec::new();
+                    loop {
+                        let index = next.fetch_add(1, Ordering::Relaxed);
+                        match tests.get(index) {
+                            Some(test) => outcomes.push((index, test.run())),
+                            None => return outcomes,
+                        }
+                    }
+                })
+            })
+            .collect();
+        handles.into_iter().flat_map(|handle| handle.join().unwrap()).collect()
+    });
+    outcomes.sort_by_key(|(index, _)| *index);
+
+    let mut passed = 0;
+    let mut skipped = 0;
+    let mut failed = Vec::new();
+    for (index, outcome) in outcomes {
+        match outcome {
+            Outcome::Pass => passed += 1,
+            Outcome::Skip(_) => skipped += 1,
+            Outcome::Fail(report) => {
+                eprintln!("---- {} ----\n{}", tests[index].name, report);
+                failed.push(&tests[index].name);
+            }
+        }
+    }
+
+    eprintln!(
+        "evm.json: {} passed, {} failed, {} skipped ({} filtered out)",
+        passed,
+        failed.len(),
+        skipped,
+        total - tests.len()
+    );
+    assert!(failed.is_empty(), "failed tests: {:?}", failed);
+}
+
+#[test]
+fn corrupt_fixture() {
+    let fixture = |asm: &str| {
+        let json = format!(
+            r#"{{"name": "ADD", "hint": "", "code": {{"asm": "{}", "bin": "6001600201"}}, "expect": {{"stack": ["0x3"], "success": true}}}}"#,
+            asm
+        );
+        serde_json::from_str::<Evmtest>(&json).unwrap().run()
+    };
+    assert_eq!(fixture("PUSH1 1\\nPUSH1 2\\nADD"), Outcome::Pass);
+
+    let Outcome::Fail(report) = fixture("PUSH1 1\\nPUSH1 3\\nADD") else { panic!("not flagged") };
+    assert!(report.starts_with("Corrupt fixture: asm assembles to 6001600301, but bin is 6001600201"), "{}", report);
+    let Outcome::Fail(report) = fixture("PUSH1 1\\nPUSH1 2\\nADDD") else { panic!("not flagged") };
+    assert!(report.starts_with("Corrupt fixture: asm doesn't assemble: line 3"), "{}", report);
+}
+
+fn parse(json: &str) -> Evmtest {
+    Evmtest::parse(7, serde_json::from_str(json).unwrap())
+}
+
+fn failure(test: &Evmtest) -> String {
+    match test.run() {
+        Outcome::Fail(report) => report,
+        outcome => panic!("{} didn't fail: {:?}", test.name, outcome),
+    }
+}
+
+#[test]
+fn stack_hex() {
+    let test = parse(r#"{"name": "ADD", "hint": "", "code": {"bin": "60ff600201"}, "expect": {"stack": ["101"], "success": true}}"#);
+    assert_eq!(test.run(), Outcome::Pass);
+    let test = parse(r#"{"name": "ADD", "hint": "", "code": {"bin": "0x60ff600201"}, "expect": {"stack": ["0x101"], "success": true}}"#);
+    assert_eq!(test.run(), Outcome::Pass);
+    let test = parse(r#"{"name": "ADD", "hint": "", "code": {"bin": "60ff600201"}, "expect": {"stack": [257], "success": true}}"#);
+    assert_eq!(test.run(), Outcome::Pass);
+}
+
+#[test]
+fn missing_fields() {
+    // No `bin` is empty code, and a failing test needs no stack
+    let test = parse(r#"{"name": "STOP", "hint": "", "code": {"asm": null}, "expect": {"success": true}}"#);
+    assert_eq!(test.run(), Outcome::Pass);
+    let test = parse(r#"{"name": "INVALID", "hint": "", "code": {"bin": "fe"}, "expect": {"success": false}}"#);
+    assert_eq!(test.run(), Outcome::Pass);
+    let test = parse(r#"{"name": "INVALID", "hint": "", "code": {"bin": "fe"}, "expect": {"stack": null, "success": false}}"#);
+    assert_eq!(test.run(), Outcome::Pass);
+}
+
+#[test]
+fn broken_fixtures() {
+    let test = parse(r#"{"name": "bad bin", "hint": "", "code": {"bin": "6001zz"}, "expect": {"success": true}}"#);
+    let report = failure(&test);
+    assert!(report.starts_with("Invalid fixture bad bin: `code.bin`: invalid hex `6001zz`"), "{}", report);
+
+    let test = parse(r#"{"name": "bad stack", "hint": "", "expect": {"stack": ["0x1", "0xgg"], "success": true}}"#);
+    let report = failure(&test);
+    assert!(report.starts_with("Invalid fixture bad stack: `expect.stack`: invalid hex `0xgg`"), "{}", report);
+
+    let test = parse(&format!(r#"{{"name": "wide", "hint": "", "expect": {{"stack": ["0x1{}"], "success": true}}}}"#, "0".repeat(64)));
+    assert!(failure(&test).contains("doesn't fit in 256 bits"));
+
+    let test = parse(r#"{"name": "bad state", "hint": "", "state": {"0x1000000000000000000000000000000000000aaa": {"code": {"bin": "0xf"}}}, "expect": {"success": true}}"#);
+    let report = failure(&test);
+    assert!(report.contains("`state.0x1000000000000000000000000000000000000aaa.code.bin`"), "{}", report);
+
+    // Named after their index when the name is missing too
+    let test = parse(r#"{"hint": "", "expect": {"success": true}}"#);
+    assert_eq!(test.name, "#7");
+    assert!(failure(&test).contains("`name`"));
+}
+
+#[test]
+fn broken_fixture_in_suite() {
+    let path = std::env::temp_dir().join(format!("evm-json-{}.json", std::process::id()));
+    let suite = r#"[
+        {"name": "PUSH1", "hint": "", "code": {"bin": "6001"}, "expect": {"stack": ["0x1"], "success": true}},
+        {"name": "broken", "hint": "", "code": {"bin": "60"}, "expect": {"stack": [true], "success": true}},
+        {"name": "PUSH2", "hint": "", "code": {"bin": "610102"}, "expect": {"stack": ["0x102"], "success": true}}
+    ]"#;
+    std::fs::write(&path, suite).unwrap();
+    let tests = evmtest::load(path.to_str().unwrap()).unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    let outcomes: Vec<Outcome> = tests.iter().map(|test| test.run()).collect();
+    assert_eq!(outcomes[0], Outcome::Pass);
+    let report = failure(&tests[1]);
+    assert!(report.starts_with("Invalid fixture broken: `expect.stack[0]`"), "{}", report);
+    assert_eq!(outcomes[2], Outcome::Pass);
+}
@@ -0,0 +1,17 @@
+// Runs the programs of `examples/`, so that they keep building and working
+// with the public API
+#[path = "../examples/simple_add.rs"]
+mod simple_add;
+
+#[path = "../examples/deploy_and_call.rs"]
+mod deploy_and_call;
+
+#[path = "../examples/trace.rs"]
+mod trace;
+
+#[test]
+fn examples() {
+    simple_add::main();
+    deploy_and_call::main();
+    trace::main();
+}
@@ -0,0 +1,99 @@
+// Forked chain state, served by a mocked transport instead of a node
+#![cfg(feature = "fork")]
+use std::sync::{Arc, Mutex};
+
+use evm_from_scrust::asm::Assembler;
+use evm_from_scrust::fork::{ForkError, ForkHost, Transport};
+use evm_from_scrust::*;
+use serde_json::{json, Value};
+use Opcode::*;
+
+mod common;
+use common::contract;
+
+// Methods and params of the requests sent
+type Requests = Arc<Mutex<Vec<(String, Value)>>>;
+
+// Serves canned results, keeping every request it gets
+#[derive(Debug, Default)]
+struct Mock {
+    results: Vec<(&'static str, Result<Value, String>)>,
+    requests: Requests,
+}
+
+impl Transport for Mock {
+    fn request(&self, method: &str, params: Value) -> Result<Value, String> {
+        self.requests.lock().unwrap().push((method.to_string(), params.clone()));
+        let result = self.results.iter().find(|(name, _)| *name == method).map(|(_, result)| result.clone());
+        match method {
+            // Slot `key` holds `key + 1`
+            "eth_getStorageAt" if result.is_none() => {
+                let key = U256::from_str_radix(params[1].as_str().unwrap().trim_start_matches("0x"), 16).unwrap();
+                Ok(json!(format!("{:#x}", key + 1)))
+            }
+            _ => result.unwrap_or_else(|| Ok(json!("0x0"))),
+        }
+    }
+}
+
+fn fork_host(results: Vec<(&'static str, Result<Value, String>)>) -> (Arc<ForkHost>, Requests) {
+    let mock = Mock { results, ..Mock::default() };
+    let requests = mock.requests.clone();
+    (Arc::new(ForkHost::with_transport(Box::new(mock), U64::from(100))), requests)
+}
+
+// Adds the slots 0, 1 and 0 again
+fn contract_code() -> Bytes {
+    Assembler::new().push(0).op(SLOAD).push(1).op(SLOAD).op(ADD).push(0).op(SLOAD).op(ADD).build()
+}
+
+#[test]
+fn cached_requests() {
+    let code = format!("0x{}", hex::encode(contract_code().as_slice()));
+    let (host, requests) = fork_host(vec![("eth_getBalance", Ok(json!("0x64"))), ("eth_getCode", Ok(json!(code)))]);
+
+    // Two transactions, each on a new state on top of the same host
+    for _ in 0..2 {
+        let mut evm = Evm::new(Block::default(), State::with_host(host.clone()));
+        let call = Call::new(Address::zero(), contract(), contract(), Bytes::new(), U256::zero(), false);
+        let result = evm.transact(TxEnv::default(), call);
+        assert!(result.success, "{:?}", result.halt);
+        assert_eq!(result.stack, [Bytes32::from_u256(4.into())]);
+        assert_eq!(evm.state().balance(&contract()), 100.into());
+    }
+    assert_eq!(host.storage(&contract(), 1.into()), Bytes32::from_u256(2.into()));
+
+    // Each slot and each account field was fetched once, at the pinned block
+    let requests = requests.lock().unwrap();
+    for (index, request) in requests.iter().enumerate() {
+        assert!(!requests[..index].contains(request), "{:?} fetched twice", request);
+        assert_eq!(request.1.as_array().unwrap().last().unwrap(), &json!("0x64"));
+    }
+    let slots = requests.iter().filter(|(method, _)| method == "eth_getStorageAt").count();
+    assert_eq!(slots, 2);
+    assert!(host.errors().is_empty());
+}
+
+// Failed requests serve a missing account or a zero slot, are recorded, and
+// are retried on the next access
+#[test]
+fn failed_requests() {
+    let (host, requests) = fork_host(vec![("eth_getStorageAt", Err("connection refused".to_string()))]);
+    assert!(host.storage(&contract(), 0.into()).is_zero());
+    assert!(host.storage(&contract(), 0.into()).is_zero());
+    assert_eq!(requests.lock().unwrap().len(), 2);
+    let error = ForkError::Request { method: "eth_getStorageAt".to_string(), error: "connection refused".to_string() };
+    assert_eq!(host.errors(), [error.clone(), error]);
+    assert_eq!(
+        host.try_storage(&contract(), 0.into()).unwrap_err().to_string(),
+        "eth_getStorageAt failed: connection refused"
+    );
+
+    let (host, _) = fork_host(vec![("eth_getBalance", Ok(json!(100)))]);
+    assert!(host.account(&contract()).is_none());
+    assert_eq!(host.errors(), [ForkError::UnexpectedResult { method: "eth_getBalance".to_string(), result: "100".to_string() }]);
+
+    let (host, _) = fork_host(vec![("eth_getCode", Ok(json!("0xzz")))]);
+    assert!(host.account(&contract()).is_none());
+    assert_eq!(host.errors(), [ForkError::InvalidHex { method: "eth_getCode".to_string(), data: "0xzz".to_string() }]);
+}
@@ -0,0 +1,423 @@
+// Checks that every opcode charges its fixed gas. Each opcode is executed once,
+// after pushing zeros for its inputs, with arguments that don't incur dynamic
+// costs. The total must match the gas table.
+use evm_from_scrust::asm::Assembler;
+use evm_from_scrust::*;
+use Opcode::*;
+
+// Opcodes and their number of stack inputs. Those whose cost always has a
+// dynamic part (state and memory accesses, calls...) or that need a specific
+// setup (jumps, halting ones) are covered by the evm.json suite instead.
+const OPCODES: [(u8, usize); 53] = [
+    (0x00, 0), // STOP
+    (0x01, 2), // ADD
+    (0x02, 2), // MUL
+    (0x03, 2), // SUB
+    (0x04, 2), // DIV
+    (0x05, 2), // SDIV
+    (0x06, 2), // MOD
+    (0x07, 2), // SMOD
+    (0x08, 3), // ADDMOD
+    (0x09, 3), // MULMOD
+    (0x0A, 2), // EXP
+    (0x0B, 2), // SIGNEXTEND
+    (0x10, 2), // LT
+    (0x11, 2), // GT
+    (0x12, 2), // SLT
+    (0x13, 2), // SGT
+    (0x14, 2), // EQ
+    (0x15, 1), // ISZERO
+    (0x16, 2), // AND
+    (0x17, 2), // OR
+    (0x18, 2), // XOR
+    (0x19, 1), // NOT
+    (0x1A, 2), // BYTE
+    (0x1B, 2), // SHL
+    (0x1C, 2), // SHR
+    (0x1D, 2), // SAR
+    (0x20, 2), // SHA3
+    (0x30, 0), // ADDRESS
+    (0x32, 0), // ORIGIN
+    (0x33, 0), // CALLER
+    (0x34, 0), // CALLVALUE
+    (0x35, 1), // CALLDATALOAD
+    (0x36, 0), // CALLDATASIZE
+    (0x37, 3), // CALLDATACOPY
+    (0x38, 0), // CODESIZE
+    (0x39, 3), // CODECOPY
+    (0x3A, 0), // GASPRICE
+    (0x3D, 0), // RETURNDATASIZE
+    (0x40, 1), // BLOCKHASH
+    (0x41, 0), // COINBASE
+    (0x42, 0), // TIMESTAMP
+    (0x43, 0), // NUMBER
+    (0x44, 0), // PREVRANDAO
+    (0x45, 0), // GASLIMIT
+    (0x46, 0), // CHAINID
+    (0x47, 0), // SELFBALANCE
+    (0x48, 0), // BASEFEE
+    (0x50, 1), // POP
+    (0x58, 0), // PC
+    (0x59, 0), // MSIZE
+    (0x5A, 0), // GAS
+    (0x5B, 0), // JUMPDEST
+    (0x5F, 0), // PUSH0
+];
+
+const PUSH1_GAS: usize = 3;
+
+fn gas_used(code: Vec<u8>) -> usize {
+    let data = Bytes::from_vec(vec![0; 32]);
+    let call = Call::new(Address::zero(), Address::zero(), Address::zero(), data, U256::zero(), false);
+    let mut ctx = ExecutionContext::new(
+        TxEnv::default(),
+        call,
+        Block::default(),
+        State::new(),
+        Bytes::from_vec(code),
+    );
+    let result = ctx.run();
+    assert!(result.success);
+    result.gas_used
+}
+
+fn check(code: Vec<u8>, opcode: u8, inputs: usize) -> Option<String> {
+    let expected = inputs * PUSH1_GAS + Opcode::try_from(opcode).unwrap().fix_gas(SpecId::Shanghai);
+    let actual = gas_used(code);
+    if actual != expected {
+        Some(format!("{:#04X}: expected {} gas, actual {}", opcode, expected, actual))
+    } else {
+        None
+    }
+}
+
+fn with_inputs(inputs: usize, opcode: u8) -> Vec<u8> {
+    let mut code = [0x60, 0x00].repeat(inputs);
+    code.push(opcode);
+    code
+}
+
+#[test]
+fn fixed_gas() {
+    let mut mismatches: Vec<String> = OPCODES
+        .iter()
+        .filter_map(|&(opcode, inputs)| check(with_inputs(inputs, opcode), opcode, inputs))
+        .collect();
+
+    // PUSH1..PUSH32, with zeros as immediates
+    for n in 1..=32 {
+        let opcode = 0x5F + n;
+        let mut code = vec![opcode];
+        code.extend(vec![0; n as usize]);
+        mismatches.extend(check(code, opcode, 0));
+    }
+    // DUP1..DUP16 and SWAP1..SWAP16
+    for n in 1..=16 {
+        mismatches.extend(check(with_inputs(n, 0x7F + n as u8), 0x7F + n as u8, n));
+        mismatches.extend(check(with_inputs(n + 1, 0x8F + n as u8), 0x8F + n as u8, n + 1));
+    }
+
+    assert!(mismatches.is_empty(), "{:#?}", mismatches);
+}
+
+#[test]
+fn gas_result() {
+    // PUSH1 1, PUSH1 2, ADD, STOP
+    let code = Bytes::from_vec(vec![0x60, 0x01, 0x60, 0x02, 0x01, 0x00]);
+    let tx = TxEnv::new(Address::zero(), U256::zero(), 100.into(), U256::zero());
+    let result = ExecutionContext::new(tx, Call::default(), Block::default(), State::new(), code).run();
+    assert!(result.success);
+    assert_eq!(result.gas_used, 9);
+    assert_eq!(result.gas_refunded, 0);
+    assert_eq!(result.gas_limit, 100);
+    assert_eq!(result.gas_left(), 91);
+}
+
+// Static gas of every opcode in Shanghai (and of those added by Cancun), as
+// per the execution specs (EELS).
+// The dynamic part (memory expansion, cold accesses, copied words...) is
+// charged on top by each opcode.
+const SHANGHAI_GAS: [(u8, usize); 146] = [
+    (0x00, 0), // STOP
+    (0x01, 3), // ADD
+    (0x02, 5), // MUL
+    (0x03, 3), // SUB
+    (0x04, 5), // DIV
+    (0x05, 5), // SDIV
+    (0x06, 5), // MOD
+    (0x07, 5), // SMOD
+    (0x08, 8), // ADDMOD
+    (0x09, 8), // MULMOD
+    (0x0A, 10), // EXP
+    (0x0B, 5), // SIGNEXTEND
+    (0x10, 3), // LT
+    (0x11, 3), // GT
+    (0x12, 3), // SLT
+    (0x13, 3), // SGT
+    (0x14, 3), // EQ
+    (0x15, 3), // ISZERO
+    (0x16, 3), // AND
+    (0x17, 3), // OR
+    (0x18, 3), // XOR
+    (0x19, 3), // NOT
+    (0x1A, 3), // BYTE
+    (0x1B, 3), // SHL
+    (0x1C, 3), // SHR
+    (0x1D, 3), // SAR
+    (0x20, 30), // SHA3
+    (0x30, 2), // ADDRESS
+    (0x31, 100), // BALANCE
+    (0x32, 2), // ORIGIN
+    (0x33, 2), // CALLER
+    (0x34, 2), // CALLVALUE
+    (0x35, 3), // CALLDATALOAD
+    (0x36, 2), // CALLDATASIZE
+    (0x37, 3), // CALLDATACOPY
+    (0x38, 2), // CODESIZE
+    (0x39, 3), // CODECOPY
+    (0x3A, 2), // GASPRICE
+    (0x3B, 100), // EXTCODESIZE
+    (0x3C, 100), // EXTCODECOPY
+    (0x3D, 2), // RETURNDATASIZE
+    (0x3E, 3), // RETURNDATACOPY
+    (0x3F, 100), // EXTCODEHASH
+    (0x40, 20), // BLOCKHASH
+    (0x41, 2), // COINBASE
+    (0x42, 2), // TIMESTAMP
+    (0x43, 2), // NUMBER
+    (0x44, 2), // PREVRANDAO
+    (0x45, 2), // GASLIMIT
+    (0x46, 2), // CHAINID
+    (0x47, 5), // SELFBALANCE
+    (0x48, 2), // BASEFEE
+    (0x49, 3), // BLOBHASH (Cancun)
+    (0x4A, 2), // BLOBBASEFEE (Cancun)
+    (0x50, 2), // POP
+    (0x51, 3), // MLOAD
+    (0x52, 3), // MSTORE
+    (0x53, 3), // MSTORE8
+    (0x54, 100), // SLOAD
+    (0x55, 0), // SSTORE
+    (0x56, 8), // JUMP
+    (0x57, 10), // JUMPI
+    (0x58, 2), // PC
+    (0x59, 2), // MSIZE
+    (0x5A, 2), // GAS
+    (0x5B, 1), // JUMPDEST
+    (0x5F, 2), // PUSH0
+    (0x60, 3), // PUSH1
+    (0x61, 3), // PUSH2
+    (0x62, 3), // PUSH3
+    (0x63, 3), // PUSH4
+    (0x64, 3), // PUSH5
+    (0x65, 3), // PUSH6
+    (0x66, 3), // PUSH7
+    (0x67, 3), // PUSH8
+    (0x68, 3), // PUSH9
+    (0x69, 3), // PUSH10
+    (0x6A, 3), // PUSH11
+    (0x6B, 3), // PUSH12
+    (0x6C, 3), // PUSH13
+    (0x6D, 3), // PUSH14
+    (0x6E, 3), // PUSH15
+    (0x6F, 3), // PUSH16
+    (0x70, 3), // PUSH17
+    (0x71, 3), // PUSH18
+    (0x72, 3), // PUSH19
+    (0x73, 3), // PUSH20
+    (0x74, 3), // PUSH21
+    (0x75, 3), // PUSH22
+    (0x76, 3), // PUSH23
+    (0x77, 3), // PUSH24
+    (0x78, 3), // PUSH25
+    (0x79, 3), // PUSH26
+    (0x7A, 3), // PUSH27
+    (0x7B, 3), // PUSH28
+    (0x7C, 3), // PUSH29
+    (0x7D, 3), // PUSH30
+    (0x7E, 3), // PUSH31
+    (0x7F, 3), // PUSH32
+    (0x80, 3), // DUP1
+    (0x81, 3), // DUP2
+    (0x82, 3), // DUP3
+    (0x83, 3), // DUP4
+    (0x84, 3), // DUP5
+    (0x85, 3), // DUP6
+    (0x86, 3), // DUP7
+    (0x87, 3), // DUP8
+    (0x88, 3), // DUP9
+    (0x89, 3), // DUP10
+    (0x8A, 3), // DUP11
+    (0x8B, 3), // DUP12
+    (0x8C, 3), // DUP13
+    (0x8D, 3), // DUP14
+    (0x8E, 3), // DUP15
+    (0x8F, 3), // DUP16
+    (0x90, 3), // SWAP1
+    (0x91, 3), // SWAP2
+    (0x92, 3), // SWAP3
+    (0x93, 3), // SWAP4
+    (0x94, 3), // SWAP5
+    (0x95, 3), // SWAP6
+    (0x96, 3), // SWAP7
+    (0x97, 3), // SWAP8
+    (0x98, 3), // SWAP9
+    (0x99, 3), // SWAP10
+    (0x9A, 3), // SWAP11
+    (0x9B, 3), // SWAP12
+    (0x9C, 3), // SWAP13
+    (0x9D, 3), // SWAP14
+    (0x9E, 3), // SWAP15
+    (0x9F, 3), // SWAP16
+    (0xA0, 375), // LOG0
+    (0xA1, 375), // LOG1
+    (0xA2, 375), // LOG2
+    (0xA3, 375), // LOG3
+    (0xA4, 375), // LOG4
+    (0xF0, 32000), // CREATE
+    (0xF1, 100), // CALL
+    (0xF2, 100), // CALLCODE
+    (0xF3, 0), // RETURN
+    (0xF4, 100), // DELEGATECALL
+    (0xF5, 32000), // CREATE2
+    (0xFA, 100), // STATICCALL
+    (0xFD, 0), // REVERT
+    (0xFE, 0), // INVALID
+    (0xFF, 5000), // SELFDESTRUCT
+];
+
+#[test]
+fn gas_table() {
+    let mut mismatches = Vec::new();
+    for byte in 0..=u8::MAX {
+        let expected = SHANGHAI_GAS.iter().find(|(opcode, _)| *opcode == byte).map(|(_, gas)| *gas);
+        match (Opcode::try_from(byte), expected) {
+            (Ok(opcode), Some(gas)) if opcode.fix_gas(SpecId::Shanghai) != gas => {
+                mismatches.push(format!("{:#04X}: expected {} gas, actual {}", byte, gas, opcode.fix_gas(SpecId::Shanghai)))
+            }
+            (Ok(_), None) => mismatches.push(format!("{:#04X}: missing from the table", byte)),
+            (Err(_), Some(_)) => mismatches.push(format!("{:#04X}: not implemented", byte)),
+            _ => {}
+        }
+    }
+
+    assert!(mismatches.is_empty(), "{:#?}", mismatches);
+}
+
+// Gas charged by the last instruction of `code`, run with 32 bytes of call
+// data on top of `state`
+fn last_step_gas(code: Bytes, state: State) -> usize {
+    let data = Bytes::from_vec(vec![0; 32]);
+    let call = Call::new(Address::zero(), Address::zero(), Address::zero(), data, U256::zero(), false);
+    let mut ctx = ExecutionContext::new(TxEnv::default(), call, Block::default(), state, code).with_step_tracer();
+    let result = ctx.run();
+    assert!(result.success, "{:?}", result.halt);
+    ctx.steps().unwrap().last().unwrap().gas_cost
+}
+
+// Copies pay 3 gas per copied word, on top of their static gas and the memory
+// expansion
+#[test]
+fn copy_word_gas() {
+    for opcode in [CALLDATACOPY, CODECOPY] {
+        // 64 bytes into an empty memory: 2 words to copy, and to expand to
+        let code = Assembler::new().push(64).push(0).push(0).op(opcode).build();
+        assert_eq!(last_step_gas(code, State::new()), 3 + 6 + 6, "{:?}", opcode);
+        // 33 bytes, past the end of the data: still 2 words
+        let code = Assembler::new().push(33).push(10).push(0).op(opcode).build();
+        assert_eq!(last_step_gas(code, State::new()), 3 + 6 + 6, "{:?}", opcode);
+        // Nothing copied, nothing paid but the static gas
+        let code = Assembler::new().push(0).push(0).push(0).op(opcode).build();
+        assert_eq!(last_step_gas(code, State::new()), 3, "{:?}", opcode);
+    }
+
+    // Of a cold account
+    let other = Address::from_slice(&[0xcc; 20]);
+    let code = Assembler::new().push(64).push(0).push(0).push(other.to_u256()).op(EXTCODECOPY).build();
+    assert_eq!(last_step_gas(code, State::new()), 2600 + 6 + 6);
+
+    // 64 bytes returned by a call
+    let mut state = State::new();
+    state.set_code(&other, Assembler::new().push(64).push(0).op(RETURN).build());
+    let code = Assembler::new()
+        .push(0)
+        .push(0)
+        .push(0)
+        .push(0)
+        .push(0)
+        .push(other.to_u256())
+        .op(GAS)
+        .op(CALL)
+        .op(POP)
+        .push(64)
+        .push(0)
+        .push(0)
+        .op(RETURNDATACOPY)
+        .build();
+    assert_eq!(last_step_gas(code, state), 3 + 6 + 6);
+    assert_eq!(copy_gas(65), 9);
+}
+
+// An expansion the frame can't pay for halts before the memory grows, however
+// large it is
+#[test]
+fn unaffordable_memory_expansion() {
+    let huge = U256::from(0xffffffe0u64);
+    let codes = [
+        Assembler::new().push(huge).op(MLOAD),
+        Assembler::new().push(0).push(huge).op(MSTORE),
+        Assembler::new().push(0).push(huge).op(MSTORE8),
+        Assembler::new().push(huge).push(0).push(0).op(CALLDATACOPY),
+        Assembler::new().push(huge).push(0).op(SHA3),
+        Assembler::new().push(huge).push(0).op(RETURN),
+    ];
+    for code in codes {
+        let code = code.build();
+        let call = Call::new(Address::zero(), Address::zero(), Address::zero(), Bytes::new(), U256::zero(), false);
+        let mut ctx = ExecutionContext::new(TxEnv::default(), call, Block::default(), State::new(), code.clone());
+        let result = ctx.run();
+        assert_eq!(result.halt, Some(Halt::OutOfGas), "{:?}", code);
+        assert_eq!(result.peak_memory_bytes, 0, "{:?}", code);
+        assert_eq!(ctx.memory.size(), 0, "{:?}", code);
+    }
+}
+
+// EXP pays per byte of its exponent
+#[test]
+fn exp_byte_gas() {
+    let exp = |exponent: u64| Assembler::new().push(exponent).push(2).op(EXP).build();
+    assert_eq!(last_step_gas(exp(0), State::new()), 10);
+    assert_eq!(last_step_gas(exp(2), State::new()), 10 + 50);
+    assert_eq!(last_step_gas(exp(0xff), State::new()), 10 + 50);
+    assert_eq!(last_step_gas(exp(0x100), State::new()), 10 + 2 * 50);
+    assert_eq!(last_step_gas(Assembler::new().push(U256::MAX).push(2).op(EXP).build(), State::new()), 10 + 32 * 50);
+
+    // PUSH2 2 PUSH2 2 EXP
+    assert_eq!(gas_used(vec![0x61, 0x00, 0x02, 0x61, 0x00, 0x02, 0x0a]), 66);
+
+    // 10 per byte before Spurious Dragon (EIP-160)
+    assert_eq!(exp_gas(SpecId::Tangerine, U256::from(0x100)), 20);
+    assert_eq!(exp_gas(SpecId::SpuriousDragon, U256::from(0x100)), 100);
+    assert_eq!(exp_gas(SpecId::default(), U256::zero()), 0);
+}
+
+// What a successful static call accessed stays warm for its caller
+#[test]
+fn static_call_accesses() {
+    let other = Address::from_slice(&[0x0b; 20]);
+    let target = Address::from_slice(&[0x0c; 20]);
+    let mut state = State::new();
+    state.set_code(&other, Assembler::new().push(target.to_u256()).op(BALANCE).build());
+    let code = Assembler::new()
+        .push(0)
+        .push(0)
+        .push(0)
+        .push(0)
+        .push(other.to_u256())
+        .op(GAS)
+        .op(STATICCALL)
+        .push(target.to_u256())
+        .op(BALANCE)
+        .build();
+    assert_eq!(last_step_gas(code, state), WARM_STORAGE_READ_COST);
+}
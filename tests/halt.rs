@@ -0,0 +1,155 @@
+// Every failure class surfaces as its own `Halt`, and only an explicit REVERT
+// returns data
+use evm_from_scrust::asm::Assembler;
+use evm_from_scrust::*;
+use Opcode::*;
+
+fn run_with(call: Call, spec: SpecId, code: Bytes) -> EvmResult {
+    let mut evm = ExecutionContext::new(TxEnv::default(), call, Block::default(), State::default(), code).with_spec(spec);
+    evm.run()
+}
+
+fn run(code: Assembler) -> EvmResult {
+    run_with(Call::default(), SpecId::default(), code.build())
+}
+
+fn assert_halt(result: &EvmResult, halt: Halt) {
+    assert!(!result.success);
+    assert_eq!(result.halt, Some(halt));
+    if result.halt != Some(Halt::Revert) {
+        assert!(result.result.is_empty());
+    }
+}
+
+#[test]
+fn success() {
+    let result = run(Assembler::new().push(1).op(STOP));
+    assert!(result.success);
+    assert_eq!(result.halt, None);
+}
+
+// STOP in the middle of the code ends the frame there, with no return data
+#[test]
+fn stop_mid_code() {
+    let result = run(Assembler::new().push(1).op(STOP).push(2));
+    assert!(result.success);
+    assert_eq!(result.stack, [Bytes32::from_u256(1.into())]);
+    assert!(result.result.is_empty());
+}
+
+#[test]
+fn stack_underflow() {
+    assert_halt(&run(Assembler::new().push(1).op(ADD)), Halt::StackUnderflow);
+    assert_halt(&run(Assembler::new().op(POP)), Halt::StackUnderflow);
+    assert_halt(&run(Assembler::new().push(1).op(DUP2)), Halt::StackUnderflow);
+    assert_halt(&run(Assembler::new().push(1).op(SWAP1)), Halt::StackUnderflow);
+}
+
+#[test]
+fn stack_overflow() {
+    let result = run(Assembler::new().push(1).jumpdest_label("loop").push(1).jump_to("loop"));
+    assert_halt(&result, Halt::StackOverflow);
+    assert_eq!(result.peak_stack_depth, 1024);
+}
+
+#[test]
+fn invalid_jump() {
+    // Offset 1 is the immediate of PUSH1 0x5b, not a JUMPDEST
+    assert_halt(&run(Assembler::new().push(0x5b).push(1).op(JUMP)), Halt::InvalidJump);
+    assert_halt(&run(Assembler::new().push(1).push(2).op(JUMPI)), Halt::InvalidJump);
+}
+
+#[test]
+fn invalid_opcode() {
+    assert_halt(&run(Assembler::new().op(INVALID)), Halt::InvalidOpcode);
+    assert_halt(&run(Assembler::new().bytes(&[0x0c])), Halt::InvalidOpcode);
+    // PUSH0 only exists since Shanghai
+    let code = Assembler::new().op(PUSH0).build();
+    assert_halt(&run_with(Call::default(), SpecId::London, code), Halt::InvalidOpcode);
+}
+
+#[test]
+fn static_call_violation() {
+    let call = Call::new(Address::zero(), Address::zero(), Address::zero(), Bytes::new(), U256::zero(), true);
+    let code = Assembler::new().push(1).push(0).op(SSTORE).build();
+    assert_halt(&run_with(call, SpecId::default(), code), Halt::StaticCallViolation);
+}
+
+#[test]
+fn memory_limit_exceeded() {
+    let result = run(Assembler::new().push(1).push(U256::MAX).op(MSTORE));
+    assert_halt(&result, Halt::MemoryLimitExceeded);
+}
+
+#[test]
+fn out_of_gas() {
+    // With the memory limit lifted, the memory cost of hashing 2^63 bytes
+    // overflows instead of wrapping around
+    let code = Assembler::new().push(U256::one() << 63).push(0).op(SHA3).build();
+    let mut evm = ExecutionContext::new(TxEnv::default(), Call::default(), Block::default(), State::default(), code)
+        .with_memory_limit(usize::MAX);
+    assert_halt(&evm.run(), Halt::OutOfGas);
+}
+
+#[test]
+fn insufficient_balance() {
+    let sender = Address::from_slice(&[0xaa; 20]);
+    let recipient = Address::from_slice(&[0xbb; 20]);
+    let mut state = State::new();
+    state.insert(sender, AccountState::from_parts(sender, 5.into(), U256::zero(), Bytes::new()));
+    let call = Call::new(sender, recipient, recipient, Bytes::new(), 6.into(), false);
+
+    let (result, receipt, _) = ExecutionContext::transact(TxEnv::default(), call, Block::default(), SpecId::default(), state, 0);
+    assert_halt(&result, Halt::InsufficientBalance { address: sender, balance: 5.into(), value: 6.into() });
+    assert!(!receipt.is_success());
+}
+
+#[test]
+fn revert() {
+    let result = run(Assembler::new().push(0xaa).push(0).op(MSTORE8).push(1).push(0).op(REVERT));
+    assert_halt(&result, Halt::Revert);
+    assert_eq!(result.result.as_slice(), &[0xaa]);
+}
+
+// Exceptional halts use up all the gas of the frame, a REVERT only what it spent
+#[test]
+fn invalid_uses_all_gas() {
+    let result = run(Assembler::new().push(1).op(INVALID));
+    assert_halt(&result, Halt::InvalidOpcode);
+    assert_eq!(result.gas_used, result.gas_limit);
+
+    let result = run(Assembler::new().push(0).push(0).op(REVERT));
+    assert_halt(&result, Halt::Revert);
+    assert_eq!(result.gas_used, 6);
+}
+
+// Calls a child running `child` with 10k gas, returning the parent's result
+fn call_child(child: Assembler) -> EvmResult {
+    let address = Address::from_slice(&[0xcc; 20]);
+    let mut state = State::new();
+    state.insert(address, AccountState::from_parts(address, U256::zero(), U256::zero(), child.build()));
+    let code = Assembler::new()
+        .push(0)
+        .push(0)
+        .push(0)
+        .push(0)
+        .push(0)
+        .push(address.to_u256())
+        .push(10_000)
+        .op(CALL)
+        .build();
+    let mut evm = ExecutionContext::new(TxEnv::default(), Call::default(), Block::default(), state, code);
+    evm.run()
+}
+
+#[test]
+fn child_gas() {
+    let invalid = call_child(Assembler::new().op(INVALID));
+    let revert = call_child(Assembler::new().push(0).push(0).op(REVERT));
+    assert!(invalid.success && revert.success);
+    assert_eq!(invalid.stack, [Bytes32::zero()]);
+    assert_eq!(revert.stack, [Bytes32::zero()]);
+    // The parent loses the 10k it forwarded to INVALID, but only the 6 spent
+    // by the REVERT
+    assert_eq!(invalid.gas_used - revert.gas_used, 10_000 - 6);
+}
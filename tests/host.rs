@@ -0,0 +1,130 @@
+// Hosts are used as trait objects: boxed, shared or borrowed, and swapped
+// between transactions
+use std::sync::Arc;
+
+use evm_from_scrust::asm::Assembler;
+use evm_from_scrust::*;
+use Opcode::*;
+
+mod common;
+use common::contract;
+
+// Host whose every slot holds the same value, and whose accounts all run the
+// same code
+#[derive(Debug)]
+struct Uniform {
+    code: Bytes,
+    value: Bytes32,
+}
+
+impl Host for Uniform {
+    fn account(&self, address: &Address) -> Option<AccountState> {
+        Some(AccountState::from_parts(*address, U256::zero(), U256::zero(), self.code.clone()))
+    }
+
+    fn storage(&self, _address: &Address, _key: U256) -> Bytes32 {
+        self.value
+    }
+}
+
+fn uniform(value: u64) -> Uniform {
+    // Returns the slot 0
+    let code = Assembler::new()
+        .push(0)
+        .op(SLOAD)
+        .push(0)
+        .op(MSTORE)
+        .push(32)
+        .push(0)
+        .op(RETURN)
+        .build();
+    Uniform { code, value: Bytes32::from_u256(value.into()) }
+}
+
+// Slot 0 of `contract()` through a generic host
+fn slot<H: Host>(host: H) -> Bytes32 {
+    host.storage(&contract(), U256::zero())
+}
+
+#[test]
+fn forwarding() {
+    let host = uniform(1);
+    assert_eq!(slot(&host), Bytes32::from_u256(1.into()));
+    let boxed: Box<dyn Host> = Box::new(uniform(2));
+    assert_eq!(slot(&boxed), Bytes32::from_u256(2.into()));
+    assert_eq!(slot(boxed), Bytes32::from_u256(2.into()));
+    let shared: Arc<dyn Host> = Arc::new(uniform(3));
+    assert_eq!(slot(shared.clone()), Bytes32::from_u256(3.into()));
+    let borrowed: &dyn Host = &*shared;
+    assert_eq!(slot(borrowed), Bytes32::from_u256(3.into()));
+}
+
+#[test]
+fn swap_at_runtime() {
+    let hosts: Vec<Box<dyn Host>> = (1..=3).map(|value| Box::new(uniform(value)) as Box<dyn Host>).collect();
+    let mut evm = Evm::new(Block::default(), State::default()).disable_nonce_check(true);
+    for (value, host) in (1..=3).zip(hosts) {
+        evm.set_host(Arc::new(host));
+        let call = Call::new(Address::zero(), contract(), contract(), Bytes::new(), U256::zero(), false);
+        let result = evm.transact(TxEnv::default(), call);
+        assert!(result.success, "{:?}", result.halt);
+        assert_eq!(result.result.as_slice(), Bytes32::from_u256(U256::from(value)).as_slice());
+    }
+}
+
+// Code provided by the host stops at a STOP in its middle
+#[test]
+fn stop_mid_code() {
+    let code = Assembler::new().push(1).op(STOP).push(2).build();
+    let mut evm = Evm::new(Block::default(), State::with_host(Arc::new(Uniform { code, value: Bytes32::zero() })));
+    let call = Call::new(Address::zero(), contract(), contract(), Bytes::new(), U256::zero(), false);
+    let result = evm.transact(TxEnv::default(), call);
+    assert!(result.success, "{:?}", result.halt);
+    assert_eq!(result.stack, [Bytes32::from_u256(1.into())]);
+    assert!(result.result.is_empty());
+}
+
+// Host for which every access is cold, even a repeated one
+#[derive(Debug)]
+struct AlwaysCold(Uniform);
+
+impl Host for AlwaysCold {
+    fn account(&self, address: &Address) -> Option<AccountState> {
+        self.0.account(address)
+    }
+
+    fn storage(&self, address: &Address, key: U256) -> Bytes32 {
+        self.0.storage(address, key)
+    }
+
+    fn is_cold_account(&self, _address: &Address, _first_access: bool) -> bool {
+        true
+    }
+
+    fn is_cold_slot(&self, _address: &Address, _key: U256, _first_access: bool) -> bool {
+        true
+    }
+}
+
+// Gas charged by each of two accesses with `opcode` to `operand`
+fn access_gas(host: Arc<dyn Host>, opcode: Opcode, operand: U256) -> [usize; 2] {
+    let code = Assembler::new().push(operand).op(opcode).op(POP).push(operand).op(opcode).build();
+    let call = Call::new(Address::zero(), contract(), contract(), Bytes::new(), U256::zero(), false);
+    let mut ctx = ExecutionContext::new(TxEnv::default(), call, Block::default(), State::with_host(host), code).with_step_tracer();
+    let result = ctx.run();
+    assert!(result.success, "{:?}", result.halt);
+    let steps = ctx.steps().unwrap();
+    [steps[1].gas_cost, steps[steps.len() - 1].gas_cost]
+}
+
+#[test]
+fn cold_accesses() {
+    let other = Address::from_slice(&[0xdd; 20]).to_u256();
+    // Warm after the first access by default
+    assert_eq!(access_gas(Arc::new(uniform(1)), BALANCE, other), [2600, 100]);
+    assert_eq!(access_gas(Arc::new(uniform(1)), SLOAD, U256::zero()), [2100, 100]);
+    // Every time if the host has every access cold
+    assert_eq!(access_gas(Arc::new(AlwaysCold(uniform(1))), BALANCE, other), [2600, 2600]);
+    assert_eq!(access_gas(Arc::new(AlwaysCold(uniform(1))), SLOAD, U256::zero()), [2100, 2100]);
+    assert_eq!(access_gas(Arc::new(AlwaysCold(uniform(1))), EXTCODESIZE, other), [2600, 2600]);
+}
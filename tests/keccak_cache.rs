@@ -0,0 +1,86 @@
+// The cache of SHA3 results enabled by `CfgEnv::keccak_cache`
+use evm_from_scrust::asm::Assembler;
+use evm_from_scrust::evmtest::{self, Outcome};
+use evm_from_scrust::*;
+use Opcode::*;
+
+fn run(code: Bytes, keccak_cache: bool) -> ExecutionContext {
+    let cfg = CfgEnv { keccak_cache, ..CfgEnv::default() };
+    let mut evm = ExecutionContext::new(TxEnv::default(), Call::default(), Block::default(), State::default(), code)
+        .with_cfg(cfg);
+    assert!(evm.run().success);
+    evm
+}
+
+fn word(value: u64) -> Vec<u8> {
+    Bytes32::from_u256(value.into()).as_bytes().as_slice().to_vec()
+}
+
+// Hashes 1, then 2 over it (same offset, other contents), then 1 again at
+// another offset
+#[test]
+fn keyed_by_contents() {
+    let code = Assembler::new()
+        .push(1)
+        .push(0)
+        .op(MSTORE)
+        .push(32)
+        .push(0)
+        .op(SHA3)
+        .push(2)
+        .push(0)
+        .op(MSTORE)
+        .push(32)
+        .push(0)
+        .op(SHA3)
+        .push(1)
+        .push(64)
+        .op(MSTORE)
+        .push(32)
+        .push(64)
+        .op(SHA3)
+        .build();
+
+    let cached = run(code.clone(), true);
+    let uncached = run(code, false);
+    assert_eq!(cached.stack.items_top_first(), uncached.stack.items_top_first());
+
+    let [third, second, first] = <[Bytes32; 3]>::try_from(cached.stack.items_top_first()).unwrap();
+    assert_eq!(first, keccak256(&word(1)));
+    assert_eq!(second, keccak256(&word(2)));
+    assert_eq!(third, first);
+    // The third hash was a hit
+    assert_eq!(cached.keccak_cache.unwrap().len(), 2);
+    assert!(uncached.keccak_cache.is_none());
+}
+
+#[test]
+fn evicts_least_recently_used() {
+    let mut cache = KeccakCache::new();
+    for value in 0..KECCAK_CACHE_SIZE as u64 {
+        cache.hash(&word(value));
+    }
+    // Touches the first input, so that the second one is evicted instead
+    cache.hash(&word(0));
+    cache.hash(&word(1000));
+    assert_eq!(cache.len(), KECCAK_CACHE_SIZE);
+
+    for value in (0..KECCAK_CACHE_SIZE as u64).chain([1000]) {
+        assert_eq!(cache.hash(&word(value)), keccak256(&word(value)));
+    }
+    assert_eq!(cache.len(), KECCAK_CACHE_SIZE);
+
+    let long = vec![0xab; KECCAK_CACHE_MAX_INPUT + 1];
+    assert_eq!(cache.hash(&long), keccak256(&long));
+}
+
+// Every case of evm.json has the same outcome with the cache
+#[test]
+fn evm_json() {
+    let path = format!("{}/{}", env!("CARGO_MANIFEST_DIR"), evmtest::SUITE);
+    for test in evmtest::load(&path).unwrap() {
+        let outcome = test.run_with(|cfg| cfg.keccak_cache = true);
+        assert_eq!(outcome, test.run(), "{}", test.name);
+        assert!(!matches!(outcome, Outcome::Fail(_)), "{}", test.name);
+    }
+}
@@ -0,0 +1,118 @@
+// Logs of nested calls: kept in execution order, and dropped with the frame
+// that emitted them when it fails. Also the parsing of the expected logs.
+use evm_from_scrust::asm::Assembler;
+use evm_from_scrust::*;
+use Opcode::*;
+
+fn parent() -> Address {
+    Address::from_slice(&[0xaa; 20])
+}
+
+fn child() -> Address {
+    Address::from_slice(&[0xbb; 20])
+}
+
+// LOG0 of the single byte `byte`
+fn log(code: Assembler, byte: u64) -> Assembler {
+    code.push(byte).push(0).op(MSTORE8).push(1).push(0).op(LOG0)
+}
+
+// Logs 0x0a, calls the child, then logs 0x0c
+fn parent_code() -> Bytes {
+    let code = log(Assembler::new(), 0x0a);
+    let child = U256::from_big_endian(child().as_slice());
+    let code = code.push(0).push(0).push(0).push(0).push(0).push(child).op(GAS).op(CALL).op(POP);
+    log(code, 0x0c).build()
+}
+
+// Logs 0x0b, then ends with `last`
+fn child_code(last: Opcode) -> Bytes {
+    log(Assembler::new(), 0x0b).push(0).push(0).op(last).build()
+}
+
+fn logs(child_ends: Opcode) -> Vec<Bytes> {
+    let mut evm = Evm::new(Block::default(), State::new());
+    evm.set_code(&parent(), parent_code());
+    evm.set_code(&child(), child_code(child_ends));
+    let call = Call::new(Address::zero(), parent(), parent(), Bytes::new(), U256::zero(), false);
+    let result = evm.transact(TxEnv::default(), call);
+    assert!(result.success, "{:?}", result.halt);
+    assert_eq!(result.logs, evm.receipts()[0].logs);
+    result.logs.into_iter().map(|log| log.data).collect()
+}
+
+#[test]
+fn execution_order() {
+    let data: Vec<Bytes> = [0x0a, 0x0b, 0x0c].iter().map(|byte| Bytes::from_vec(vec![*byte])).collect();
+    assert_eq!(logs(RETURN), data);
+}
+
+#[test]
+fn reverted_frame() {
+    let data: Vec<Bytes> = [0x0a, 0x0c].iter().map(|byte| Bytes::from_vec(vec![*byte])).collect();
+    assert_eq!(logs(REVERT), data);
+}
+
+#[test]
+fn reverted_transaction() {
+    let mut evm = Evm::new(Block::default(), State::new());
+    evm.set_code(&child(), child_code(REVERT));
+    let call = Call::new(Address::zero(), child(), child(), Bytes::new(), U256::zero(), false);
+    let result = evm.transact(TxEnv::default(), call);
+    assert!(!result.success);
+    assert!(result.logs.is_empty());
+    assert!(evm.logs().is_empty());
+}
+
+fn parse(json: &str) -> Log {
+    Log::from_json(&serde_json::from_str(json).unwrap()).unwrap()
+}
+
+#[test]
+fn empty_data_and_topics() {
+    let address = Address::from_slice(&[0x10; 20]);
+    let expected = Log::new(address, Bytes::new());
+    let json = r#"{"address": "0x1010101010101010101010101010101010101010", "data": "0x", "topics": []}"#;
+    assert_eq!(parse(json), expected);
+    let json = r#"{"address": "0x1010101010101010101010101010101010101010", "data": "", "topics": ["", "0x"]}"#;
+    assert_eq!(parse(json), expected);
+    let json = r#"{"address": "0x1010101010101010101010101010101010101010"}"#;
+    assert_eq!(parse(json), expected);
+
+    // Wider than a word
+    let json = format!(r#"{{"address": "0x1010101010101010101010101010101010101010", "topics": ["0x{}"]}}"#, "01".repeat(33));
+    assert!(Log::from_json(&serde_json::from_str(&json).unwrap()).is_err());
+}
+
+// Serialized like eth_getLogs, against `tests/fixtures/rpc_logs.json`
+#[test]
+fn rpc_logs() {
+    // LOG1 of 0x0a with topic 0x01, then LOG2 of nothing with topics 0x02, 0x03
+    let emitter = Address::from_slice(&[0xab; 20]);
+    let code = Assembler::new()
+        .push(0x0a)
+        .push(0)
+        .op(MSTORE8)
+        .push(1)
+        .push(1)
+        .push(0)
+        .op(LOG1)
+        .push(3)
+        .push(2)
+        .push(0)
+        .push(0)
+        .op(LOG2)
+        .build();
+    let mut evm = Evm::new(Block::default(), State::new()).disable_nonce_check(true);
+    // So that the logs come from the second transaction of the block
+    evm.transact(TxEnv::default(), Call::new(Address::zero(), parent(), parent(), Bytes::new(), U256::zero(), false));
+    evm.set_code(&emitter, code);
+    let result = evm.transact(TxEnv::default(), Call::new(Address::zero(), emitter, emitter, Bytes::new(), U256::zero(), false));
+    assert!(result.success, "{:?}", result.halt);
+
+    let rpc_logs = evm.receipts()[1].rpc_logs(1);
+    assert_eq!(rpc_logs[1], result.logs[1].to_rpc(1, 1));
+    let path = format!("{}/tests/fixtures/rpc_logs.json", env!("CARGO_MANIFEST_DIR"));
+    let expected = std::fs::read_to_string(path).unwrap();
+    assert_eq!(serde_json::to_string_pretty(&rpc_logs).unwrap(), expected.trim_end());
+}
@@ -0,0 +1,59 @@
+// Memory grows in whole words on reads as well as writes, and reports the
+// words an access would add
+use evm_from_scrust::*;
+
+#[test]
+fn load_past_the_end() {
+    let mut memory = Memory::new();
+    assert_eq!(memory.expansion_words(100, 32), 5);
+    assert_eq!(memory.load(100, 32), Bytes::from_vec(vec![0; 32]));
+    assert_eq!(memory.size(), 160);
+    // Covered now
+    assert_eq!(memory.expansion_words(100, 32), 0);
+    assert_eq!(memory.expansion_cost(100, 32), 0);
+    assert_eq!(memory.load(100, 32).len(), 32);
+    assert_eq!(memory.size(), 160);
+}
+
+#[test]
+fn load_after_store() {
+    let mut memory = Memory::new();
+    let _ = memory.set_byte(0, 0xff);
+    // Zero-filled past the byte written, and grown to the second word
+    let data = memory.load(0, 40);
+    assert_eq!(data.len(), 40);
+    assert_eq!(data[0], 0xff);
+    assert!(data.as_slice()[1..].iter().all(|byte| *byte == 0));
+    assert_eq!(memory.size(), 64);
+}
+
+#[test]
+fn empty_accesses() {
+    let mut memory = Memory::new();
+    assert_eq!(memory.expansion_words(1 << 40, 0), 0);
+    assert!(memory.load(1 << 40, 0).is_empty());
+    assert_eq!(memory.size(), 0);
+}
+
+// The word-level accesses agree with the general ones
+#[test]
+fn words_and_bytes() {
+    let word = Bytes32::from_slice(&(1..=32).collect::<Vec<u8>>());
+    for offset in [0, 1, 31, 32, 100] {
+        let mut by_word = Memory::new();
+        let mut by_bytes = Memory::new();
+        let word_cost = by_word.set_word(offset, &word);
+        let bytes_cost = by_bytes.store(offset, word.as_bytes());
+        assert_eq!(word_cost, bytes_cost, "offset {}", offset);
+        assert_eq!(by_word.size(), by_bytes.size(), "offset {}", offset);
+        assert_eq!(by_word.get_word(offset), by_bytes.load(offset, 32).as_bytes32(), "offset {}", offset);
+        assert_eq!(by_word.get_word(offset), word);
+        // Straddling the word written
+        assert_eq!(by_word.get_word(offset + 16), by_bytes.load(offset + 16, 32).as_bytes32());
+        assert_eq!(by_word.size(), by_bytes.size());
+
+        let _ = by_word.set_byte(offset + 40, 0xab);
+        let _ = by_bytes.store(offset + 40, Bytes::from_vec(vec![0xab]));
+        assert_eq!(by_word.load(0, by_word.size()), by_bytes.load(0, by_bytes.size()));
+    }
+}
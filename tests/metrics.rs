@@ -0,0 +1,62 @@
+// Opcode-level metrics of an execution
+use evm_from_scrust::asm::Assembler;
+use evm_from_scrust::*;
+use Opcode::*;
+
+fn profile(code: Assembler) -> Metrics {
+    let mut evm = ExecutionContext::new(TxEnv::default(), Call::default(), Block::default(), State::default(), code.build())
+        .with_metrics();
+    assert!(evm.run().success);
+    evm.metrics().unwrap().clone()
+}
+
+#[test]
+fn adds() {
+    let code = (0..1000).fold(Assembler::new().push(1), |code, _| code.push(1).op(ADD));
+    let metrics = profile(code.push(0).op(MSTORE));
+
+    assert_eq!(metrics.count(ADD), 1000);
+    assert_eq!(metrics.gas(ADD), 3000);
+    assert_eq!(metrics.count(PUSH1), 1002);
+    // The MSTORE expands the memory to a word
+    assert_eq!(metrics.gas(MSTORE), 3 + 3);
+    assert_eq!(metrics.max_memory_bytes, 32);
+    assert_eq!(metrics.max_call_depth, 0);
+
+    // The most expensive first
+    assert_eq!(metrics.report(), [(PUSH1, 1002, 3006), (ADD, 1000, 3000), (MSTORE, 1, 6)]);
+    let table = metrics.to_string();
+    assert!(table.lines().nth(2).unwrap().starts_with("ADD "), "{}", table);
+}
+
+#[test]
+fn storage() {
+    let code = Assembler::new().push(1).push(0).op(SSTORE).push(0).op(SLOAD).push(1).op(SLOAD);
+    let metrics = profile(code);
+    assert_eq!(metrics.storage_reads, 2);
+    assert_eq!(metrics.storage_writes, 1);
+}
+
+#[test]
+fn disabled() {
+    let mut evm = ExecutionContext::new(TxEnv::default(), Call::default(), Block::default(), State::default(), Bytes::new());
+    evm.run();
+    assert!(evm.metrics().is_none());
+}
+
+#[test]
+fn subcalls() {
+    let callee = Address::from_slice(&[0xcc; 20]);
+    let mut state = State::default();
+    state.set_code(&callee, Assembler::new().push(1).push(64).op(MSTORE).build());
+    let code = Assembler::new().push(0).push(0).push(0).push(0).push(0).push(callee.to_u256()).op(GAS).op(CALL).build();
+
+    let mut evm = ExecutionContext::new(TxEnv::default(), Call::default(), Block::default(), state, code).with_metrics();
+    assert!(evm.run().success);
+    let metrics = evm.metrics().unwrap();
+    assert_eq!(metrics.count(CALL), 1);
+    assert_eq!(metrics.count(MSTORE), 1);
+    assert_eq!(metrics.count(PUSH1), 7);
+    assert_eq!(metrics.max_call_depth, 1);
+    assert_eq!(metrics.max_memory_bytes, 96);
+}
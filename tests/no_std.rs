@@ -0,0 +1,25 @@
+// Smoke test of the interpreter core from a `no_std` crate, using only what
+// the core offers (`cargo build --no-default-features` builds it without std,
+// e.g. for wasm32-unknown-unknown)
+#![no_std]
+
+extern crate alloc;
+
+use alloc::vec;
+use evm_from_scrust::*;
+
+#[test]
+fn interpreter_core() {
+    // 2 + 3, stored at slot 0
+    let code = Bytes::from_vec(vec![0x60, 0x02, 0x60, 0x03, 0x01, 0x80, 0x60, 0x00, 0x55]);
+    let contract = Address::from_slice(&[0xcc; 20]);
+    let mut state = State::new();
+    state.set_code(&contract, code);
+
+    let mut evm = Evm::new(Block::default(), state);
+    let call = Call::new(Address::zero(), contract, contract, Bytes::new(), U256::zero(), false);
+    let result = evm.transact(TxEnv::default(), call);
+    assert!(result.success);
+    assert_eq!(result.stack, [Bytes32::from_u256(5.into())]);
+    assert_eq!(evm.state().storage_load(&contract, U256::zero()), Bytes32::from_u256(5.into()));
+}
@@ -0,0 +1,63 @@
+// Numeric JSON fields accept JSON numbers, decimal strings and 0x-hex strings
+use evm_from_scrust::*;
+
+const ENCODINGS: [&str; 3] = ["1000", "\"1000\"", "\"0x3e8\""];
+
+#[test]
+fn balance() {
+    for encoding in ENCODINGS {
+        let address = Address::from_slice(&[0xaa; 20]);
+        let json = format!(r#"{{"{:#X}": {{"balance": {}, "nonce": {}}}}}"#, address, encoding, encoding);
+        let state: State = serde_json::from_str(&json).unwrap();
+        assert_eq!(state.balance(&address), 1000.into(), "{}", encoding);
+        assert_eq!(state.nonce(&address), 1000.into(), "{}", encoding);
+    }
+}
+
+#[test]
+fn gas_limit() {
+    for encoding in ENCODINGS {
+        let block: Block = serde_json::from_str(&format!(r#"{{"gasLimit": {}}}"#, encoding)).unwrap();
+        assert_eq!(block.gas_limit, 1000.into(), "{}", encoding);
+        let tx: TxEnv = serde_json::from_str(&format!(r#"{{"gas": {}}}"#, encoding)).unwrap();
+        assert_eq!(tx.gas_limit, 1000.into(), "{}", encoding);
+    }
+}
+
+#[test]
+fn mixed_block() {
+    let json = r#"{"number": "18000000", "timestamp": "0x64", "baseFee": 7}"#;
+    let block: Block = serde_json::from_str(json).unwrap();
+    assert_eq!(block.number, Some(18_000_000.into()));
+    assert_eq!(block.timestamp, 100.into());
+    assert_eq!(block.base_fee, Some(7.into()));
+}
+
+#[test]
+fn parse() {
+    assert_eq!(parse_u256("0x"), Ok(U256::zero()));
+    assert_eq!(parse_u256("0XFF"), Ok(255.into()));
+    assert_eq!(parse_u256(&U256::MAX.to_string()), Ok(U256::MAX));
+    assert_eq!(parse_u64("18446744073709551615"), Ok(U64::MAX));
+
+    for invalid in ["", "ten", "0xg", "-1", "1.5", " 1"] {
+        assert_eq!(parse_u256(invalid), Err(InvalidNumber::Format(invalid.to_string())));
+    }
+    let too_large = format!("0x1{}", "0".repeat(64));
+    assert_eq!(parse_u256(&too_large), Err(InvalidNumber::Overflow(too_large.clone(), 256)));
+    assert_eq!(parse_u64("0x10000000000000000"), Err(InvalidNumber::Overflow("0x10000000000000000".to_string(), 64)));
+}
+
+#[test]
+fn errors() {
+    for (json, message) in [
+        (r#"{"gasLimit": "ten"}"#, "Invalid number `ten`"),
+        (r#"{"gasLimit": -1}"#, "invalid type: integer `-1`"),
+        (r#"{"gasLimit": 1.5}"#, "invalid type: floating point `1.5`"),
+        (r#"{"gasLimit": true}"#, "invalid type: boolean `true`"),
+        (r#"{"number": "0x10000000000000000"}"#, "doesn't fit in 64 bits"),
+    ] {
+        let error = serde_json::from_str::<Block>(json).unwrap_err().to_string();
+        assert!(error.contains(message), "{}: {}", json, error);
+    }
+}
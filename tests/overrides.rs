@@ -0,0 +1,125 @@
+// State overrides of simulated calls (eth_call's `stateOverride`), which only
+// last for the call
+use evm_from_scrust::abi::{encode_call, AbiValue};
+use evm_from_scrust::asm::Assembler;
+use evm_from_scrust::*;
+use sha3::{Digest, Keccak256};
+use Opcode::*;
+
+fn token() -> Address {
+    Address::from_slice(&[0xcc; 20])
+}
+
+fn holder() -> Address {
+    Address::from_slice(&[0x11; 20])
+}
+
+// Slot of the balance of `holder` in the mapping at slot 0
+fn balance_slot() -> U256 {
+    let mut key = [0u8; 64];
+    key[12..32].copy_from_slice(holder().as_slice());
+    U256::from_big_endian(&Keccak256::digest(key))
+}
+
+fn returning(code: Assembler) -> Bytes {
+    code.push(0).op(MSTORE).push(32).push(0).op(RETURN).build()
+}
+
+// balanceOf(address), with the balances in a mapping at slot 0
+fn evm() -> Evm {
+    let code = returning(
+        Assembler::new()
+            .push(4)
+            .op(CALLDATALOAD)
+            .push(0)
+            .op(MSTORE)
+            .push(0)
+            .push(32)
+            .op(MSTORE)
+            .push(64)
+            .push(0)
+            .op(SHA3)
+            .op(SLOAD),
+    );
+    let mut evm = Evm::new(Block::default(), State::new());
+    evm.set_code(&token(), code);
+    evm.set_storage(&token(), balance_slot(), Bytes32::from_u256(1000.into()));
+    evm.set_storage(&token(), 7.into(), Bytes32::from_u256(7.into()));
+    evm
+}
+
+fn balance_of() -> Call {
+    let data = encode_call("balanceOf(address)", &[AbiValue::Address(holder())]);
+    Call::new(holder(), token(), token(), data, U256::zero(), false)
+}
+
+fn returned(result: &EvmResult) -> U256 {
+    assert!(result.success, "{:?}", result.halt);
+    U256::from_big_endian(result.result.as_slice())
+}
+
+fn overrides(json: &str) -> Overrides {
+    serde_json::from_str(&json.replace("TOKEN", &format!("0x{}", hex::encode(token().as_slice())))).unwrap()
+}
+
+#[test]
+fn balance_of_overridden() {
+    let mut evm = evm();
+    let state = evm.state().clone();
+
+    let state_diff = overrides(&format!(r#"{{"TOKEN": {{"stateDiff": {{"{:#x}": "0x05"}}}}}}"#, balance_slot()));
+    assert_eq!(returned(&evm.call_with_overrides(TxEnv::default(), balance_of(), &state_diff).unwrap()), 5.into());
+
+    // Returns 42, whatever the storage
+    let code = returning(Assembler::new().push(42));
+    let code = overrides(&format!(r#"{{"TOKEN": {{"code": "0x{}"}}}}"#, hex::encode(code.as_slice())));
+    assert_eq!(returned(&evm.call_with_overrides(TxEnv::default(), balance_of(), &code).unwrap()), 42.into());
+
+    // Nothing of it is kept
+    assert!(evm.state().diff(&state).is_empty());
+    assert_eq!(returned(&evm.call(TxEnv::default(), balance_of())), 1000.into());
+}
+
+#[test]
+fn state_replaces_the_storage() {
+    let mut evm = evm();
+    let slot = |key: u64| Bytes32::from_u256(key.into());
+
+    let mut overrides = Overrides::new();
+    overrides.account(token()).state = Some([(8.into(), 8.into())].into_iter().collect());
+    // Every other slot reads as zero for the call
+    let code = returning(Assembler::new().push(7).op(SLOAD).push(8).op(SLOAD).op(ADD));
+    overrides.account(token()).code = Some(code);
+    assert_eq!(returned(&evm.call_with_overrides(TxEnv::default(), balance_of(), &overrides).unwrap()), 8.into());
+    overrides.account(token()).code = None;
+    assert_eq!(returned(&evm.call_with_overrides(TxEnv::default(), balance_of(), &overrides).unwrap()), 0.into());
+
+    assert_eq!(evm.state().storage_load(&token(), 7.into()), slot(7));
+    assert_eq!(evm.state().storage_load(&token(), 8.into()), slot(0));
+}
+
+#[test]
+fn balance_and_nonce() {
+    let mut evm = evm();
+    evm.set_code(&token(), returning(Assembler::new().op(SELFBALANCE)));
+    let overrides = overrides(r#"{"TOKEN": {"balance": "0x64", "nonce": "0x3"}, "0x1111111111111111111111111111111111111111": {"balance": "0x1"}}"#);
+    assert_eq!(returned(&evm.call_with_overrides(TxEnv::default(), balance_of(), &overrides).unwrap()), 100.into());
+
+    let mut state = evm.state().clone();
+    overrides.apply(&mut state).unwrap();
+    assert_eq!(state.nonce(&token()), 3.into());
+    assert_eq!(state.balance(&holder()), 1.into());
+    assert!(!evm.state().exists(&holder()));
+}
+
+#[test]
+fn state_and_state_diff_conflict() {
+    let mut evm = evm();
+    let overrides = overrides(r#"{"TOKEN": {"balance": "0x1", "state": {}, "stateDiff": {"0x1": "0x1"}}}"#);
+    let error = evm.call_with_overrides(TxEnv::default(), balance_of(), &overrides).unwrap_err();
+    assert_eq!(error, OverrideError::StateAndStateDiff(token()));
+
+    let mut state = evm.state().clone();
+    assert_eq!(overrides.apply(&mut state), Err(OverrideError::StateAndStateDiff(token())));
+    assert!(state.diff(evm.state()).is_empty());
+}
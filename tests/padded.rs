@@ -0,0 +1,54 @@
+// The zero-padded reads of `Bytes` behind the copies of call data, code and
+// return data, against a byte-by-byte reference over random offsets and sizes
+use evm_from_scrust::*;
+
+mod common;
+use common::Rng;
+
+// Mostly around the data, sometimes near the end of the address space
+fn offset(rng: &mut Rng, len: usize) -> usize {
+    match rng.below(4) {
+        0 => usize::MAX - rng.below(64) as usize,
+        _ => rng.below(len as u64 + 64) as usize,
+    }
+}
+
+fn reference(data: &[u8], offset: usize, size: usize) -> Vec<u8> {
+    (0..size)
+        .map(|i| offset.checked_add(i).and_then(|index| data.get(index)).copied().unwrap_or(0))
+        .collect()
+}
+
+#[test]
+fn padded_slice() {
+    let mut rng = Rng::new(1);
+    for _ in 0..10_000 {
+        let data: Vec<u8> = (0..rng.below(100)).map(|_| rng.next() as u8).collect();
+        let offset = offset(&mut rng, data.len());
+        let size = rng.below(128) as usize;
+        let bytes = Bytes::from_slice(&data);
+        assert_eq!(bytes.padded_slice(offset, size).as_slice(), reference(&data, offset, size), "{:?}", (&data, offset, size));
+    }
+}
+
+#[test]
+fn padded_word() {
+    let mut rng = Rng::new(2);
+    for _ in 0..10_000 {
+        let data: Vec<u8> = (0..rng.below(100)).map(|_| rng.next() as u8).collect();
+        let offset = offset(&mut rng, data.len());
+        let bytes = Bytes::from_slice(&data);
+        assert_eq!(bytes.padded_word(offset), Bytes32::from_slice(&reference(&data, offset, 32)), "{:?}", (&data, offset));
+    }
+}
+
+#[test]
+fn edges() {
+    let bytes = Bytes::from_slice(&[1, 2, 3]);
+    assert_eq!(bytes.padded_slice(0, 0), Bytes::new());
+    assert_eq!(bytes.padded_slice(usize::MAX, 2).as_slice(), [0, 0]);
+    assert_eq!(bytes.padded_slice(2, 3).as_slice(), [3, 0, 0]);
+    assert_eq!(bytes.padded_slice(3, 1).as_slice(), [0]);
+    assert_eq!(Bytes::new().padded_word(0), Bytes32::zero());
+    assert_eq!(bytes.padded_word(usize::MAX), Bytes32::zero());
+}
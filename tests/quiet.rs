@@ -0,0 +1,64 @@
+// The interpreter must not write to stdout: diagnostics go through the `log`
+// facade (targets `evm::opcode`, `evm::call` and `evm::state`), which is a
+// no-op unless the embedder installs a logger. Printing every PUSH and MSTORE
+// used to make this loop take seconds in debug builds; it now runs in a
+// fraction of that.
+use std::process::Command;
+
+use evm_from_scrust::*;
+
+const ITERATIONS: usize = 100_000;
+
+// PUSH3 100000, then `counter -= 1` (JUMPDEST PUSH1 1 SWAP1 SUB DUP1 PUSH1 4
+// JUMPI) until the counter is zero
+fn countdown() -> Bytes {
+    Bytes::from_vec(hex::decode("620186a05b6001900380600457").unwrap())
+}
+
+#[test]
+fn hot_loop() {
+    let mut evm = ExecutionContext::new(TxEnv::default(), Call::default(), Block::default(), State::default(), countdown());
+    let result = evm.run();
+
+    assert!(result.success);
+    assert_eq!(result.stack, vec![Bytes32::zero()]);
+    // PUSH3, then JUMPDEST + 5 VERYLOW + JUMPI per iteration
+    assert_eq!(result.gas_used, 3 + ITERATIONS * (1 + 5 * 3 + 10));
+}
+
+// Runs the test `name` in a child process and returns what it printed
+fn printed_by(name: &str) -> Vec<String> {
+    let output = Command::new(std::env::current_exe().unwrap())
+        .args([name, "--exact", "--nocapture", "--test-threads=1"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    stdout
+        .lines()
+        .filter(|line| !line.is_empty() && !line.starts_with("running ") && !line.starts_with("test "))
+        .map(String::from)
+        .collect()
+}
+
+#[test]
+fn hot_loop_is_silent() {
+    let printed = printed_by("hot_loop");
+    assert!(printed.is_empty(), "the interpreter printed: {:?}", printed);
+}
+
+// `evm::eval` goes through the whole transaction path (nonce, transfer,
+// receipt), which must be just as quiet
+#[test]
+fn eval() {
+    let result = evm::eval(countdown().as_slice(), &[]);
+    assert!(result.success);
+    assert_eq!(result.stack, vec![Bytes32::zero()]);
+}
+
+#[test]
+fn eval_is_silent() {
+    let printed = printed_by("eval");
+    assert!(printed.is_empty(), "eval printed: {:?}", printed);
+}
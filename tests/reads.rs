@@ -0,0 +1,65 @@
+// Reading the state never creates accounts nor storage slots, only marks them
+// warm
+use evm_from_scrust::asm::Assembler;
+use evm_from_scrust::*;
+use Opcode::*;
+
+mod common;
+use common::contract;
+
+fn probed() -> Vec<Address> {
+    (1..=10u8).map(|index| Address::from_slice(&[index.wrapping_mul(0x3b); 20])).collect()
+}
+
+// BALANCE, EXTCODESIZE, EXTCODEHASH and EXTCODECOPY of each probed address,
+// and SLOAD of slots 1 to 10
+fn probes() -> Bytes {
+    let code = probed().into_iter().fold(Assembler::new(), |asm, address| {
+        let address = Bytes32::from_address(address).to_u256();
+        asm.push(address)
+            .op(BALANCE)
+            .op(POP)
+            .push(address)
+            .op(EXTCODESIZE)
+            .op(POP)
+            .push(address)
+            .op(EXTCODEHASH)
+            .op(POP)
+            .push(1)
+            .push(0)
+            .push(0)
+            .push(address)
+            .op(EXTCODECOPY)
+    });
+    (1..=10u64).fold(code, |asm, key| asm.push(key).op(SLOAD).op(POP)).build()
+}
+
+#[test]
+fn probes_leave_the_state_unchanged() {
+    let mut state = State::new();
+    state.set_code(&contract(), probes());
+    state.set_storage(&contract(), U256::zero(), Bytes32::one());
+    let addresses = state.addresses();
+
+    let call = Call::new(Address::zero(), contract(), contract(), Bytes::new(), U256::zero(), false);
+    let mut evm = ExecutionContext::new(TxEnv::default(), call, Block::default(), state, probes());
+    let result = evm.run();
+    assert!(result.success, "{:?}", result.halt);
+
+    assert_eq!(evm.state.addresses(), addresses);
+    assert_eq!(evm.state.get(&contract()).unwrap().storage().slots().count(), 1);
+    // But the probes were accesses all the same
+    assert!(probed().iter().all(|address| evm.state.is_warm_account(address)));
+    assert!((1..=10u64).all(|key| evm.state.is_warm_slot(&contract(), key.into())));
+}
+
+// Nor through a transaction, which only adds its sender (for the nonce bump)
+#[test]
+fn transaction() {
+    let sender = Address::from_slice(&[0xaa; 20]);
+    let mut evm = Evm::new(Block::default(), State::new());
+    evm.set_code(&contract(), probes());
+    let result = evm.transact(TxEnv::default(), Call::new(sender, contract(), contract(), Bytes::new(), U256::zero(), false));
+    assert!(result.success, "{:?}", result.halt);
+    assert_eq!(evm.state().addresses(), [sender, contract()]);
+}
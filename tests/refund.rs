@@ -0,0 +1,151 @@
+// Gas refunds: earned by SSTOREs clearing slots (and SELFDESTRUCT before
+// London), rolled back with the frames that earned them, and capped when the
+// transaction ends (EIP-2200, EIP-3529)
+use evm_from_scrust::asm::Assembler;
+use evm_from_scrust::*;
+use Opcode::*;
+
+mod common;
+use common::contract;
+
+fn callee() -> Address {
+    Address::from_slice(&[0xdd; 20])
+}
+
+// Value held by slots 1 and 2 of both contracts before the transaction
+fn original() -> Bytes32 {
+    Bytes32::from_u256(0x42.into())
+}
+
+// SSTORE of each (key, value)
+fn stores(asm: Assembler, writes: &[(u64, u64)]) -> Assembler {
+    writes.iter().fold(asm, |asm, (key, value)| asm.push(*value).push(*key).op(SSTORE))
+}
+
+fn state(code: Bytes, callee_code: Bytes) -> State {
+    let mut state = State::new();
+    for (address, code) in [(contract(), code), (callee(), callee_code)] {
+        state.set_code(&address, code);
+        state.set_storage(&address, 1.into(), original());
+        state.set_storage(&address, 2.into(), original());
+    }
+    state
+}
+
+fn call() -> Call {
+    Call::new(Address::zero(), contract(), contract(), Bytes::new(), U256::zero(), false)
+}
+
+// Runs the contract as a frame, with its refunds uncapped
+fn run_in(spec: SpecId, code: Bytes, callee_code: Bytes) -> EvmResult {
+    let state = state(code.clone(), callee_code);
+    let result = ExecutionContext::new(TxEnv::default(), call(), Block::default(), state, code)
+        .with_spec(spec)
+        .run();
+    assert!(result.success);
+    result
+}
+
+fn run(writes: &[(u64, u64)]) -> EvmResult {
+    run_in(SpecId::default(), stores(Assembler::new(), writes).build(), Bytes::new())
+}
+
+#[test]
+fn clear_two_slots() {
+    let code = stores(Assembler::new(), &[(1, 0), (2, 0)]).build();
+    assert_eq!(run_in(SpecId::default(), code.clone(), Bytes::new()).gas_refunded, 9600);
+    assert_eq!(run_in(SpecId::Berlin, code.clone(), Bytes::new()).gas_refunded, 30000);
+
+    // A transaction only gets back a fifth of the gas it used
+    let mut evm = Evm::new(Block::default(), state(code.clone(), Bytes::new()));
+    let result = evm.transact(TxEnv::default(), call());
+    assert!(result.success);
+    // 4 PUSH1, and 2 resets of cold slots
+    let gas = 4 * 3 + 2 * (2100 + 2900);
+    assert_eq!(result.gas_refunded, gas / 5);
+    assert_eq!(result.gas_used, gas - gas / 5);
+    assert_eq!(evm.receipts()[0].gas_used, result.gas_used);
+
+    // Half of it before London
+    let mut evm = Evm::new(Block::default(), state(code, Bytes::new())).with_spec(SpecId::Berlin);
+    let result = evm.transact(TxEnv::default(), call());
+    assert_eq!(result.gas_refunded, gas / 2);
+}
+
+// The net refunds of EIP-2200 (with the amounts of EIP-2929 and EIP-3529)
+#[test]
+fn net_metering() {
+    // Cleared, then set back: the clearing refund is taken back, and restoring
+    // the original value refunds the reset minus a warm read
+    assert_eq!(run(&[(1, 0), (1, 0x42)]).gas_refunded, 2800);
+    // Cleared, then set to something else: only the clearing is taken back
+    assert_eq!(run(&[(1, 0), (1, 7)]).gas_refunded, 0);
+    // Changed, then cleared
+    assert_eq!(run(&[(1, 7), (1, 0)]).gas_refunded, 4800);
+    // Changed, then set back
+    assert_eq!(run(&[(1, 7), (1, 0x42)]).gas_refunded, 2800);
+    // An empty slot set, then cleared
+    assert_eq!(run(&[(3, 7), (3, 0)]).gas_refunded, 19900);
+    // Writing the current value changes nothing
+    assert_eq!(run(&[(1, 0x42), (3, 0)]).gas_refunded, 0);
+
+    // With the amounts of Istanbul
+    let code = stores(Assembler::new(), &[(1, 0), (1, 0x42), (3, 7), (3, 0)]).build();
+    assert_eq!(run_in(SpecId::Istanbul, code.clone(), Bytes::new()).gas_refunded, 4200 + 19200);
+    // And without net metering
+    assert_eq!(run_in(SpecId::Petersburg, code, Bytes::new()).gas_refunded, 2 * 15000);
+}
+
+// Calls the callee, which clears its slot 1 and then stops or reverts
+fn call_clearing(revert: bool) -> EvmResult {
+    let callee_code = stores(Assembler::new(), &[(1, 0)]);
+    let callee_code = match revert {
+        true => callee_code.push(0).push(0).op(REVERT),
+        false => callee_code,
+    };
+    let code = Assembler::new()
+        .push(0)
+        .push(0)
+        .push(0)
+        .push(0)
+        .push(0)
+        .push(Bytes32::from_address(callee()).to_u256())
+        .push(0xffff)
+        .op(CALL)
+        .build();
+    run_in(SpecId::default(), code, callee_code.build())
+}
+
+#[test]
+fn reverted_subcall() {
+    let result = call_clearing(true);
+    assert_eq!(result.stack, [Bytes32::zero()]);
+    assert_eq!(result.gas_refunded, 0);
+
+    let result = call_clearing(false);
+    assert_eq!(result.stack, [Bytes32::one()]);
+    assert_eq!(result.gas_refunded, 4800);
+}
+
+// The original values are the ones at the start of each transaction
+#[test]
+fn original_values_per_transaction() {
+    let set = |value: u64| stores(Assembler::new(), &[(1, value)]).build();
+    let mut evm = Evm::new(Block::default(), state(set(0), Bytes::new())).disable_nonce_check(true);
+    assert!(evm.transact(TxEnv::default(), call()).gas_refunded > 0);
+
+    // Setting the slot back doesn't take the refund of the previous
+    // transaction back, nor earns another one
+    evm.set_code(&contract(), set(0x42));
+    let result = evm.transact(TxEnv::default(), call());
+    assert!(result.success);
+    assert_eq!(result.gas_refunded, 0);
+}
+
+// Before London, SELFDESTRUCT is refunded (once per account)
+#[test]
+fn selfdestruct() {
+    let code = Assembler::new().push(0).op(SELFDESTRUCT).build();
+    assert_eq!(run_in(SpecId::Berlin, code.clone(), Bytes::new()).gas_refunded, 24000);
+    assert_eq!(run_in(SpecId::London, code, Bytes::new()).gas_refunded, 0);
+}
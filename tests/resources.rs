@@ -0,0 +1,112 @@
+// Resource high-water marks of an execution, and the isolation of the frames
+// they are tracked over
+use evm_from_scrust::asm::Assembler;
+use evm_from_scrust::*;
+use Opcode::*;
+
+mod common;
+use common::contract;
+
+fn callee() -> Address {
+    Address::from_slice(&[0xdd; 20])
+}
+
+// CALL to `address`, with the return data copied to `ret_offset`
+fn call_to(code: Assembler, address: Address, args_size: u64, ret_offset: u64, ret_size: u64) -> Assembler {
+    code.push(ret_size)
+        .push(ret_offset)
+        .push(args_size)
+        .push(0)
+        .push(0)
+        .push(address.to_u256())
+        .op(GAS)
+        .op(CALL)
+}
+
+fn run(code: Bytes, data: Bytes, state: State) -> EvmResult {
+    let call = Call::new(Address::zero(), contract(), contract(), data, U256::zero(), false);
+    let result = ExecutionContext::new(TxEnv::default(), call, Block::default(), state, code).run();
+    assert!(result.success, "{:?}", result.halt);
+    result
+}
+
+// 8 KiB of memory in the parent, 4 KiB in the child while the parent's is live
+#[test]
+fn nested_frames() {
+    let mut state = State::new();
+    state.set_code(&callee(), Assembler::new().push(1).push(0x0fe0).op(MSTORE).build());
+    let code = call_to(Assembler::new().push(1).push(0x1fe0).op(MSTORE), callee(), 0, 0, 0).build();
+
+    let result = run(code, Bytes::new(), state);
+    assert_eq!(result.stack, [Bytes32::one()]);
+    assert_eq!(result.peak_memory_bytes, 8192);
+    assert_eq!(result.peak_live_memory_bytes, 8192 + 4096);
+    assert_eq!(result.peak_stack_depth, 7);
+    assert_eq!(result.max_call_depth, 1);
+}
+
+// The contract calls itself with a decreasing counter until it reaches zero
+#[test]
+fn recursion() {
+    let code = Assembler::new()
+        .push(0)
+        .op(CALLDATALOAD)
+        .op(DUP1)
+        .op(ISZERO)
+        .push(0x1d)
+        .op(JUMPI)
+        .push(1)
+        .op(SWAP1)
+        .op(SUB)
+        .push(0)
+        .op(MSTORE)
+        .push(0)
+        .push(0)
+        .push(32)
+        .push(0)
+        .push(0)
+        .op(ADDRESS)
+        .op(GAS)
+        .op(CALL)
+        .op(STOP)
+        .op(JUMPDEST)
+        .build();
+    let mut state = State::new();
+    state.set_code(&contract(), code.clone());
+
+    let result = run(code, Bytes::from_vec(Bytes32::from_u256(10.into()).as_slice().to_vec()), state);
+    assert_eq!(result.stack, [Bytes32::one()]);
+    assert_eq!(result.max_call_depth, 10);
+}
+
+#[test]
+fn no_code() {
+    let result = run(Bytes::new(), Bytes::new(), State::new());
+    assert_eq!(result.peak_memory_bytes, 0);
+    assert_eq!(result.peak_live_memory_bytes, 0);
+    assert_eq!(result.peak_stack_depth, 0);
+    assert_eq!(result.max_call_depth, 0);
+}
+
+// Subcalls start with an empty stack and memory, and leave the caller's as
+// they were, but for the return data copied into it
+#[test]
+fn fresh_frames() {
+    // Returns the size of its memory on entry
+    let mut state = State::new();
+    state.set_code(&callee(), Assembler::new().op(MSIZE).push(0).op(MSTORE).push(32).push(0).op(RETURN).build());
+    let code = Assembler::new().push(0xaa).push(0x11).push(0).op(MSTORE);
+    let code = call_to(code, callee(), 32, 32, 32).push(0).op(MLOAD).push(32).op(MLOAD).op(MSIZE).build();
+
+    let result = run(code, Bytes::new(), state.clone());
+    let stack: Vec<U256> = result.stack.iter().map(|word| word.to_u256()).collect();
+    // MSIZE, the callee's MSIZE (not the caller's 32), the caller's word at 0
+    // (not the callee's), the call's success, and the item pushed before it
+    assert_eq!(stack, [U256::from(64), U256::zero(), 0x11.into(), U256::one(), 0xaa.into()]);
+
+    // A callee popping on entry underflows its own empty stack
+    state.set_code(&callee(), Assembler::new().op(POP).build());
+    let code = call_to(Assembler::new().push(0xaa), callee(), 0, 0, 0).build();
+    let result = run(code, Bytes::new(), state);
+    assert_eq!(result.stack, [Bytes32::zero(), Bytes32::from_u256(0xaa.into())]);
+}
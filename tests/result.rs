@@ -0,0 +1,48 @@
+// The result of an execution: one type, exported at the root of the crate and
+// from `interpreter`
+use evm_from_scrust::asm::Assembler;
+use evm_from_scrust::utils::create_address;
+use evm_from_scrust::*;
+use Opcode::*;
+
+// Compiles only if both paths name the same type
+fn same_type(result: evm_from_scrust::EvmResult) -> evm_from_scrust::interpreter::EvmResult {
+    result
+}
+
+#[test]
+fn import_paths() {
+    let result = same_type(evm::eval(&[0x00], &[]));
+    assert!(result.success);
+}
+
+#[test]
+fn constructors() {
+    let output = Bytes::from_slice(&[1, 2]);
+    let result = EvmResult::success(output.clone());
+    assert!(result.success && result.halt.is_none());
+    assert_eq!(result.result, output);
+
+    let result = EvmResult::revert(output.clone());
+    assert!(!result.success);
+    assert_eq!((result.halt, result.result), (Some(Halt::Revert), output));
+
+    let result = EvmResult::failure(Halt::InvalidJump);
+    assert!(!result.success);
+    assert_eq!((result.halt, result.result), (Some(Halt::InvalidJump), Bytes::new()));
+}
+
+#[test]
+fn created_address() {
+    let sender = Address::from_slice(&[0xaa; 20]);
+    let creation = |init: Bytes| {
+        let mut evm = Evm::new(Block::default(), State::new());
+        evm.transact(TxEnv::default(), Call::new(sender, Address::zero(), Address::zero(), init, U256::zero(), false))
+    };
+    let result = creation(Assembler::new().push(0).push(0).op(RETURN).build());
+    assert_eq!(result.created_address, Some(create_address(&sender, U256::zero())));
+    let result = creation(Assembler::new().push(0).push(0).op(REVERT).build());
+    assert_eq!(result.created_address, None);
+    // Nor for calls
+    assert_eq!(evm::eval(&[0x00], &[]).created_address, None);
+}
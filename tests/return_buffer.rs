@@ -0,0 +1,59 @@
+// The return buffer of a call only gets the bytes the callee returned: when
+// it returns fewer than the buffer holds, the rest keeps what it had. The
+// same goes for the data of a REVERT, whatever the call opcode.
+use evm_from_scrust::asm::Assembler;
+use evm_from_scrust::*;
+use Opcode::*;
+
+mod common;
+use common::{contract, sender};
+
+fn callee() -> Address {
+    Address::from_slice(&[0x0b; 20])
+}
+
+// The callee ends with `ending`, returning the single byte 0xab
+fn callee_code(ending: Opcode) -> Bytes {
+    Assembler::new().push(0xab).push(0).op(MSTORE8).push(1).push(0).op(ending).build()
+}
+
+// Fills the 32 bytes at 0 with 0xff, enters the callee with `opcode` using
+// them as the return buffer, and loads them back
+fn run(opcode: Opcode, ending: Opcode) -> Vec<Bytes32> {
+    let code = Assembler::new().push(U256::MAX).push(0).op(MSTORE).push(32).push(0).push(0).push(0);
+    let code = match opcode {
+        CALL | CALLCODE => code.push(0),
+        _ => code,
+    };
+    let code = code.push(callee().to_u256()).op(GAS).op(opcode).push(0).op(MLOAD).build();
+
+    let mut evm = Evm::new(Block::default(), State::new());
+    evm.set_code(&contract(), code);
+    evm.set_code(&callee(), callee_code(ending));
+    let call = Call::new(sender(), contract(), contract(), Bytes::new(), U256::zero(), false);
+    let result = evm.transact(TxEnv::default(), call);
+    assert!(result.success, "{:?}", result.halt);
+    result.stack
+}
+
+fn success(ending: Opcode) -> Bytes32 {
+    match ending {
+        RETURN => Bytes32::one(),
+        _ => Bytes32::zero(),
+    }
+}
+
+fn buffer() -> Bytes32 {
+    let mut bytes = [0xff; 32];
+    bytes[0] = 0xab;
+    Bytes32::from_slice(&bytes)
+}
+
+#[test]
+fn every_call_opcode() {
+    for opcode in [CALL, CALLCODE, DELEGATECALL, STATICCALL] {
+        for ending in [RETURN, REVERT] {
+            assert_eq!(run(opcode, ending), [buffer(), success(ending)], "{:?} ending with {:?}", opcode, ending);
+        }
+    }
+}
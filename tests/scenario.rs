@@ -0,0 +1,97 @@
+// Sequences of transactions against the same state: the scenarios under
+// `tests/fixtures/scenarios.json`, and the `Evm` facade directly
+use evm_from_scrust::asm::Assembler;
+use evm_from_scrust::evmtest::{self, Outcome};
+use evm_from_scrust::*;
+use Opcode::*;
+
+#[test]
+fn scenarios() {
+    let path = format!("{}/tests/fixtures/scenarios.json", env!("CARGO_MANIFEST_DIR"));
+    let scenarios = evmtest::load_scenarios(&path).unwrap();
+    assert!(!scenarios.is_empty());
+    for scenario in scenarios {
+        if let Outcome::Fail(report) = scenario.run() {
+            panic!("---- {} ----\n{}", scenario.name, report);
+        }
+    }
+}
+
+#[test]
+fn failing_transaction() {
+    let json = r#"{"name": "two", "hint": "", "state": {"0x1000000000000000000000000000000000000aaa": {"code": {"bin": "6001"}}},
+        "txs": [
+            {"tx": {"to": "0x1000000000000000000000000000000000000aaa"}, "expect": {"stack": ["0x1"], "success": true}},
+            {"tx": {"to": "0x1000000000000000000000000000000000000aaa"}, "expect": {"stack": ["0x2"], "success": true}}
+        ]}"#;
+    let Outcome::Fail(report) = evmtest::Scenario::parse(0, serde_json::from_str(json).unwrap()).run() else { panic!("not failed") };
+    assert!(report.starts_with("Transaction 2 of 2:"), "{}", report);
+}
+
+#[test]
+fn code_in_tx() {
+    let json = r#"{"name": "inline", "hint": "", "txs": [{"tx": {"code": {"bin": "6001"}}, "expect": {"success": true}}]}"#;
+    let Outcome::Fail(report) = evmtest::Scenario::parse(0, serde_json::from_str(json).unwrap()).run() else { panic!("not failed") };
+    assert!(report.starts_with("Invalid scenario inline: `txs[0].tx.code`"), "{}", report);
+}
+
+// Increments the counter at slot 0 without call data, and returns it with any
+fn counter() -> Bytes {
+    Assembler::new()
+        .op(CALLDATASIZE)
+        .jumpi_to("read")
+        .push(0)
+        .op(SLOAD)
+        .push(1)
+        .op(ADD)
+        .push(0)
+        .op(SSTORE)
+        .op(STOP)
+        .jumpdest_label("read")
+        .push(0)
+        .op(SLOAD)
+        .push(0)
+        .op(MSTORE)
+        .push(32)
+        .push(0)
+        .op(RETURN)
+        .build()
+}
+
+// Init code returning the runtime code (copied from the end of the init code)
+fn creation_code(runtime: &Bytes) -> Bytes {
+    let prefix = Assembler::new()
+        .push(runtime.len() as u64)
+        .op(DUP1)
+        .push(11)
+        .push(0)
+        .op(CODECOPY)
+        .push(0)
+        .op(RETURN)
+        .build();
+    Bytes::from_vec([prefix.as_slice(), runtime.as_slice()].concat())
+}
+
+#[test]
+fn deploy_and_count() {
+    let sender = Address::from_slice(&[0xde; 20]);
+    let mut evm = Evm::new(Block::default(), State::new()).disable_nonce_check(true);
+
+    let creation = Call::new(sender, Address::zero(), Address::zero(), creation_code(&counter()), U256::zero(), false);
+    assert!(evm.transact(TxEnv::default(), creation).success);
+    let contract = evm.receipts()[0].contract_address.unwrap();
+
+    for _ in 0..2 {
+        let increment = Call::new(sender, contract, contract, Bytes::new(), U256::zero(), false);
+        assert!(evm.transact(TxEnv::default(), increment).success);
+    }
+    let read = Call::new(sender, contract, contract, Bytes::from_vec(vec![1]), U256::zero(), false);
+    let result = evm.transact(TxEnv::default(), read);
+    assert_eq!(Bytes32::from_slice(result.result.as_slice()).to_u256(), U256::from(2));
+
+    // Every transaction bumped the nonce of the sender
+    assert_eq!(evm.state().nonce(&sender), U256::from(4));
+    let state = evm.take_state();
+    assert_eq!(state.storage_load(&contract, U256::zero()).to_u256(), U256::from(2));
+    assert_eq!(evm.state().nonce(&sender), U256::zero());
+}
@@ -0,0 +1,73 @@
+// Self-destructed accounts send their balance right away, but are only deleted
+// once the transaction is over, and only if the frame that self-destructed
+// them (and its callers) succeeded
+use evm_from_scrust::asm::Assembler;
+use evm_from_scrust::*;
+use Opcode::*;
+
+mod common;
+use common::{contract, sender};
+
+fn victim() -> Address {
+    Address::from_slice(&[0xdd; 20])
+}
+
+fn relay() -> Address {
+    Address::from_slice(&[0xee; 20])
+}
+
+fn sink() -> Address {
+    Address::from_slice(&[0xff; 20])
+}
+
+fn victim_code() -> Bytes {
+    Assembler::new().push(sink().to_u256()).op(SELFDESTRUCT).build()
+}
+
+fn call_to(code: Assembler, address: Address) -> Assembler {
+    code.push(0).push(0).push(0).push(0).push(0).push(address.to_u256()).op(GAS).op(CALL)
+}
+
+// Pushes the code size of the victim
+fn victim_size(code: Assembler) -> Assembler {
+    code.push(victim().to_u256()).op(EXTCODESIZE)
+}
+
+// Runs the contract on London, with the relay running `relay_code`
+fn run(code: Assembler, relay_code: Assembler) -> (EvmResult, Evm) {
+    let mut evm = Evm::new(Block::default(), State::new()).with_spec(SpecId::London);
+    evm.set_code(&contract(), code.build());
+    evm.set_code(&relay(), relay_code.build());
+    evm.set_code(&victim(), victim_code());
+    evm.set_balance(&victim(), 7.into());
+    let result = evm.transact(TxEnv::default(), Call::new(sender(), contract(), contract(), Bytes::new(), U256::zero(), false));
+    assert!(result.success, "{:?}", result.halt);
+    (result, evm)
+}
+
+#[test]
+fn deleted_at_the_end_of_the_transaction() {
+    // The caller reads the victim's code after the call, and so does the relay
+    // it calls next, which returns it
+    let code = victim_size(call_to(Assembler::new(), victim()).op(POP));
+    let code = call_to(code, relay()).op(POP).push(32).push(0).push(0).op(RETURNDATACOPY).push(0).op(MLOAD);
+    let relay_code = victim_size(Assembler::new()).push(0).op(MSTORE).push(32).push(0).op(RETURN);
+    let (result, evm) = run(code, relay_code);
+
+    let size = Bytes32::from_u256(victim_code().len().into());
+    assert_eq!(result.stack, [size, size]);
+    assert_eq!(evm.state().balance(&sink()), 7.into());
+    assert!(!evm.state().exists(&victim()));
+}
+
+// A frame that fails takes the self-destructs of its subcalls with it
+#[test]
+fn reverted_frame() {
+    let relay_code = call_to(Assembler::new(), victim()).push(0).push(0).op(REVERT);
+    let (result, evm) = run(victim_size(call_to(Assembler::new(), relay()).op(POP)), relay_code);
+
+    assert_eq!(result.stack, [Bytes32::from_u256(victim_code().len().into())]);
+    assert_eq!(evm.state().code(&victim()), victim_code());
+    assert_eq!(evm.state().balance(&victim()), 7.into());
+    assert!(evm.state().balance(&sink()).is_zero());
+}
@@ -0,0 +1,76 @@
+// The core types can be moved to (and shared between) threads, to run
+// fixtures or simulations in parallel
+use evm_from_scrust::asm::Assembler;
+use evm_from_scrust::*;
+use rayon::prelude::*;
+use std::sync::Arc;
+use Opcode::*;
+
+fn assert_send<T: Send>() {}
+fn assert_sync<T: Sync>() {}
+
+#[test]
+fn core_types() {
+    assert_send::<State>();
+    assert_sync::<State>();
+    assert_send::<Env>();
+    assert_sync::<Env>();
+    assert_send::<Bytes>();
+    assert_sync::<Bytes>();
+    assert_send::<Bytes32>();
+    assert_sync::<Bytes32>();
+    assert_send::<Address>();
+    assert_sync::<Address>();
+    assert_send::<Log>();
+    assert_sync::<Log>();
+    assert_send::<Receipt>();
+    assert_send::<EvmResult>();
+    assert_send::<Evm>();
+    assert_sync::<Evm>();
+    assert_send::<ExecutionContext>();
+    assert_send::<CodeCache>();
+    assert_sync::<CodeCache>();
+    assert_send::<KeccakCache>();
+}
+
+// 100 transactions on top of the same pre-state, across a thread pool
+#[test]
+fn parallel_simulations() {
+    let contract = Address::from_slice(&[0xcc; 20]);
+    // Adds the call data to slot 0 and returns the sum
+    let code = Assembler::new()
+        .push(0)
+        .op(CALLDATALOAD)
+        .push(0)
+        .op(SLOAD)
+        .op(ADD)
+        .op(DUP1)
+        .push(0)
+        .op(SSTORE)
+        .push(0)
+        .op(MSTORE)
+        .push(32)
+        .push(0)
+        .op(RETURN)
+        .build();
+    let mut state = State::new();
+    state.set_code(&contract, code);
+    state.set_storage(&contract, U256::zero(), Bytes32::from_u256(1000.into()));
+    let pre_state = Arc::new(state);
+
+    let results: Vec<U256> = (0..100u64)
+        .into_par_iter()
+        .map(|index| {
+            let mut evm = Evm::new(Block::default(), State::clone(&pre_state)).with_keccak_cache();
+            let data = Bytes::from_slice(Bytes32::from_u256(index.into()).as_slice());
+            let result = evm.transact(TxEnv::default(), Call::new(Address::zero(), contract, contract, data, U256::zero(), false));
+            assert!(result.success);
+            assert_eq!(evm.state().storage_load(&contract, U256::zero()), result.result.as_bytes32());
+            result.result.as_bytes32().to_u256()
+        })
+        .collect();
+
+    let expected: Vec<U256> = (0..100u64).map(|index| U256::from(1000 + index)).collect();
+    assert_eq!(results, expected);
+    assert_eq!(pre_state.storage_load(&contract, U256::zero()), Bytes32::from_u256(1000.into()));
+}
@@ -0,0 +1,117 @@
+// Signed arithmetic on two's complement words: the remainder of SMOD takes the
+// sign of the dividend, and INT256_MIN doesn't overflow on the way
+use evm_from_scrust::asm::Assembler;
+use evm_from_scrust::*;
+use Opcode::*;
+
+// Word of a signed number
+fn int(value: i128) -> U256 {
+    match value < 0 {
+        true => !U256::from(value.unsigned_abs()) + 1,
+        false => U256::from(value),
+    }
+}
+
+fn int256_min() -> U256 {
+    U256::one() << 255
+}
+
+fn int256_max() -> U256 {
+    int256_min() - 1
+}
+
+fn run(opcode: Opcode, a: U256, b: U256) -> U256 {
+    let code = Assembler::new().push(b).push(a).op(opcode).build();
+    let result = evm::eval(code.as_slice(), &[]);
+    assert!(result.success);
+    result.stack[0].to_u256()
+}
+
+#[test]
+fn smod() {
+    let cases = [
+        (int(7), int(3), int(1)),
+        (int(7), int(-3), int(1)),
+        (int(-7), int(3), int(-1)),
+        (int(-7), int(-3), int(-1)),
+        (int(6), int(-3), int(0)),
+        (int(-6), int(3), int(0)),
+        // Zero divisor
+        (int(7), int(0), int(0)),
+        (int(-7), int(0), int(0)),
+        (int(0), int(-3), int(0)),
+        // INT256_MIN
+        (int256_min(), int(-1), int(0)),
+        (int256_min(), int(1), int(0)),
+        (int256_min(), int(3), int(-2)),
+        (int256_min(), int(-3), int(-2)),
+        (int256_min(), int256_min(), int(0)),
+        (int256_min(), int256_max(), int(-1)),
+        (int256_max(), int256_min(), int256_max()),
+        (int(-1), int256_min(), int(-1)),
+    ];
+    for (a, b, expected) in cases {
+        assert_eq!(run(SMOD, a, b), expected, "SMOD({:#x}, {:#x})", a, b);
+    }
+}
+
+#[test]
+fn sdiv() {
+    let cases = [
+        (int(7), int(3), int(2)),
+        (int(7), int(-3), int(-2)),
+        (int(-7), int(3), int(-2)),
+        (int(-7), int(-3), int(2)),
+        (int(1), int(-1), int(-1)),
+        (int(-1), int(-1), int(1)),
+        // Zero dividend or divisor
+        (int(7), int(0), int(0)),
+        (int(-7), int(0), int(0)),
+        (int(0), int(-7), int(0)),
+        (int(0), int(0), int(0)),
+        (int256_min(), int(0), int(0)),
+        // The only overflow: INT256_MIN / -1 wraps to INT256_MIN
+        (int256_min(), int(-1), int256_min()),
+        (int256_min(), int(1), int256_min()),
+        (int256_min(), int256_min(), int(1)),
+        (int256_max(), int(-1), int256_min() + 1),
+    ];
+    for (a, b, expected) in cases {
+        assert_eq!(run(SDIV, a, b), expected, "SDIV({:#x}, {:#x})", a, b);
+    }
+}
+
+fn flag(value: bool) -> U256 {
+    U256::from(value as u8)
+}
+
+#[test]
+fn comparisons() {
+    for a in -3..=3 {
+        for b in -3..=3 {
+            assert_eq!(run(SLT, int(a), int(b)), flag(a < b), "SLT({}, {})", a, b);
+            assert_eq!(run(SGT, int(a), int(b)), flag(a > b), "SGT({}, {})", a, b);
+        }
+    }
+}
+
+#[test]
+fn comparisons_at_the_bounds() {
+    // (a, b, a < b), in signed order
+    let cases = [
+        (int256_min(), int256_max(), true),
+        (int256_max(), int256_min(), false),
+        (int256_min(), int(-1), true),
+        (int256_min(), int(0), true),
+        (int(-1), int256_max(), true),
+        (int(0), int256_max(), true),
+        (int256_max(), int(1), false),
+        (int256_min(), int256_min(), false),
+        (int256_max(), int256_max(), false),
+        (int256_min() + 1, int256_min(), false),
+    ];
+    for (a, b, less) in cases {
+        assert_eq!(run(SLT, a, b), flag(less), "SLT({:#x}, {:#x})", a, b);
+        assert_eq!(run(SGT, b, a), flag(less), "SGT({:#x}, {:#x})", b, a);
+    }
+}
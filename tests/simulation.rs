@@ -0,0 +1,109 @@
+// Simulated transactions (`Evm::call`), which run like real ones but leave
+// nothing behind
+use evm_from_scrust::asm::Assembler;
+use evm_from_scrust::*;
+use Opcode::*;
+
+mod common;
+use common::sender;
+
+fn counter() -> Address {
+    Address::from_slice(&[0xcc; 20])
+}
+
+// Increments slot 0, logs its new value and returns it along with the balance
+// of the contract
+fn evm() -> Evm {
+    let code = Assembler::new()
+        .push(0)
+        .op(SLOAD)
+        .push(1)
+        .op(ADD)
+        .op(DUP1)
+        .push(0)
+        .op(SSTORE)
+        .push(0)
+        .op(MSTORE)
+        .op(SELFBALANCE)
+        .push(32)
+        .op(MSTORE)
+        .push(32)
+        .push(0)
+        .op(LOG0)
+        .push(64)
+        .push(0)
+        .op(RETURN)
+        .build();
+    let mut evm = Evm::new(Block::default(), State::new());
+    evm.set_code(&counter(), code);
+    evm.set_balance(&sender(), 10.into());
+    evm
+}
+
+fn increment(value: u64) -> Call {
+    Call::new(sender(), counter(), counter(), Bytes::new(), value.into(), false)
+}
+
+fn returned(result: &EvmResult) -> Vec<Bytes32> {
+    assert!(result.success, "{:?}", result.halt);
+    result.result.as_slice().chunks(32).map(Bytes32::from_slice).collect()
+}
+
+fn word(value: u64) -> Bytes32 {
+    Bytes32::from_u256(value.into())
+}
+
+#[test]
+fn state_discarded() {
+    let mut evm = evm();
+    let result = evm.call(TxEnv::default(), increment(3));
+    assert_eq!(returned(&result), [word(1), word(3)]);
+    assert_eq!(result.logs.len(), 1);
+
+    // Nothing of it is kept
+    assert_eq!(evm.state().storage_load(&counter(), U256::zero()), Bytes32::zero());
+    assert_eq!(evm.state().balance(&sender()), 10.into());
+    assert!(evm.state().nonce(&sender()).is_zero());
+    assert!(evm.logs().is_empty() && evm.receipts().is_empty());
+    assert_eq!(returned(&evm.call(TxEnv::default(), increment(0))), [word(1), word(0)]);
+
+    // While a transaction persists its changes
+    assert_eq!(returned(&evm.transact(TxEnv::default(), increment(3))), [word(1), word(3)]);
+    assert_eq!(evm.state().storage_load(&counter(), U256::zero()), word(1));
+    assert_eq!(returned(&evm.call(TxEnv::default(), increment(0))), [word(2), word(3)]);
+    assert_eq!(evm.receipts().len(), 1);
+}
+
+// Simulations don't check the nonce
+#[test]
+fn nonce() {
+    let mut evm = evm();
+    let tx = TxEnv { nonce: 5.into(), ..TxEnv::default() };
+    assert!(evm.call(tx.clone(), increment(0)).success);
+    assert_eq!(evm.transact(tx, increment(0)).halt, Some(Halt::InvalidNonce { expected: U256::zero(), actual: 5.into() }));
+}
+
+// Nor do they pay for gas: a sender without the funds for it can simulate,
+// and sees its balance untouched
+#[test]
+fn unfunded_sender() {
+    let probe = Address::from_slice(&[0xdd; 20]);
+    let block = Block::builder().base_fee(1).build();
+    let mut evm = Evm::new(block, State::new());
+    evm.set_code(&probe, Assembler::new().op(ORIGIN).op(BALANCE).build());
+    let tx = TxEnv::new(sender(), 10.into(), 1_000_000.into(), U256::zero());
+    let call = Call::new(sender(), probe, probe, Bytes::new(), U256::zero(), false);
+
+    let result = evm.call(tx.clone(), call.clone());
+    assert!(result.success, "{:?}", result.halt);
+    assert_eq!(result.stack, [Bytes32::zero()]);
+
+    evm.set_balance(&sender(), 1000.into());
+    let result = evm.call(tx.clone(), call.clone());
+    assert!(result.success, "{:?}", result.halt);
+    assert_eq!(result.stack, [Bytes32::from_u256(1000.into())]);
+    assert_eq!(evm.state().balance(&sender()), 1000.into());
+
+    // Which a transaction can't do
+    assert!(matches!(evm.transact(tx, call).halt, Some(Halt::InsufficientBalance { .. })));
+}
@@ -0,0 +1,113 @@
+// Snapshots of the `Evm` facade, interleaved with transactions
+use evm_from_scrust::asm::Assembler;
+use evm_from_scrust::*;
+use Opcode::*;
+
+mod common;
+use common::{contract, sender};
+
+// Logs the value it receives, and counts its calls in slot 0
+fn evm() -> Evm {
+    let code = Assembler::new()
+        .op(CALLVALUE)
+        .push(0)
+        .op(MSTORE)
+        .push(1)
+        .push(32)
+        .push(0)
+        .op(LOG1)
+        .push(0)
+        .op(SLOAD)
+        .push(1)
+        .op(ADD)
+        .push(0)
+        .op(SSTORE)
+        .build();
+    let mut state = State::new();
+    state.insert(sender(), AccountState::from_parts(sender(), 100.into(), U256::zero(), Bytes::new()));
+    state.insert(contract(), AccountState::from_parts(contract(), U256::zero(), U256::one(), code));
+    Evm::new(Block::default(), state).disable_nonce_check(true)
+}
+
+fn send(evm: &mut Evm, value: u64) {
+    let call = Call::new(sender(), contract(), contract(), Bytes::new(), value.into(), false);
+    assert!(evm.transact(TxEnv::default(), call).success);
+}
+
+// Checks that `evm` is exactly as it was when `expected` was cloned from it
+fn assert_restored(evm: &Evm, expected: &Evm) {
+    assert!(evm.state().diff(expected.state()).is_empty(), "{}", evm.state().diff(expected.state()));
+    assert_eq!(evm.logs(), expected.logs());
+    assert_eq!(evm.receipts(), expected.receipts());
+    assert_eq!(evm.cumulative_gas_used(), expected.cumulative_gas_used());
+}
+
+#[test]
+fn revert_to() {
+    let mut evm = evm();
+    send(&mut evm, 1);
+    let first = evm.snapshot();
+    let after_first = evm.clone();
+
+    send(&mut evm, 2);
+    let second = evm.snapshot();
+    let after_second = evm.clone();
+
+    send(&mut evm, 3);
+    assert_eq!(evm.logs().len(), 3);
+    assert_eq!(evm.state().balance(&contract()), 6.into());
+
+    assert!(evm.revert_to(second));
+    assert_restored(&evm, &after_second);
+    assert_eq!(evm.state().balance(&contract()), 3.into());
+    assert_eq!(evm.state().storage_load(&contract(), U256::zero()), Bytes32::from_u256(2.into()));
+
+    // A snapshot can be reverted to several times
+    send(&mut evm, 4);
+    assert!(evm.revert_to(second));
+    assert_restored(&evm, &after_second);
+
+    assert!(evm.revert_to(first));
+    assert_restored(&evm, &after_first);
+    assert_eq!(evm.logs().len(), 1);
+    assert_eq!(evm.logs()[0].data, Bytes32::from_u256(1.into()).as_bytes());
+    assert_eq!(evm.state().balance(&sender()), 99.into());
+}
+
+#[test]
+fn older_revert_invalidates_newer() {
+    let mut evm = evm();
+    let first = evm.snapshot();
+    send(&mut evm, 1);
+    let second = evm.snapshot();
+    send(&mut evm, 2);
+
+    assert!(evm.revert_to(first));
+    assert!(!evm.revert_to(second));
+    assert!(evm.logs().is_empty());
+    assert_eq!(evm.state().balance(&sender()), 100.into());
+
+    // New snapshots get new ids
+    let third = evm.snapshot();
+    assert_ne!(third, second);
+    send(&mut evm, 5);
+    assert!(evm.revert_to(third));
+    assert!(evm.revert_to(first));
+}
+
+#[test]
+fn discard() {
+    let mut evm = evm();
+    let first = evm.snapshot();
+    send(&mut evm, 1);
+    let second = evm.snapshot();
+    let after_first = evm.clone();
+    send(&mut evm, 2);
+
+    // Discarding a snapshot keeps the others valid
+    assert!(evm.discard(first));
+    assert!(!evm.discard(first));
+    assert!(!evm.revert_to(first));
+    assert!(evm.revert_to(second));
+    assert_restored(&evm, &after_first);
+}
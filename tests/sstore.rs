@@ -0,0 +1,146 @@
+// SSTORE pricing and refunds by original, current and new value (EIP-2200,
+// with the amounts of EIP-2929 and EIP-3529)
+use evm_from_scrust::asm::Assembler;
+use evm_from_scrust::*;
+use Opcode::*;
+
+fn word(value: u64) -> Bytes32 {
+    Bytes32::from_u256(value.into())
+}
+
+// (original, current, new, gas, refund), with the slot already warm
+const TABLE: [(u64, u64, u64, usize, i64); 15] = [
+    (0, 0, 0, 100, 0),
+    (0, 0, 1, 20000, 0),
+    (0, 1, 0, 100, 19900),
+    (0, 1, 1, 100, 0),
+    (0, 1, 2, 100, 0),
+    (1, 0, 0, 100, 0),
+    (1, 0, 1, 100, -4800 + 2800),
+    (1, 0, 2, 100, -4800),
+    (1, 1, 0, 2900, 4800),
+    (1, 1, 1, 100, 0),
+    (1, 1, 2, 2900, 0),
+    (1, 2, 0, 100, 4800),
+    (1, 2, 1, 100, 2800),
+    (1, 2, 2, 100, 0),
+    (1, 2, 3, 100, 0),
+];
+
+#[test]
+fn table() {
+    for (original, current, new, gas, refund) in TABLE {
+        let spec = SpecId::default();
+        let (original, current, new) = (word(original), word(current), word(new));
+        assert_eq!(sstore_gas(spec, original, current, new), gas, "{:?}", (original, current, new));
+        assert_eq!(sstore_refund(spec, original, current, new), refund, "{:?}", (original, current, new));
+    }
+}
+
+// The same table, executed: the slot holds the original value, is warmed up
+// by an SLOAD, set to the current value if it differs, and then to the new one
+#[test]
+fn executed() {
+    let contract = Address::from_slice(&[0xcc; 20]);
+    for (original, current, new, gas, refund) in TABLE {
+        let mut code = Assembler::new().push(0).op(SLOAD).op(POP);
+        if current != original {
+            code = code.push(current).push(0).op(SSTORE);
+        }
+        let code = code.push(new).push(0).op(SSTORE).build();
+
+        let mut state = State::new();
+        state.set_storage(&contract, U256::zero(), word(original));
+        let call = Call::new(Address::zero(), contract, contract, Bytes::new(), U256::zero(), false);
+        let mut evm = ExecutionContext::new(TxEnv::default(), call, Block::default(), state, code).with_step_tracer();
+        let result = evm.run();
+        assert!(result.success);
+
+        let last = evm.steps().unwrap().last().unwrap();
+        assert_eq!(last.gas_cost, gas, "{:?}", (original, current, new));
+        assert_eq!(result.gas_refunded as i64 - last.refund, refund, "{:?}", (original, current, new));
+    }
+}
+
+#[test]
+fn before_berlin() {
+    let (zero, one, two) = (word(0), word(1), word(2));
+    // Istanbul prices the warm reads as SLOADs (EIP-1884)
+    assert_eq!(sstore_gas(SpecId::Istanbul, one, one, two), 5000);
+    assert_eq!(sstore_gas(SpecId::Istanbul, one, two, one), 800);
+    assert_eq!(sstore_refund(SpecId::Istanbul, one, two, one), 4200);
+    assert_eq!(sstore_refund(SpecId::Istanbul, zero, one, zero), 19200);
+    // Before, only the current and new values count
+    assert_eq!(sstore_gas(SpecId::Petersburg, one, two, one), 5000);
+    assert_eq!(sstore_gas(SpecId::Petersburg, one, zero, one), 20000);
+    assert_eq!(sstore_refund(SpecId::Petersburg, one, two, zero), 15000);
+}
+
+// SSTORE needs more than a CALL stipend left (EIP-2200)
+#[test]
+fn sentry() {
+    let code = Assembler::new().push(1).push(0).op(SSTORE).build();
+    let run = |gas_limit: u64| {
+        let tx = TxEnv::new(Address::zero(), U256::zero(), gas_limit.into(), U256::zero());
+        ExecutionContext::new(tx, Call::default(), Block::default(), State::new(), code.clone()).run()
+    };
+    assert_eq!(run(6 + 2300).halt, Some(Halt::OutOfGas));
+    assert!(run(6 + 2100 + 20000).success);
+}
+
+#[test]
+fn storage_originals() {
+    let mut storage = Storage::new();
+    storage.store(U256::one(), word(1));
+    assert_eq!(storage.original(U256::one()), Some(word(1)));
+    assert_eq!(storage.original(U256::zero()), None);
+
+    storage.write(U256::one(), word(2));
+    storage.write(U256::one(), word(3));
+    storage.write(U256::zero(), word(4));
+    assert_eq!(storage.original(U256::one()), Some(word(1)));
+    assert_eq!(storage.original(U256::zero()), Some(word(0)));
+    assert_eq!(storage.load(U256::one()), word(3));
+
+    storage.commit_tx();
+    assert_eq!(storage.original(U256::one()), Some(word(3)));
+    assert_eq!(storage.original(U256::zero()), Some(word(4)));
+}
+
+// Writing zero clears the slot: it reads as zero within the transaction, and
+// is gone from the storage once the transaction is committed
+#[test]
+fn zero_deletes() {
+    let contract = Address::from_slice(&[0xcc; 20]);
+    let code = Assembler::new()
+        .push(5).push(1).op(SSTORE)
+        .push(0).push(1).op(SSTORE)
+        .push(0).push(2).op(SSTORE)
+        .push(1).op(SLOAD)
+        .build();
+    let mut state = State::new();
+    state.set_storage(&contract, 2.into(), word(7));
+    state.set_storage(&contract, 3.into(), word(9));
+    let mut evm = Evm::new(Block::default(), state);
+    evm.set_code(&contract, code);
+    let call = Call::new(Address::zero(), contract, contract, Bytes::new(), U256::zero(), false);
+    let result = evm.transact(TxEnv::default(), call);
+    assert!(result.success);
+    assert_eq!(result.stack, [word(0)]);
+
+    assert_eq!(evm.state().storage_dump(&contract), [(3.into(), word(9))]);
+    assert_eq!(evm.state().get(&contract).unwrap().storage().slots().count(), 1);
+    assert_eq!(evm.state().storage_load(&contract, 1.into()), word(0));
+}
+
+#[test]
+fn dump_omits_zeros() {
+    let mut storage = Storage::new();
+    storage.store(2.into(), word(2));
+    storage.store(1.into(), word(0));
+    storage.store(0.into(), word(1));
+    assert_eq!(storage.dump(), [(0.into(), word(1)), (2.into(), word(2))]);
+    storage.remove_zeros();
+    assert_eq!(storage.get(1.into()), None);
+    assert_eq!(storage.load(1.into()), word(0));
+}
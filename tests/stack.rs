@@ -0,0 +1,79 @@
+// Locks in the orientation of the stack API: `peek` and `swap_top` count from
+// the top, `get_item` from the bottom, and the item lists go either way.
+// Each property is checked for every stack size up to `MAX_ITEMS`.
+use evm_from_scrust::*;
+
+const MAX_ITEMS: usize = 32;
+
+// Stack holding 1, 2, ..., k (so k is at the top)
+fn stack_of(k: usize) -> Stack {
+    let mut stack = Stack::new();
+    for i in 1..=k {
+        stack.push_usize(i);
+    }
+    stack
+}
+
+fn item(i: usize) -> Bytes32 {
+    Bytes32::from_u256(i.into())
+}
+
+#[test]
+fn peek() {
+    for k in 0..=MAX_ITEMS {
+        let stack = stack_of(k);
+        for depth in 0..k {
+            assert_eq!(stack.peek(depth), Some(item(k - depth)), "peek({}) of {} items", depth, k);
+            assert_eq!(stack.get_item(depth), Some(item(depth + 1)), "get_item({}) of {} items", depth, k);
+        }
+        assert_eq!(stack.peek(k), None, "peek({}) of {} items", k, k);
+        assert_eq!(stack.get_item(k), None, "get_item({}) of {} items", k, k);
+        assert_eq!(stack.top(), if k == 0 { None } else { Some(item(k)) });
+        assert_eq!(stack.depth(), k, "peeking must not pop");
+    }
+}
+
+#[test]
+fn swap_top() {
+    for k in 0..=MAX_ITEMS {
+        for depth in 1..k {
+            let mut stack = stack_of(k);
+            stack.swap_top(depth).unwrap();
+            assert_eq!(stack.top(), Some(item(k - depth)), "swap_top({}) of {} items", depth, k);
+            assert_eq!(stack.peek(depth), Some(item(k)), "swap_top({}) of {} items", depth, k);
+            for other in (1..k).filter(|other| *other != depth) {
+                assert_eq!(stack.peek(other), Some(item(k - other)), "swap_top({}) moved peek({})", depth, other);
+            }
+        }
+
+        let mut stack = stack_of(k);
+        assert!(stack.swap_top(k).is_err(), "swap_top({}) of {} items", k, k);
+        assert_eq!(stack.items_bottom_first(), stack_of(k).items_bottom_first(), "a failed swap must leave the stack untouched");
+    }
+}
+
+#[test]
+fn items_top_first() {
+    for k in 0..=MAX_ITEMS {
+        let stack = stack_of(k);
+        let expected: Vec<Bytes32> = (1..=k).rev().map(item).collect();
+        assert_eq!(stack.items_top_first(), expected, "{} items", k);
+    }
+}
+
+#[test]
+fn orderings() {
+    let stack = stack_of(3);
+    assert_eq!(stack.items_bottom_first(), [item(1), item(2), item(3)]);
+    assert_eq!(stack.items_top_first(), [item(3), item(2), item(1)]);
+}
+
+#[test]
+fn fmt() {
+    let mut stack = Stack::new();
+    assert_eq!(fmt_stack(&stack), "[]");
+    stack.push_usize(0xff);
+    stack.push_usize(0);
+    stack.push(Bytes32::from_u256(U256::MAX));
+    assert_eq!(fmt_stack(&stack), format!("[0x{}, 0x0, 0xff]", "f".repeat(64)));
+}
@@ -0,0 +1,86 @@
+// Ordered iteration over states and storage, and diffs between states
+use evm_from_scrust::*;
+
+fn address(byte: u8) -> Address {
+    Address::from_slice(&[byte])
+}
+
+fn account(byte: u8, balance: u64) -> AccountState {
+    AccountState::from_parts(address(byte), balance.into(), U256::one(), Bytes::from_vec(vec![0x00]))
+}
+
+// Accounts 0x03, 0x01 and 0x02, with slots 3, 1 and 2 in the first one
+fn state() -> State {
+    let mut state = State::new();
+    for byte in [3, 1, 2] {
+        state.insert(address(byte), account(byte, 10));
+    }
+    let storage = state.get_mut(&address(3)).unwrap().storage_mut();
+    for key in [3u64, 1, 2] {
+        storage.store(key.into(), Bytes32::from_u256(key.into()));
+    }
+    state
+}
+
+#[test]
+fn iter_sorted() {
+    let state = state();
+    let addresses: Vec<Address> = state.iter_sorted().map(|(address, _)| *address).collect();
+    assert_eq!(addresses, [address(1), address(2), address(3)]);
+
+    let keys: Vec<U256> = state.get(&address(3)).unwrap().storage().iter_sorted().map(|(key, _)| *key).collect();
+    assert_eq!(keys, [1.into(), 2.into(), 3.into()]);
+}
+
+#[test]
+fn balance_and_slot() {
+    let before = state();
+    let mut after = state();
+    after.insert(address(1), account(1, 7));
+    after.get_mut(&address(3)).unwrap().storage_mut().store(2.into(), Bytes32::zero());
+
+    let diff = before.diff(&after);
+    assert_eq!(diff.len(), 2);
+    assert_eq!(
+        diff.accounts,
+        [
+            AccountDiff { address: address(1), status: AccountStatus::Changed, changes: vec![Change::Balance(10.into(), 7.into())] },
+            AccountDiff {
+                address: address(3),
+                status: AccountStatus::Changed,
+                changes: vec![Change::Storage(2.into(), Bytes32::from_u256(2.into()), Bytes32::zero())],
+            },
+        ]
+    );
+    assert_eq!(
+        diff.to_string(),
+        format!(
+            "{:#X} (changed)\n  balance: 0xa -> 0x7\n{:#X} (changed)\n  storage[0x2]: 0x2 -> 0x0\n",
+            address(1),
+            address(3)
+        )
+    );
+    assert!(before.diff(&before).is_empty());
+}
+
+#[test]
+fn added_and_removed() {
+    let before = state();
+    let mut after = state();
+    after.delete(&address(2));
+    after.insert(address(4), account(4, 1));
+    // Empty accounts are the same as missing ones
+    after.insert(address(5), AccountState::new(address(5)));
+
+    let diff = before.diff(&after);
+    let statuses: Vec<(Address, AccountStatus)> = diff.accounts.iter().map(|account| (account.address, account.status)).collect();
+    assert_eq!(statuses, [(address(2), AccountStatus::Removed), (address(4), AccountStatus::Added)]);
+    assert_eq!(
+        diff.accounts[1].changes,
+        [
+            Change::Balance(U256::zero(), 1.into()),
+            Change::Nonce(U256::zero(), U256::one()),
+            Change::Code(Bytes::new(), Bytes::from_vec(vec![0x00])),
+        ]
+    );
+}
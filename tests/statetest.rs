@@ -0,0 +1,40 @@
+// Runs the GeneralStateTests fixtures vendored under `tests/fixtures/state`,
+// checking the post-states of `DEFAULT_FORK`.
+use evm_from_scrust::evmtest::Outcome;
+use evm_from_scrust::statetest::{self, DEFAULT_FORK};
+
+#[test]
+fn state_tests() {
+    let dir = format!("{}/tests/fixtures/state", env!("CARGO_MANIFEST_DIR"));
+    let mut paths: Vec<_> = std::fs::read_dir(&dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|extension| extension == "json"))
+        .collect();
+    paths.sort();
+
+    let mut passed = 0;
+    let mut skipped = 0;
+    let mut failed = Vec::new();
+    for path in paths {
+        for test in statetest::load(path.to_str().unwrap()).unwrap() {
+            for (label, outcome) in test.run(DEFAULT_FORK) {
+                match outcome {
+                    Outcome::Pass => passed += 1,
+                    Outcome::Skip(reason) => {
+                        eprintln!("SKIP {}: {}", label, reason);
+                        skipped += 1;
+                    }
+                    Outcome::Fail(report) => {
+                        eprintln!("---- {} ----\n{}", label, report);
+                        failed.push(label);
+                    }
+                }
+            }
+        }
+    }
+
+    eprintln!("state tests: {} passed, {} failed, {} skipped", passed, failed.len(), skipped);
+    assert!(passed > 0);
+    assert!(failed.is_empty(), "failed tests: {:?}", failed);
+}
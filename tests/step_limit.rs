@@ -0,0 +1,70 @@
+// The `max_steps` guard against code looping forever
+use evm_from_scrust::asm::Assembler;
+use evm_from_scrust::*;
+use Opcode::*;
+
+// JUMPDEST PUSH1 0 JUMP, forever
+fn endless_loop() -> Bytes {
+    Assembler::new().jumpdest_label("loop").jump_to("loop").build()
+}
+
+fn run(code: Bytes, max_steps: Option<u64>) -> EvmResult {
+    let mut evm = ExecutionContext::new(TxEnv::default(), Call::default(), Block::default(), State::default(), code);
+    if let Some(max_steps) = max_steps {
+        evm = evm.with_max_steps(max_steps);
+    }
+    evm.run()
+}
+
+#[test]
+fn endless_loop_halts() {
+    let result = run(endless_loop(), Some(10_000));
+    assert!(!result.success);
+    assert_eq!(result.halt, Some(Halt::StepLimit));
+    assert_eq!(result.step_count, 10_000);
+    assert_eq!(result.gas_used, result.gas_limit);
+}
+
+#[test]
+fn counted_without_limit() {
+    let result = run(Assembler::new().push(1).push(2).op(ADD).build(), None);
+    assert!(result.success);
+    assert_eq!(result.step_count, 3);
+
+    // Reaching the limit on the last instruction is fine
+    let result = run(Assembler::new().push(1).push(2).op(ADD).build(), Some(3));
+    assert!(result.success);
+}
+
+// The steps of a subcall count towards the limit, and running out of them in
+// the subcall halts the caller too
+#[test]
+fn shared_with_subcalls() {
+    let looping = Address::from_slice(&[0xcc; 20]);
+    let mut evm = Evm::new(Block::default(), State::new()).with_max_steps(1_000);
+    evm.set_code(&looping, endless_loop());
+    let caller = Address::from_slice(&[0xca; 20]);
+    let code = Assembler::new()
+        .push(0)
+        .push(0)
+        .push(0)
+        .push(0)
+        .push(0)
+        .push(looping.to_u256())
+        .push(100_000)
+        .op(CALL)
+        .build();
+    evm.set_code(&caller, code);
+
+    let call = Call::new(Address::zero(), caller, caller, Bytes::new(), U256::zero(), false);
+    let result = evm.transact(TxEnv::default(), call);
+    assert_eq!(result.halt, Some(Halt::StepLimit));
+    assert_eq!(result.step_count, 1_000);
+}
+
+#[test]
+fn eval_with_limit() {
+    let result = evm::eval_with(endless_loop().as_slice(), &[], |env| env.cfg.max_steps = Some(50));
+    assert_eq!(result.halt, Some(Halt::StepLimit));
+    assert_eq!(result.step_count, 50);
+}
@@ -0,0 +1,84 @@
+// Steps recorded by the step tracer: one per instruction, subcalls included in
+// execution order, and their EIP-3155 JSON
+use evm_from_scrust::asm::Assembler;
+use evm_from_scrust::*;
+use serde_json::json;
+use Opcode::*;
+
+fn parent() -> Address {
+    Address::from_slice(&[0xa0; 20])
+}
+
+fn child() -> Address {
+    Address::from_slice(&[0xb1; 20])
+}
+
+fn steps(code: Bytes, state: State) -> (EvmResult, Vec<Step>) {
+    let call = Call::new(Address::zero(), parent(), parent(), Bytes::new(), U256::zero(), false);
+    let mut ctx = ExecutionContext::new(TxEnv::default(), call, Block::default(), state, code).with_step_tracer();
+    let result = ctx.run();
+    (result, ctx.steps().unwrap().to_vec())
+}
+
+#[test]
+fn subcalls_in_order() {
+    let mut state = State::new();
+    state.set_code(&child(), Assembler::new().push(1).op(POP).build());
+    let code = Assembler::new()
+        .push(0)
+        .push(0)
+        .push(0)
+        .push(0)
+        .push(0)
+        .push(child().to_u256())
+        .push(1000)
+        .op(CALL)
+        .op(POP)
+        .build();
+    let (result, steps) = steps(code, state);
+    assert!(result.success);
+
+    let names: Vec<(&str, usize)> = steps.iter().map(|step| (step.op_name.as_str(), step.depth)).collect();
+    let mut expected = vec![("PUSH1", 1); 5];
+    expected.extend([("PUSH20", 1), ("PUSH2", 1), ("CALL", 1), ("PUSH1", 2), ("POP", 2), ("POP", 1)]);
+    assert_eq!(names, expected);
+
+    // Each step starts with the gas the previous one left
+    let call = &steps[7];
+    assert_eq!(steps[8].gas, 1000);
+    assert_eq!(steps[10].gas, call.gas - call.gas_cost);
+    assert_eq!(call.stack.len(), 7);
+    assert_eq!(call.stack[6], U256::from(1000));
+}
+
+#[test]
+fn halting_step() {
+    let (result, steps) = steps(Assembler::new().push(1).op(ADD).build(), State::new());
+    assert!(!result.success);
+    assert_eq!(steps.len(), 2);
+    assert_eq!(steps[1].error, Some(Halt::StackUnderflow));
+    assert_eq!(steps[0].error, None);
+}
+
+#[test]
+fn eip3155_json() {
+    let (_, steps) = steps(Assembler::new().push(2).op(MSTORE8).build(), State::new());
+    let step = serde_json::to_value(&steps[0]).unwrap();
+    assert_eq!(
+        step,
+        json!({
+            "pc": 0,
+            "op": 0x60,
+            "gas": format!("{:#x}", 30_000_000),
+            "gasCost": "0x3",
+            "memSize": 0,
+            "stack": [],
+            "depth": 1,
+            "refund": 0,
+            "opName": "PUSH1",
+        })
+    );
+    let step = serde_json::to_value(&steps[1]).unwrap();
+    assert_eq!(step["stack"], json!(["0x2"]));
+    assert_eq!(step["error"], json!(Halt::StackUnderflow.to_string()));
+}
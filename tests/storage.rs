@@ -0,0 +1,126 @@
+// Storage values are words and keys full 256-bit numbers on every path:
+// SSTORE/SLOAD, the state and the hosts
+use evm_from_scrust::asm::Assembler;
+use evm_from_scrust::*;
+use Opcode::*;
+
+mod common;
+use common::contract;
+
+fn word(hex: &str) -> Bytes32 {
+    Bytes32::from_slice(&hex::decode(hex).unwrap())
+}
+
+// Values that would be mangled by a conversion through `usize`, or by reading
+// the word in the wrong byte order
+fn values() -> Vec<Bytes32> {
+    vec![
+        word("00000000000000000000000000000000000000000000000000000000000000ff"),
+        word("0000000000000000000000000000000000000000000000010000000000000000"),
+        word("00ff00000000000000000000000000000000000000000000000000000000ab00"),
+        Bytes32::from([0xff; 32]),
+    ]
+}
+
+// Keys that alias 1 when truncated to 64 bits
+fn keys() -> Vec<U256> {
+    vec![U256::one(), (U256::one() << 64) + 1, (U256::one() << 255) + 1, U256::MAX]
+}
+
+// Stores `value` at every key, then loads them back
+fn round_trip(value: Bytes32) -> EvmResult {
+    let code = keys().iter().fold(Assembler::new(), |code, key| {
+        code.push(value.to_u256()).push(*key).op(SSTORE)
+    });
+    let code = keys().iter().fold(code, |code, key| code.push(*key).op(SLOAD)).build();
+
+    let mut evm = Evm::new(Block::default(), State::new());
+    evm.set_code(&contract(), code);
+    let call = Call::new(Address::zero(), contract(), contract(), Bytes::new(), U256::zero(), false);
+    let result = evm.transact(TxEnv::default(), call);
+    assert!(result.success);
+    for key in keys() {
+        assert_eq!(evm.state().storage_load(&contract(), key), value, "key {:#x}", key);
+    }
+    result
+}
+
+#[test]
+fn opcodes() {
+    for value in values() {
+        let result = round_trip(value);
+        assert_eq!(result.stack, vec![value; keys().len()]);
+    }
+}
+
+#[test]
+fn distinct_keys() {
+    let mut state = State::new();
+    for (key, value) in keys().into_iter().zip(values()) {
+        state.storage_store(&contract(), key, value);
+    }
+    for (key, value) in keys().into_iter().zip(values()) {
+        assert_eq!(state.storage_load(&contract(), key), value, "key {:#x}", key);
+    }
+}
+
+// Host serving a single slot, like a forked chain
+#[derive(Debug)]
+struct OneSlot(U256, Bytes32);
+
+impl Host for OneSlot {
+    fn account(&self, address: &Address) -> Option<AccountState> {
+        Some(AccountState::new(*address))
+    }
+
+    fn storage(&self, _address: &Address, key: U256) -> Bytes32 {
+        if key == self.0 { self.1 } else { Bytes32::zero() }
+    }
+}
+
+#[test]
+fn host() {
+    for (key, value) in keys().into_iter().zip(values()) {
+        let mut state = State::with_host(std::sync::Arc::new(OneSlot(key, value)));
+        assert_eq!(state.sload(&contract(), key).0, value, "key {:#x}", key);
+        // Only the exact key is served
+        assert!(state.storage_load(&contract(), key - 1).is_zero());
+    }
+}
+
+#[cfg(feature = "fork")]
+mod fork {
+    use evm_from_scrust::fork::{ForkHost, Transport};
+    use evm_from_scrust::*;
+    use serde_json::{json, Value};
+
+    // Answers every `eth_getStorageAt` with the same data
+    #[derive(Debug)]
+    struct StorageAt(String);
+
+    impl Transport for StorageAt {
+        fn request(&self, method: &str, _params: Value) -> Result<Value, String> {
+            match method {
+                "eth_getStorageAt" => Ok(json!(self.0)),
+                _ => Ok(json!("0x0")),
+            }
+        }
+    }
+
+    fn fetched(data: &str) -> Bytes32 {
+        let host = ForkHost::with_transport(Box::new(StorageAt(data.to_string())), U64::from(1));
+        host.storage(&Address::zero(), U256::MAX)
+    }
+
+    #[test]
+    fn storage_values() {
+        assert_eq!(fetched(&format!("0x{}", "ff".repeat(32))), Bytes32::from([0xff; 32]));
+        assert_eq!(
+            fetched("0x00000000000000000000000000000000000000000000000000000000000001ff"),
+            Bytes32::from_u256(0x1ff.into())
+        );
+        // Leading zeros dropped by the node
+        assert_eq!(fetched("0x1ff"), Bytes32::from_u256(0x1ff.into()));
+        assert!(fetched("0x0").is_zero());
+    }
+}
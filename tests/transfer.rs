@@ -0,0 +1,124 @@
+// Value transfers between accounts: checked before any balance changes, so
+// that a failing one leaves the state untouched and no sequence of them
+// creates or destroys funds
+use evm_from_scrust::asm::Assembler;
+use evm_from_scrust::*;
+use Opcode::*;
+
+mod common;
+use common::Rng;
+
+fn account(index: u8) -> Address {
+    Address::from_slice(&[index + 1; 20])
+}
+
+// Accounts 0 to 2 hold funds, 3 has a balance about to overflow and 4 doesn't
+// exist
+fn state() -> State {
+    let mut state = State::new();
+    state.set_balance(&account(0), 1000.into());
+    state.set_balance(&account(1), 1.into());
+    state.set_balance(&account(2), U256::MAX / 2);
+    state.set_balance(&account(3), U256::MAX - 10);
+    state
+}
+
+fn total(state: &State) -> U512 {
+    (0..5).map(|index| U512::from(state.balance(&account(index)))).fold(U512::zero(), |sum, balance| sum + balance)
+}
+
+#[test]
+fn insufficient_balance() {
+    let mut state = state();
+    let error = state.transfer(&account(1), &account(0), 2.into()).unwrap_err();
+    assert_eq!(error, TransferError::InsufficientBalance { address: account(1), balance: 1.into(), value: 2.into() });
+    assert_eq!(state.balance(&account(1)), 1.into());
+    assert_eq!(state.balance(&account(0)), 1000.into());
+
+    // From an account that doesn't exist
+    let error = state.transfer(&account(4), &account(0), 1.into()).unwrap_err();
+    assert_eq!(error, TransferError::InsufficientBalance { address: account(4), balance: U256::zero(), value: 1.into() });
+    assert!(!state.exists(&account(4)));
+}
+
+#[test]
+fn balance_overflow() {
+    let mut state = state();
+    let error = state.transfer(&account(0), &account(3), 11.into()).unwrap_err();
+    assert_eq!(error, TransferError::BalanceOverflow { address: account(3), balance: U256::MAX - 10, value: 11.into() });
+    // The sender wasn't debited
+    assert_eq!(state.balance(&account(0)), 1000.into());
+
+    state.transfer(&account(0), &account(3), 10.into()).unwrap();
+    assert_eq!(state.balance(&account(3)), U256::MAX);
+    assert_eq!(state.balance(&account(0)), 990.into());
+}
+
+#[test]
+fn self_transfer() {
+    let mut state = state();
+    state.transfer(&account(0), &account(0), 1000.into()).unwrap();
+    assert_eq!(state.balance(&account(0)), 1000.into());
+    // The funds must still be there
+    assert!(state.transfer(&account(0), &account(0), 1001.into()).is_err());
+    // Even with a balance that would overflow if credited first
+    state.transfer(&account(3), &account(3), 5.into()).unwrap();
+    assert_eq!(state.balance(&account(3)), U256::MAX - 10);
+}
+
+#[test]
+fn new_recipient() {
+    let mut state = state();
+    state.transfer(&account(0), &account(4), U256::zero()).unwrap();
+    assert!(!state.exists(&account(4)));
+    state.transfer(&account(0), &account(4), 5.into()).unwrap();
+    assert_eq!(state.balance(&account(4)), 5.into());
+}
+
+// The account a transfer creates goes away with the frame that reverts it: A
+// calls B, which sends 1 wei to a fresh C and then reverts
+#[test]
+fn created_in_reverted_frame() {
+    let (a, b, c) = (account(10), account(11), account(12));
+    let call = |to: Address, value: u64| {
+        Assembler::new().push(0).push(0).push(0).push(0).push(value).push(to.to_u256()).push(100_000).op(CALL)
+    };
+    let mut evm = Evm::new(Block::default(), State::new());
+    evm.set_code(&a, call(b, 0).op(POP).push(c.to_u256()).op(EXTCODEHASH).build());
+    evm.set_code(&b, call(c, 1).op(POP).push(0).push(0).op(REVERT).build());
+    evm.set_balance(&b, 1.into());
+
+    let result = evm.transact(TxEnv::default(), Call::new(account(0), a, a, Bytes::new(), U256::zero(), false));
+    assert!(result.success);
+    assert_eq!(result.stack, [Bytes32::zero()]);
+    assert!(!evm.state().exists(&c));
+    assert_eq!(evm.state().balance(&b), 1.into());
+}
+
+#[test]
+fn conservation() {
+    for seed in 1..=64 {
+        let mut rng = Rng::new(seed);
+        let mut state = state();
+        let initial = total(&state);
+        for step in 0..200 {
+            let from = account(rng.below(5) as u8);
+            let to = account(rng.below(5) as u8);
+            // Small values, whole balances and values that overflow
+            let value = match rng.below(4) {
+                0 => U256::from(rng.below(1200)),
+                1 => state.balance(&from),
+                2 => U256::MAX - rng.below(16),
+                _ => U256::MAX / 2 + rng.below(16),
+            };
+            let before = state.clone();
+            let outcome = state.transfer(&from, &to, value);
+            assert_eq!(total(&state), initial, "seed {} step {}: {:?}", seed, step, outcome);
+            if outcome.is_err() {
+                for index in 0..5 {
+                    assert_eq!(state.balance(&account(index)), before.balance(&account(index)));
+                }
+            }
+        }
+    }
+}
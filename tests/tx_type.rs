@@ -0,0 +1,124 @@
+// Gas pricing of the transaction types: legacy ones pay their gas price, and
+// EIP-1559 ones the base fee of the block plus the tip their max fee allows
+use evm_from_scrust::asm::Assembler;
+use evm_from_scrust::*;
+use Opcode::*;
+
+mod common;
+use common::{contract, sender};
+
+fn coinbase() -> Address {
+    Address::from_slice(&[0xcb; 20])
+}
+
+// Runs GASPRICE in a block with a base fee of 10
+fn gas_price(tx: TxEnv) -> EvmResult {
+    let mut evm = Evm::new(Block::builder().base_fee(10).build(), State::new());
+    evm.set_code(&contract(), Assembler::new().op(GASPRICE).build());
+    evm.set_balance(&sender(), U256::exp10(18));
+    let call = Call::new(sender(), contract(), contract(), Bytes::new(), U256::zero(), false);
+    evm.transact(tx, call)
+}
+
+fn max_fees(max_fee: u64, max_priority_fee: u64) -> TxEnv {
+    TxEnv::default().with_max_fees(max_fee.into(), max_priority_fee.into())
+}
+
+#[test]
+fn effective_gas_price() {
+    assert_eq!(gas_price(max_fees(100, 2)).stack, [Bytes32::from_u256(12.into())]);
+    // The tip is what the max fee leaves over the base fee
+    assert_eq!(gas_price(max_fees(11, 2)).stack, [Bytes32::from_u256(11.into())]);
+    assert_eq!(gas_price(max_fees(10, 2)).stack, [Bytes32::from_u256(10.into())]);
+
+    // Legacy transactions pay their gas price, whatever the base fee
+    let tx = TxEnv::new(sender(), 3.into(), DEFAULT_GAS_LIMIT.into(), U256::zero());
+    assert_eq!(gas_price(tx.clone()).stack, [Bytes32::from_u256(3.into())]);
+    assert_eq!(tx.effective_gas_price(Some(10.into())), 3.into());
+    // And no base fee (before London) leaves only the tip
+    assert_eq!(max_fees(100, 2).effective_gas_price(None), 2.into());
+}
+
+#[test]
+fn max_fee_below_base_fee() {
+    let mut evm = Evm::new(Block::builder().base_fee(10).build(), State::new());
+    evm.set_code(&contract(), Assembler::new().op(GASPRICE).build());
+    let call = Call::new(sender(), contract(), contract(), Bytes::new(), U256::zero(), false);
+    let result = evm.transact(max_fees(9, 2), call);
+    assert!(!result.success);
+    assert_eq!(result.halt, Some(Halt::MaxFeeBelowBaseFee { max_fee: 9.into(), base_fee: 10.into() }));
+    assert_eq!(result.halt.unwrap().to_string(), "MaxFeeBelowBaseFee: 9 < 10");
+    assert_eq!(result.gas_used, 0);
+    assert!(evm.state().nonce(&sender()).is_zero());
+}
+
+// Runs `code` from a sender holding `balance`, in a block with a base fee of 10
+fn pay(tx: TxEnv, code: Assembler, balance: u64) -> (EvmResult, Evm) {
+    let mut evm = Evm::new(Block::builder().base_fee(10).coinbase(coinbase()).build(), State::new());
+    evm.set_code(&contract(), code.build());
+    evm.set_balance(&sender(), balance.into());
+    let call = Call::new(sender(), contract(), contract(), Bytes::new(), 1.into(), false);
+    let result = evm.transact(tx, call);
+    (result, evm)
+}
+
+// The sender pays the gas it uses at the effective price, of which the
+// coinbase gets the tip (the base fee is burnt)
+#[test]
+fn fees() {
+    let tx = TxEnv { gas_limit: 10_000.into(), ..max_fees(100, 2) };
+    let (result, evm) = pay(tx.clone(), Assembler::new().op(GASPRICE), 1_000_001);
+    assert!(result.success, "{:?}", result.halt);
+    assert_eq!(result.gas_used, 2);
+    assert_eq!(evm.state().balance(&sender()), (1_000_000 - 2 * 12).into());
+    assert_eq!(evm.state().balance(&coinbase()), (2 * 2).into());
+    assert_eq!(evm.state().balance(&contract()), 1.into());
+
+    // Failed transactions pay too (the value goes back)
+    let (result, evm) = pay(tx, Assembler::new().push(0).op(JUMP), 1_000_001);
+    assert_eq!(result.halt, Some(Halt::InvalidJump));
+    assert_eq!(result.gas_used, 10_000);
+    assert_eq!(evm.state().balance(&sender()), (1_000_001 - 10_000 * 12).into());
+    assert_eq!(evm.state().balance(&coinbase()), (10_000 * 2).into());
+    assert!(evm.state().balance(&contract()).is_zero());
+
+    // Legacy transactions tip what their price leaves over the base fee
+    let tx = TxEnv::new(sender(), 15.into(), 10_000.into(), U256::zero());
+    let (result, evm) = pay(tx, Assembler::new().op(GASPRICE), 1_000_001);
+    assert!(result.success, "{:?}", result.halt);
+    assert_eq!(evm.state().balance(&sender()), (1_000_000 - 2 * 15).into());
+    assert_eq!(evm.state().balance(&coinbase()), (2 * 5).into());
+}
+
+// The balance must cover the gas limit at the max fee, and the value, before
+// anything runs
+#[test]
+fn unaffordable_max_fee() {
+    let tx = TxEnv { gas_limit: 10_000.into(), ..max_fees(100, 2) };
+    let (result, evm) = pay(tx.clone(), Assembler::new().op(GASPRICE), 1_000_000);
+    assert_eq!(
+        result.halt,
+        Some(Halt::InsufficientBalance { address: sender(), balance: 1_000_000.into(), value: 1_000_001.into() })
+    );
+    assert_eq!(result.gas_used, 0);
+    assert_eq!(evm.state().balance(&sender()), 1_000_000.into());
+    assert!(evm.state().nonce(&sender()).is_zero());
+    assert!(!evm.state().exists(&coinbase()));
+
+    // Even if the effective price is lower
+    let (result, _) = pay(tx, Assembler::new().op(GASPRICE), 12 * 10_000 + 1);
+    assert!(matches!(result.halt, Some(Halt::InsufficientBalance { .. })));
+}
+
+#[test]
+fn json() {
+    let tx: TxEnv = serde_json::from_str(r#"{"type": "0x2", "maxFeePerGas": "0x64", "maxPriorityFeePerGas": "0x2"}"#).unwrap();
+    assert_eq!(tx.tx_type, TxType::Eip1559);
+    assert_eq!(tx.effective_gas_price(Some(10.into())), 12.into());
+
+    let tx: TxEnv = serde_json::from_str(r#"{"gasprice": "0x3"}"#).unwrap();
+    assert_eq!(tx.tx_type, TxType::Legacy);
+    assert_eq!(tx.gas_price, 3.into());
+
+    assert!(serde_json::from_str::<TxEnv>(r#"{"type": "0x5"}"#).is_err());
+}
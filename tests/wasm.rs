@@ -0,0 +1,56 @@
+// The JavaScript bindings: `run_json` natively, and `run` itself under
+// `wasm-pack test --headless --chrome --features wasm`
+#![cfg(feature = "wasm")]
+use evm_from_scrust::wasm::*;
+use serde_json::json;
+
+// PUSH1 1 PUSH1 2 ADD
+const ADD: &str = "0x6001600201";
+
+#[test]
+fn add() {
+    let output = run_json(ADD, "", "").unwrap();
+    assert_eq!(output, json!({"success": true, "stack": ["0x3"], "logs": [], "returnData": "0x", "gasUsed": 9}));
+}
+
+#[test]
+fn state_and_logs() {
+    // MSTORE of SELFBALANCE, then LOG0 and RETURN of the word
+    let code = "4760005260206000a060206000f3";
+    let state = r#"{"0xeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeee": {"balance": "0x2a"}}"#;
+    let output = run_json(code, "", state).unwrap();
+    assert_eq!(output["success"], true);
+    assert_eq!(output["returnData"], format!("0x{:064x}", 42));
+    assert_eq!(output["logs"][0]["address"], "0xeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeee");
+    assert_eq!(output["logs"][0]["data"], output["returnData"]);
+}
+
+#[test]
+fn invalid_inputs() {
+    assert_eq!(run_json("0x6", "", "").unwrap_err(), "Invalid code: Odd number of digits");
+    assert!(run_json(ADD, "zz", "").unwrap_err().starts_with("Invalid call data"));
+    assert!(run_json(ADD, "", "[]").unwrap_err().starts_with("Invalid state"));
+}
+
+#[cfg(target_arch = "wasm32")]
+mod browser {
+    use super::*;
+    use wasm_bindgen_test::*;
+
+    wasm_bindgen_test_configure!(run_in_browser);
+
+    #[wasm_bindgen_test]
+    fn add() {
+        let output = run(ADD, "", "").unwrap();
+        let output: serde_json::Value = serde_json::from_str(&js_sys::JSON::stringify(&output).unwrap().as_string().unwrap()).unwrap();
+        assert_eq!(output["success"], true);
+        assert_eq!(output["stack"], json!(["0x3"]));
+        assert_eq!(output["returnData"], "0x");
+        assert_eq!(output["gasUsed"], 9);
+    }
+
+    #[wasm_bindgen_test]
+    fn invalid_code() {
+        assert!(run("0x6", "", "").is_err());
+    }
+}